@@ -0,0 +1,493 @@
+use super::graphics_engine::{GraphicsEngine, Instance, Mesh, SkinnedMesh, UniformValue};
+use super::render_queue::{BoundingSphere, DrawItem, Frustum, RenderQueue};
+use super::{BlendMode, RGBATexture, TextureOptions};
+use nalgebra::{Matrix4, UnitQuaternion, Vector3, Vector4};
+use std::rc::Rc;
+/// Most joints a skeleton can have, the size of the bone array in the skinning shader
+pub const MAX_JOINTS: usize = 32;
+/// Poses per second baked for characters drawn without skinning
+const BAKED_FRAME_RATE: f32 = 10.0;
+/// Characters further than this from the camera are drawn with baked poses, which are instanced
+/// together instead of taking a draw call each
+const SKINNED_DISTANCE: f32 = 45.0;
+/// Position, rotation and scale of a joint relative to its parent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JointTransform {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+impl JointTransform {
+    pub fn identity() -> Self {
+        Self {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+    /// Scales, then rotates, then translates
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+    /// Moves `t` of the way from this transform to `other`
+    pub fn blend(&self, other: &Self, t: f32) -> Self {
+        Self {
+            translation: self.translation.lerp(&other.translation, t),
+            rotation: slerp(&self.rotation, &other.rotation, t),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+}
+/// Interpolates along the shorter way between two rotations
+pub fn slerp(a: &UnitQuaternion<f32>, b: &UnitQuaternion<f32>, t: f32) -> UnitQuaternion<f32> {
+    //q and -q are the same rotation, flipping b keeps the interpolation from going the long way
+    let b = if a.coords.dot(&b.coords) < 0.0 {
+        UnitQuaternion::new_unchecked(-b.into_inner())
+    } else {
+        *b
+    };
+    a.try_slerp(&b, t, 1.0e-6).unwrap_or_else(|| a.nlerp(&b, t))
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Joint {
+    pub name: String,
+    /// Index of the parent joint, which always comes before this one in the skeleton
+    pub parent: Option<usize>,
+    /// Transform when no animation moves the joint
+    pub rest: JointTransform,
+    /// Moves a vertex from model space into the joint's space in the bind pose
+    pub inverse_bind: Matrix4<f32>,
+}
+/// Joints of a character ordered so every parent comes before its children
+#[derive(Clone, Debug, PartialEq)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+    /// Applied above the root joints, for example the rotation of an armature
+    root_transform: Matrix4<f32>,
+}
+impl Skeleton {
+    pub fn new(joints: Vec<Joint>, root_transform: Matrix4<f32>) -> Result<Self, String> {
+        if joints.len() > MAX_JOINTS {
+            return Err(format!(
+                "skeleton has {} joints but at most {} are supported",
+                joints.len(),
+                MAX_JOINTS
+            ));
+        }
+        for (index, joint) in joints.iter().enumerate() {
+            if let Some(parent) = joint.parent {
+                if parent >= index {
+                    return Err(format!("joint \"{}\" comes before its parent", joint.name));
+                }
+            }
+        }
+        Ok(Self {
+            joints,
+            root_transform,
+        })
+    }
+    pub fn joints(&self) -> &[Joint] {
+        &self.joints
+    }
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            joints: self.joints.iter().map(|joint| joint.rest).collect(),
+        }
+    }
+    /// Transform of each joint in model space
+    pub fn global_transforms(&self, pose: &Pose) -> Vec<Matrix4<f32>> {
+        let mut globals: Vec<Matrix4<f32>> = Vec::with_capacity(self.joints.len());
+        for (joint, local) in self.joints.iter().zip(pose.joints.iter()) {
+            let parent = match joint.parent {
+                Some(parent) => globals[parent],
+                None => self.root_transform,
+            };
+            globals.push(parent * local.matrix());
+        }
+        globals
+    }
+    /// Matrices moving each vertex from the bind pose into `pose`, sent to the skinning shader
+    pub fn bone_matrices(&self, pose: &Pose) -> Vec<Matrix4<f32>> {
+        self.global_transforms(pose)
+            .iter()
+            .zip(self.joints.iter())
+            .map(|(global, joint)| global * joint.inverse_bind)
+            .collect()
+    }
+}
+/// Local transform of every joint in a skeleton
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pose {
+    pub joints: Vec<JointTransform>,
+}
+impl Pose {
+    /// Moves `t` of the way from this pose to `other`
+    pub fn blend(&self, other: &Self, t: f32) -> Self {
+        Self {
+            joints: self
+                .joints
+                .iter()
+                .zip(other.joints.iter())
+                .map(|(a, b)| a.blend(b, t))
+                .collect(),
+        }
+    }
+}
+/// Values a channel animates, one per keyframe
+#[derive(Clone, Debug, PartialEq)]
+pub enum Keyframes {
+    Translation(Vec<Vector3<f32>>),
+    Rotation(Vec<UnitQuaternion<f32>>),
+    Scale(Vec<Vector3<f32>>),
+}
+/// How a channel moves between keyframes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    /// Holds each keyframe until the next one
+    Step,
+}
+/// Animates one property of one joint between keyframes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    pub joint: usize,
+    /// Time of each keyframe in seconds, in increasing order
+    pub times: Vec<f32>,
+    pub keyframes: Keyframes,
+    pub interpolation: Interpolation,
+}
+impl Channel {
+    /// Sets the animated property of `transform` to its value at `time`
+    pub fn apply(&self, time: f32, transform: &mut JointTransform) {
+        if self.times.is_empty() {
+            return;
+        }
+        let next = self
+            .times
+            .iter()
+            .position(|t| *t > time)
+            .unwrap_or(self.times.len() - 1);
+        let previous = next.saturating_sub(1);
+        let span = self.times[next] - self.times[previous];
+        let t = match self.interpolation {
+            Interpolation::Step if time >= self.times[next] => 1.0,
+            Interpolation::Step => 0.0,
+            Interpolation::Linear if span > 0.0 => {
+                ((time - self.times[previous]) / span).clamp(0.0, 1.0)
+            }
+            Interpolation::Linear => 0.0,
+        };
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                transform.translation = values[previous].lerp(&values[next], t)
+            }
+            Keyframes::Rotation(values) => {
+                transform.rotation = slerp(&values[previous], &values[next], t)
+            }
+            Keyframes::Scale(values) => transform.scale = values[previous].lerp(&values[next], t),
+        }
+    }
+}
+/// Named animation such as "skiing", played on a skeleton
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    /// Length in seconds
+    pub duration: f32,
+    /// Looping clips start over when they end, others hold their last pose
+    pub looping: bool,
+    pub channels: Vec<Channel>,
+}
+impl AnimationClip {
+    /// Clip lasting until its last keyframe
+    pub fn new(name: &str, looping: bool, channels: Vec<Channel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last())
+            .fold(0.0, |a: f32, b| a.max(*b));
+        Self {
+            name: name.to_string(),
+            duration,
+            looping,
+            channels,
+        }
+    }
+    /// Wraps or clamps `time` into the clip
+    pub fn local_time(&self, time: f32) -> f32 {
+        if self.duration <= 0.0 {
+            0.0
+        } else if self.looping {
+            time.rem_euclid(self.duration)
+        } else {
+            time.clamp(0.0, self.duration)
+        }
+    }
+    /// Pose at `time`. Joints the clip does not animate keep their rest transform.
+    pub fn sample(&self, skeleton: &Skeleton, time: f32) -> Pose {
+        self.sample_local(skeleton, self.local_time(time))
+    }
+    /// Pose at a time already within the clip
+    fn sample_local(&self, skeleton: &Skeleton, time: f32) -> Pose {
+        let mut pose = skeleton.rest_pose();
+        for channel in self.channels.iter() {
+            if let Some(transform) = pose.joints.get_mut(channel.joint) {
+                channel.apply(time, transform);
+            }
+        }
+        pose
+    }
+}
+/// Clip being played and how far into it
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Playing {
+    clip: usize,
+    time: f32,
+    /// Holds the last pose at the end even if the clip loops
+    once: bool,
+}
+impl Playing {
+    fn new(clip: usize, once: bool) -> Self {
+        Self {
+            clip,
+            time: 0.0,
+            once,
+        }
+    }
+}
+/// Plays the clips of a character, cross fading from the last clip when a new one starts
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animator {
+    current: Playing,
+    /// Clip being faded out, with the seconds the fade has run and how long it lasts
+    fading: Option<(Playing, f32, f32)>,
+}
+impl Animator {
+    pub fn new(clip: usize) -> Self {
+        Self {
+            current: Playing::new(clip, false),
+            fading: None,
+        }
+    }
+    /// Index of the clip being played
+    pub fn clip(&self) -> usize {
+        self.current.clip
+    }
+    /// Seconds since the current clip started
+    pub fn time(&self) -> f32 {
+        self.current.time
+    }
+    /// Whether the current clip holds its last pose once it ends, even if the clip loops
+    pub fn plays_once(&self) -> bool {
+        self.current.once
+    }
+    /// Starts `clip` from the beginning, blending from the current pose over `fade` seconds. Does
+    /// nothing if the clip is already playing.
+    pub fn play(&mut self, clip: usize, fade: f32) {
+        self.start(Playing::new(clip, false), fade)
+    }
+    /// Like `play` but holds the last pose of the clip once it ends, such as for a fall
+    pub fn play_once(&mut self, clip: usize, fade: f32) {
+        self.start(Playing::new(clip, true), fade)
+    }
+    fn start(&mut self, playing: Playing, fade: f32) {
+        if playing.clip == self.current.clip {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.current, playing);
+        self.fading = if fade > 0.0 {
+            Some((previous, 0.0, fade))
+        } else {
+            None
+        };
+    }
+    pub fn update(&mut self, delta_seconds: f32) {
+        self.current.time += delta_seconds;
+        if let Some((previous, elapsed, fade)) = self.fading.as_mut() {
+            previous.time += delta_seconds;
+            *elapsed += delta_seconds;
+            if *elapsed >= *fade {
+                self.fading = None;
+            }
+        }
+    }
+    pub fn pose(&self, skeleton: &Skeleton, clips: &[AnimationClip]) -> Pose {
+        let sample = |playing: &Playing| match clips.get(playing.clip) {
+            Some(clip) if playing.once => {
+                clip.sample_local(skeleton, playing.time.clamp(0.0, clip.duration))
+            }
+            Some(clip) => clip.sample(skeleton, playing.time),
+            None => skeleton.rest_pose(),
+        };
+        let current = sample(&self.current);
+        match &self.fading {
+            Some((previous, elapsed, fade)) => sample(previous).blend(&current, elapsed / fade),
+            None => current,
+        }
+    }
+}
+/// Skinned mesh with the skeleton and clips that move it
+#[derive(Clone)]
+pub struct Character {
+    pub mesh: SkinnedMesh,
+    pub texture: RGBATexture,
+    pub skeleton: Skeleton,
+    pub clips: Vec<AnimationClip>,
+}
+impl Character {
+    pub fn clip_index(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name == name)
+    }
+    /// Makes the clip called `name` loop or hold its last pose. Returns false if there is no such
+    /// clip
+    pub fn set_looping(&mut self, name: &str, looping: bool) -> bool {
+        match self.clips.iter_mut().find(|clip| clip.name == name) {
+            Some(clip) => {
+                clip.looping = looping;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Skins the mesh on the cpu into a static mesh in `pose`, used to bake poses for instancing
+    pub fn posed_mesh(&self, pose: &Pose) -> Mesh {
+        let bones = self.skeleton.bone_matrices(pose);
+        let vertices = self
+            .mesh
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let mut skin = Matrix4::zeros();
+                for (joint, weight) in vertex.joints.iter().zip(vertex.weights.iter()) {
+                    if let Some(bone) = bones.get(*joint as usize) {
+                        skin += bone * *weight;
+                    }
+                }
+                let position = skin.transform_point(&vertex.position.into()).coords;
+                (position, vertex.uv)
+            })
+            .collect();
+        Mesh { vertices }
+    }
+}
+/// Character close enough to the camera to be skinned on the gpu this frame
+struct SkinnedItem {
+    instance: Instance,
+    bones: Vec<Matrix4<f32>>,
+    bounds: BoundingSphere,
+}
+/// Draws every copy of a character. Near characters are skinned on the gpu with their own bone
+/// matrices while distant ones use poses baked when the renderer is made.
+pub struct CharacterRenderer<E: GraphicsEngine> {
+    character: Rc<Character>,
+    mesh: E::RuntimeMesh,
+    texture: E::RuntimeTexture,
+    /// Posed mesh of each baked frame, by clip
+    baked: Vec<Vec<E::RuntimeMesh>>,
+    bounds: BoundingSphere,
+    skinned: Vec<SkinnedItem>,
+}
+impl<E: GraphicsEngine> CharacterRenderer<E> {
+    pub fn new(engine: &mut E, character: Rc<Character>) -> Result<Self, E::ErrorType> {
+        let mut baked = vec![];
+        for clip in character.clips.iter() {
+            let mut frames = vec![];
+            for frame in 0..Self::frame_count(clip) {
+                let time = (frame as f32 / BAKED_FRAME_RATE).min(clip.duration);
+                let pose = clip.sample_local(&character.skeleton, time);
+                frames.push(engine.build_mesh(character.posed_mesh(&pose))?);
+            }
+            baked.push(frames);
+        }
+        let points: Vec<Vector3<f32>> = character
+            .mesh
+            .vertices
+            .iter()
+            .map(|vertex| vertex.position)
+            .collect();
+        //poses can stretch limbs past the bind pose so the bounds get some room
+        let mut bounds = BoundingSphere::from_points(&points);
+        bounds.radius *= 1.5;
+        Ok(Self {
+            mesh: engine.build_skinned_mesh(character.mesh.clone())?,
+            texture: engine
+                .build_texture(character.texture.clone(), TextureOptions::pixelated())?,
+            baked,
+            bounds,
+            skinned: vec![],
+            character,
+        })
+    }
+    /// Frames baked for a clip, ending with its last pose so clips played once can hold it
+    fn frame_count(clip: &AnimationClip) -> usize {
+        (clip.duration * BAKED_FRAME_RATE).ceil() as usize + 1
+    }
+    /// Queues one copy of the character posed by `animator`. Distant copies go into the render
+    /// queue with the baked frame closest to their pose, cross fades are skipped for them.
+    pub fn queue(
+        &mut self,
+        animator: &Animator,
+        transform: Matrix4<f32>,
+        tint: Vector4<f32>,
+        camera_position: Vector3<f32>,
+        material: &E::Material,
+        queue: &mut RenderQueue<E::RuntimeMesh, E::RuntimeTexture, E::Material>,
+    ) {
+        let bounds = self.bounds.transform(&transform);
+        let baked = self.character.clips.get(animator.clip()).and_then(|clip| {
+            let time = if animator.plays_once() {
+                animator.time().clamp(0.0, clip.duration)
+            } else {
+                clip.local_time(animator.time())
+            };
+            let frame = (time * BAKED_FRAME_RATE).round() as usize;
+            let frames = &self.baked[animator.clip()];
+            frames.get(frame.min(frames.len() - 1))
+        });
+        match baked {
+            Some(mesh) if (bounds.center - camera_position).norm() > SKINNED_DISTANCE => queue
+                .push(DrawItem {
+                    mesh: mesh.clone(),
+                    texture: self.texture.clone(),
+                    material: material.clone(),
+                    transform,
+                    tint,
+                    layer: 0,
+                    blend: BlendMode::Opaque,
+                    bounds: Some(self.bounds.clone()),
+                }),
+            _ => {
+                let pose = animator.pose(&self.character.skeleton, &self.character.clips);
+                self.skinned.push(SkinnedItem {
+                    instance: Instance { transform, tint },
+                    bones: self.character.skeleton.bone_matrices(&pose),
+                    bounds,
+                });
+            }
+        }
+    }
+    /// Draws the near characters queued this frame that are inside `view_projection`. Also used
+    /// with a skinned shadow depth material to draw their shadows.
+    pub fn draw(&self, engine: &mut E, view_projection: Matrix4<f32>, material: &E::Material) {
+        let frustum = Frustum::new(&view_projection);
+        engine.set_blend_mode(BlendMode::Opaque);
+        engine.bind_material(material);
+        engine.send_view_matrix(view_projection);
+        engine.send_uniform("alpha_cutoff", UniformValue::Float(0.0));
+        engine.bind_texture(&self.texture);
+        for item in self.skinned.iter() {
+            if frustum.intersects(&item.bounds) {
+                engine.send_uniform("bones", UniformValue::Mat4Array(item.bones.clone()));
+                engine.draw_instanced(&self.mesh, std::slice::from_ref(&item.instance));
+            }
+        }
+    }
+    /// Forgets the characters queued this frame
+    pub fn clear(&mut self) {
+        self.skinned.clear();
+    }
+}
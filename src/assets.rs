@@ -0,0 +1,538 @@
+use super::animation::Character;
+use super::model_loader;
+use super::{Mesh, Model, RGBATexture, TextureOptions};
+use generational_arena::{Arena, Index};
+use nalgebra::{Vector2, Vector3, Vector4};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::rc::Rc;
+/// Files compiled into the game, served by `EmbeddedSource`
+const EMBEDDED: &[(&str, &[u8])] = &[
+    ("built_in/skier.glb", include_bytes!("../skier.glb")),
+    ("built_in/snow.png", include_bytes!("../snow.png")),
+];
+/// Assets made from the files compiled into the game
+pub const BUILT_IN_MANIFEST: &str = "
+bundle built_in
+skier built_in/skier.glb
+snow built_in/snow.png
+";
+/// Handle to an asset in an `AssetManager`, valid until its last reference is released
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssetId(Index);
+/// Decoded contents of an asset
+#[derive(Clone)]
+pub enum AssetData {
+    Texture(RGBATexture),
+    /// One model per material
+    Models(Vec<Model>),
+    /// Rigged model with its animations, from a glTF file with a skin
+    Character(Rc<Character>),
+}
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssetState {
+    /// Some of the asset's files have not arrived yet
+    Loading,
+    Loaded,
+    Failed(String),
+}
+/// Reads the files of assets without blocking the game
+pub trait AssetSource {
+    /// Starts reading `path`. The result is returned by a later call to `poll`.
+    fn request(&mut self, path: &str);
+    /// Files that finished reading since the last poll, with their path
+    fn poll(&mut self) -> Vec<(String, Result<Vec<u8>, String>)>;
+}
+/// Fetches files from the server the page was loaded from, relative to `base_url`
+#[cfg(target_arch = "wasm32")]
+pub struct FetchSource {
+    base_url: String,
+    finished: std::rc::Rc<std::cell::RefCell<Vec<(String, Result<Vec<u8>, String>)>>>,
+}
+#[cfg(target_arch = "wasm32")]
+impl FetchSource {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            finished: Default::default(),
+        }
+    }
+    async fn fetch(url: String) -> Result<Vec<u8>, String> {
+        use wasm_bindgen::JsCast;
+        use wasm_bindgen_futures::JsFuture;
+        let window = web_sys::window().ok_or_else(|| "no window".to_string())?;
+        let response: web_sys::Response = JsFuture::from(window.fetch_with_str(&url))
+            .await
+            .map_err(|e| format!("{:?}", e))?
+            .dyn_into()
+            .map_err(|_| "fetch did not return a response".to_string())?;
+        if !response.ok() {
+            return Err(format!("{} returned status {}", url, response.status()));
+        }
+        let buffer = response.array_buffer().map_err(|e| format!("{:?}", e))?;
+        let buffer = JsFuture::from(buffer)
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+    }
+}
+#[cfg(target_arch = "wasm32")]
+impl AssetSource for FetchSource {
+    fn request(&mut self, path: &str) {
+        let finished = self.finished.clone();
+        let path = path.to_string();
+        let url = format!("{}{}", self.base_url, path);
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = Self::fetch(url).await;
+            finished.borrow_mut().push((path, result));
+        });
+    }
+    fn poll(&mut self) -> Vec<(String, Result<Vec<u8>, String>)> {
+        self.finished.borrow_mut().drain(..).collect()
+    }
+}
+/// Serves the files compiled into the game on the next poll and passes requests for any other file
+/// on to `inner`
+pub struct EmbeddedSource {
+    inner: Box<dyn AssetSource>,
+    finished: Vec<(String, Result<Vec<u8>, String>)>,
+}
+impl EmbeddedSource {
+    pub fn new(inner: Box<dyn AssetSource>) -> Self {
+        Self {
+            inner,
+            finished: vec![],
+        }
+    }
+}
+impl AssetSource for EmbeddedSource {
+    fn request(&mut self, path: &str) {
+        match EMBEDDED.iter().find(|(embedded, _)| *embedded == path) {
+            Some((_, bytes)) => self.finished.push((path.to_string(), Ok(bytes.to_vec()))),
+            None => self.inner.request(path),
+        }
+    }
+    fn poll(&mut self) -> Vec<(String, Result<Vec<u8>, String>)> {
+        let mut finished = std::mem::take(&mut self.finished);
+        finished.extend(self.inner.poll());
+        finished
+    }
+}
+/// Reads files below `root` on background threads
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileSource {
+    root: std::path::PathBuf,
+    sender: std::sync::mpsc::Sender<(String, Result<Vec<u8>, String>)>,
+    receiver: std::sync::mpsc::Receiver<(String, Result<Vec<u8>, String>)>,
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl FileSource {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        Self {
+            root: root.as_ref().to_path_buf(),
+            sender,
+            receiver,
+        }
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl AssetSource for FileSource {
+    fn request(&mut self, path: &str) {
+        let sender = self.sender.clone();
+        let file = self.root.join(path);
+        let path = path.to_string();
+        std::thread::spawn(move || {
+            let result = std::fs::read(&file).map_err(|e| format!("{}: {}", file.display(), e));
+            //the manager may have been dropped, in which case nobody wants the file
+            sender.send((path, result)).ok();
+        });
+    }
+    fn poll(&mut self) -> Vec<(String, Result<Vec<u8>, String>)> {
+        self.receiver.try_iter().collect()
+    }
+}
+/// Asset listed in a manifest, made from one or more files. The first file decides how the asset is
+/// decoded and the rest are files it uses, such as the material library of an obj.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestAsset {
+    pub id: String,
+    pub files: Vec<String>,
+}
+/// Assets loaded and unloaded together, such as everything needed by one kind of building
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bundle {
+    pub name: String,
+    pub assets: Vec<ManifestAsset>,
+}
+/// Lists the bundles of assets the game can load. Each bundle starts with a `bundle <name>` line
+/// followed by one `<id> <file> <other files>` line per asset. Lines starting with `#` are
+/// comments.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Manifest {
+    pub bundles: Vec<Bundle>,
+}
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut bundles: Vec<Bundle> = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (None, _) => {}
+                (Some(word), _) if word.starts_with('#') => {}
+                (Some("bundle"), Some(name)) => bundles.push(Bundle {
+                    name: name.to_string(),
+                    assets: vec![],
+                }),
+                (Some(id), Some(file)) => {
+                    let bundle = bundles.last_mut().ok_or_else(|| {
+                        format!("line {}: asset \"{}\" is not in a bundle", number + 1, id)
+                    })?;
+                    bundle.assets.push(ManifestAsset {
+                        id: id.to_string(),
+                        files: std::iter::once(file)
+                            .chain(words)
+                            .map(|file| file.to_string())
+                            .collect(),
+                    });
+                }
+                (Some(word), None) => {
+                    return Err(format!("line {}: \"{}\" has no file", number + 1, word))
+                }
+            }
+        }
+        Ok(Self { bundles })
+    }
+}
+enum Status {
+    Loading,
+    Loaded(AssetData),
+    Failed(String),
+}
+struct Asset {
+    /// Path of the first file, used to find the asset when it is requested again. Inserted assets
+    /// also have a hash of their files.
+    key: String,
+    files: Vec<String>,
+    /// Contents of each file once it has arrived
+    bytes: Vec<Option<Vec<u8>>>,
+    status: Status,
+    references: u32,
+}
+/// Loads assets from an `AssetSource` and shares them. Requesting an asset that is already loaded
+/// or loading returns the same handle, and an asset is unloaded once every request for it has
+/// been released.
+pub struct AssetManager {
+    source: Box<dyn AssetSource>,
+    assets: Arena<Asset>,
+    /// Asset of each key
+    keys: HashMap<String, AssetId>,
+    /// Files of each manifest id
+    manifest_ids: HashMap<String, Vec<String>>,
+    bundles: HashMap<String, Vec<String>>,
+    /// Handles held by each loaded bundle
+    loaded_bundles: HashMap<String, Vec<AssetId>>,
+    /// Assets waiting on each requested file, with the index of the file in the asset
+    waiting: HashMap<String, Vec<(AssetId, usize)>>,
+    /// Assets that finished loading since the last call to `take_loaded`
+    loaded: Vec<AssetId>,
+    /// Assets released since the last call to `take_unloaded`, with their key
+    unloaded: Vec<(AssetId, String)>,
+}
+impl AssetManager {
+    pub fn new(source: Box<dyn AssetSource>) -> Self {
+        Self {
+            source,
+            assets: Arena::new(),
+            keys: HashMap::new(),
+            manifest_ids: HashMap::new(),
+            bundles: HashMap::new(),
+            loaded_bundles: HashMap::new(),
+            waiting: HashMap::new(),
+            loaded: vec![],
+            unloaded: vec![],
+        }
+    }
+    /// Adds the assets and bundles of a manifest so they can be requested by id
+    pub fn add_manifest(&mut self, manifest: Manifest) {
+        for bundle in manifest.bundles.into_iter() {
+            let ids = bundle.assets.iter().map(|asset| asset.id.clone()).collect();
+            for asset in bundle.assets.into_iter() {
+                self.manifest_ids.insert(asset.id, asset.files);
+            }
+            self.bundles.insert(bundle.name, ids);
+        }
+    }
+    /// Gets a handle to the asset with the manifest id `name`, or to the file at path `name` if no
+    /// asset has that id. The asset starts loading if it is not loaded already. Every request
+    /// should be matched by a call to `release`.
+    pub fn request(&mut self, name: &str) -> AssetId {
+        let files = self
+            .manifest_ids
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| vec![name.to_string()]);
+        if let Some(id) = self.retain_key(&files[0]) {
+            return id;
+        }
+        let id = self.insert_asset(&files[0], &files, vec![None; files.len()]);
+        for (index, file) in files.iter().enumerate() {
+            let waiting = self.waiting.entry(file.clone()).or_default();
+            //files shared by several assets are only read once
+            if waiting.is_empty() {
+                self.source.request(file);
+            }
+            waiting.push((id, index));
+        }
+        id
+    }
+    /// Adds an asset from files that are already in memory, such as files fetched by the page. The
+    /// first file decides how the asset is decoded. Assets are keyed by their first path and a hash
+    /// of every file, so inserting the same files again shares the asset while different files
+    /// under the same path make a new one.
+    pub fn insert(&mut self, files: Vec<(String, Vec<u8>)>) -> Result<AssetId, String> {
+        let path = files
+            .first()
+            .map(|(path, _)| path.clone())
+            .ok_or_else(|| "an asset needs at least one file".to_string())?;
+        let mut hasher = DefaultHasher::new();
+        files.hash(&mut hasher);
+        let key = format!("{}#{:016x}", path, hasher.finish());
+        if let Some(id) = self.retain_key(&key) {
+            return Ok(id);
+        }
+        let (paths, bytes): (Vec<String>, Vec<Vec<u8>>) = files.into_iter().unzip();
+        let id = self.insert_asset(&key, &paths, bytes.into_iter().map(Some).collect());
+        self.decode(id);
+        Ok(id)
+    }
+    fn retain_key(&mut self, key: &str) -> Option<AssetId> {
+        let id = *self.keys.get(key)?;
+        self.retain(id);
+        Some(id)
+    }
+    fn insert_asset(
+        &mut self,
+        key: &str,
+        files: &[String],
+        bytes: Vec<Option<Vec<u8>>>,
+    ) -> AssetId {
+        let id = AssetId(self.assets.insert(Asset {
+            key: key.to_string(),
+            files: files.to_vec(),
+            bytes,
+            status: Status::Loading,
+            references: 1,
+        }));
+        self.keys.insert(key.to_string(), id);
+        id
+    }
+    /// Adds a reference to an asset, for example when a handle is stored in a second place
+    pub fn retain(&mut self, id: AssetId) {
+        if let Some(asset) = self.assets.get_mut(id.0) {
+            asset.references += 1;
+        }
+    }
+    /// Drops a reference to an asset. Returns true if it was the last one and the asset was
+    /// unloaded.
+    pub fn release(&mut self, id: AssetId) -> bool {
+        let asset = match self.assets.get_mut(id.0) {
+            Some(asset) => asset,
+            None => return false,
+        };
+        asset.references -= 1;
+        if asset.references > 0 {
+            return false;
+        }
+        let asset = self.assets.remove(id.0).unwrap();
+        self.keys.remove(&asset.key);
+        //files still being read are dropped when they arrive
+        for waiting in self.waiting.values_mut() {
+            waiting.retain(|(waiting_id, _)| *waiting_id != id);
+        }
+        self.loaded.retain(|loaded| *loaded != id);
+        self.unloaded.push((id, asset.key));
+        true
+    }
+    /// Requests every asset of a bundle. Loading a bundle twice does nothing.
+    pub fn load_bundle(&mut self, name: &str) -> Result<(), String> {
+        if self.loaded_bundles.contains_key(name) {
+            return Ok(());
+        }
+        let ids = self
+            .bundles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("bundle \"{}\" not found", name))?;
+        let handles = ids.iter().map(|id| self.request(id)).collect();
+        self.loaded_bundles.insert(name.to_string(), handles);
+        Ok(())
+    }
+    /// Releases the assets requested by `load_bundle`. Assets also requested elsewhere stay
+    /// loaded. Returns false if the bundle was not loaded.
+    pub fn unload_bundle(&mut self, name: &str) -> bool {
+        match self.loaded_bundles.remove(name) {
+            Some(handles) => {
+                for id in handles.into_iter() {
+                    self.release(id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+    /// Collects the files that have arrived and decodes assets that have all of their files
+    pub fn update(&mut self) {
+        for (path, result) in self.source.poll().into_iter() {
+            let waiting = match self.waiting.remove(&path) {
+                Some(waiting) => waiting,
+                None => continue,
+            };
+            for (id, index) in waiting.into_iter() {
+                let asset = match self.assets.get_mut(id.0) {
+                    Some(asset) => asset,
+                    None => continue,
+                };
+                match &result {
+                    Ok(bytes) => asset.bytes[index] = Some(bytes.clone()),
+                    Err(e) => asset.status = Status::Failed(e.clone()),
+                }
+                self.decode(id);
+            }
+        }
+    }
+    /// Decodes an asset once all of its files have arrived. The file bytes are dropped afterwards.
+    fn decode(&mut self, id: AssetId) {
+        let asset = match self.assets.get_mut(id.0) {
+            Some(asset) => asset,
+            None => return,
+        };
+        if !matches!(asset.status, Status::Loading) || asset.bytes.iter().any(Option::is_none) {
+            return;
+        }
+        let bytes: Vec<Vec<u8>> = asset.bytes.drain(..).flatten().collect();
+        let extension = Path::new(&asset.files[0])
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let data = match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "tga" => {
+                RGBATexture::from_extension(&bytes[0], &extension).map(AssetData::Texture)
+            }
+            "glb" | "gltf" if model_loader::is_character(&bytes[0]) => {
+                model_loader::load_character(&bytes[0])
+                    .map(|character| AssetData::Character(Rc::new(character)))
+            }
+            "glb" | "gltf" => model_loader::load_gltf(&bytes[0]).map(AssetData::Models),
+            "obj" => {
+                //the obj finds its other files by file name
+                let files = asset.files[1..]
+                    .iter()
+                    .zip(bytes[1..].iter())
+                    .filter_map(|(path, bytes)| {
+                        let name = Path::new(path).file_name()?.to_string_lossy().to_string();
+                        Some((name, bytes.clone()))
+                    })
+                    .collect();
+                model_loader::load_obj(&bytes[0], &files).map(AssetData::Models)
+            }
+            _ => Err(format!("\"{}\" is not a supported asset", asset.files[0])),
+        };
+        asset.status = match data {
+            Ok(data) => {
+                self.loaded.push(id);
+                Status::Loaded(data)
+            }
+            Err(e) => Status::Failed(format!("{}: {}", asset.files[0], e)),
+        };
+    }
+    /// None if the asset has been unloaded
+    pub fn state(&self, id: AssetId) -> Option<AssetState> {
+        self.assets.get(id.0).map(|asset| match &asset.status {
+            Status::Loading => AssetState::Loading,
+            Status::Loaded(_) => AssetState::Loaded,
+            Status::Failed(e) => AssetState::Failed(e.clone()),
+        })
+    }
+    /// Contents of a loaded asset
+    pub fn data(&self, id: AssetId) -> Option<&AssetData> {
+        match &self.assets.get(id.0)?.status {
+            Status::Loaded(data) => Some(data),
+            _ => None,
+        }
+    }
+    /// Path of the first file of an asset followed by a hash of its files for inserted assets
+    pub fn key(&self, id: AssetId) -> Option<&str> {
+        self.assets.get(id.0).map(|asset| asset.key.as_str())
+    }
+    /// Number of requests holding an asset
+    pub fn references(&self, id: AssetId) -> u32 {
+        self.assets
+            .get(id.0)
+            .map(|asset| asset.references)
+            .unwrap_or(0)
+    }
+    pub fn loading_count(&self) -> usize {
+        self.assets
+            .iter()
+            .filter(|(_, asset)| matches!(asset.status, Status::Loading))
+            .count()
+    }
+    /// Assets that finished loading since the last call, used to upload them to the gpu
+    pub fn take_loaded(&mut self) -> Vec<AssetId> {
+        std::mem::take(&mut self.loaded)
+    }
+    /// Assets unloaded since the last call with their key, used to free their gpu resources
+    pub fn take_unloaded(&mut self) -> Vec<(AssetId, String)> {
+        std::mem::take(&mut self.unloaded)
+    }
+}
+/// Grey and magenta checkered box one unit wide standing on the origin, shown in place of models
+/// that are still loading or failed to load
+pub fn placeholder_model() -> Model {
+    let corner = |i: usize| {
+        Vector3::new(
+            if i & 1 == 0 { -0.5 } else { 0.5 },
+            if i & 2 == 0 { 0.0 } else { 1.0 },
+            if i & 4 == 0 { -0.5 } else { 0.5 },
+        )
+    };
+    let uv = [
+        Vector2::new(0.0, 0.0),
+        Vector2::new(1.0, 0.0),
+        Vector2::new(1.0, 1.0),
+        Vector2::new(0.0, 1.0),
+    ];
+    //corners of each side in counter clockwise order seen from outside
+    let sides = [
+        [0, 1, 3, 2],
+        [4, 6, 7, 5],
+        [0, 2, 6, 4],
+        [1, 5, 7, 3],
+        [0, 4, 5, 1],
+        [2, 3, 7, 6],
+    ];
+    let mut vertices = vec![];
+    for side in sides.iter() {
+        for i in [0, 1, 2, 0, 2, 3].iter() {
+            vertices.push((corner(side[*i]), uv[*i]));
+        }
+    }
+    let mut texture =
+        RGBATexture::constant_color(Vector4::new(128, 128, 128, 255), Vector2::new(4, 4));
+    for x in 0..4 {
+        for y in 0..4 {
+            if (x + y) % 2 == 0 {
+                texture.set_pixel(x, y, Vector4::new(255, 0, 255, 255));
+            }
+        }
+    }
+    Model {
+        mesh: Mesh { vertices },
+        texture,
+        texture_options: TextureOptions::pixelated(),
+        shared_texture: None,
+    }
+}
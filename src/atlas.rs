@@ -0,0 +1,221 @@
+use super::{Mesh, Model, RGBATexture, TextureOptions, TextureWrap};
+use nalgebra::{Vector2, Vector4};
+/// Largest atlas width that is used unless `AtlasBuilder::with_max_width` is called. Every WebGL2
+/// implementation supports textures at least this large.
+const DEFAULT_MAX_WIDTH: u32 = 4096;
+/// Identifies a texture added to an `AtlasBuilder`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AtlasId(usize);
+/// Where a texture was placed in an atlas
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasRegion {
+    /// Bottom left pixel of the region
+    pub min: Vector2<u32>,
+    /// One past the top right pixel of the region
+    pub max: Vector2<u32>,
+    pub uv_min: Vector2<f32>,
+    pub uv_max: Vector2<f32>,
+}
+impl AtlasRegion {
+    /// Maps a texture coordinate of the original texture to the atlas
+    pub fn to_atlas_uv(&self, uv: Vector2<f32>) -> Vector2<f32> {
+        self.uv_min + (self.uv_max - self.uv_min).component_mul(&uv)
+    }
+    /// Maps all texture coordinates of a mesh built for the original texture. Coordinates outside of
+    /// 0 to 1 will sample neighboring textures as an atlas can not repeat.
+    pub fn remap_mesh(&self, mesh: &Mesh) -> Mesh {
+        Mesh {
+            vertices: mesh
+                .vertices
+                .iter()
+                .map(|(position, uv)| (*position, self.to_atlas_uv(*uv)))
+                .collect(),
+        }
+    }
+}
+/// Many textures packed into one so they can be drawn without switching textures
+#[derive(Clone)]
+pub struct TextureAtlas {
+    pub texture: RGBATexture,
+    regions: Vec<AtlasRegion>,
+}
+impl TextureAtlas {
+    pub fn region(&self, id: AtlasId) -> &AtlasRegion {
+        &self.regions[id.0]
+    }
+    pub fn remap_mesh(&self, id: AtlasId, mesh: &Mesh) -> Mesh {
+        self.region(id).remap_mesh(mesh)
+    }
+}
+/// Packs textures into a `TextureAtlas` using shelf packing.
+pub struct AtlasBuilder {
+    textures: Vec<RGBATexture>,
+    padding: u32,
+    max_width: u32,
+}
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        Self {
+            textures: vec![],
+            padding: 1,
+            max_width: DEFAULT_MAX_WIDTH,
+        }
+    }
+    /// Space around each texture that is filled by extending its edge pixels. Keeps linear filtering
+    /// and mipmapping from blending in neighboring textures.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+    pub fn with_max_width(mut self, max_width: u32) -> Self {
+        self.max_width = max_width;
+        self
+    }
+    pub fn add(&mut self, texture: RGBATexture) -> AtlasId {
+        self.textures.push(texture);
+        AtlasId(self.textures.len() - 1)
+    }
+    /// Packs all added textures. Fails if a texture is wider than the maximum width.
+    pub fn build(self) -> Result<TextureAtlas, String> {
+        let padded_size =
+            |texture: &RGBATexture| texture.dimensions() + Vector2::repeat(2 * self.padding);
+        let widest = self
+            .textures
+            .iter()
+            .map(|t| padded_size(t).x)
+            .max()
+            .unwrap_or(1);
+        if widest > self.max_width {
+            return Err(format!(
+                "texture of width {} does not fit in atlas of width {}",
+                widest, self.max_width
+            ));
+        }
+        let area: u32 = self
+            .textures
+            .iter()
+            .map(|t| {
+                let size = padded_size(t);
+                size.x * size.y
+            })
+            .sum();
+        let width = ((area as f32).sqrt().ceil() as u32)
+            .max(widest)
+            .next_power_of_two()
+            .min(self.max_width);
+        //tallest textures first so each shelf wastes little space
+        let mut order: Vec<usize> = (0..self.textures.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse(padded_size(&self.textures[*i]).y));
+        let mut positions = vec![Vector2::new(0, 0); self.textures.len()];
+        let mut cursor = Vector2::new(0, 0);
+        let mut shelf_height = 0;
+        for i in order {
+            let size = padded_size(&self.textures[i]);
+            if cursor.x + size.x > width {
+                cursor = Vector2::new(0, cursor.y + shelf_height);
+                shelf_height = 0;
+            }
+            positions[i] = cursor;
+            cursor.x += size.x;
+            shelf_height = shelf_height.max(size.y);
+        }
+        let height = (cursor.y + shelf_height).max(1).next_power_of_two();
+        let dimensions = Vector2::new(width, height);
+        let mut atlas = RGBATexture::constant_color(Vector4::new(0, 0, 0, 0), dimensions);
+        let mut regions = vec![];
+        for (texture, position) in self.textures.iter().zip(positions.iter()) {
+            let min = position + Vector2::repeat(self.padding);
+            let max = min + texture.dimensions();
+            Self::blit_with_bleed(&mut atlas, texture, *position, self.padding);
+            regions.push(AtlasRegion {
+                min,
+                max,
+                uv_min: Vector2::new(min.x as f32 / width as f32, min.y as f32 / height as f32),
+                uv_max: Vector2::new(max.x as f32 / width as f32, max.y as f32 / height as f32),
+            });
+        }
+        Ok(TextureAtlas {
+            texture: atlas,
+            regions,
+        })
+    }
+    /// Copies `texture` with its padded corner at `position`, repeating edge pixels into the padding
+    fn blit_with_bleed(
+        atlas: &mut RGBATexture,
+        texture: &RGBATexture,
+        position: Vector2<u32>,
+        padding: u32,
+    ) {
+        let dimensions = texture.dimensions();
+        if dimensions.x == 0 || dimensions.y == 0 {
+            return;
+        }
+        for y in 0..dimensions.y + 2 * padding {
+            for x in 0..dimensions.x + 2 * padding {
+                let source_x = x.saturating_sub(padding).min(dimensions.x - 1);
+                let source_y = y.saturating_sub(padding).min(dimensions.y - 1);
+                atlas.set_pixel(
+                    position.x + x,
+                    position.y + y,
+                    texture.get_pixel(source_x, source_y),
+                );
+            }
+        }
+    }
+}
+/// Packs the textures of models into atlases so they are drawn with one texture. Models are
+/// grouped by texture options and each group gets an atlas shared under the name
+/// `"{name}_{group}"`. Models with repeating textures or texture coordinates outside of 0 to 1 keep
+/// their own texture, as do models alone in their group.
+pub fn pack_models(models: Vec<Model>, name: &str) -> Vec<Model> {
+    let fits = |model: &Model| {
+        let dimensions = model.texture.dimensions();
+        model.shared_texture.is_none()
+            && model.texture_options.wrap_s == TextureWrap::ClampToEdge
+            && model.texture_options.wrap_t == TextureWrap::ClampToEdge
+            && dimensions.x + 2 <= DEFAULT_MAX_WIDTH
+            && model
+                .mesh
+                .vertices
+                .iter()
+                .all(|(_, uv)| (0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y))
+    };
+    let mut groups: Vec<(TextureOptions, Vec<usize>)> = vec![];
+    for (index, model) in models.iter().enumerate().filter(|(_, model)| fits(model)) {
+        match groups
+            .iter_mut()
+            .find(|(options, _)| *options == model.texture_options)
+        {
+            Some((_, members)) => members.push(index),
+            None => groups.push((model.texture_options, vec![index])),
+        }
+    }
+    let mut models: Vec<Option<Model>> = models.into_iter().map(Some).collect();
+    for (group, (_, members)) in groups.iter().enumerate().filter(|(_, (_, m))| m.len() > 1) {
+        let mut builder = AtlasBuilder::new();
+        let ids: Vec<AtlasId> = members
+            .iter()
+            .map(|index| builder.add(models[*index].as_ref().unwrap().texture.clone()))
+            .collect();
+        let atlas = match builder.build() {
+            Ok(atlas) => atlas,
+            Err(_) => continue,
+        };
+        let shared_texture = format!("{}_{}", name, group);
+        for (index, id) in members.iter().zip(ids) {
+            let model = models[*index].take().unwrap();
+            models[*index] = Some(Model {
+                mesh: atlas.remap_mesh(id, &model.mesh),
+                texture: atlas.texture.clone(),
+                texture_options: model.texture_options,
+                shared_texture: Some(shared_texture.clone()),
+            });
+        }
+    }
+    models.into_iter().flatten().collect()
+}
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
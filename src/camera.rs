@@ -1,5 +1,7 @@
-use super::log;
 use nalgebra::{Matrix4, Point, Vector3};
+/// Largest angle above or below the horizon. Looking straight up or down leaves no direction for
+/// the sides of the screen.
+const MAX_THETA: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
 pub struct Camera {
     /// Origin about which camera rotates
     origin: Vector3<f32>,
@@ -11,41 +13,48 @@ pub struct Camera {
 }
 impl Camera {
     pub fn new(origin: Vector3<f32>, radius: f32, phi: f32, theta: f32) -> Self {
-        log(&format!(
-            "origin: {}, radius: {} phi: {} theta: {}",
-            origin, radius, phi, theta
-        ));
         Self {
             origin,
             radius,
             phi,
-            theta,
+            theta: theta.clamp(-MAX_THETA, MAX_THETA),
         }
     }
     pub fn rotate_phi(&mut self, delta_phi: f32) {
         self.phi += delta_phi;
     }
     pub fn rotate_theta(&mut self, delta_theta: f32) {
-        self.theta += delta_theta;
+        self.theta = (self.theta + delta_theta).clamp(-MAX_THETA, MAX_THETA);
     }
     /// Increases by value proportional to delta radius
     pub fn update_radius(&mut self, delta_radius: f32) {
         self.radius += delta_radius * self.radius;
     }
+    /// Where the camera is in the world
+    pub fn position(&self) -> Vector3<f32> {
+        self.origin
+            + self.radius
+                * Vector3::new(
+                    self.phi.cos() * self.theta.cos(),
+                    self.theta.sin(),
+                    (self.phi).sin() * self.theta.cos(),
+                )
+    }
+    /// Right and up directions of the screen in the world, used to draw things facing the camera
+    pub fn screen_axes(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let forward = (self.origin - self.position()).normalize();
+        let right = forward.cross(&Vector3::new(0.0, 1.0, 0.0)).normalize();
+        (right, right.cross(&forward))
+    }
+    /// View projection matrix looking from `position` at the origin the camera orbits. The eye
+    /// is offset from the origin, so moving the origin moves the camera with it.
     pub fn get_mat(&self) -> Matrix4<f32> {
-        let delta_position = self.radius
-            * Vector3::new(
-                self.phi.cos() * self.theta.cos(),
-                self.theta.sin(),
-                (self.phi).sin() * self.theta.cos(),
-            );
         let face = Matrix4::look_at_rh(
-            &Point::from(delta_position),
+            &Point::from(self.position()),
             &Point::from(self.origin),
             &Vector3::new(0.0, 1.0, 0.0),
         );
-        let cam = Matrix4::new_perspective(1.0, 3.14 / 3.0, 0.1, 100.0);
-        let mat = cam * face;
-        mat
+        let cam = Matrix4::new_perspective(1.0, std::f32::consts::PI / 3.0, 0.1, 100.0);
+        cam * face
     }
 }
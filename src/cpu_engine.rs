@@ -0,0 +1,685 @@
+use super::graphics_engine::{
+    fits, BlendMode, GpuResource, GraphicsEngine, Instance, LineVertex, Mesh, RenderStats,
+    ResourceReport, ShaderDescription, SkinnedMesh, TextureFilter, TextureOptions, TextureWrap,
+    UniformValue,
+};
+use super::RGBATexture;
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+/// Runs in place of a fragment shader, returning the color of one fragment
+pub type FragmentProgram = Rc<dyn Fn(&Fragment) -> Vector4<f32>>;
+/// What a `FragmentProgram` can read, like the inputs of a fragment shader
+pub struct Fragment<'a> {
+    /// Interpolated texture coordinate of the mesh
+    pub uv: Vector2<f32>,
+    /// Tint of the instance being drawn
+    pub tint: Vector4<f32>,
+    uniforms: &'a HashMap<String, UniformValue>,
+    textures: &'a HashMap<u32, CpuTexture>,
+}
+impl<'a> Fragment<'a> {
+    pub fn uniform(&self, name: &str) -> Option<&UniformValue> {
+        self.uniforms.get(name)
+    }
+    /// Value of a float uniform, 0 if it was never sent like in glsl
+    pub fn float(&self, name: &str) -> f32 {
+        match self.uniform(name) {
+            Some(UniformValue::Float(value)) => *value,
+            _ => 0.0,
+        }
+    }
+    /// Texture the sampler uniform points at, unit 0 if it was never sent like in glsl
+    fn texture(&self, sampler: &str) -> Option<&CpuTexture> {
+        let unit = match self.uniform(sampler) {
+            Some(UniformValue::Texture(unit)) => *unit,
+            _ => 0,
+        };
+        self.textures.get(&unit)
+    }
+    /// Samples the texture bound to the sampler, like `texture` in glsl. Missing or deleted
+    /// textures are black.
+    pub fn sample(&self, sampler: &str, uv: Vector2<f32>) -> Vector4<f32> {
+        match self.texture(sampler) {
+            Some(texture) => texture.data.borrow().sample(uv),
+            None => Vector4::new(0.0, 0.0, 0.0, 1.0),
+        }
+    }
+    /// Size of one texel of the texture bound to the sampler, `1.0/textureSize` in glsl
+    pub fn texel(&self, sampler: &str) -> Vector2<f32> {
+        let dimensions = self
+            .texture(sampler)
+            .map(|texture| texture.data.borrow().dimensions)
+            .unwrap_or_else(|| Vector2::new(1, 1));
+        Vector2::new(1.0 / dimensions.x as f32, 1.0 / dimensions.y as f32)
+    }
+}
+/// Counts a resource as live until it is deleted or dropped, see `GlOwner`
+struct Owner {
+    live: Rc<Cell<ResourceReport>>,
+    count: fn(&mut ResourceReport) -> &mut u32,
+    deleted: Cell<bool>,
+}
+impl Owner {
+    fn new(engine: &CpuEngine, count: fn(&mut ResourceReport) -> &mut u32) -> Self {
+        let mut live = engine.live.get();
+        *count(&mut live) += 1;
+        engine.live.set(live);
+        Self {
+            live: engine.live.clone(),
+            count,
+            deleted: Cell::new(false),
+        }
+    }
+    fn release(&self) {
+        if self.deleted.replace(true) {
+            return;
+        }
+        let mut live = self.live.get();
+        *(self.count)(&mut live) -= 1;
+        self.live.set(live);
+    }
+}
+impl Drop for Owner {
+    fn drop(&mut self) {
+        self.release()
+    }
+}
+struct MeshData {
+    vertices: Vec<(Vector3<f32>, Vector2<f32>)>,
+    owner: Owner,
+}
+#[derive(Clone)]
+pub struct CpuMesh {
+    id: u32,
+    data: Rc<MeshData>,
+}
+struct TextureData {
+    dimensions: Vector2<u32>,
+    pixels: Vec<Vector4<f32>>,
+    options: TextureOptions,
+    /// Float textures keep values outside of 0 to 1, others are stored with 8 bits per channel
+    float: bool,
+    /// Unset for the screen, which is not counted as a resource like the default framebuffer
+    owner: Option<Owner>,
+}
+impl TextureData {
+    fn new(
+        engine: &CpuEngine,
+        dimensions: Vector2<u32>,
+        options: TextureOptions,
+        float: bool,
+    ) -> Self {
+        Self {
+            dimensions,
+            pixels: vec![Vector4::new(0.0, 0.0, 0.0, 0.0); (dimensions.x * dimensions.y) as usize],
+            options,
+            float,
+            owner: Some(Owner::new(engine, |live| &mut live.textures)),
+        }
+    }
+    fn store(&mut self, x: u32, y: u32, color: Vector4<f32>) {
+        let color = if self.float {
+            color
+        } else {
+            color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() / 255.0)
+        };
+        self.pixels[(y * self.dimensions.x + x) as usize] = color;
+    }
+    fn fetch(&self, x: i64, y: i64) -> Vector4<f32> {
+        let wrap = |c: i64, size: u32, wrap: TextureWrap| {
+            let size = size as i64;
+            match wrap {
+                TextureWrap::ClampToEdge => c.max(0).min(size - 1),
+                TextureWrap::Repeat => c.rem_euclid(size),
+                TextureWrap::MirroredRepeat => {
+                    let c = c.rem_euclid(2 * size);
+                    if c < size {
+                        c
+                    } else {
+                        2 * size - 1 - c
+                    }
+                }
+            }
+        };
+        let x = wrap(x, self.dimensions.x, self.options.wrap_s);
+        let y = wrap(y, self.dimensions.y, self.options.wrap_t);
+        self.pixels[(y * self.dimensions.x as i64 + x) as usize]
+    }
+    /// Samples the base level with the magnification filter, mipmaps are not kept
+    fn sample(&self, uv: Vector2<f32>) -> Vector4<f32> {
+        let deleted = self.owner.as_ref().is_some_and(|owner| owner.deleted.get());
+        if deleted || self.pixels.is_empty() {
+            return Vector4::new(0.0, 0.0, 0.0, 1.0);
+        }
+        let x = uv.x * self.dimensions.x as f32 - 0.5;
+        let y = uv.y * self.dimensions.y as f32 - 0.5;
+        match self.options.mag_filter {
+            TextureFilter::Nearest => self.fetch(x.round() as i64, y.round() as i64),
+            TextureFilter::Linear => {
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let bottom = self.fetch(x0, y0).lerp(&self.fetch(x0 + 1, y0), fx);
+                let top = self.fetch(x0, y0 + 1).lerp(&self.fetch(x0 + 1, y0 + 1), fx);
+                bottom.lerp(&top, fy)
+            }
+        }
+    }
+}
+#[derive(Clone)]
+pub struct CpuTexture {
+    id: u32,
+    is_depth: bool,
+    data: Rc<RefCell<TextureData>>,
+}
+#[derive(Clone)]
+pub struct CpuMaterial {
+    id: u32,
+    program: FragmentProgram,
+    uniforms: Rc<Vec<String>>,
+    values: Rc<RefCell<HashMap<String, UniformValue>>>,
+    _owner: Rc<Owner>,
+}
+impl GpuResource for CpuMesh {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+impl GpuResource for CpuTexture {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+impl GpuResource for CpuMaterial {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+/// Color texture and depth buffer draws go to
+struct Target {
+    texture: CpuTexture,
+    /// Depth of each pixel for color targets. Depth targets keep it in the texture's red channel.
+    depth: RefCell<Vec<f32>>,
+}
+impl Target {
+    fn new(texture: CpuTexture) -> Self {
+        let dimensions = texture.data.borrow().dimensions;
+        let depth = if texture.is_depth {
+            vec![]
+        } else {
+            vec![1.0; (dimensions.x * dimensions.y) as usize]
+        };
+        Self {
+            texture,
+            depth: RefCell::new(depth),
+        }
+    }
+}
+pub struct CpuFramebuffer {
+    target: Rc<Target>,
+    owner: Owner,
+}
+/// Software `GraphicsEngine` that runs fragment programs written in rust in place of shaders, so
+/// code drawing through the engine can be tested without a gpu. Materials are built from the
+/// program added with `CpuEngine::with_program` under the shader's name. Vertices are moved by the
+/// "camera" and "model" uniforms and the instance transform when the material declares them.
+/// Triangles crossing the near plane are skipped, skinned meshes are drawn in their bind pose and
+/// lines are counted but not drawn.
+pub struct CpuEngine {
+    programs: HashMap<String, FragmentProgram>,
+    live: Rc<Cell<ResourceReport>>,
+    next_id: u32,
+    stats: RenderStats,
+    screen: Rc<Target>,
+    bound_target: Rc<Target>,
+    bound_material: Option<CpuMaterial>,
+    textures: HashMap<u32, CpuTexture>,
+    depth_test: bool,
+    blend_mode: BlendMode,
+    /// Pixels draws are limited to as min and max corners
+    clip: Option<(Vector2<u32>, Vector2<u32>)>,
+}
+impl CpuEngine {
+    /// Engine drawing to a screen of the given size
+    pub fn new(screen_dimensions: Vector2<u32>) -> Self {
+        let screen_data = TextureData {
+            dimensions: screen_dimensions,
+            pixels: vec![
+                Vector4::new(0.0, 0.0, 0.0, 0.0);
+                (screen_dimensions.x * screen_dimensions.y) as usize
+            ],
+            options: TextureOptions::default(),
+            float: false,
+            owner: None,
+        };
+        let screen = Rc::new(Target::new(CpuTexture {
+            id: 0,
+            is_depth: false,
+            data: Rc::new(RefCell::new(screen_data)),
+        }));
+        Self {
+            programs: HashMap::new(),
+            live: Rc::new(Cell::new(ResourceReport::default())),
+            next_id: 1,
+            stats: RenderStats::default(),
+            bound_target: screen.clone(),
+            screen,
+            bound_material: None,
+            textures: HashMap::new(),
+            depth_test: false,
+            blend_mode: BlendMode::Opaque,
+            clip: None,
+        }
+    }
+    /// Runs `program` for materials built from shaders named `name`
+    pub fn with_program(mut self, name: &str, program: FragmentProgram) -> Self {
+        self.programs.insert(name.to_string(), program);
+        self
+    }
+    /// Copies what was drawn to the screen
+    pub fn screen(&self) -> RGBATexture {
+        Self::to_rgba(&self.screen.texture.data.borrow())
+    }
+    fn new_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+    fn to_rgba(data: &TextureData) -> RGBATexture {
+        RGBATexture::new(
+            data.dimensions,
+            data.pixels
+                .iter()
+                .map(|p| p.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+                .collect(),
+        )
+    }
+    fn build_texture_data(
+        &mut self,
+        dimensions: Vector2<u32>,
+        options: TextureOptions,
+        float: bool,
+        is_depth: bool,
+    ) -> CpuTexture {
+        let data = TextureData::new(self, dimensions, options, float);
+        CpuTexture {
+            id: self.new_id(),
+            is_depth,
+            data: Rc::new(RefCell::new(data)),
+        }
+    }
+    /// Matrix uniform of the bound material, identity if it was not declared or sent
+    fn matrix(&self, name: &str) -> Matrix4<f32> {
+        let material = match self.bound_material.as_ref() {
+            Some(material) => material,
+            None => return Matrix4::identity(),
+        };
+        match material.values.borrow().get(name) {
+            Some(UniformValue::Mat4(matrix)) => *matrix,
+            _ => Matrix4::identity(),
+        }
+    }
+    /// Bounds of the bound target in pixels, limited by the clip rectangle
+    fn draw_area(&self) -> (Vector2<u32>, Vector2<u32>) {
+        let dimensions = self.bound_target.texture.data.borrow().dimensions;
+        match self.clip {
+            Some((min, max)) => (
+                min.zip_map(&dimensions, |a, b| a.min(b)),
+                max.zip_map(&dimensions, |a, b| a.min(b)),
+            ),
+            None => (Vector2::new(0, 0), dimensions),
+        }
+    }
+    fn draw_triangles(&mut self, mesh: &CpuMesh, instance: &Instance) {
+        let material = match self.bound_material.clone() {
+            Some(material) => material,
+            None => return,
+        };
+        if mesh.data.owner.deleted.get() {
+            return;
+        }
+        let transform = self.matrix("camera") * self.matrix("model") * instance.transform;
+        let target = self.bound_target.clone();
+        let (area_min, area_max) = self.draw_area();
+        let dimensions = target.texture.data.borrow().dimensions.map(|c| c as f32);
+        let values = material.values.borrow();
+        let fragment_textures = &self.textures;
+        let mut texture = target.texture.data.borrow_mut();
+        let mut depth_buffer = target.depth.borrow_mut();
+        for triangle in mesh.data.vertices.chunks_exact(3) {
+            let clip: Vec<Vector4<f32>> = triangle
+                .iter()
+                .map(|(position, _)| transform * position.push(1.0))
+                .collect();
+            if clip.iter().any(|c| c.w <= 0.0) {
+                continue;
+            }
+            let screen: Vec<Vector3<f32>> = clip
+                .iter()
+                .map(|c| {
+                    let ndc = c.xyz() / c.w;
+                    Vector3::new(
+                        (ndc.x + 1.0) * 0.5 * dimensions.x,
+                        (ndc.y + 1.0) * 0.5 * dimensions.y,
+                        ndc.z * 0.5 + 0.5,
+                    )
+                })
+                .collect();
+            let edge = |a: &Vector3<f32>, b: &Vector3<f32>, p: Vector2<f32>| {
+                (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+            };
+            let area = edge(&screen[0], &screen[1], screen[2].xy());
+            if area == 0.0 {
+                continue;
+            }
+            //pixels on an edge shared by two triangles are only drawn by one of them
+            let owns = |a: &Vector3<f32>, b: &Vector3<f32>| {
+                let (a, b) = if area > 0.0 { (a, b) } else { (b, a) };
+                b.y > a.y || (b.y == a.y && b.x < a.x)
+            };
+            let low = screen.iter().fold(Vector2::repeat(f32::MAX), |low, p| {
+                low.zip_map(&p.xy(), f32::min)
+            });
+            let high = screen.iter().fold(Vector2::repeat(f32::MIN), |high, p| {
+                high.zip_map(&p.xy(), f32::max)
+            });
+            let start = low.map(|c| c.floor().max(0.0) as u32);
+            let x_range = start.x.max(area_min.x)..(high.x.ceil().max(0.0) as u32).min(area_max.x);
+            let y_range = start.y.max(area_min.y)..(high.y.ceil().max(0.0) as u32).min(area_max.y);
+            for y in y_range {
+                for x in x_range.clone() {
+                    let p = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    let weights = [
+                        edge(&screen[1], &screen[2], p) / area,
+                        edge(&screen[2], &screen[0], p) / area,
+                        edge(&screen[0], &screen[1], p) / area,
+                    ];
+                    let inside = weights.iter().enumerate().all(|(i, w)| {
+                        *w > 0.0 || (*w == 0.0 && owns(&screen[(i + 1) % 3], &screen[(i + 2) % 3]))
+                    });
+                    if !inside {
+                        continue;
+                    }
+                    let depth: f32 = (0..3).map(|i| weights[i] * screen[i].z).sum();
+                    let pixel = (y * texture.dimensions.x + x) as usize;
+                    let stored_depth = if target.texture.is_depth {
+                        texture.pixels[pixel].x
+                    } else {
+                        depth_buffer[pixel]
+                    };
+                    if self.depth_test && depth >= stored_depth {
+                        continue;
+                    }
+                    if !self.blend_mode.is_transparent() {
+                        if target.texture.is_depth {
+                            texture.store(x, y, Vector4::new(depth, depth, depth, 1.0));
+                        } else {
+                            depth_buffer[pixel] = depth;
+                        }
+                    }
+                    if target.texture.is_depth {
+                        continue;
+                    }
+                    //texture coordinates are interpolated in perspective
+                    let inverse_w: f32 = (0..3).map(|i| weights[i] / clip[i].w).sum();
+                    let uv = (0..3)
+                        .map(|i| triangle[i].1 * (weights[i] / clip[i].w))
+                        .fold(Vector2::new(0.0, 0.0), |sum, uv| sum + uv)
+                        / inverse_w;
+                    let source = (material.program)(&Fragment {
+                        uv,
+                        tint: instance.tint,
+                        uniforms: &values,
+                        textures: fragment_textures,
+                    });
+                    let destination = texture.pixels[pixel];
+                    let alpha = source.w;
+                    let color = match self.blend_mode {
+                        BlendMode::Opaque | BlendMode::Cutout => source,
+                        BlendMode::Alpha => Vector4::new(
+                            source.x * alpha + destination.x * (1.0 - alpha),
+                            source.y * alpha + destination.y * (1.0 - alpha),
+                            source.z * alpha + destination.z * (1.0 - alpha),
+                            alpha + destination.w * (1.0 - alpha),
+                        ),
+                        BlendMode::Additive => destination + source * alpha,
+                        BlendMode::Premultiplied => source + destination * (1.0 - alpha),
+                    };
+                    texture.store(x, y, color);
+                }
+            }
+        }
+    }
+}
+impl GraphicsEngine for CpuEngine {
+    type RuntimeMesh = CpuMesh;
+    type RuntimeTexture = CpuTexture;
+    type ErrorType = String;
+    type Framebuffer = CpuFramebuffer;
+    type Material = CpuMaterial;
+    /// Builds an engine with a 1 by 1 screen, use `CpuEngine::new` for other sizes
+    fn init() -> Result<Self, Self::ErrorType> {
+        Ok(Self::new(Vector2::new(1, 1)))
+    }
+    fn build_material(
+        &mut self,
+        shader: &ShaderDescription,
+    ) -> Result<Self::Material, Self::ErrorType> {
+        let program = self
+            .programs
+            .get(&shader.name)
+            .cloned()
+            .ok_or_else(|| format!("no fragment program for shader \"{}\"", shader.name))?;
+        Ok(CpuMaterial {
+            id: self.new_id(),
+            program,
+            uniforms: Rc::new(shader.uniforms.clone()),
+            values: Rc::new(RefCell::new(HashMap::new())),
+            _owner: Rc::new(Owner::new(self, |live| &mut live.materials)),
+        })
+    }
+    fn bind_material(&mut self, material: &Self::Material) {
+        if self.bound_material.as_ref().map(|m| m.id) == Some(material.id) {
+            return;
+        }
+        self.bound_material = Some(material.clone());
+        self.stats.state_changes += 1;
+    }
+    fn send_uniform(&mut self, name: &str, value: UniformValue) {
+        let material = match self.bound_material.as_ref() {
+            Some(material) => material,
+            None => return,
+        };
+        if !material.uniforms.iter().any(|uniform| uniform == name) {
+            return;
+        }
+        let mut values = material.values.borrow_mut();
+        if values.get(name) == Some(&value) {
+            return;
+        }
+        self.stats.uniform_uploads += 1;
+        values.insert(name.to_string(), value);
+    }
+    fn build_mesh(&mut self, mesh: Mesh) -> Result<Self::RuntimeMesh, Self::ErrorType> {
+        Ok(CpuMesh {
+            id: self.new_id(),
+            data: Rc::new(MeshData {
+                vertices: mesh.vertices,
+                owner: Owner::new(self, |live| &mut live.meshes),
+            }),
+        })
+    }
+    fn build_skinned_mesh(
+        &mut self,
+        mesh: SkinnedMesh,
+    ) -> Result<Self::RuntimeMesh, Self::ErrorType> {
+        self.build_mesh(Mesh {
+            vertices: mesh
+                .vertices
+                .iter()
+                .map(|vertex| (vertex.position, vertex.uv))
+                .collect(),
+        })
+    }
+    fn build_texture(
+        &mut self,
+        texture: RGBATexture,
+        options: TextureOptions,
+    ) -> Result<Self::RuntimeTexture, Self::ErrorType> {
+        let built = self.build_texture_data(texture.dimensions(), options, false, false);
+        built.data.borrow_mut().pixels = texture
+            .pixels()
+            .iter()
+            .map(|p| p.map(|c| c as f32 / 255.0))
+            .collect();
+        Ok(built)
+    }
+    fn build_float_texture(
+        &mut self,
+        dimensions: Vector2<u32>,
+    ) -> Result<Self::RuntimeTexture, Self::ErrorType> {
+        Ok(self.build_texture_data(dimensions, TextureOptions::default(), true, false))
+    }
+    fn update_texture_region(
+        &mut self,
+        texture: &Self::RuntimeTexture,
+        offset: Vector2<u32>,
+        region: &RGBATexture,
+    ) -> Result<(), Self::ErrorType> {
+        let mut data = texture.data.borrow_mut();
+        if !fits(offset.x, region.dimensions().x, data.dimensions.x)
+            || !fits(offset.y, region.dimensions().y, data.dimensions.y)
+        {
+            return Err("texture region is out of bounds".to_string());
+        }
+        for y in 0..region.dimensions().y {
+            for x in 0..region.dimensions().x {
+                let color = region.get_pixel(x, y).map(|c| c as f32 / 255.0);
+                data.store(offset.x + x, offset.y + y, color);
+            }
+        }
+        Ok(())
+    }
+    fn build_depth_texture(
+        &mut self,
+        dimensions: Vector2<u32>,
+    ) -> Result<Self::RuntimeTexture, Self::ErrorType> {
+        let options = TextureOptions {
+            min_filter: TextureFilter::Nearest,
+            mag_filter: TextureFilter::Nearest,
+            ..TextureOptions::default()
+        };
+        Ok(self.build_texture_data(dimensions, options, true, true))
+    }
+    fn build_framebuffer(
+        &mut self,
+        texture_attachment: &mut Self::RuntimeTexture,
+    ) -> Self::Framebuffer {
+        CpuFramebuffer {
+            target: Rc::new(Target::new(texture_attachment.clone())),
+            owner: Owner::new(self, |live| &mut live.framebuffers),
+        }
+    }
+    fn clear_screen(&mut self, color: Vector4<f32>) {
+        let (min, max) = self.draw_area();
+        let target = &self.bound_target;
+        let mut texture = target.texture.data.borrow_mut();
+        let mut depth = target.depth.borrow_mut();
+        let width = texture.dimensions.x;
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                if target.texture.is_depth {
+                    texture.store(x, y, Vector4::new(1.0, 1.0, 1.0, 1.0));
+                } else {
+                    texture.store(x, y, color);
+                    depth[(y * width + x) as usize] = 1.0;
+                }
+            }
+        }
+    }
+    fn set_depth_test(&mut self, enabled: bool) {
+        self.depth_test = enabled;
+    }
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        if self.blend_mode != mode {
+            self.blend_mode = mode;
+            self.stats.state_changes += 1;
+        }
+    }
+    fn set_clip(&mut self, clip: Option<(Vector2<f32>, Vector2<f32>)>) {
+        let dimensions = self.bound_target.texture.data.borrow().dimensions;
+        let to_pixels = |position: Vector2<f32>| {
+            ((position + Vector2::new(1.0, 1.0)) * 0.5)
+                .component_mul(&dimensions.map(|c| c as f32))
+                .map(|c| c.round().max(0.0) as u32)
+        };
+        self.clip = clip.map(|(min, max)| (to_pixels(min), to_pixels(max)));
+    }
+    fn bind_framebuffer(&mut self, framebuffer: &Self::Framebuffer) {
+        self.bound_target = framebuffer.target.clone();
+        self.stats.state_changes += 1;
+    }
+    fn bind_default_framebuffer(&mut self) {
+        self.bound_target = self.screen.clone();
+        self.stats.state_changes += 1;
+    }
+    fn bind_texture(&mut self, texture: &Self::RuntimeTexture) {
+        self.bind_texture_unit(texture, 0);
+        self.send_uniform("u_texture", UniformValue::Texture(0));
+    }
+    fn bind_texture_unit(&mut self, texture: &Self::RuntimeTexture, unit: u32) {
+        if self.textures.get(&unit).map(|t| t.id) != Some(texture.id) {
+            self.textures.insert(unit, texture.clone());
+            self.stats.state_changes += 1;
+        }
+    }
+    fn draw_mesh(&mut self, mesh: &Self::RuntimeMesh) {
+        self.draw_triangles(
+            mesh,
+            &Instance {
+                transform: Matrix4::identity(),
+                tint: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            },
+        );
+        self.stats.draw_calls += 1;
+    }
+    fn draw_instanced(&mut self, mesh: &Self::RuntimeMesh, instances: &[Instance]) {
+        for instance in instances.iter() {
+            self.draw_triangles(mesh, instance);
+        }
+        self.stats.draw_calls += 1;
+        self.stats.instances += instances.len() as u32;
+    }
+    fn draw_lines(&mut self, _vertices: &[LineVertex]) {
+        self.stats.draw_calls += 1;
+    }
+    fn take_stats(&mut self) -> RenderStats {
+        std::mem::take(&mut self.stats)
+    }
+    fn delete_mesh(&mut self, mesh: &Self::RuntimeMesh) {
+        mesh.data.owner.release();
+    }
+    fn delete_texture(&mut self, texture: &Self::RuntimeTexture) {
+        if let Some(owner) = texture.data.borrow().owner.as_ref() {
+            owner.release();
+        }
+    }
+    fn delete_framebuffer(&mut self, framebuffer: &Self::Framebuffer) {
+        framebuffer.owner.release();
+    }
+    fn read_pixels(
+        &mut self,
+        framebuffer: &Self::Framebuffer,
+    ) -> Result<RGBATexture, Self::ErrorType> {
+        self.bind_framebuffer(framebuffer);
+        Ok(Self::to_rgba(&framebuffer.target.texture.data.borrow()))
+    }
+    fn live_resources(&self) -> ResourceReport {
+        self.live.get()
+    }
+    /// Limited only by memory
+    fn max_framebuffer_size(&self) -> u32 {
+        u32::MAX
+    }
+}
@@ -0,0 +1,176 @@
+use super::graphics_engine::LineVertex;
+use super::gui::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+use nalgebra::{Vector3, Vector4};
+/// Segments used to draw each circle of a sphere
+const CIRCLE_SEGMENTS: usize = 24;
+/// Text placed in the world, turned into lines facing the camera when the frame is drawn
+struct DebugText {
+    position: Vector3<f32>,
+    text: String,
+    height: f32,
+    color: Vector4<f32>,
+}
+/// Collects shapes to draw on top of the world for one frame. Anything can add shapes while the
+/// frame is built, they are drawn as lines and cleared once the frame is drawn. Shapes added while
+/// debug drawing is disabled are ignored.
+pub struct DebugDraw {
+    enabled: bool,
+    vertices: Vec<LineVertex>,
+    texts: Vec<DebugText>,
+}
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            vertices: vec![],
+            texts: vec![],
+        }
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.vertices.clear();
+        self.texts.clear();
+    }
+    pub fn line(&mut self, from: Vector3<f32>, to: Vector3<f32>, color: Vector4<f32>) {
+        if !self.enabled {
+            return;
+        }
+        self.vertices.push(LineVertex {
+            position: from,
+            color,
+        });
+        self.vertices.push(LineVertex {
+            position: to,
+            color,
+        });
+    }
+    /// Outline of an axis aligned box
+    pub fn aabb(&mut self, min: Vector3<f32>, max: Vector3<f32>, color: Vector4<f32>) {
+        let corner = |i: usize| {
+            Vector3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        //corners differing by one bit share an edge
+        for i in 0..8 {
+            for bit in [1, 2, 4].iter() {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+    /// Three circles around the center, one in each axis plane
+    pub fn sphere(&mut self, center: Vector3<f32>, radius: f32, color: Vector4<f32>) {
+        let axes = [
+            (Vector3::x(), Vector3::y()),
+            (Vector3::y(), Vector3::z()),
+            (Vector3::z(), Vector3::x()),
+        ];
+        for (u, v) in axes.iter() {
+            let point = |i: usize| {
+                let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                center + (u * angle.cos() + v * angle.sin()) * radius
+            };
+            for i in 0..CIRCLE_SEGMENTS {
+                self.line(point(i), point(i + 1), color);
+            }
+        }
+    }
+    /// Line with a head at `to`
+    pub fn arrow(&mut self, from: Vector3<f32>, to: Vector3<f32>, color: Vector4<f32>) {
+        self.line(from, to, color);
+        let direction = to - from;
+        let length = direction.norm();
+        if length == 0.0 {
+            return;
+        }
+        let direction = direction / length;
+        //any direction not parallel to the arrow gives two sides for the head
+        let other = if direction.y.abs() > 0.9 {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        let side = direction.cross(&other).normalize();
+        let up = side.cross(&direction);
+        let head = length * 0.2;
+        for offset in [side, -side, up, -up].iter() {
+            self.line(to, to - direction * head + offset * head * 0.5, color);
+        }
+    }
+    /// Square grid on the xz plane with `divisions` cells along each side
+    pub fn grid(&mut self, center: Vector3<f32>, size: f32, divisions: u32, color: Vector4<f32>) {
+        let half = size / 2.0;
+        let divisions = divisions.max(1);
+        for i in 0..=divisions {
+            let offset = -half + size * i as f32 / divisions as f32;
+            self.line(
+                center + Vector3::new(offset, 0.0, -half),
+                center + Vector3::new(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + Vector3::new(-half, 0.0, offset),
+                center + Vector3::new(half, 0.0, offset),
+                color,
+            );
+        }
+    }
+    /// Text starting at `position` that always faces the camera, `height` tall in world units
+    pub fn text(&mut self, position: Vector3<f32>, text: &str, height: f32, color: Vector4<f32>) {
+        if !self.enabled {
+            return;
+        }
+        self.texts.push(DebugText {
+            position,
+            text: text.to_string(),
+            height,
+            color,
+        });
+    }
+    /// Takes the lines added this frame. Text is laid out along `right` and `up`, the directions of
+    /// the screen in the world.
+    pub fn take_lines(&mut self, right: Vector3<f32>, up: Vector3<f32>) -> Vec<LineVertex> {
+        let texts = std::mem::take(&mut self.texts);
+        for text in texts.iter() {
+            let pixel = text.height / GLYPH_HEIGHT as f32;
+            //each run of lit pixels in a glyph row becomes one line through the middle of the row
+            for (index, c) in text.text.chars().enumerate() {
+                let origin =
+                    text.position + right * (index as f32 * (GLYPH_WIDTH + 1) as f32 * pixel);
+                for (row_index, row) in glyph(c).iter().enumerate() {
+                    let y = (GLYPH_HEIGHT as usize - row_index) as f32 - 0.5;
+                    let mut run_start = None;
+                    for column in 0..=GLYPH_WIDTH {
+                        let lit =
+                            column < GLYPH_WIDTH && row & (1 << (GLYPH_WIDTH - 1 - column)) != 0;
+                        match (lit, run_start) {
+                            (true, None) => run_start = Some(column),
+                            (false, Some(start)) => {
+                                self.line(
+                                    origin + (right * start as f32 + up * y) * pixel,
+                                    origin + (right * column as f32 + up * y) * pixel,
+                                    text.color,
+                                );
+                                run_start = None;
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+            }
+        }
+        std::mem::take(&mut self.vertices)
+    }
+}
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,111 +1,82 @@
-use super::prelude::{Camera, GetGuiOutput, GuiMessage, GuiParent, Triangle};
-use super::{Mesh, Model, RGBATexture, RenderTransform};
+use super::animation::{Animator, Character};
+use super::assets::{self, AssetData, AssetId};
+use super::debug_draw::DebugDraw;
+use super::gui::{BoundingBox, Label, Window};
+use super::prelude::{Camera, GetGuiOutput, GuiMessage, GuiParent, Propagation, Triangle};
+use super::render_queue::BoundingSphere;
+use super::{BlendMode, Model, RenderTransform};
 use nalgebra::{Vector2, Vector3, Vector4};
+use std::rc::Rc;
 pub struct ObjectTickOutput<'a, RenderModel> {
     pub model: Option<&'a RenderModel>,
     pub transform: RenderTransform,
+    /// Multiplied with the texture
+    pub tint: Vector4<f32>,
+    /// Lower layers are drawn first
+    pub layer: i32,
+    pub blend: BlendMode,
+    /// Sphere around the model used to skip drawing it when it is out of view
+    pub bounds: Option<BoundingSphere>,
+    /// Pose of objects drawn as an animated character, whose model name is the character's name
+    pub animator: Option<&'a Animator>,
+    /// Asset drawn in place of `model` once it has loaded
+    pub asset: Option<AssetId>,
 }
 pub trait GameObject<RenderModel: std::marker::Sized> {
     fn get_model(&self) -> Model;
+    /// Objects with the same model name share one gpu model and are drawn together with a single
+    /// instanced draw call. Objects returning `None` get their own model.
+    fn get_model_name(&self) -> Option<String>;
     fn is_initilized(&self) -> bool;
-    fn get_render_model(&self) -> ObjectTickOutput<RenderModel>;
+    fn get_render_model(&self) -> ObjectTickOutput<'_, RenderModel>;
     fn submit_render_model(&mut self, model: RenderModel);
     ///GuiParent Implementation
-    fn get_gui_g(&self) -> GetGuiOutput<RenderModel>;
-    fn process_message_g(&mut self, message: &GuiMessage);
+    fn get_gui_g(&mut self) -> GetGuiOutput<RenderModel>;
+    fn process_message_g(&mut self, message: &GuiMessage) -> Propagation;
     /// Gets collider triangle in screen coordinates
     fn get_screen_collider_g(&self, camera: &Camera) -> Vec<Triangle>;
+    fn get_tooltip_g(&self) -> Option<String>;
+    /// Adds shapes shown while debug drawing is on
+    fn debug_draw(&self, _debug: &mut DebugDraw) {}
+    /// Advances the object once per frame
+    fn update(&mut self, _delta_time_ms: f32) {}
+    /// Where the object is kicking up powder, if it is
+    fn powder_position(&self) -> Option<Vector3<f32>> {
+        None
+    }
+    /// Called once the asset in `ObjectTickOutput::asset` has loaded
+    fn asset_loaded(&mut self, _data: &AssetData) {}
 }
 impl<RenderModel: std::marker::Sized> GuiParent<RenderModel> for Box<dyn GameObject<RenderModel>> {
-    fn get_gui(&self) -> GetGuiOutput<RenderModel> {
+    fn get_gui(&mut self) -> GetGuiOutput<RenderModel> {
         self.get_gui_g()
     }
-    fn process_message(&mut self, message: &GuiMessage) {
+    fn process_message(&mut self, message: &GuiMessage) -> Propagation {
         self.process_message_g(message)
     }
     /// Gets collider triangle in screen coordinates
     fn get_screen_collider(&self, camera: &Camera) -> Vec<Triangle> {
         self.get_screen_collider_g(camera)
     }
-}
-pub struct WorldGrid<RenderModel: std::marker::Sized> {
-    dim: Vector2<i32>,
-    model: Option<RenderModel>,
-}
-impl<RenderModel: std::marker::Sized> WorldGrid<RenderModel> {
-    pub fn new(dim: Vector2<i32>) -> Self {
-        Self { dim, model: None }
+    fn get_tooltip(&self) -> Option<String> {
+        self.get_tooltip_g()
     }
 }
-impl<RenderModel: std::marker::Sized> GameObject<RenderModel> for WorldGrid<RenderModel> {
-    fn get_model(&self) -> Model {
-        let mut vertices = vec![];
-        let scale = 1.0;
-        for x in 0..self.dim.x {
-            for y in 0..self.dim.y {
-                let pos = Vector3::new(x as f32, 0.0, y as f32);
-                //first traigne
-                vertices.push((
-                    scale * (Vector3::new(0.0, 0.0, 0.0) + pos),
-                    Vector2::new(0.0, 0.0),
-                ));
-                vertices.push((
-                    scale * (Vector3::new(1.0, 0.0, 1.0) + pos),
-                    Vector2::new(1.0, 1.0),
-                ));
-                vertices.push((
-                    scale * (Vector3::new(1.0, 0.0, 0.0) + pos),
-                    Vector2::new(1.0, 0.0),
-                ));
-                //second triangle
-                vertices.push((
-                    scale * (Vector3::new(0.0, 0.0, 0.0) + pos),
-                    Vector2::new(0.0, 0.0),
-                ));
-                vertices.push((
-                    scale * (Vector3::new(0.0, 0.0, 1.0) + pos),
-                    Vector2::new(0.0, 1.0),
-                ));
-                vertices.push((
-                    scale * (Vector3::new(1.0, 0.0, 1.0) + pos),
-                    Vector2::new(1.0, 1.0),
-                ));
-            }
-        }
-        Model {
-            mesh: Mesh { vertices },
-            texture: RGBATexture::constant_color(
-                Vector4::new(0, 255, 255, 255),
-                Vector2::new(8, 8),
-            ),
-        }
-    }
-    fn is_initilized(&self) -> bool {
-        self.model.is_some()
-    }
-    fn get_render_model(&self) -> ObjectTickOutput<RenderModel> {
-        ObjectTickOutput {
-            model: self.model.as_ref(),
-            transform: RenderTransform::new_scale(&Vector3::new(1.0, 1.0, 1.0)),
-        }
-    }
-    fn submit_render_model(&mut self, model: RenderModel) {
-        self.model = Some(model);
-    }
-    fn get_gui_g(&self) -> GetGuiOutput<RenderModel> {
-        GetGuiOutput::None
-    }
-    fn process_message_g(&mut self, message: &GuiMessage) {}
-    /// Gets collider triangle in screen coordinates
-    fn get_screen_collider_g(&self, camera: &Camera) -> Vec<Triangle> {
-        vec![]
-    }
+/// State of the info window an actor opens when clicked on
+#[derive(Clone, Copy, PartialEq)]
+enum InfoWindow {
+    Closed,
+    /// Window should be sent to the gui on the next call to `get_gui_g`
+    Opening,
+    Open,
 }
 /// Used for a simple Actor that moves in the game world
 struct SimpleActor<Actor: ActorBehavior, RenderModel: std::marker::Sized> {
     actor: Actor,
     render_model: Option<RenderModel>,
     collider: Collider,
+    bounds: BoundingSphere,
+    info_window: InfoWindow,
 }
 impl<Actor: ActorBehavior, RenderModel: std::marker::Sized> GameObject<RenderModel>
     for SimpleActor<Actor, RenderModel>
@@ -113,43 +84,182 @@ impl<Actor: ActorBehavior, RenderModel: std::marker::Sized> GameObject<RenderMod
     fn get_model(&self) -> Model {
         self.actor.get_model()
     }
+    /// Every actor of the same kind looks the same so they share a model
+    fn get_model_name(&self) -> Option<String> {
+        Some(self.actor.get_model_name())
+    }
     fn submit_render_model(&mut self, model: RenderModel) {
         self.render_model = Some(model);
     }
     fn is_initilized(&self) -> bool {
         self.render_model.is_some()
     }
-    fn get_render_model(&self) -> ObjectTickOutput<RenderModel> {
+    fn get_render_model(&self) -> ObjectTickOutput<'_, RenderModel> {
         ObjectTickOutput {
             model: self.render_model.as_ref(),
             transform: self.actor.get_render_transform(),
+            tint: self.actor.get_tint(),
+            layer: 0,
+            blend: BlendMode::Opaque,
+            bounds: Some(self.bounds.clone()),
+            animator: self.actor.get_animator(),
+            asset: self.actor.get_asset(),
         }
     }
-    fn get_gui_g(&self) -> GetGuiOutput<RenderModel> {
-        GetGuiOutput::None
+    fn get_gui_g(&mut self) -> GetGuiOutput<RenderModel> {
+        match self.info_window {
+            InfoWindow::Opening => {
+                self.info_window = InfoWindow::Open;
+                let name = self.actor.get_name();
+                let mut window = Window::new(
+                    &format!("{}_info", name.to_lowercase()),
+                    &name,
+                    BoundingBox::new(Vector2::new(0.4, 0.4), Vector2::new(0.95, 0.95)),
+                );
+                let content = window.content_area();
+                window.add_element(Box::new(Label::new(
+                    name,
+                    Vector2::new(content.min.x + 0.02, content.max.y - 0.07),
+                    0.05,
+                )));
+                GetGuiOutput::Contianer(window.build())
+            }
+            InfoWindow::Open => GetGuiOutput::NoChange,
+            InfoWindow::Closed => GetGuiOutput::None,
+        }
+    }
+    fn process_message_g(&mut self, message: &GuiMessage) -> Propagation {
+        match message {
+            GuiMessage::ClickedOn => {
+                if self.info_window == InfoWindow::Closed {
+                    self.info_window = InfoWindow::Opening;
+                }
+                Propagation::Stop
+            }
+            GuiMessage::WindowClosed => {
+                self.info_window = InfoWindow::Closed;
+                Propagation::Stop
+            }
+            _ => Propagation::Continue,
+        }
     }
-    fn process_message_g(&mut self, message: &GuiMessage) {}
     /// Gets collider triangle in screen coordinates
     fn get_screen_collider_g(&self, camera: &Camera) -> Vec<Triangle> {
-        self.collider.get_screenspace_collider(camera)
+        self.collider
+            .get_screenspace_collider(camera, &self.actor.get_render_transform())
+    }
+    fn get_tooltip_g(&self) -> Option<String> {
+        Some(self.actor.get_name())
+    }
+    /// Shows the collider and the name above it
+    fn debug_draw(&self, debug: &mut DebugDraw) {
+        let transform = self.actor.get_render_transform().matrix;
+        let color = Vector4::new(0.0, 1.0, 0.0, 1.0);
+        for triangle in self.collider.mesh.chunks_exact(3) {
+            let points: Vec<Vector3<f32>> = triangle
+                .iter()
+                .map(|p| transform.transform_point(&(*p).into()).coords)
+                .collect();
+            for i in 0..3 {
+                debug.line(points[i], points[(i + 1) % 3], color);
+            }
+        }
+        let bounds = self.bounds.transform(&transform);
+        debug.text(
+            bounds.center + Vector3::new(0.0, bounds.radius, 0.0),
+            &self.actor.get_name(),
+            0.1,
+            color,
+        );
+    }
+    fn update(&mut self, delta_time_ms: f32) {
+        self.actor.update(delta_time_ms)
+    }
+    fn powder_position(&self) -> Option<Vector3<f32>> {
+        self.actor.get_powder_position()
+    }
+    /// The collider and bounds are fitted to the loaded asset
+    fn asset_loaded(&mut self, data: &AssetData) {
+        self.actor.asset_loaded(data);
+        self.collider = self.actor.get_collider();
+        self.bounds = BoundingSphere::from_points(&self.collider.mesh);
     }
 }
 struct Collider {
     mesh: Vec<Vector3<f32>>,
 }
 impl Collider {
-    fn get_screenspace_collider(&self, camera: &Camera) -> Vec<Triangle> {
-        //probabbly something to do with convex hull
-        panic!("get screenspace failed")
+    /// Box around `points`
+    fn around(points: impl Iterator<Item = Vector3<f32>>) -> Self {
+        let (min, max) = points.fold(
+            (Vector3::repeat(f32::MAX), Vector3::repeat(f32::MIN)),
+            |(min, max), point| (min.inf(&point), max.sup(&point)),
+        );
+        Self::from_box(min, max)
+    }
+    /// Twelve triangles covering the sides of the box from `min` to `max`
+    fn from_box(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        let corner = |x: bool, y: bool, z: bool| {
+            Vector3::new(
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            )
+        };
+        let mut mesh = vec![];
+        //each side is split into two triangles along its diagonal
+        for axis in 0..3 {
+            for side in [false, true].iter() {
+                let point = |a: bool, b: bool| match axis {
+                    0 => corner(*side, a, b),
+                    1 => corner(a, *side, b),
+                    _ => corner(a, b, *side),
+                };
+                mesh.extend_from_slice(&[
+                    point(false, false),
+                    point(true, false),
+                    point(true, true),
+                    point(false, false),
+                    point(true, true),
+                    point(false, true),
+                ]);
+            }
+        }
+        Self { mesh }
+    }
+    /// Projects the collider mesh onto the screen. Triangles behind the camera are skipped
+    fn get_screenspace_collider(
+        &self,
+        camera: &Camera,
+        transform: &RenderTransform,
+    ) -> Vec<Triangle> {
+        let matrix = camera.get_mat() * transform.matrix;
+        self.mesh
+            .chunks_exact(3)
+            .filter_map(|triangle| {
+                let mut points = [Vector2::new(0.0, 0.0); 3];
+                for (point, vertex) in points.iter_mut().zip(triangle.iter()) {
+                    let clip = matrix * Vector4::new(vertex.x, vertex.y, vertex.z, 1.0);
+                    if clip.w <= 0.0 {
+                        return None;
+                    }
+                    *point = Vector2::new(clip.x / clip.w, clip.y / clip.w);
+                }
+                Some(Triangle::new(points))
+            })
+            .collect()
     }
 }
 impl<Actor: ActorBehavior, RenderModel: std::marker::Sized> SimpleActor<Actor, RenderModel> {
     pub fn new(actor: Actor) -> Self {
         let collider = actor.get_collider();
+        let bounds = BoundingSphere::from_points(&collider.mesh);
         Self {
             actor,
             render_model: None,
             collider,
+            bounds,
+            info_window: InfoWindow::Closed,
         }
     }
 }
@@ -157,123 +267,223 @@ trait ActorBehavior {
     fn get_model(&self) -> Model;
     fn get_collider(&self) -> Collider;
     fn get_render_transform(&self) -> RenderTransform;
+    /// Color the shared model is multiplied with
+    fn get_tint(&self) -> Vector4<f32>;
+    /// Name shown in the tooltip
+    fn get_name(&self) -> String;
+    /// Actors with the same model name share a model
+    fn get_model_name(&self) -> String {
+        self.get_name()
+    }
+    /// Actors that are animated characters return the animator posing them
+    fn get_animator(&self) -> Option<&Animator> {
+        None
+    }
+    /// Actors drawn with a loaded asset return it. Their model is shown until the asset has
+    /// loaded.
+    fn get_asset(&self) -> Option<AssetId> {
+        None
+    }
+    /// Actors kicking up powder return where it comes from
+    fn get_powder_position(&self) -> Option<Vector3<f32>> {
+        None
+    }
+    /// Called once the asset from `get_asset` has loaded, before the collider is rebuilt
+    fn asset_loaded(&mut self, _data: &AssetData) {}
+    fn update(&mut self, _delta_time_ms: f32) {}
+}
+/// Clips a skiier goes through and how many seconds each one lasts, repeated forever
+const SKIIER_ROUTINE: &[(&str, f32)] = &[
+    ("skiing", 6.0),
+    ("turning", 4.0),
+    ("skiing", 3.0),
+    ("falling", 3.0),
+    ("idle", 4.0),
+    ("lift_riding", 8.0),
+];
+/// Clips of the routine played once, holding their last pose until the next clip
+const SKIIER_ONCE_CLIPS: &[&str] = &["falling"];
+/// Clips of the routine during which a skiier kicks up powder
+const SKIIER_POWDER_CLIPS: &[&str] = &["skiing", "turning"];
+/// Seconds taken to blend from one clip into the next
+const SKIIER_FADE: f32 = 0.3;
+pub struct Skiier {
+    position: Vector3<f32>,
+    /// Color of the skiier's jacket
+    tint: Vector4<f32>,
+    /// Character asset the skiier is drawn with, shared by every skiier
+    asset: AssetId,
+    /// None until the asset has loaded
+    character: Option<Rc<Character>>,
+    animator: Animator,
+    /// Seconds into `SKIIER_ROUTINE`
+    routine_time: f32,
 }
-pub struct Skiier {}
 impl Skiier {
-    pub fn new<RenderModel: 'static>() -> Box<dyn GameObject<RenderModel>> {
-        Box::new(SimpleActor::new(Self {}))
+    /// The skiier starts `routine_time` seconds into its routine so skiiers made together do not
+    /// move in step. A placeholder is shown until the character `asset` has loaded.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<RenderModel: 'static>(
+        position: Vector3<f32>,
+        tint: Vector4<f32>,
+        asset: AssetId,
+        routine_time: f32,
+    ) -> Box<dyn GameObject<RenderModel>> {
+        Box::new(SimpleActor::new(Self {
+            position,
+            tint,
+            asset,
+            character: None,
+            animator: Animator::new(0),
+            routine_time,
+        }))
+    }
+    /// Name of the clip the routine is at
+    fn routine_name(&self) -> &'static str {
+        let length: f32 = SKIIER_ROUTINE.iter().map(|(_, seconds)| seconds).sum();
+        let mut time = self.routine_time.rem_euclid(length);
+        for (name, seconds) in SKIIER_ROUTINE.iter() {
+            if time < *seconds {
+                return name;
+            }
+            time -= seconds;
+        }
+        SKIIER_ROUTINE[0].0
+    }
+    /// Clip the routine is at, or the first clip if the character does not have it
+    fn routine_clip(&self) -> usize {
+        self.character
+            .as_ref()
+            .and_then(|character| character.clip_index(self.routine_name()))
+            .unwrap_or(0)
     }
 }
+/// Box around the placeholder model
+fn placeholder_collider() -> Collider {
+    Collider::around(
+        assets::placeholder_model()
+            .mesh
+            .vertices
+            .iter()
+            .map(|(position, _)| *position),
+    )
+}
 impl ActorBehavior for Skiier {
+    /// Box around the character in its bind pose
     fn get_collider(&self) -> Collider {
-        Collider {
-            mesh: vec![
-                Vector3::new(-1.0, -1.0, 1.0),
-                Vector3::new(1.0, 1.0, 1.0),
-                Vector3::new(1.0, -1.0, 1.0),
-                //second triangle
-                Vector3::new(-1.0, -1.0, 1.0),
-                Vector3::new(-1.0, 1.0, 1.0),
-                Vector3::new(1.0, 1.0, 1.0),
-                //third triangle
-                Vector3::new(1.0, -1.0, 1.0),
-                Vector3::new(1.0, 1.0, -1.0),
-                Vector3::new(1.0, -1.0, -1.0),
-                //fourth triangle
-                Vector3::new(1.0, -1.0, 1.0),
-                Vector3::new(1.0, 1.0, 1.0),
-                Vector3::new(1.0, 1.0, -1.0),
-                //fith triangle
-                Vector3::new(1.0, -1.0, -1.0),
-                Vector3::new(-1.0, -1.0, -1.0),
-                Vector3::new(1.0, 1.0, -1.0),
-                //sixth triangle
-                Vector3::new(-1.0, -1.0, -1.0),
-                Vector3::new(-1.0, 1.0, -1.0),
-                Vector3::new(1.0, 1.0, -1.0),
-                //seventh triangle
-                Vector3::new(-1.0, -1.0, -1.0),
-                Vector3::new(-1.0, -1.0, 1.0),
-                Vector3::new(-1.0, 1.0, 1.0),
-                //eighth triangle
-                Vector3::new(-1.0, -1.0, -1.0),
-                Vector3::new(-1.0, 1.0, 1.0),
-                Vector3::new(-1.0, 1.0, -1.0),
-                //9th triangle
-                Vector3::new(1.0, 1.0, 1.0),
-                Vector3::new(1.0, 1.0, -1.0),
-                Vector3::new(-1.0, 1.0, -1.0),
-                //10th triangle
-                Vector3::new(1.0, 1.0, 1.0),
-                Vector3::new(-1.0, 1.0, -1.0),
-                Vector3::new(-1.0, 1.0, 1.0),
-                //11th triangle
-                Vector3::new(1.0, -1.0, 1.0),
-                Vector3::new(-1.0, -1.0, 1.0),
-                Vector3::new(-1.0, -1.0, -1.0),
-                //12th triangle
-                Vector3::new(1.0, -1.0, 1.0),
-                Vector3::new(-1.0, -1.0, -1.0),
-                Vector3::new(1.0, -1.0, -1.0),
-            ],
+        match &self.character {
+            Some(character) => {
+                Collider::around(character.mesh.vertices.iter().map(|vertex| vertex.position))
+            }
+            None => placeholder_collider(),
         }
     }
+    /// Shown while the character is loading. Once it has loaded skiiers are drawn posed by the
+    /// character renderer.
     fn get_model(&self) -> Model {
-        let vertices = vec![
-            (Vector3::new(-1.0, -1.0, 1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(1.0, 1.0, 1.0), Vector2::new(1.0, 1.0)),
-            (Vector3::new(1.0, -1.0, 1.0), Vector2::new(1.0, 0.0)),
-            //second triangle
-            (Vector3::new(-1.0, -1.0, 1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(-1.0, 1.0, 1.0), Vector2::new(0.0, 1.0)),
-            (Vector3::new(1.0, 1.0, 1.0), Vector2::new(1.0, 1.0)),
-            //third triangle
-            (Vector3::new(1.0, -1.0, 1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(1.0, 1.0, -1.0), Vector2::new(0.0, 1.0)),
-            (Vector3::new(1.0, -1.0, -1.0), Vector2::new(0.0, 1.0)),
-            //fourth triangle
-            (Vector3::new(1.0, -1.0, 1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(1.0, 1.0, 1.0), Vector2::new(0.0, 1.0)),
-            (Vector3::new(1.0, 1.0, -1.0), Vector2::new(1.0, 1.0)),
-            //fith triangle
-            (Vector3::new(1.0, -1.0, -1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(-1.0, -1.0, -1.0), Vector2::new(1.0, 0.0)),
-            (Vector3::new(1.0, 1.0, -1.0), Vector2::new(0.0, 1.0)),
-            //sixth triangle
-            (Vector3::new(-1.0, -1.0, -1.0), Vector2::new(1.0, 0.0)),
-            (Vector3::new(-1.0, 1.0, -1.0), Vector2::new(1.0, 1.0)),
-            (Vector3::new(1.0, 1.0, -1.0), Vector2::new(0.0, 1.0)),
-            //seventh triangle
-            (Vector3::new(-1.0, -1.0, -1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(-1.0, -1.0, 1.0), Vector2::new(1.0, 0.0)),
-            (Vector3::new(-1.0, 1.0, 1.0), Vector2::new(1.0, 1.0)),
-            //eighth triangle
-            (Vector3::new(-1.0, -1.0, -1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(-1.0, 1.0, 1.0), Vector2::new(1.0, 1.0)),
-            (Vector3::new(-1.0, 1.0, -1.0), Vector2::new(0.0, 1.0)),
-            //9th triangle
-            (Vector3::new(1.0, 1.0, 1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(1.0, 1.0, -1.0), Vector2::new(0.0, 1.0)),
-            (Vector3::new(-1.0, 1.0, -1.0), Vector2::new(1.0, 1.0)),
-            //10th triangle
-            (Vector3::new(1.0, 1.0, 1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(-1.0, 1.0, -1.0), Vector2::new(1.0, 1.0)),
-            (Vector3::new(-1.0, 1.0, 1.0), Vector2::new(0.0, 1.0)),
-            //11th triangle
-            (Vector3::new(1.0, -1.0, 1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(-1.0, -1.0, 1.0), Vector2::new(1.0, 0.0)),
-            (Vector3::new(-1.0, -1.0, -1.0), Vector2::new(1.0, 1.0)),
-            //12th triangle
-            (Vector3::new(1.0, -1.0, 1.0), Vector2::new(0.0, 0.0)),
-            (Vector3::new(-1.0, -1.0, -1.0), Vector2::new(1.0, 1.0)),
-            (Vector3::new(1.0, -1.0, -1.0), Vector2::new(0.0, 1.0)),
-        ];
-        Model {
-            mesh: Mesh { vertices },
-            texture: RGBATexture::constant_color(Vector4::new(255, 0, 0, 255), Vector2::new(8, 8)),
+        assets::placeholder_model()
+    }
+    fn get_render_transform(&self) -> RenderTransform {
+        RenderTransform::new_translation_scale(&self.position, &Vector3::new(0.5, 0.5, 0.5))
+    }
+    fn get_tint(&self) -> Vector4<f32> {
+        self.tint
+    }
+    fn get_name(&self) -> String {
+        "Skiier".to_string()
+    }
+    fn get_model_name(&self) -> String {
+        "placeholder".to_string()
+    }
+    fn get_animator(&self) -> Option<&Animator> {
+        Some(&self.animator)
+    }
+    fn get_asset(&self) -> Option<AssetId> {
+        Some(self.asset)
+    }
+    fn asset_loaded(&mut self, data: &AssetData) {
+        if let AssetData::Character(character) = data {
+            self.character = Some(character.clone());
+            self.animator = Animator::new(self.routine_clip());
         }
     }
+    /// Powder comes off the skis while skiing downhill
+    fn get_powder_position(&self) -> Option<Vector3<f32>> {
+        if SKIIER_POWDER_CLIPS.contains(&self.routine_name()) {
+            Some(self.position)
+        } else {
+            None
+        }
+    }
+    fn update(&mut self, delta_time_ms: f32) {
+        self.routine_time += delta_time_ms / 1000.0;
+        self.animator.update(delta_time_ms / 1000.0);
+        let clip = self.routine_clip();
+        if SKIIER_ONCE_CLIPS.contains(&self.routine_name()) {
+            self.animator.play_once(clip, SKIIER_FADE);
+        } else {
+            self.animator.play(clip, SKIIER_FADE);
+        }
+    }
+}
+/// Static model placed in the world, such as a lodge, lift tower or tree, drawn with every part of
+/// a loaded asset
+pub struct Prop {
+    name: String,
+    asset: AssetId,
+    position: Vector3<f32>,
+    /// Vertices of every part of the loaded asset, empty while it is loading
+    points: Vec<Vector3<f32>>,
+}
+impl Prop {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<RenderModel: 'static>(
+        name: &str,
+        asset: AssetId,
+        position: Vector3<f32>,
+    ) -> Box<dyn GameObject<RenderModel>> {
+        Box::new(SimpleActor::new(Self {
+            name: name.to_string(),
+            asset,
+            position,
+            points: vec![],
+        }))
+    }
+}
+impl ActorBehavior for Prop {
+    /// Box around the loaded asset, or the placeholder while it loads
+    fn get_collider(&self) -> Collider {
+        if self.points.is_empty() {
+            placeholder_collider()
+        } else {
+            Collider::around(self.points.iter().copied())
+        }
+    }
+    /// Shown while the asset is loading or if it failed to load
+    fn get_model(&self) -> Model {
+        assets::placeholder_model()
+    }
     fn get_render_transform(&self) -> RenderTransform {
-        RenderTransform::new_scale(&Vector3::new(0.1, 0.1, 0.1))
+        RenderTransform::new_translation_scale(&self.position, &Vector3::new(1.0, 1.0, 1.0))
+    }
+    fn get_tint(&self) -> Vector4<f32> {
+        Vector4::new(1.0, 1.0, 1.0, 1.0)
+    }
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+    fn get_model_name(&self) -> String {
+        "placeholder".to_string()
+    }
+    fn get_asset(&self) -> Option<AssetId> {
+        Some(self.asset)
+    }
+    fn asset_loaded(&mut self, data: &AssetData) {
+        if let AssetData::Models(models) = data {
+            self.points = models
+                .iter()
+                .flat_map(|model| model.mesh.vertices.iter().map(|(position, _)| *position))
+                .collect();
+        }
     }
 }
@@ -1,86 +1,736 @@
 use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
 use wasm_bindgen::{JsCast, JsValue};
 use web_sys::{
-    WebGl2RenderingContext, WebGlBuffer, WebGlFramebuffer, WebGlProgram, WebGlShader, WebGlTexture,
-    WebGlUniformLocation, WebGlVertexArrayObject,
+    WebGl2RenderingContext, WebGlBuffer, WebGlFramebuffer, WebGlProgram, WebGlRenderbuffer,
+    WebGlShader, WebGlTexture, WebGlUniformLocation, WebGlVertexArrayObject,
 };
 #[derive(Clone)]
 pub struct Mesh {
     pub vertices: Vec<(Vector3<f32>, Vector2<f32>)>,
 }
+/// Vertex of a `SkinnedMesh`, moved by a weighted blend of up to four joints
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkinnedVertex {
+    pub position: Vector3<f32>,
+    pub uv: Vector2<f32>,
+    /// Indices of the joints in the bone matrices sent to the skinning shader
+    pub joints: [u16; 4],
+    /// How much each joint moves the vertex, summing to 1
+    pub weights: [f32; 4],
+}
+impl SkinnedVertex {
+    /// Number of floats each vertex takes up in the vertex buffer
+    const FLOATS: usize = 13;
+}
+/// Mesh bent by the joints of a skeleton, posed in its bind pose
+#[derive(Clone)]
+pub struct SkinnedMesh {
+    pub vertices: Vec<SkinnedVertex>,
+}
 #[derive(Clone)]
 pub struct RGBATexture {
     dimensions: Vector2<u32>,
     pixels: Vec<Vector4<u8>>,
 }
+/// Whether a span starting at `start` of `size` pixels ends within `limit`, without overflowing
+pub(crate) fn fits(start: u32, size: u32, limit: u32) -> bool {
+    start
+        .checked_add(size)
+        .map(|end| end <= limit)
+        .unwrap_or(false)
+}
 impl RGBATexture {
+    /// Builds a texture from pixels in row major order. Panics if the number of pixels does not match the
+    /// dimensions
+    pub fn new(dimensions: Vector2<u32>, pixels: Vec<Vector4<u8>>) -> Self {
+        assert_eq!((dimensions.x * dimensions.y) as usize, pixels.len());
+        Self { dimensions, pixels }
+    }
+    pub fn dimensions(&self) -> Vector2<u32> {
+        self.dimensions
+    }
     pub fn get_raw_vector(&self) -> Vec<u8> {
-        let mut v = vec![];
-        v.reserve((self.dimensions.x * self.dimensions.y * 4) as usize);
+        let mut v = Vec::with_capacity((self.dimensions.x * self.dimensions.y * 4) as usize);
         for pixel in self.pixels.iter() {
             v.push(pixel.x);
             v.push(pixel.y);
             v.push(pixel.z);
             v.push(pixel.w);
         }
-        return v;
+        v
     }
     pub fn constant_color(color: Vector4<u8>, dimensions: Vector2<u32>) -> Self {
-        let pixels = (0..(dimensions.x * dimensions.y))
-            .map(|_| color.clone())
-            .collect();
+        let pixels = (0..(dimensions.x * dimensions.y)).map(|_| color).collect();
         Self { dimensions, pixels }
     }
+    /// Decodes an image, guessing the format from its header. Rows are flipped so that row 0 is the
+    /// bottom of the image, matching texture coordinates
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let format = image::guess_format(bytes).map_err(|e| e.to_string())?;
+        Self::decode(bytes, format)
+    }
+    pub fn from_png(bytes: &[u8]) -> Result<Self, String> {
+        Self::decode(bytes, image::ImageFormat::Png)
+    }
+    pub fn from_jpeg(bytes: &[u8]) -> Result<Self, String> {
+        Self::decode(bytes, image::ImageFormat::Jpeg)
+    }
+    /// TGA has no magic number so it can not be detected by `from_bytes`
+    pub fn from_tga(bytes: &[u8]) -> Result<Self, String> {
+        Self::decode(bytes, image::ImageFormat::Tga)
+    }
+    /// Decodes an image in the format named by a file extension such as "png" or "tga". Unknown
+    /// extensions fall back to guessing the format like `from_bytes`
+    pub fn from_extension(bytes: &[u8], extension: &str) -> Result<Self, String> {
+        match extension.to_lowercase().as_str() {
+            "png" => Self::from_png(bytes),
+            "jpg" | "jpeg" => Self::from_jpeg(bytes),
+            "tga" => Self::from_tga(bytes),
+            _ => Self::from_bytes(bytes),
+        }
+    }
+    fn decode(bytes: &[u8], format: image::ImageFormat) -> Result<Self, String> {
+        let image = image::load_from_memory_with_format(bytes, format)
+            .map_err(|e| e.to_string())?
+            .to_rgba8();
+        let dimensions = Vector2::new(image.width(), image.height());
+        let pixels = image
+            .pixels()
+            .map(|p| Vector4::new(p[0], p[1], p[2], p[3]))
+            .collect();
+        let mut texture = Self { dimensions, pixels };
+        texture.flip_vertical();
+        Ok(texture)
+    }
+    /// Encodes the texture as a png. Rows are flipped back so row 0 is the top of the png
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let mut flipped = self.clone();
+        flipped.flip_vertical();
+        let mut bytes = vec![];
+        image::png::PngEncoder::new(&mut bytes)
+            .encode(
+                &flipped.get_raw_vector(),
+                self.dimensions.x,
+                self.dimensions.y,
+                image::ColorType::Rgba8,
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(bytes)
+    }
+    /// Pixels in row major order starting at the bottom left
+    pub fn pixels(&self) -> &[Vector4<u8>] {
+        &self.pixels
+    }
+    pub fn get_pixel(&self, x: u32, y: u32) -> Vector4<u8> {
+        self.pixels[(y * self.dimensions.x + x) as usize]
+    }
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Vector4<u8>) {
+        self.pixels[(y * self.dimensions.x + x) as usize] = color;
+    }
+    /// Copies out the region starting at `min` with size `dimensions`
+    pub fn sub_image(&self, min: Vector2<u32>, dimensions: Vector2<u32>) -> Result<Self, String> {
+        if !fits(min.x, dimensions.x, self.dimensions.x)
+            || !fits(min.y, dimensions.y, self.dimensions.y)
+        {
+            return Err(format!(
+                "sub image at ({}, {}) of size {}x{} is outside of {}x{} texture",
+                min.x, min.y, dimensions.x, dimensions.y, self.dimensions.x, self.dimensions.y
+            ));
+        }
+        let mut pixels = Vec::with_capacity((dimensions.x * dimensions.y) as usize);
+        for y in min.y..min.y + dimensions.y {
+            let start = (y * self.dimensions.x + min.x) as usize;
+            pixels.extend_from_slice(&self.pixels[start..start + dimensions.x as usize]);
+        }
+        Ok(Self { dimensions, pixels })
+    }
+    /// Copies `source` into this texture with its bottom left corner at `position`
+    pub fn copy_from(&mut self, source: &Self, position: Vector2<u32>) -> Result<(), String> {
+        if !fits(position.x, source.dimensions.x, self.dimensions.x)
+            || !fits(position.y, source.dimensions.y, self.dimensions.y)
+        {
+            return Err(format!(
+                "{}x{} texture does not fit at ({}, {}) in {}x{} texture",
+                source.dimensions.x,
+                source.dimensions.y,
+                position.x,
+                position.y,
+                self.dimensions.x,
+                self.dimensions.y
+            ));
+        }
+        let width = source.dimensions.x as usize;
+        if width == 0 {
+            return Ok(());
+        }
+        for (y, row) in source.pixels.chunks(width).enumerate() {
+            let start = ((position.y + y as u32) * self.dimensions.x + position.x) as usize;
+            self.pixels[start..start + width].copy_from_slice(row);
+        }
+        Ok(())
+    }
+    pub fn flip_vertical(&mut self) {
+        let width = self.dimensions.x as usize;
+        let height = self.dimensions.y as usize;
+        for y in 0..height / 2 {
+            let (top, bottom) = self.pixels.split_at_mut((height - 1 - y) * width);
+            top[y * width..(y + 1) * width].swap_with_slice(&mut bottom[..width]);
+        }
+    }
+    /// Halves the texture until it is 1x1, averaging each 2x2 block. Odd sized textures drop their last
+    /// row or column like WebGL does. Returns every level after the full size texture.
+    pub fn mipmap_chain(&self) -> Vec<Self> {
+        let mut levels: Vec<Self> = vec![];
+        let mut previous = self;
+        while previous.dimensions.x > 1 || previous.dimensions.y > 1 {
+            let dimensions = Vector2::new(
+                (previous.dimensions.x / 2).max(1),
+                (previous.dimensions.y / 2).max(1),
+            );
+            let mut pixels = Vec::with_capacity((dimensions.x * dimensions.y) as usize);
+            for y in 0..dimensions.y {
+                for x in 0..dimensions.x {
+                    let x0 = (2 * x).min(previous.dimensions.x - 1);
+                    let x1 = (2 * x + 1).min(previous.dimensions.x - 1);
+                    let y0 = (2 * y).min(previous.dimensions.y - 1);
+                    let y1 = (2 * y + 1).min(previous.dimensions.y - 1);
+                    let sum = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)]
+                        .iter()
+                        .map(|(x, y)| previous.get_pixel(*x, *y).map(u32::from))
+                        .fold(Vector4::new(0, 0, 0, 0), |acc, p| acc + p);
+                    pixels.push((sum / 4).map(|c| c as u8));
+                }
+            }
+            levels.push(Self { dimensions, pixels });
+            previous = levels.last().unwrap();
+        }
+        levels
+    }
+    pub fn flip_horizontal(&mut self) {
+        if self.dimensions.x == 0 {
+            return;
+        }
+        for row in self.pixels.chunks_mut(self.dimensions.x as usize) {
+            row.reverse();
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mipmaps {
+    None,
+    /// Generated by the graphics api
+    Gpu,
+    /// Generated with `RGBATexture::mipmap_chain` and uploaded level by level
+    Cpu,
+}
+/// How a texture is sampled
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    pub mipmaps: Mipmaps,
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    /// Maximum anisotropic filtering samples. 1.0 disables it and it is ignored when the backend does
+    /// not support it
+    pub anisotropy: f32,
+}
+impl TextureOptions {
+    /// Repeating texture with mipmaps, used for textures tiled over large surfaces like terrain
+    pub fn tiled() -> Self {
+        Self {
+            mipmaps: Mipmaps::Gpu,
+            wrap_s: TextureWrap::Repeat,
+            wrap_t: TextureWrap::Repeat,
+            anisotropy: 4.0,
+            ..Self::default()
+        }
+    }
+    /// Sharp pixels, used for text and pixel art
+    pub fn pixelated() -> Self {
+        Self {
+            min_filter: TextureFilter::Nearest,
+            mag_filter: TextureFilter::Nearest,
+            ..Self::default()
+        }
+    }
+    pub fn with_filter(mut self, min_filter: TextureFilter, mag_filter: TextureFilter) -> Self {
+        self.min_filter = min_filter;
+        self.mag_filter = mag_filter;
+        self
+    }
+    pub fn with_wrap(mut self, wrap_s: TextureWrap, wrap_t: TextureWrap) -> Self {
+        self.wrap_s = wrap_s;
+        self.wrap_t = wrap_t;
+        self
+    }
+    pub fn with_mipmaps(mut self, mipmaps: Mipmaps) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+    pub fn with_anisotropy(mut self, anisotropy: f32) -> Self {
+        self.anisotropy = anisotropy;
+        self
+    }
+}
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            min_filter: TextureFilter::Linear,
+            mag_filter: TextureFilter::Linear,
+            mipmaps: Mipmaps::None,
+            wrap_s: TextureWrap::ClampToEdge,
+            wrap_t: TextureWrap::ClampToEdge,
+            anisotropy: 1.0,
+        }
+    }
+}
+/// Vertex inputs every mesh provides. Each is bound to the same location in every program so a mesh
+/// can be drawn with any material.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VertexAttribute {
+    Position,
+    Uv,
+    /// Per instance model matrix, takes up four locations, one per column
+    InstanceTransform,
+    /// Per instance color multiplied with the texture
+    InstanceTint,
+    /// Color of a `LineVertex`
+    Color,
+    /// Joint indices of a `SkinnedVertex`
+    Joints,
+    /// Joint weights of a `SkinnedVertex`
+    Weights,
+}
+impl VertexAttribute {
+    pub fn location(&self) -> u32 {
+        match self {
+            Self::Position => 0,
+            Self::Uv => 1,
+            Self::InstanceTransform => 2,
+            Self::InstanceTint => 6,
+            Self::Color => 7,
+            Self::Joints => 8,
+            Self::Weights => 9,
+        }
+    }
+}
+/// One copy of a mesh drawn by `GraphicsEngine::draw_instanced`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    pub transform: Matrix4<f32>,
+    pub tint: Vector4<f32>,
+}
+impl Instance {
+    /// Number of floats each instance takes up in the instance buffer
+    const FLOATS: usize = 20;
+}
+/// End of a line drawn by `GraphicsEngine::draw_lines`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineVertex {
+    pub position: Vector3<f32>,
+    pub color: Vector4<f32>,
+}
+impl LineVertex {
+    /// Number of floats each vertex takes up in the line buffer
+    const FLOATS: usize = 7;
+}
+/// Source and interface of a shader program
+#[derive(Clone, Debug)]
+pub struct ShaderDescription {
+    /// Used in error messages
+    pub name: String,
+    pub vertex_source: String,
+    pub fragment_source: String,
+    /// Names of the vertex shader inputs and the mesh data they receive
+    pub attributes: Vec<(String, VertexAttribute)>,
+    /// Names of uniforms that can be set with `GraphicsEngine::send_uniform`
+    pub uniforms: Vec<String>,
+}
+/// Value of a shader uniform
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Int(i32),
+    Vec2(Vector2<f32>),
+    Vec3(Vector3<f32>),
+    Vec4(Vector4<f32>),
+    Mat4(Matrix4<f32>),
+    /// Array of matrices, such as the bones of a skinned mesh
+    Mat4Array(Vec<Matrix4<f32>>),
+    /// Texture unit a sampler reads from
+    Texture(u32),
+}
+/// Work done by the graphics api since the counters were last taken
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    /// Copies of meshes drawn by instanced draw calls
+    pub instances: u32,
+    /// Program, texture, vertex array and framebuffer binds that were not skipped
+    pub state_changes: u32,
+    pub uniform_uploads: u32,
+}
+/// Number of gpu objects that have been created and not deleted
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceReport {
+    pub meshes: u32,
+    pub textures: u32,
+    pub framebuffers: u32,
+    pub materials: u32,
+}
+/// How drawn fragments are combined with what is already in the framebuffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BlendMode {
+    /// Replaces the framebuffer and writes depth
+    Opaque,
+    /// Like `Opaque` but fragments with alpha below one half are discarded, used for foliage
+    Cutout,
+    /// Blends by the fragment's alpha without writing depth
+    Alpha,
+    /// Adds the fragment scaled by its alpha without writing depth, used for glowing effects
+    Additive,
+    /// Blends colors that were already multiplied by alpha without writing depth
+    Premultiplied,
+}
+impl BlendMode {
+    /// Transparent modes need to be drawn after opaque geometry from back to front
+    pub fn is_transparent(&self) -> bool {
+        match self {
+            Self::Opaque | Self::Cutout => false,
+            Self::Alpha | Self::Additive | Self::Premultiplied => true,
+        }
+    }
+    /// Alpha below which fragments are discarded, sent to shaders as "alpha_cutoff"
+    pub fn alpha_cutoff(&self) -> f32 {
+        match self {
+            Self::Cutout => 0.5,
+            _ => 0.0,
+        }
+    }
+}
+/// Gpu objects have ids so draws can be sorted and grouped by the state they need
+pub trait GpuResource {
+    /// Unique among all resources made by the same engine
+    fn id(&self) -> u32;
 }
 pub trait GraphicsEngine: std::marker::Sized {
-    type RuntimeMesh: Clone;
-    type RuntimeTexture: Clone;
+    type RuntimeMesh: Clone + GpuResource;
+    type RuntimeTexture: Clone + GpuResource;
     type ErrorType;
     type Framebuffer;
+    type Material: Clone + GpuResource;
     fn init() -> Result<Self, Self::ErrorType>;
+    /// Compiles and links a shader program
+    fn build_material(
+        &mut self,
+        shader: &ShaderDescription,
+    ) -> Result<Self::Material, Self::ErrorType>;
+    /// Uses the material for all draws until another material is bound
+    fn bind_material(&mut self, material: &Self::Material);
+    /// Sets a uniform of the bound material. Uniforms the material did not declare are ignored.
+    fn send_uniform(&mut self, name: &str, value: UniformValue);
     fn build_mesh(&mut self, mesh: Mesh) -> Result<Self::RuntimeMesh, Self::ErrorType>;
+    /// Builds a mesh that also provides the `Joints` and `Weights` attributes, drawn the same way
+    /// as any other mesh
+    fn build_skinned_mesh(
+        &mut self,
+        mesh: SkinnedMesh,
+    ) -> Result<Self::RuntimeMesh, Self::ErrorType>;
     fn build_texture(
         &mut self,
         texture: RGBATexture,
+        options: TextureOptions,
+    ) -> Result<Self::RuntimeTexture, Self::ErrorType>;
+    /// Builds an empty color texture that keeps values outside of 0 to 1 when drawn into, so effects
+    /// like tone mapping see the full range. Holds 8 bits per channel where floating point
+    /// framebuffers are not supported.
+    fn build_float_texture(
+        &mut self,
+        dimensions: Vector2<u32>,
+    ) -> Result<Self::RuntimeTexture, Self::ErrorType>;
+    /// Replaces the part of a color texture starting at `offset` with `region`, so small edits do
+    /// not upload the whole texture. Mipmap levels are left as they were.
+    fn update_texture_region(
+        &mut self,
+        texture: &Self::RuntimeTexture,
+        offset: Vector2<u32>,
+        region: &RGBATexture,
+    ) -> Result<(), Self::ErrorType>;
+    /// Builds a texture holding depth instead of color, used as a shadow map. Sampling it gives
+    /// the depth in the red channel.
+    fn build_depth_texture(
+        &mut self,
+        dimensions: Vector2<u32>,
     ) -> Result<Self::RuntimeTexture, Self::ErrorType>;
+    /// Builds a framebuffer drawing into the texture. Color textures get a depth buffer of the same
+    /// size, depth textures are drawn into without any color.
     fn build_framebuffer(
         &mut self,
         texture_attachment: &mut Self::RuntimeTexture,
     ) -> Self::Framebuffer;
+    /// Clears the color and depth of the bound framebuffer
     fn clear_screen(&mut self, color: Vector4<f32>);
+    /// When enabled fragments behind what was already drawn are discarded
+    fn set_depth_test(&mut self, enabled: bool);
+    /// Sets how following draws are blended and whether they write depth
+    fn set_blend_mode(&mut self, mode: BlendMode);
+    /// Limits following draws to the rectangle from `min` to `max` in screen coordinates, which
+    /// range from -1 to 1 over the bound framebuffer. `None` draws everywhere.
+    fn set_clip(&mut self, clip: Option<(Vector2<f32>, Vector2<f32>)>);
+    /// Binds the framebuffer and sets the viewport to its size
     fn bind_framebuffer(&mut self, framebuffer: &Self::Framebuffer);
     /// Binds the screen and all rendercalls made after this calls will draw to the screen.
     fn bind_default_framebuffer(&mut self);
+    /// Binds the texture to unit 0 and points the "u_texture" sampler at it
     fn bind_texture(&mut self, texture: &Self::RuntimeTexture);
+    fn bind_texture_unit(&mut self, texture: &Self::RuntimeTexture, unit: u32);
     fn draw_mesh(&mut self, mesh: &Self::RuntimeMesh);
-    fn send_model_matrix(&mut self, matrix: Matrix4<f32>);
-    fn send_view_matrix(&mut self, matrix: Matrix4<f32>);
+    /// Draws the mesh once per instance in a single draw call. The bound material reads the
+    /// instance data through the `InstanceTransform` and `InstanceTint` attributes.
+    fn draw_instanced(&mut self, mesh: &Self::RuntimeMesh, instances: &[Instance]);
+    /// Draws a line between each pair of vertices. The bound material reads them through the
+    /// `Position` and `Color` attributes.
+    fn draw_lines(&mut self, vertices: &[LineVertex]);
+    /// Gets the counters since the last call and resets them
+    fn take_stats(&mut self) -> RenderStats;
+    /// Frees the gpu memory of a mesh. Resources are also freed when the last handle to them is
+    /// dropped, this frees them right away even if other handles remain. Drawing a deleted mesh
+    /// draws nothing.
+    fn delete_mesh(&mut self, mesh: &Self::RuntimeMesh);
+    fn delete_texture(&mut self, texture: &Self::RuntimeTexture);
+    fn delete_framebuffer(&mut self, framebuffer: &Self::Framebuffer);
+    /// Copies the color of a framebuffer built from a color texture back from the gpu. Leaves the
+    /// framebuffer bound.
+    fn read_pixels(
+        &mut self,
+        framebuffer: &Self::Framebuffer,
+    ) -> Result<RGBATexture, Self::ErrorType>;
+    /// Counts resources that have not been freed, used to find leaks
+    fn live_resources(&self) -> ResourceReport;
+    /// Largest width or height of a texture that can be drawn into
+    fn max_framebuffer_size(&self) -> u32;
+    /// Sets the "model" uniform
+    fn send_model_matrix(&mut self, matrix: Matrix4<f32>) {
+        self.send_uniform("model", UniformValue::Mat4(matrix));
+    }
+    /// Sets the "camera" uniform
+    fn send_view_matrix(&mut self, matrix: Matrix4<f32>) {
+        self.send_uniform("camera", UniformValue::Mat4(matrix));
+    }
 }
+/// Constants from EXT_texture_filter_anisotropic, which web_sys does not expose
+const TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FF;
 pub struct WebGl {
     context: WebGl2RenderingContext,
-    position_attribute_location: i32,
-    uv_attribute_location: i32,
-    texture_sampler_location: Option<WebGlUniformLocation>,
+    /// Shared with every resource so they can update it when they are freed
+    live: Rc<Cell<ResourceReport>>,
+    bound_material: Option<WebGlMaterial>,
+    /// Set if EXT_texture_filter_anisotropic is supported
+    max_anisotropy: Option<f32>,
+    /// Whether EXT_color_buffer_float is supported, which float textures need to be drawn into
+    float_color: bool,
+    /// Smaller of MAX_TEXTURE_SIZE and MAX_RENDERBUFFER_SIZE
+    max_framebuffer_size: u32,
+    /// Used to give every gl object an id so bindings can be compared without calling into javascript
+    next_id: u32,
+    bound: BoundState,
+    stats: RenderStats,
+    /// Size of the canvas, used as the viewport of the default framebuffer
+    screen_dimensions: Vector2<u32>,
+    /// Holds the instances of the current instanced draw call. Every mesh reads its instance
+    /// attributes from it.
+    instance_buffer: Option<WebGlBuffer>,
+    /// Built the first time lines are drawn
+    lines: Option<LineObjects>,
+}
+/// Buffer and vertex array reused by every call to `draw_lines`
+struct LineObjects {
+    id: u32,
+    vertex_array: Option<WebGlVertexArrayObject>,
+    buffer: Option<WebGlBuffer>,
+}
+/// Ids of the objects currently bound, used to skip redundant binds
+#[derive(Default)]
+struct BoundState {
+    program: Option<u32>,
+    vertex_array: Option<u32>,
+    active_unit: u32,
+    depth_test: bool,
+    /// Starts unset so the first call to `set_blend_mode` always sets the gl state
+    blend_mode: Option<BlendMode>,
+    /// Texture bound to each texture unit
+    textures: HashMap<u32, u32>,
+    /// Size of the bound framebuffer
+    viewport: Vector2<u32>,
+    /// Scissor rectangle in pixels as x, y, width and height
+    clip: Option<[i32; 4]>,
+}
+/// Deletes gl objects once, either when asked to or when the object owning it is dropped
+struct GlOwner {
+    context: WebGl2RenderingContext,
+    live: Rc<Cell<ResourceReport>>,
+    deleted: Cell<bool>,
+}
+impl GlOwner {
+    fn new(engine: &WebGl, count: impl FnOnce(&mut ResourceReport)) -> Self {
+        let mut live = engine.live.get();
+        count(&mut live);
+        engine.live.set(live);
+        Self {
+            context: engine.context.clone(),
+            live: engine.live.clone(),
+            deleted: Cell::new(false),
+        }
+    }
+    /// Runs `delete` and updates the live counts the first time it is called
+    fn release(
+        &self,
+        count: impl FnOnce(&mut ResourceReport),
+        delete: impl FnOnce(&WebGl2RenderingContext),
+    ) {
+        if self.deleted.replace(true) {
+            return;
+        }
+        delete(&self.context);
+        let mut live = self.live.get();
+        count(&mut live);
+        self.live.set(live);
+    }
+}
+struct MeshObjects {
+    vertex_array_object: Option<WebGlVertexArrayObject>,
+    buffer: Option<WebGlBuffer>,
+    owner: GlOwner,
+}
+impl MeshObjects {
+    fn delete(&self) {
+        self.owner.release(
+            |live| live.meshes -= 1,
+            |context| {
+                context.delete_vertex_array(self.vertex_array_object.as_ref());
+                context.delete_buffer(self.buffer.as_ref());
+            },
+        );
+    }
+}
+impl Drop for MeshObjects {
+    fn drop(&mut self) {
+        self.delete()
+    }
+}
+struct TextureObject {
+    texture: Option<WebGlTexture>,
+    owner: GlOwner,
+}
+impl TextureObject {
+    fn delete(&self) {
+        self.owner.release(
+            |live| live.textures -= 1,
+            |context| context.delete_texture(self.texture.as_ref()),
+        );
+    }
+}
+impl Drop for TextureObject {
+    fn drop(&mut self) {
+        self.delete()
+    }
+}
+struct ProgramObject {
     program: WebGlProgram,
+    owner: GlOwner,
+}
+impl Drop for ProgramObject {
+    fn drop(&mut self) {
+        let program = &self.program;
+        self.owner.release(
+            |live| live.materials -= 1,
+            |context| context.delete_program(Some(program)),
+        );
+    }
 }
+/// Mesh on the gpu, freed when the last clone is dropped
 #[derive(Clone)]
 pub struct WebGlMesh {
-    vertex_array_object: Option<WebGlVertexArrayObject>,
-    position_buffer: Option<WebGlBuffer>,
+    id: u32,
+    objects: Rc<MeshObjects>,
     count: i32,
 }
+/// Texture on the gpu, freed when the last clone is dropped
 #[derive(Clone)]
 pub struct WebGlRenderTexture {
-    texture: Option<WebGlTexture>,
+    id: u32,
+    dimensions: Vector2<u32>,
+    is_depth: bool,
+    object: Rc<TextureObject>,
+}
+impl GpuResource for WebGlMesh {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+impl GpuResource for WebGlRenderTexture {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+impl GpuResource for WebGlMaterial {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+#[derive(Clone)]
+pub struct WebGlMaterial {
+    id: u32,
+    program: Rc<ProgramObject>,
+    uniforms: Rc<HashMap<String, WebGlUniformLocation>>,
+    /// Last value sent to each uniform. Uniform values belong to the program so they persist
+    /// between binds.
+    values: Rc<RefCell<HashMap<String, UniformValue>>>,
 }
 pub struct WebFramebuffer {
     framebuffer: Option<WebGlFramebuffer>,
+    dimensions: Vector2<u32>,
+    depth: Option<WebGlRenderbuffer>,
+    owner: GlOwner,
+}
+impl WebFramebuffer {
+    fn delete(&self) {
+        self.owner.release(
+            |live| live.framebuffers -= 1,
+            |context| {
+                context.delete_framebuffer(self.framebuffer.as_ref());
+                context.delete_renderbuffer(self.depth.as_ref());
+            },
+        );
+    }
+}
+impl Drop for WebFramebuffer {
+    fn drop(&mut self) {
+        self.delete()
+    }
 }
 impl GraphicsEngine for WebGl {
     type RuntimeMesh = WebGlMesh;
     type RuntimeTexture = WebGlRenderTexture;
     type ErrorType = JsValue;
     type Framebuffer = WebFramebuffer;
+    type Material = WebGlMaterial;
     fn init() -> Result<Self, Self::ErrorType> {
         let window = web_sys::window().unwrap();
         let document = window.document().unwrap();
@@ -90,55 +740,50 @@ impl GraphicsEngine for WebGl {
             .get_context("webgl2")?
             .unwrap()
             .dyn_into::<WebGl2RenderingContext>()?;
-        let vert_shader = Self::compile_shader(
-            &context,
-            WebGl2RenderingContext::VERTEX_SHADER,
-            r#"#version 300 es
-        in vec3 position;
-        in vec2 uv;
-        out vec2 o_uv;
-        uniform mat4 camera;
-        uniform mat4 model;
-        void main() {
-            gl_Position = camera*model*vec4(position,1.0);
-            o_uv = uv;
-        }
-    "#,
-        )?;
-        let frag_shader = Self::compile_shader(
+        let max_anisotropy = match context.get_extension("EXT_texture_filter_anisotropic")? {
+            Some(_) => context
+                .get_parameter(MAX_TEXTURE_MAX_ANISOTROPY_EXT)?
+                .as_f64()
+                .map(|max| max as f32),
+            None => None,
+        };
+        let float_color = context.get_extension("EXT_color_buffer_float")?.is_some();
+        let max_size = |parameter: u32| -> Result<u32, JsValue> {
+            //every WebGL2 implementation supports at least 2048
+            Ok(context.get_parameter(parameter)?.as_f64().unwrap_or(2048.0) as u32)
+        };
+        let max_framebuffer_size = max_size(WebGl2RenderingContext::MAX_TEXTURE_SIZE)?
+            .min(max_size(WebGl2RenderingContext::MAX_RENDERBUFFER_SIZE)?);
+        //starts with one instance so meshes drawn without instancing still have valid instance data
+        let instance_buffer = context.create_buffer();
+        context.bind_buffer(
+            WebGl2RenderingContext::ARRAY_BUFFER,
+            instance_buffer.as_ref(),
+        );
+        Self::upload_instances(
             &context,
-            WebGl2RenderingContext::FRAGMENT_SHADER,
-            r#"#version 300 es
-        precision highp float;
-        out vec4 color;
-        in vec2 o_uv;
-        uniform sampler2D u_texture;
-        void main() {
-            color = texture(u_texture,o_uv);
-        }
-    "#,
-        )?;
-        let program = Self::link_program(&context, &vert_shader, &frag_shader)?;
-        context.use_program(Some(&program));
-        let position_attribute_location = context.get_attrib_location(&program, "position");
-        let uv_attribute_location = context.get_attrib_location(&program, "uv");
-        let texture_sampler_location = context.get_uniform_location(&program, "u_texture");
+            &[Instance {
+                transform: Matrix4::identity(),
+                tint: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            }],
+        );
         Ok(Self {
             context,
-            position_attribute_location,
-            uv_attribute_location,
-            texture_sampler_location,
-            program,
+            screen_dimensions: Vector2::new(canvas.width(), canvas.height()),
+            instance_buffer,
+            lines: None,
+            live: Rc::new(Cell::new(ResourceReport::default())),
+            bound_material: None,
+            max_anisotropy,
+            float_color,
+            max_framebuffer_size,
+            next_id: 0,
+            bound: BoundState::default(),
+            stats: RenderStats::default(),
         })
     }
     fn build_mesh(&mut self, mesh: Mesh) -> Result<Self::RuntimeMesh, Self::ErrorType> {
-        let position_buffer = self.context.create_buffer();
         let mut array: Vec<f32> = vec![];
-
-        self.context.bind_buffer(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            (&position_buffer).as_ref(),
-        );
         for (vertex, uv) in mesh.vertices.iter() {
             array.push(vertex.x);
             array.push(vertex.y);
@@ -146,102 +791,200 @@ impl GraphicsEngine for WebGl {
             array.push(uv.x);
             array.push(uv.y);
         }
-        //  Note that `Float32Array::view` is somewhat dangerous (hence the
-        // `unsafe`!). This is creating a raw view into our module's
-        // `WebAssembly.Memory` buffer, but if we allocate more pages for ourself
-        // (aka do a memory allocation in Rust) it'll cause the buffer to change,
-        // causing the `Float32Array` to be invalid.
-        unsafe {
-            let vert_array = js_sys::Float32Array::view(&array);
-
-            self.context.buffer_data_with_array_buffer_view(
-                WebGl2RenderingContext::ARRAY_BUFFER,
-                &vert_array,
-                WebGl2RenderingContext::STATIC_DRAW,
-            );
+        Ok(self.build_vertex_array(
+            &array,
+            &[(VertexAttribute::Position, 3), (VertexAttribute::Uv, 2)],
+        ))
+    }
+    fn build_skinned_mesh(
+        &mut self,
+        mesh: SkinnedMesh,
+    ) -> Result<Self::RuntimeMesh, Self::ErrorType> {
+        let mut array: Vec<f32> = Vec::with_capacity(mesh.vertices.len() * SkinnedVertex::FLOATS);
+        for vertex in mesh.vertices.iter() {
+            array.extend_from_slice(vertex.position.as_slice());
+            array.extend_from_slice(vertex.uv.as_slice());
+            //joint indices are small enough to be exact as floats
+            array.extend(vertex.joints.iter().map(|joint| *joint as f32));
+            array.extend_from_slice(&vertex.weights);
         }
-        let vao = self.context.create_vertex_array();
-        self.context.bind_vertex_array(vao.as_ref());
-        self.context
-            .enable_vertex_attrib_array(self.position_attribute_location as u32);
-        self.context
-            .enable_vertex_attrib_array(self.uv_attribute_location as u32);
-        self.context.vertex_attrib_pointer_with_f64(
-            self.position_attribute_location as u32,
-            3,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            5 * std::mem::size_of::<f32>() as i32,
-            0.0,
-        );
-        self.context.vertex_attrib_pointer_with_i32(
-            self.uv_attribute_location as u32,
-            2,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            5 * std::mem::size_of::<f32>() as i32,
-            3 * std::mem::size_of::<f32>() as i32,
-        );
-        Ok(WebGlMesh {
-            vertex_array_object: vao,
-            position_buffer,
-            count: mesh.vertices.len() as i32,
-        })
+        Ok(self.build_vertex_array(
+            &array,
+            &[
+                (VertexAttribute::Position, 3),
+                (VertexAttribute::Uv, 2),
+                (VertexAttribute::Joints, 4),
+                (VertexAttribute::Weights, 4),
+            ],
+        ))
     }
     fn build_texture(
         &mut self,
         texture: RGBATexture,
+        options: TextureOptions,
     ) -> Result<Self::RuntimeTexture, Self::ErrorType> {
         let gl_texture = self.context.create_texture();
         assert!(gl_texture.is_some());
+        let id = self.new_id();
+        let texture_unit = 0;
+        self.context
+            .active_texture(WebGl2RenderingContext::TEXTURE0 + texture_unit);
         self.context
             .bind_texture(WebGl2RenderingContext::TEXTURE_2D, gl_texture.as_ref());
+        self.bound.active_unit = texture_unit;
+        self.bound.textures.insert(texture_unit, id);
+        self.upload_texture_level(0, &texture)?;
+        match options.mipmaps {
+            Mipmaps::None => (),
+            Mipmaps::Gpu => self
+                .context
+                .generate_mipmap(WebGl2RenderingContext::TEXTURE_2D),
+            Mipmaps::Cpu => {
+                for (level, mip) in texture.mipmap_chain().iter().enumerate() {
+                    self.upload_texture_level(level as i32 + 1, mip)?;
+                }
+            }
+        }
+        let min_filter = match (options.min_filter, options.mipmaps) {
+            (TextureFilter::Nearest, Mipmaps::None) => WebGl2RenderingContext::NEAREST,
+            (TextureFilter::Linear, Mipmaps::None) => WebGl2RenderingContext::LINEAR,
+            (TextureFilter::Nearest, _) => WebGl2RenderingContext::NEAREST_MIPMAP_NEAREST,
+            (TextureFilter::Linear, _) => WebGl2RenderingContext::LINEAR_MIPMAP_LINEAR,
+        };
+        let mag_filter = match options.mag_filter {
+            TextureFilter::Nearest => WebGl2RenderingContext::NEAREST,
+            TextureFilter::Linear => WebGl2RenderingContext::LINEAR,
+        };
+        let wrap = |wrap: TextureWrap| match wrap {
+            TextureWrap::Repeat => WebGl2RenderingContext::REPEAT,
+            TextureWrap::MirroredRepeat => WebGl2RenderingContext::MIRRORED_REPEAT,
+            TextureWrap::ClampToEdge => WebGl2RenderingContext::CLAMP_TO_EDGE,
+        };
+        let parameters = [
+            (WebGl2RenderingContext::TEXTURE_MIN_FILTER, min_filter),
+            (WebGl2RenderingContext::TEXTURE_MAG_FILTER, mag_filter),
+            (WebGl2RenderingContext::TEXTURE_WRAP_S, wrap(options.wrap_s)),
+            (WebGl2RenderingContext::TEXTURE_WRAP_T, wrap(options.wrap_t)),
+        ];
+        for (parameter, value) in parameters.iter() {
+            self.context.tex_parameteri(
+                WebGl2RenderingContext::TEXTURE_2D,
+                *parameter,
+                *value as i32,
+            );
+        }
+        if let Some(max_anisotropy) = self.max_anisotropy {
+            if options.anisotropy > 1.0 {
+                self.context.tex_parameterf(
+                    WebGl2RenderingContext::TEXTURE_2D,
+                    TEXTURE_MAX_ANISOTROPY_EXT,
+                    options.anisotropy.min(max_anisotropy),
+                );
+            }
+        }
+        Ok(WebGlRenderTexture {
+            id,
+            dimensions: texture.dimensions,
+            is_depth: false,
+            object: Rc::new(TextureObject {
+                texture: gl_texture,
+                owner: GlOwner::new(self, |live| live.textures += 1),
+            }),
+        })
+    }
+    fn build_float_texture(
+        &mut self,
+        dimensions: Vector2<u32>,
+    ) -> Result<Self::RuntimeTexture, Self::ErrorType> {
+        if !self.float_color {
+            return self.build_texture(
+                RGBATexture::constant_color(Vector4::new(0, 0, 0, 0), dimensions),
+                TextureOptions::default(),
+            );
+        }
+        let gl_texture = self.context.create_texture();
+        let id = self.new_id();
         let texture_unit = 0;
         self.context
             .active_texture(WebGl2RenderingContext::TEXTURE0 + texture_unit);
-        let level = 0;
         self.context
-            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_u8_array_and_src_offset(
+            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, gl_texture.as_ref());
+        self.bound.active_unit = texture_unit;
+        self.bound.textures.insert(texture_unit, id);
+        //half floats can be filtered without any extension
+        self.context
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
                 WebGl2RenderingContext::TEXTURE_2D,
-                level,
-                //  Use RGBA Format
-                WebGl2RenderingContext::RGBA as i32,
-                //width
-                texture.dimensions.x as i32,
-                //height
-                texture.dimensions.y as i32,
-                //must be 0 specifies the border
                 0,
-                //  Use RGB Format
-                WebGl2RenderingContext::RGBA,
-                WebGl2RenderingContext::UNSIGNED_BYTE,
-                texture.get_raw_vector().as_slice(),
+                WebGl2RenderingContext::RGBA16F as i32,
+                dimensions.x as i32,
+                dimensions.y as i32,
                 0,
+                WebGl2RenderingContext::RGBA,
+                WebGl2RenderingContext::HALF_FLOAT,
+                None,
             )?;
-        //self.gl_context.generate_mipmap(WebGl2RenderingContext::TEXTURE_2D);
-        //getting location of sampler
-
-        self.context
-            .uniform1i(self.texture_sampler_location.as_ref(), texture_unit as i32);
-        self.context.tex_parameteri(
-            WebGl2RenderingContext::TEXTURE_2D,
-            WebGl2RenderingContext::TEXTURE_MIN_FILTER,
-            WebGl2RenderingContext::LINEAR as i32,
-        );
-        self.context.tex_parameteri(
-            WebGl2RenderingContext::TEXTURE_2D,
-            WebGl2RenderingContext::TEXTURE_WRAP_S,
-            WebGl2RenderingContext::CLAMP_TO_EDGE as i32,
-        );
-        self.context.tex_parameteri(
-            WebGl2RenderingContext::TEXTURE_2D,
-            WebGl2RenderingContext::TEXTURE_WRAP_T,
-            WebGl2RenderingContext::CLAMP_TO_EDGE as i32,
-        );
+        let parameters = [
+            (
+                WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+                WebGl2RenderingContext::LINEAR,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+                WebGl2RenderingContext::LINEAR,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_WRAP_S,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_WRAP_T,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+            ),
+        ];
+        for (parameter, value) in parameters.iter() {
+            self.context.tex_parameteri(
+                WebGl2RenderingContext::TEXTURE_2D,
+                *parameter,
+                *value as i32,
+            );
+        }
         Ok(WebGlRenderTexture {
-            texture: gl_texture,
+            id,
+            dimensions,
+            is_depth: false,
+            object: Rc::new(TextureObject {
+                texture: gl_texture,
+                owner: GlOwner::new(self, |live| live.textures += 1),
+            }),
         })
     }
+    fn update_texture_region(
+        &mut self,
+        texture: &Self::RuntimeTexture,
+        offset: Vector2<u32>,
+        region: &RGBATexture,
+    ) -> Result<(), Self::ErrorType> {
+        if !fits(offset.x, region.dimensions().x, texture.dimensions.x)
+            || !fits(offset.y, region.dimensions().y, texture.dimensions.y)
+        {
+            return Err(JsValue::from_str("texture region is out of bounds"));
+        }
+        self.bind_texture_unit(texture, 0);
+        self.context
+            .tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_u8_array_and_src_offset(
+                WebGl2RenderingContext::TEXTURE_2D,
+                0,
+                offset.x as i32,
+                offset.y as i32,
+                region.dimensions().x as i32,
+                region.dimensions().y as i32,
+                WebGl2RenderingContext::RGBA,
+                WebGl2RenderingContext::UNSIGNED_BYTE,
+                region.get_raw_vector().as_slice(),
+                0,
+            )
+    }
     fn build_framebuffer(
         &mut self,
         texture_attachment: &mut Self::RuntimeTexture,
@@ -249,66 +992,603 @@ impl GraphicsEngine for WebGl {
         let framebuffer = self.context.create_framebuffer();
         self.context
             .bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, framebuffer.as_ref());
-        self.context.framebuffer_texture_2d(
-            WebGl2RenderingContext::FRAMEBUFFER,
-            WebGl2RenderingContext::COLOR_ATTACHMENT0,
-            WebGl2RenderingContext::TEXTURE_2D,
-            texture_attachment.texture.as_ref(),
-            0,
-        );
+        let depth = if texture_attachment.is_depth {
+            self.context.framebuffer_texture_2d(
+                WebGl2RenderingContext::FRAMEBUFFER,
+                WebGl2RenderingContext::DEPTH_ATTACHMENT,
+                WebGl2RenderingContext::TEXTURE_2D,
+                texture_attachment.object.texture.as_ref(),
+                0,
+            );
+            None
+        } else {
+            self.context.framebuffer_texture_2d(
+                WebGl2RenderingContext::FRAMEBUFFER,
+                WebGl2RenderingContext::COLOR_ATTACHMENT0,
+                WebGl2RenderingContext::TEXTURE_2D,
+                texture_attachment.object.texture.as_ref(),
+                0,
+            );
+            let depth = self.context.create_renderbuffer();
+            self.context
+                .bind_renderbuffer(WebGl2RenderingContext::RENDERBUFFER, depth.as_ref());
+            self.context.renderbuffer_storage(
+                WebGl2RenderingContext::RENDERBUFFER,
+                WebGl2RenderingContext::DEPTH_COMPONENT16,
+                texture_attachment.dimensions.x as i32,
+                texture_attachment.dimensions.y as i32,
+            );
+            self.context.framebuffer_renderbuffer(
+                WebGl2RenderingContext::FRAMEBUFFER,
+                WebGl2RenderingContext::DEPTH_ATTACHMENT,
+                WebGl2RenderingContext::RENDERBUFFER,
+                depth.as_ref(),
+            );
+            depth
+        };
         // rebinding to default framebuffer to prevent side effects
         self.bind_default_framebuffer();
-        WebFramebuffer { framebuffer }
+        WebFramebuffer {
+            framebuffer,
+            dimensions: texture_attachment.dimensions,
+            depth,
+            owner: GlOwner::new(self, |live| live.framebuffers += 1),
+        }
     }
     fn bind_default_framebuffer(&mut self) {
         self.context
             .bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+        self.context.viewport(
+            0,
+            0,
+            self.screen_dimensions.x as i32,
+            self.screen_dimensions.y as i32,
+        );
+        self.bound.viewport = self.screen_dimensions;
+        self.stats.state_changes += 1;
+    }
+    fn build_depth_texture(
+        &mut self,
+        dimensions: Vector2<u32>,
+    ) -> Result<Self::RuntimeTexture, Self::ErrorType> {
+        let gl_texture = self.context.create_texture();
+        let id = self.new_id();
+        let texture_unit = 0;
+        self.context
+            .active_texture(WebGl2RenderingContext::TEXTURE0 + texture_unit);
+        self.context
+            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, gl_texture.as_ref());
+        self.bound.active_unit = texture_unit;
+        self.bound.textures.insert(texture_unit, id);
+        self.context
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                WebGl2RenderingContext::TEXTURE_2D,
+                0,
+                WebGl2RenderingContext::DEPTH_COMPONENT24 as i32,
+                dimensions.x as i32,
+                dimensions.y as i32,
+                0,
+                WebGl2RenderingContext::DEPTH_COMPONENT,
+                WebGl2RenderingContext::UNSIGNED_INT,
+                None,
+            )?;
+        //depth textures can not be filtered
+        let parameters = [
+            (
+                WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+                WebGl2RenderingContext::NEAREST,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+                WebGl2RenderingContext::NEAREST,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_WRAP_S,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_WRAP_T,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+            ),
+        ];
+        for (parameter, value) in parameters.iter() {
+            self.context.tex_parameteri(
+                WebGl2RenderingContext::TEXTURE_2D,
+                *parameter,
+                *value as i32,
+            );
+        }
+        Ok(WebGlRenderTexture {
+            id,
+            dimensions,
+            is_depth: true,
+            object: Rc::new(TextureObject {
+                texture: gl_texture,
+                owner: GlOwner::new(self, |live| live.textures += 1),
+            }),
+        })
     }
     fn clear_screen(&mut self, color: Vector4<f32>) {
         self.context.clear_color(color.x, color.y, color.z, color.w);
-        self.context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+        //depth is only cleared while depth writes are enabled
+        self.context.depth_mask(true);
+        self.context.clear(
+            WebGl2RenderingContext::COLOR_BUFFER_BIT | WebGl2RenderingContext::DEPTH_BUFFER_BIT,
+        );
+        if let Some(mode) = self.bound.blend_mode {
+            self.context.depth_mask(!mode.is_transparent());
+        }
+    }
+    fn set_depth_test(&mut self, enabled: bool) {
+        if self.bound.depth_test == enabled {
+            return;
+        }
+        if enabled {
+            self.context.enable(WebGl2RenderingContext::DEPTH_TEST);
+        } else {
+            self.context.disable(WebGl2RenderingContext::DEPTH_TEST);
+        }
+        self.bound.depth_test = enabled;
+        self.stats.state_changes += 1;
+    }
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        if self.bound.blend_mode == Some(mode) {
+            return;
+        }
+        let gl = &self.context;
+        match mode {
+            BlendMode::Opaque | BlendMode::Cutout => gl.disable(WebGl2RenderingContext::BLEND),
+            BlendMode::Alpha => {
+                gl.enable(WebGl2RenderingContext::BLEND);
+                //alpha is accumulated so the world framebuffer stays opaque
+                gl.blend_func_separate(
+                    WebGl2RenderingContext::SRC_ALPHA,
+                    WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+                    WebGl2RenderingContext::ONE,
+                    WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+                );
+            }
+            BlendMode::Additive => {
+                gl.enable(WebGl2RenderingContext::BLEND);
+                gl.blend_func(
+                    WebGl2RenderingContext::SRC_ALPHA,
+                    WebGl2RenderingContext::ONE,
+                );
+            }
+            BlendMode::Premultiplied => {
+                gl.enable(WebGl2RenderingContext::BLEND);
+                gl.blend_func(
+                    WebGl2RenderingContext::ONE,
+                    WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+                );
+            }
+        }
+        //transparent geometry is tested against the depth buffer but does not hide what is behind it
+        gl.depth_mask(!mode.is_transparent());
+        self.bound.blend_mode = Some(mode);
+        self.stats.state_changes += 1;
     }
     fn bind_texture(&mut self, texture: &Self::RuntimeTexture) {
-        self.context
-            .active_texture(WebGl2RenderingContext::TEXTURE0);
-        self.context
-            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, texture.texture.as_ref());
-        self.context
-            .uniform1i(self.texture_sampler_location.as_ref(), 0);
+        self.bind_texture_unit(texture, 0);
+        self.send_uniform("u_texture", UniformValue::Texture(0));
+    }
+    fn bind_texture_unit(&mut self, texture: &Self::RuntimeTexture, unit: u32) {
+        if self.bound.textures.get(&unit) == Some(&texture.id) {
+            return;
+        }
+        if self.bound.active_unit != unit {
+            self.context
+                .active_texture(WebGl2RenderingContext::TEXTURE0 + unit);
+            self.bound.active_unit = unit;
+        }
+        self.context.bind_texture(
+            WebGl2RenderingContext::TEXTURE_2D,
+            texture.object.texture.as_ref(),
+        );
+        self.bound.textures.insert(unit, texture.id);
+        self.stats.state_changes += 1;
     }
     fn bind_framebuffer(&mut self, framebuffer: &Self::Framebuffer) {
         self.context.bind_framebuffer(
             WebGl2RenderingContext::FRAMEBUFFER,
             framebuffer.framebuffer.as_ref(),
         );
+        self.context.viewport(
+            0,
+            0,
+            framebuffer.dimensions.x as i32,
+            framebuffer.dimensions.y as i32,
+        );
+        self.bound.viewport = framebuffer.dimensions;
+        self.stats.state_changes += 1;
+    }
+    fn set_clip(&mut self, clip: Option<(Vector2<f32>, Vector2<f32>)>) {
+        let viewport = self.bound.viewport.map(|c| c as f32);
+        let to_pixels = |position: Vector2<f32>| {
+            ((position + Vector2::new(1.0, 1.0)) * 0.5)
+                .component_mul(&viewport)
+                .map(|c| c.round() as i32)
+        };
+        let clip = clip.map(|(min, max)| {
+            let (min, max) = (to_pixels(min), to_pixels(max));
+            [min.x, min.y, (max.x - min.x).max(0), (max.y - min.y).max(0)]
+        });
+        if self.bound.clip == clip {
+            return;
+        }
+        match clip {
+            Some([x, y, width, height]) => {
+                self.context.enable(WebGl2RenderingContext::SCISSOR_TEST);
+                self.context.scissor(x, y, width, height);
+            }
+            None => self.context.disable(WebGl2RenderingContext::SCISSOR_TEST),
+        }
+        self.bound.clip = clip;
+        self.stats.state_changes += 1;
     }
     fn draw_mesh(&mut self, mesh: &Self::RuntimeMesh) {
-        self.context
-            .bind_vertex_array(mesh.vertex_array_object.as_ref());
+        if self.bound.vertex_array != Some(mesh.id) {
+            self.context
+                .bind_vertex_array(mesh.objects.vertex_array_object.as_ref());
+            self.bound.vertex_array = Some(mesh.id);
+            self.stats.state_changes += 1;
+        }
         self.context
             .draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, mesh.count);
+        self.stats.draw_calls += 1;
     }
-    fn send_model_matrix(&mut self, matrix: Matrix4<f32>) {
-        let model_uniform = self.context.get_uniform_location(&self.program, "model");
-        self.context.uniform_matrix4fv_with_f32_array(
-            model_uniform.as_ref(),
-            false,
-            matrix.as_slice(),
+    fn draw_instanced(&mut self, mesh: &Self::RuntimeMesh, instances: &[Instance]) {
+        if instances.is_empty() {
+            return;
+        }
+        if self.bound.vertex_array != Some(mesh.id) {
+            self.context
+                .bind_vertex_array(mesh.objects.vertex_array_object.as_ref());
+            self.bound.vertex_array = Some(mesh.id);
+            self.stats.state_changes += 1;
+        }
+        self.context.bind_buffer(
+            WebGl2RenderingContext::ARRAY_BUFFER,
+            self.instance_buffer.as_ref(),
         );
-    }
-    fn send_view_matrix(&mut self, matrix: Matrix4<f32>) {
-        let model_uniform = self.context.get_uniform_location(&self.program, "camera");
-        self.context.uniform_matrix4fv_with_f32_array(
-            model_uniform.as_ref(),
-            false,
-            matrix.as_slice(),
+        Self::upload_instances(&self.context, instances);
+        self.context.draw_arrays_instanced(
+            WebGl2RenderingContext::TRIANGLES,
+            0,
+            mesh.count,
+            instances.len() as i32,
         );
+        self.stats.draw_calls += 1;
+        self.stats.instances += instances.len() as u32;
+    }
+    fn draw_lines(&mut self, vertices: &[LineVertex]) {
+        if vertices.is_empty() {
+            return;
+        }
+        if self.lines.is_none() {
+            let lines = self.build_line_objects();
+            self.lines = Some(lines);
+        }
+        let lines = self.lines.as_ref().unwrap();
+        if self.bound.vertex_array != Some(lines.id) {
+            self.context.bind_vertex_array(lines.vertex_array.as_ref());
+            self.bound.vertex_array = Some(lines.id);
+            self.stats.state_changes += 1;
+        }
+        self.context
+            .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, lines.buffer.as_ref());
+        let mut array: Vec<f32> = Vec::with_capacity(vertices.len() * LineVertex::FLOATS);
+        for vertex in vertices.iter() {
+            array.extend_from_slice(vertex.position.as_slice());
+            array.extend_from_slice(vertex.color.as_slice());
+        }
+        // see the note in `build_mesh`, no allocations may happen while the view exists
+        unsafe {
+            let line_array = js_sys::Float32Array::view(&array);
+            self.context.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &line_array,
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+            );
+        }
+        self.context
+            .draw_arrays(WebGl2RenderingContext::LINES, 0, vertices.len() as i32);
+        self.stats.draw_calls += 1;
+    }
+    fn build_material(
+        &mut self,
+        shader: &ShaderDescription,
+    ) -> Result<Self::Material, Self::ErrorType> {
+        let vert_shader = Self::compile_shader(
+            &self.context,
+            WebGl2RenderingContext::VERTEX_SHADER,
+            &shader.name,
+            &shader.vertex_source,
+        )?;
+        let frag_shader = Self::compile_shader(
+            &self.context,
+            WebGl2RenderingContext::FRAGMENT_SHADER,
+            &shader.name,
+            &shader.fragment_source,
+        )?;
+        let program = self
+            .context
+            .create_program()
+            .ok_or_else(|| String::from("Unable to create shader object"))?;
+        for (name, attribute) in shader.attributes.iter() {
+            self.context
+                .bind_attrib_location(&program, attribute.location(), name);
+        }
+        let linked = Self::link_program(&self.context, &program, &vert_shader, &frag_shader);
+        //the shaders are no longer needed once the program is linked
+        self.context.delete_shader(Some(&vert_shader));
+        self.context.delete_shader(Some(&frag_shader));
+        if let Err(e) = linked {
+            self.context.delete_program(Some(&program));
+            return Err(format!("shader \"{}\" failed to link: {}", shader.name, e).into());
+        }
+        let uniforms = shader
+            .uniforms
+            .iter()
+            .filter_map(|name| {
+                self.context
+                    .get_uniform_location(&program, name)
+                    .map(|location| (name.clone(), location))
+            })
+            .collect();
+        Ok(WebGlMaterial {
+            id: self.new_id(),
+            program: Rc::new(ProgramObject {
+                program,
+                owner: GlOwner::new(self, |live| live.materials += 1),
+            }),
+            uniforms: Rc::new(uniforms),
+            values: Rc::new(RefCell::new(HashMap::new())),
+        })
+    }
+    fn bind_material(&mut self, material: &Self::Material) {
+        if self.bound.program == Some(material.id) {
+            return;
+        }
+        self.context.use_program(Some(&material.program.program));
+        self.bound.program = Some(material.id);
+        self.bound_material = Some(material.clone());
+        self.stats.state_changes += 1;
+    }
+    fn send_uniform(&mut self, name: &str, value: UniformValue) {
+        let material = match self.bound_material.as_ref() {
+            Some(material) => material,
+            None => return,
+        };
+        let location = match material.uniforms.get(name) {
+            Some(location) => location,
+            None => return,
+        };
+        let mut values = material.values.borrow_mut();
+        if values.get(name) == Some(&value) {
+            return;
+        }
+        self.stats.uniform_uploads += 1;
+        match &value {
+            UniformValue::Float(v) => self.context.uniform1f(Some(location), *v),
+            UniformValue::Int(v) => self.context.uniform1i(Some(location), *v),
+            UniformValue::Vec2(v) => self.context.uniform2f(Some(location), v.x, v.y),
+            UniformValue::Vec3(v) => self.context.uniform3f(Some(location), v.x, v.y, v.z),
+            UniformValue::Vec4(v) => self.context.uniform4f(Some(location), v.x, v.y, v.z, v.w),
+            UniformValue::Mat4(m) => {
+                self.context
+                    .uniform_matrix4fv_with_f32_array(Some(location), false, m.as_slice())
+            }
+            UniformValue::Mat4Array(matrices) => {
+                let floats: Vec<f32> = matrices
+                    .iter()
+                    .flat_map(|m| m.as_slice().iter().copied())
+                    .collect();
+                self.context
+                    .uniform_matrix4fv_with_f32_array(Some(location), false, &floats)
+            }
+            UniformValue::Texture(unit) => self.context.uniform1i(Some(location), *unit as i32),
+        }
+        values.insert(name.to_string(), value);
+    }
+    fn take_stats(&mut self) -> RenderStats {
+        std::mem::take(&mut self.stats)
+    }
+    fn delete_mesh(&mut self, mesh: &Self::RuntimeMesh) {
+        mesh.objects.delete();
+    }
+    fn delete_texture(&mut self, texture: &Self::RuntimeTexture) {
+        texture.object.delete();
+    }
+    fn delete_framebuffer(&mut self, framebuffer: &Self::Framebuffer) {
+        framebuffer.delete();
+    }
+    fn read_pixels(
+        &mut self,
+        framebuffer: &Self::Framebuffer,
+    ) -> Result<RGBATexture, Self::ErrorType> {
+        self.bind_framebuffer(framebuffer);
+        let dimensions = framebuffer.dimensions;
+        let mut bytes = vec![0; (dimensions.x * dimensions.y * 4) as usize];
+        //rows are read starting at the bottom which matches the order of `RGBATexture`
+        self.context.read_pixels_with_opt_u8_array(
+            0,
+            0,
+            dimensions.x as i32,
+            dimensions.y as i32,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+            Some(&mut bytes),
+        )?;
+        let pixels = bytes
+            .chunks_exact(4)
+            .map(|p| Vector4::new(p[0], p[1], p[2], p[3]))
+            .collect();
+        Ok(RGBATexture::new(dimensions, pixels))
+    }
+    fn live_resources(&self) -> ResourceReport {
+        self.live.get()
+    }
+    fn max_framebuffer_size(&self) -> u32 {
+        self.max_framebuffer_size
     }
 }
 impl WebGl {
+    /// Fills the bound array buffer with instance data
+    fn upload_instances(context: &WebGl2RenderingContext, instances: &[Instance]) {
+        let mut array: Vec<f32> = Vec::with_capacity(instances.len() * Instance::FLOATS);
+        for instance in instances.iter() {
+            array.extend_from_slice(instance.transform.as_slice());
+            array.extend_from_slice(instance.tint.as_slice());
+        }
+        // see the note in `build_mesh`, no allocations may happen while the view exists
+        unsafe {
+            let instance_array = js_sys::Float32Array::view(&array);
+            context.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &instance_array,
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+            );
+        }
+    }
+    /// Builds the vertex array used by `draw_lines`. Lines are drawn without instance data
+    fn build_line_objects(&mut self) -> LineObjects {
+        let buffer = self.context.create_buffer();
+        let vertex_array = self.context.create_vertex_array();
+        self.context.bind_vertex_array(vertex_array.as_ref());
+        self.context
+            .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, buffer.as_ref());
+        let stride = (LineVertex::FLOATS * std::mem::size_of::<f32>()) as i32;
+        self.context
+            .enable_vertex_attrib_array(VertexAttribute::Position.location());
+        self.context.vertex_attrib_pointer_with_i32(
+            VertexAttribute::Position.location(),
+            3,
+            WebGl2RenderingContext::FLOAT,
+            false,
+            stride,
+            0,
+        );
+        self.context
+            .enable_vertex_attrib_array(VertexAttribute::Color.location());
+        self.context.vertex_attrib_pointer_with_i32(
+            VertexAttribute::Color.location(),
+            4,
+            WebGl2RenderingContext::FLOAT,
+            false,
+            stride,
+            3 * std::mem::size_of::<f32>() as i32,
+        );
+        let id = self.new_id();
+        self.bound.vertex_array = Some(id);
+        LineObjects {
+            id,
+            vertex_array,
+            buffer,
+        }
+    }
+    fn new_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+    /// Uploads interleaved vertices made of `attributes`, each with the given number of floats, and
+    /// builds a vertex array reading them along with the instance buffer
+    fn build_vertex_array(
+        &mut self,
+        array: &[f32],
+        attributes: &[(VertexAttribute, i32)],
+    ) -> WebGlMesh {
+        let vertex_buffer = self.context.create_buffer();
+        self.context
+            .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, vertex_buffer.as_ref());
+        //  Note that `Float32Array::view` is somewhat dangerous (hence the
+        // `unsafe`!). This is creating a raw view into our module's
+        // `WebAssembly.Memory` buffer, but if we allocate more pages for ourself
+        // (aka do a memory allocation in Rust) it'll cause the buffer to change,
+        // causing the `Float32Array` to be invalid.
+        unsafe {
+            let vert_array = js_sys::Float32Array::view(array);
+
+            self.context.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &vert_array,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+        }
+        let vao = self.context.create_vertex_array();
+        self.context.bind_vertex_array(vao.as_ref());
+        let floats_per_vertex: i32 = attributes.iter().map(|(_, size)| size).sum();
+        let float_size = std::mem::size_of::<f32>() as i32;
+        let mut offset = 0;
+        for (attribute, size) in attributes.iter() {
+            self.context
+                .enable_vertex_attrib_array(attribute.location());
+            self.context.vertex_attrib_pointer_with_i32(
+                attribute.location(),
+                *size,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                floats_per_vertex * float_size,
+                offset * float_size,
+            );
+            offset += size;
+        }
+        self.context.bind_buffer(
+            WebGl2RenderingContext::ARRAY_BUFFER,
+            self.instance_buffer.as_ref(),
+        );
+        let stride = (Instance::FLOATS * std::mem::size_of::<f32>()) as i32;
+        //the transform is split into one attribute per column followed by the tint
+        for column in 0..5 {
+            let location = VertexAttribute::InstanceTransform.location() + column;
+            self.context.enable_vertex_attrib_array(location);
+            self.context.vertex_attrib_pointer_with_i32(
+                location,
+                4,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                stride,
+                (4 * column as usize * std::mem::size_of::<f32>()) as i32,
+            );
+            self.context.vertex_attrib_divisor(location, 1);
+        }
+        let id = self.new_id();
+        self.bound.vertex_array = Some(id);
+        WebGlMesh {
+            id,
+            objects: Rc::new(MeshObjects {
+                vertex_array_object: vao,
+                buffer: vertex_buffer,
+                owner: GlOwner::new(self, |live| live.meshes += 1),
+            }),
+            count: (array.len() as i32) / floats_per_vertex,
+        }
+    }
+    /// Uploads one mip level to the bound texture
+    fn upload_texture_level(&mut self, level: i32, texture: &RGBATexture) -> Result<(), JsValue> {
+        self.context
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_u8_array_and_src_offset(
+                WebGl2RenderingContext::TEXTURE_2D,
+                level,
+                //  Use RGBA Format
+                WebGl2RenderingContext::RGBA as i32,
+                //width
+                texture.dimensions.x as i32,
+                //height
+                texture.dimensions.y as i32,
+                //must be 0 specifies the border
+                0,
+                //  Use RGB Format
+                WebGl2RenderingContext::RGBA,
+                WebGl2RenderingContext::UNSIGNED_BYTE,
+                texture.get_raw_vector().as_slice(),
+                0,
+            )
+    }
     fn compile_shader(
         context: &WebGl2RenderingContext,
         shader_type: u32,
+        name: &str,
         source: &str,
     ) -> Result<WebGlShader, String> {
         let shader = context
@@ -324,46 +1604,62 @@ impl WebGl {
         {
             Ok(shader)
         } else {
-            Err(context
+            let stage = if shader_type == WebGl2RenderingContext::VERTEX_SHADER {
+                "vertex"
+            } else {
+                "fragment"
+            };
+            let log = context
                 .get_shader_info_log(&shader)
-                .unwrap_or_else(|| String::from("Unknown error creating shader")))
+                .unwrap_or_else(|| String::from("Unknown error creating shader"));
+            Err(format!(
+                "{} shader of \"{}\" failed to compile:\n{}",
+                stage,
+                name,
+                annotate_shader_log(&log, source)
+            ))
         }
     }
     fn link_program(
         context: &WebGl2RenderingContext,
+        program: &WebGlProgram,
         vert_shader: &WebGlShader,
         frag_shader: &WebGlShader,
-    ) -> Result<WebGlProgram, String> {
-        let program = context
-            .create_program()
-            .ok_or_else(|| String::from("Unable to create shader object"))?;
-
-        context.attach_shader(&program, vert_shader);
-        context.attach_shader(&program, frag_shader);
-        context.link_program(&program);
+    ) -> Result<(), String> {
+        context.attach_shader(program, vert_shader);
+        context.attach_shader(program, frag_shader);
+        context.link_program(program);
 
         if context
-            .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
+            .get_program_parameter(program, WebGl2RenderingContext::LINK_STATUS)
             .as_bool()
             .unwrap_or(false)
         {
-            Ok(program)
+            Ok(())
         } else {
             Err(context
-                .get_program_info_log(&program)
+                .get_program_info_log(program)
                 .unwrap_or_else(|| String::from("Unknown error creating program object")))
         }
     }
-    fn bind_gl_texture(&self, texture: Option<&WebGlTexture>) {
-        self.context
-            .active_texture(WebGl2RenderingContext::TEXTURE0);
-        self.context
-            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, texture);
-        self.context
-            .uniform1i(self.texture_sampler_location.as_ref(), 0);
-    }
-    fn bind_framebuffer(&self, framebuffer: Option<&WebGlFramebuffer>) {
-        self.context
-            .bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, framebuffer);
-    }
+}
+/// Adds the source line after each error in a shader info log. WebGL logs errors as
+/// "ERROR: 0:<line>: <message>".
+pub fn annotate_shader_log(log: &str, source: &str) -> String {
+    let source_lines: Vec<&str> = source.lines().collect();
+    log.lines()
+        .map(|line| {
+            let source_line = line
+                .split(':')
+                .nth(2)
+                .and_then(|number| number.trim().parse::<usize>().ok())
+                .and_then(|number| number.checked_sub(1))
+                .and_then(|index| source_lines.get(index));
+            match source_line {
+                Some(source_line) => format!("{}\n    {}", line, source_line.trim()),
+                None => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
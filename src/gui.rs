@@ -1,61 +1,348 @@
 use super::prelude::{Camera, Event, Model, MouseClick};
+use super::KeyPress;
+use super::MouseButton;
+use super::{Mesh, RGBATexture, TextureOptions};
 use generational_arena::{Arena, Index as ArenaIndex};
-use nalgebra::Vector2;
+use nalgebra::{Vector2, Vector3, Vector4};
 use std::collections::{HashMap, HashSet};
 use std::marker::Sized;
-struct GuiContainer<RenderModel> {
-    elements: Arena<Box<dyn GuiElement<RenderModel>>>,
-    render_models: Arena<Option<RenderModel>>,
-    /// points to index of parent in world arena
-    parent_index: ArenaIndex,
+mod button;
+mod font;
+mod label;
+mod panel;
+mod slider;
+mod text_input;
+mod theme;
+mod window;
+pub use button::Button;
+pub use font::Font;
+/// Used to draw text in the world with lines
+pub(crate) use font::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+pub use label::Label;
+pub use panel::Panel;
+pub use slider::Slider;
+pub use text_input::TextInput;
+pub use theme::{NineSlice, Palette, PanelColors, PanelKind, Theme};
+pub use window::Window;
+use window::WindowFrame;
+/// How long the mouse has to rest on something before its tooltip is shown
+const TOOLTIP_DELAY_MS: f32 = 500.0;
+/// Height of tooltip text in screen coordinates
+const TOOLTIP_HEIGHT: f32 = 0.05;
+/// How far each newly opened window is moved from the last one so they do not hide each other
+const WINDOW_CASCADE: f32 = 0.05;
+pub struct GuiContainer<RenderModel> {
+    elements: Arena<Box<dyn GuiElement>>,
+    render_models: HashMap<ArenaIndex, Vec<RenderModel>>,
+    /// Translation applied to every element. Changed by dragging windows
+    offset: Vector2<f32>,
+    window: Option<Box<WindowFrame>>,
 }
 impl<RenderModel: Sized> GuiContainer<RenderModel> {
-    fn get_screen_collider(&self) -> Vec<Triangle> {
-        todo!("get_screen collider")
+    pub fn new() -> Self {
+        Self {
+            elements: Arena::new(),
+            render_models: HashMap::new(),
+            offset: Vector2::new(0.0, 0.0),
+            window: None,
+        }
+    }
+    pub fn add_element(&mut self, element: Box<dyn GuiElement>) -> ArenaIndex {
+        self.elements.insert(element)
+    }
+}
+impl<RenderModel: Sized + Clone> GuiContainer<RenderModel> {
+    /// Translation of an element from where it placed itself to where it is drawn
+    fn element_offset(&self, idx: ArenaIndex) -> Vector2<f32> {
+        match &self.window {
+            Some(window) if window.content.contains(&idx) => {
+                self.offset + Vector2::new(0.0, window.scroll)
+            }
+            _ => self.offset,
+        }
+    }
+    /// Content scrolled completely outside of the window is hidden. Content partly inside is
+    /// clipped to the content area when drawn.
+    fn is_visible(&self, idx: ArenaIndex, element: &dyn GuiElement) -> bool {
+        match &self.window {
+            Some(window) if window.content.contains(&idx) => {
+                let bounding_box = element.get_box();
+                let scroll = Vector2::new(0.0, window.scroll);
+                window.content_area.intersects(&BoundingBox::new(
+                    bounding_box.min + scroll,
+                    bounding_box.max + scroll,
+                ))
+            }
+            _ => true,
+        }
+    }
+    /// Screen area drawing of the element is limited to, if any
+    fn element_clip(&self, idx: ArenaIndex) -> Option<BoundingBox> {
+        match &self.window {
+            Some(window) if window.content.contains(&idx) => Some(BoundingBox::new(
+                window.content_area.min + self.offset,
+                window.content_area.max + self.offset,
+            )),
+            _ => None,
+        }
+    }
+    /// Whether `position` is on the element `idx`
+    fn element_hit(
+        &self,
+        idx: ArenaIndex,
+        element: &dyn GuiElement,
+        position: &Vector2<f32>,
+    ) -> bool {
+        let local = position - self.element_offset(idx);
+        let clipped = self
+            .element_clip(idx)
+            .map(|clip| !clip.contains(position))
+            .unwrap_or(false);
+        !clipped
+            && self.is_visible(idx, element)
+            && element
+                .get_screen_collider()
+                .iter()
+                .any(|t| t.intersects(&local))
+    }
+    /// Index of the top element under `position`
+    fn top_element(&self, position: &Vector2<f32>) -> Option<ArenaIndex> {
+        self.front_to_back()
+            .into_iter()
+            .find(|idx| self.element_hit(*idx, self.elements[*idx].as_ref(), position))
+    }
+    /// Elements ordered from the top one to the bottom one. Elements added later are drawn on top
+    fn front_to_back(&self) -> Vec<ArenaIndex> {
+        let mut indices: Vec<ArenaIndex> = self.elements.iter().map(|(idx, _)| idx).collect();
+        indices.reverse();
+        indices
+    }
+    fn in_title_bar(&self, position: &Vector2<f32>) -> bool {
+        self.window
+            .as_ref()
+            .map(|window| window.title_bar.contains(&(position - self.offset)))
+            .unwrap_or(false)
+    }
+    fn in_content_area(&self, position: &Vector2<f32>) -> bool {
+        self.window
+            .as_ref()
+            .map(|window| window.content_area.contains(&(position - self.offset)))
+            .unwrap_or(false)
     }
-    fn get_render_model(&self) -> Vec<RenderModel> {
-        todo!("get render model")
+    /// Scrolls the content area of a window
+    fn scroll(&mut self, delta_y: f32) {
+        let elements = &self.elements;
+        if let Some(window) = self.window.as_mut() {
+            let content_bottom = window
+                .content
+                .iter()
+                .filter_map(|idx| elements.get(*idx))
+                .map(|element| element.get_box().min.y)
+                .fold(window.content_area.min.y, f32::min);
+            window.scroll(delta_y, content_bottom);
+        }
+    }
+    /// Runtime models in drawing order along with the translation to draw them at and the area
+    /// they are clipped to
+    fn get_render_model(&self) -> Vec<GuiRenderModel<RenderModel>> {
+        self.elements
+            .iter()
+            .filter(|(idx, element)| self.is_visible(*idx, element.as_ref()))
+            .filter_map(|(idx, _element)| self.render_models.get(&idx).map(|models| (idx, models)))
+            .flat_map(|(idx, models)| {
+                let offset = self.element_offset(idx);
+                let clip = self.element_clip(idx);
+                models
+                    .iter()
+                    .map(move |model| (model.clone(), offset, clip.clone()))
+            })
+            .collect()
     }
     // to call after state updates
-    fn get_model(&mut self) -> HashMap<ArenaIndex, Model> {
-        todo!("get model")
+    fn get_model(&mut self, theme: &Theme) -> HashMap<ArenaIndex, Vec<Model>> {
+        self.elements
+            .iter()
+            .map(|(idx, element)| (idx, element.get_model(theme)))
+            .collect()
     }
-    fn submit_model(&mut self, models: &HashMap<ArenaIndex, RenderModel>) {
-        todo!("submit model")
+    fn submit_model(&mut self, models: &HashMap<ArenaIndex, Vec<RenderModel>>) {
+        for (idx, model) in models.iter() {
+            self.render_models.insert(*idx, model.clone());
+        }
+    }
+}
+impl<RenderModel: Sized> Default for GuiContainer<RenderModel> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+/// Runtime model of a gui element, the translation it is drawn at and the area it is clipped to
+pub type GuiRenderModel<RenderModel> = (RenderModel, Vector2<f32>, Option<BoundingBox>);
+/// Axis aligned box in screen coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+impl BoundingBox {
+    pub fn new(min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        Self { min, max }
+    }
+    pub fn contains(&self, point: &Vector2<f32>) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+    }
+    /// Whether the boxes overlap by more than an edge
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x < other.max.x
+            && other.min.x < self.max.x
+            && self.min.y < other.max.y
+            && other.min.y < self.max.y
+    }
+    pub fn to_triangles(&self) -> Vec<Triangle> {
+        let top_left = Vector2::new(self.min.x, self.max.y);
+        let bottom_right = Vector2::new(self.max.x, self.min.y);
+        vec![
+            Triangle::new([self.min, bottom_right, self.max]),
+            Triangle::new([self.min, self.max, top_left]),
+        ]
+    }
+    /// Builds a flat colored quad covering the box
+    pub fn to_model(&self, color: Vector4<u8>) -> Model {
+        let z = 0.0;
+        Model {
+            mesh: Mesh {
+                vertices: vec![
+                    (
+                        Vector3::new(self.min.x, self.min.y, z),
+                        Vector2::new(0.0, 0.0),
+                    ),
+                    (
+                        Vector3::new(self.max.x, self.min.y, z),
+                        Vector2::new(1.0, 0.0),
+                    ),
+                    (
+                        Vector3::new(self.max.x, self.max.y, z),
+                        Vector2::new(1.0, 1.0),
+                    ),
+                    (
+                        Vector3::new(self.min.x, self.min.y, z),
+                        Vector2::new(0.0, 0.0),
+                    ),
+                    (
+                        Vector3::new(self.max.x, self.max.y, z),
+                        Vector2::new(1.0, 1.0),
+                    ),
+                    (
+                        Vector3::new(self.min.x, self.max.y, z),
+                        Vector2::new(0.0, 1.0),
+                    ),
+                ],
+            },
+            texture: RGBATexture::constant_color(color, Vector2::new(1, 1)),
+            texture_options: TextureOptions::default(),
+            shared_texture: None,
+        }
     }
 }
-struct BoundingBox {}
 pub struct EventPacket {
     pub mouse_position: Vector2<f32>,
     pub events: Vec<Event>,
+    /// Time since the last frame
+    pub delta_time_ms: f32,
 }
-#[derive(Clone)]
-pub struct MouseEvent {
-    pub mouse_position: Vector2<f32>,
-}
-enum KeyboardKey {}
-pub enum Event_dep {
-    MouseEvent(MouseEvent),
-}
-//wheter or not to update gui
-#[derive(Clone, Debug)]
+/// Identifies the widget that emitted a message so that a parent with several widgets can tell them apart
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WidgetId(pub u32);
+#[derive(Clone, Debug, PartialEq)]
 pub enum Message {
     /// Clicked on Mesh
     ClickedOn,
+    /// A button was pressed
+    ButtonPressed { id: WidgetId },
+    /// A slider was moved. `value` is the value the slider now holds
+    SliderChanged { id: WidgetId, value: f32 },
+    /// The text of a text input changed
+    TextChanged { id: WidgetId, text: String },
+    /// Enter was pressed in a text input
+    TextSubmitted { id: WidgetId, text: String },
+    /// An item was selected. `index` is the index of the selected item
+    Selected { id: WidgetId, index: usize },
+    /// The mouse started hovering over the object
+    HoverEnter,
+    /// The mouse stopped hovering over the object
+    HoverLeave,
+    /// The close button of a window was pressed
+    WindowClosed,
 }
-enum StateChange {
+/// Cursor the gui would like the page to show. Maps directly to css cursors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorStyle {
+    Default,
+    Pointer,
+    Grab,
+    Grabbing,
+    Crosshair,
+    Text,
+}
+impl CursorStyle {
+    pub fn to_css(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Pointer => "pointer",
+            Self::Grab => "grab",
+            Self::Grabbing => "grabbing",
+            Self::Crosshair => "crosshair",
+            Self::Text => "text",
+        }
+    }
+}
+/// Whether a message should keep bubbling up to the next handler
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Propagation {
+    /// Pass the message on to the next handler
+    Continue,
+    /// The message was handled, do not send it any further
+    Stop,
+}
+//wheter or not to update gui
+pub enum StateChange {
     NoChange,
     UpdateGui,
     DeleteParent,
 }
-trait GuiElement<RenderModel> {
+pub trait GuiElement {
     fn get_box(&self) -> BoundingBox;
     /// Recieves events from the runtime. Includes thing like click events. If the state is changed get model will be called.
-    fn process_event(&self, event: Event) -> (StateChange, Vec<Message>);
-    /// Gets the model. Should only be called when first constructed or process event returns `StateChange::UpdateGui`
-    fn get_model(&self) -> RenderModel;
+    fn process_event(&mut self, event: &Event) -> (StateChange, Vec<Message>);
+    /// Gets the models, drawn in order. Should only be called when first constructed, when process event returns
+    /// `StateChange::UpdateGui` or when the theme changes
+    fn get_model(&self, theme: &Theme) -> Vec<Model>;
     /// Gets collider triangle in screen coordinates
-    fn get_screen_collider(&self) -> Vec<Triangle>;
+    fn get_screen_collider(&self) -> Vec<Triangle> {
+        self.get_box().to_triangles()
+    }
+    /// Called when the mouse starts (`hovered == true`) or stops hovering over the element
+    fn process_hover(&mut self, _hovered: bool) -> (StateChange, Vec<Message>) {
+        (StateChange::NoChange, vec![])
+    }
+    /// Text shown after the mouse rests on the element
+    fn get_tooltip(&self) -> Option<String> {
+        None
+    }
+    fn get_cursor(&self) -> CursorStyle {
+        CursorStyle::Default
+    }
+    /// Whether clicking on the element gives it keyboard focus
+    fn is_focusable(&self) -> bool {
+        false
+    }
+    /// Called when the element gains (`focused == true`) or loses keyboard focus
+    fn process_focus(&mut self, _focused: bool) -> (StateChange, Vec<Message>) {
+        (StateChange::NoChange, vec![])
+    }
 }
 pub enum GetGuiOutput<RenderModel> {
     //Spawn a container. If one already exists replace existing gui with current container
@@ -65,186 +352,651 @@ pub enum GetGuiOutput<RenderModel> {
     //No gui to be emmitted. If one exists delete the gui
     None,
 }
-
+/// Identifies a container. Containers are either owned by a game object or are global and owned by the
+/// `GuiState` itself, for example a toolbar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContainerKey {
+    Object(ArenaIndex),
+    Global(ArenaIndex),
+}
+impl ContainerKey {
+    /// Index of the game object that owns the container, if any
+    pub fn parent_index(&self) -> Option<ArenaIndex> {
+        match self {
+            Self::Object(index) => Some(*index),
+            Self::Global(_) => None,
+        }
+    }
+}
+#[derive(Clone, Debug)]
 pub struct Triangle {
     points: [Vector2<f32>; 3],
 }
 impl Triangle {
+    pub fn new(points: [Vector2<f32>; 3]) -> Self {
+        Self { points }
+    }
     pub fn intersects(&self, point: &Vector2<f32>) -> bool {
-        todo!("figure out triangle does intersect")
+        let sign = |a: &Vector2<f32>, b: &Vector2<f32>, c: &Vector2<f32>| {
+            (a.x - c.x) * (b.y - c.y) - (b.x - c.x) * (a.y - c.y)
+        };
+        let d1 = sign(point, &self.points[0], &self.points[1]);
+        let d2 = sign(point, &self.points[1], &self.points[2]);
+        let d3 = sign(point, &self.points[2], &self.points[0]);
+        let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+        let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+        !(has_negative && has_positive)
     }
 }
 //represents an object that may own a container
 pub trait GuiParent<RenderModel> {
-    fn get_gui(&self) -> GetGuiOutput<RenderModel>;
-    fn process_message(&mut self, message: &Message);
+    fn get_gui(&mut self) -> GetGuiOutput<RenderModel>;
+    /// Recieves a message from the gui. Returning `Propagation::Continue` passes the message on to the
+    /// global handlers.
+    fn process_message(&mut self, message: &Message) -> Propagation;
     /// Gets collider triangle in screen coordinates
     fn get_screen_collider(&self, camera: &Camera) -> Vec<Triangle>;
+    /// Text shown after the mouse rests on the object
+    fn get_tooltip(&self) -> Option<String>;
+}
+/// Handles messages that are not owned by any game object, such as messages from a toolbar. Messages from
+/// containers owned by game objects bubble up to global handlers if the owning object does not stop them.
+pub trait GlobalHandler {
+    fn process_message(&mut self, key: ContainerKey, message: &Message) -> Propagation;
+}
+/// Thing the mouse is currently over
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HoverTarget {
+    /// Gui element. Contains the container and the index of the element in the container
+    Element(ContainerKey, ArenaIndex),
+    /// Object in the game world
+    Object(ArenaIndex),
 }
 pub struct GuiState<RenderModel: Clone> {
-    containers: HashMap<ArenaIndex, GuiContainer<RenderModel>>,
+    containers: HashMap<ContainerKey, GuiContainer<RenderModel>>,
+    /// Used to allocate keys for global containers
+    global_keys: Arena<()>,
+    global_handlers: Vec<Box<dyn GlobalHandler>>,
+    hovered: Option<HoverTarget>,
+    /// How long the current target has been hovered over
+    hover_time_ms: f32,
+    tooltip: Option<ContainerKey>,
+    cursor: CursorStyle,
+    /// Cursor set by game code, for example while placing a building. Takes priority over hovering
+    cursor_override: Option<CursorStyle>,
+    /// Element that recieves keyboard events
+    focused: Option<(ContainerKey, ArenaIndex)>,
+    /// Keys pressed while no element had focus, left for the game to use
+    unhandled_keys: Vec<KeyPress>,
+    /// Containers ordered from back to front
+    z_order: Vec<ContainerKey>,
+    /// Window being dragged and where the mouse was last frame
+    drag: Option<(ContainerKey, Vector2<f32>)>,
+    left_button_down: bool,
+    /// Where windows were moved to, by window name
+    window_positions: HashMap<String, Vector2<f32>>,
+    theme: Theme,
+    /// Set when the theme changes so every container gets rebuilt
+    theme_changed: bool,
 }
-
-/// What needs to get changed (tommorow)
-/// Add two new functions that look like
-/// ```
-/// //processes events and sends new models to update
-/// fn process_events(&mut self,events: EventPacket,objects: &mut Arena<Box<dyn GuiParent>>,)->HashMap<ArenaIndex,Model>;
-/// //submitting hashmap
-/// fn submit_model(&mut self,map: HashMap<(ArenaIndex,ArenaIndex),RuntimeModel>);
-/// //called after process event every frame. gets all models to be drawn.
-/// fn get_runtime_model(&self)->Vec<RuntimeModel>;
-/// ```
+impl<RenderModel: Clone> Default for GuiState<RenderModel> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+/// Messages waiting to be routed. Messages from world objects have no container.
+type PendingMessage = (Option<ContainerKey>, Option<ArenaIndex>, Message);
 impl<RenderModel: Clone> GuiState<RenderModel> {
     pub fn new() -> Self {
         Self {
             containers: HashMap::new(),
+            global_keys: Arena::new(),
+            global_handlers: vec![],
+            hovered: None,
+            hover_time_ms: 0.0,
+            tooltip: None,
+            cursor: CursorStyle::Default,
+            cursor_override: None,
+            focused: None,
+            unhandled_keys: vec![],
+            z_order: vec![],
+            drag: None,
+            left_button_down: false,
+            window_positions: HashMap::new(),
+            theme: Theme::default(),
+            theme_changed: false,
+        }
+    }
+    /// Restyles the whole gui
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.theme_changed = true;
+    }
+    pub fn get_theme(&self) -> &Theme {
+        &self.theme
+    }
+    /// Whether a gui element has keyboard focus. While it does keyboard events should not be used for anything
+    /// else, like moving the camera.
+    pub fn has_focus(&self) -> bool {
+        self.focused.is_some()
+    }
+    /// Keys from the last `game_loop` that did not go to a focused element, in the order they were
+    /// pressed
+    pub fn take_unhandled_keys(&mut self) -> Vec<KeyPress> {
+        std::mem::take(&mut self.unhandled_keys)
+    }
+    /// Cursor that should currently be shown
+    pub fn get_cursor(&self) -> CursorStyle {
+        self.cursor
+    }
+    /// Forces the cursor to `cursor`. `None` goes back to picking the cursor from what is hovered
+    pub fn set_cursor_override(&mut self, cursor: Option<CursorStyle>) {
+        self.cursor_override = cursor;
+    }
+    /// Adds a container that is not owned by any game object
+    pub fn add_global_container(&mut self, container: GuiContainer<RenderModel>) -> ContainerKey {
+        let key = ContainerKey::Global(self.global_keys.insert(()));
+        self.insert_container(key, container);
+        key
+    }
+    pub fn remove_global_container(&mut self, key: ContainerKey) {
+        if let ContainerKey::Global(_) = key {
+            self.remove_container(key);
+        }
+    }
+    /// Whether the mouse is over the content of a window. Scrolling there scrolls the window rather than
+    /// moving the camera
+    pub fn captures_scroll(&self, position: &Vector2<f32>) -> bool {
+        self.containers
+            .values()
+            .any(|container| container.in_content_area(position))
+    }
+    /// Saves where windows have been moved to. One window per line in the form `name x y`
+    pub fn save_layout(&self) -> String {
+        self.window_positions
+            .iter()
+            .map(|(name, position)| format!("{} {} {}\n", name, position.x, position.y))
+            .collect()
+    }
+    /// Loads window positions saved by `save_layout`. Malformed lines are skipped
+    pub fn load_layout(&mut self, layout: &str) {
+        for line in layout.lines() {
+            let mut parts = line.rsplitn(3, ' ');
+            if let (Some(y), Some(x), Some(name)) = (parts.next(), parts.next(), parts.next()) {
+                if let (Ok(x), Ok(y)) = (x.parse(), y.parse()) {
+                    self.window_positions
+                        .insert(name.to_string(), Vector2::new(x, y));
+                }
+            }
         }
     }
-    #[allow(dead_code)]
+    /// Inserts or replaces a container. Replaced containers keep their place in the drawing order and
+    /// windows are moved to where they were last left
+    fn insert_container(&mut self, key: ContainerKey, mut container: GuiContainer<RenderModel>) {
+        if let (ContainerKey::Object(index), Some(window)) = (key, container.window.as_mut()) {
+            //objects of the same kind open windows with the same name, so the object's slot is
+            //added to give each object its own saved position
+            window.name = format!("{}_{}", window.name, index.into_raw_parts().0);
+        }
+        if let Some(window) = container.window.as_ref() {
+            if let Some(position) = self.window_positions.get(&window.name) {
+                container.offset = *position;
+            } else if let Some(old) = self.containers.get(&key) {
+                container.offset = old.offset;
+            } else {
+                let open = self
+                    .containers
+                    .values()
+                    .filter(|container| container.window.is_some())
+                    .count();
+                container.offset +=
+                    Vector2::new(-WINDOW_CASCADE, -WINDOW_CASCADE) * (open % 5) as f32;
+            }
+        }
+        if !self.z_order.contains(&key) {
+            self.z_order.push(key);
+        }
+        self.containers.insert(key, container);
+    }
+    fn remove_container(&mut self, key: ContainerKey) {
+        self.containers.remove(&key);
+        self.z_order.retain(|k| *k != key);
+        if let ContainerKey::Global(index) = key {
+            self.global_keys.remove(index);
+        }
+    }
+    fn bring_to_front(&mut self, key: ContainerKey) {
+        self.z_order.retain(|k| *k != key);
+        self.z_order.push(key);
+        //tooltips stay above everything
+        if let Some(tooltip) = self.tooltip {
+            self.z_order.retain(|k| *k != tooltip);
+            self.z_order.push(tooltip);
+        }
+    }
+    /// Adds a handler. Handlers are called in the order they are added
+    pub fn add_global_handler(&mut self, handler: Box<dyn GlobalHandler>) {
+        self.global_handlers.push(handler);
+    }
+    /// Processes events and returns the models of gui elements that need to be rebuilt
     pub fn game_loop<Parent: GuiParent<RenderModel>>(
         &mut self,
         events: EventPacket,
         camera: &Camera,
         objects: &mut Arena<Parent>,
-    ) -> HashMap<(ArenaIndex, ArenaIndex), Model> {
+    ) -> HashMap<(ContainerKey, ArenaIndex), Vec<Model>> {
         //list of models to update
         let mut to_update = HashSet::new();
         //1. process event. Mark key if state needs changing
         let mut update_gui = vec![];
-        let mut messages = vec![];
+        let mut messages: Vec<PendingMessage> = vec![];
+        self.process_drag(&events);
+        self.process_hover(
+            &events,
+            camera,
+            objects,
+            &mut update_gui,
+            &mut messages,
+            &mut to_update,
+        );
         for event in events.events.iter() {
-            if let Some((mut gui, mut msg)) = match event {
-                Event::MouseClick(m) => Some(self.process_mouse_gui(m)),
-                _ => None,
-            } {
-                update_gui.append(&mut gui);
-                messages.append(&mut msg);
+            match event {
+                Event::MouseClick(m) => {
+                    let (mut gui, mut msg) = self.process_mouse_gui(m);
+                    //clicks that land on the gui do not reach the world
+                    if gui.is_empty() {
+                        messages.append(&mut Self::process_mouse_world(m, camera, objects));
+                    }
+                    let focus = gui
+                        .iter()
+                        .map(|(_state, key, child_index)| (*key, *child_index))
+                        .find(|(key, child_index)| {
+                            self.containers
+                                .get(key)
+                                .and_then(|c| c.elements.get(*child_index))
+                                .map(|element| element.is_focusable())
+                                .unwrap_or(false)
+                        });
+                    update_gui.append(&mut gui);
+                    messages.append(&mut msg);
+                    self.set_focus(focus, &mut update_gui, &mut messages);
+                }
+                Event::Scroll { delta_y, .. } => {
+                    let top = self
+                        .front_to_back()
+                        .into_iter()
+                        .find(|key| self.containers[key].in_content_area(&events.mouse_position));
+                    if let Some(key) = top {
+                        self.containers.get_mut(&key).unwrap().scroll(*delta_y);
+                    }
+                }
+                Event::KeyDown(key) if self.focused.is_none() => {
+                    self.unhandled_keys.push(key.clone())
+                }
+                Event::KeyDown(key) if key.key == "Escape" => {
+                    self.set_focus(None, &mut update_gui, &mut messages)
+                }
+                Event::KeyDown(_) | Event::Paste(_) => {
+                    if let Some((key, child_index)) = self.focused {
+                        if let Some(element) = self
+                            .containers
+                            .get_mut(&key)
+                            .and_then(|c| c.elements.get_mut(child_index))
+                        {
+                            let (state_change, element_messages) = element.process_event(event);
+                            update_gui.push((state_change, key, child_index));
+                            for msg in element_messages.into_iter() {
+                                messages.push((Some(key), key.parent_index(), msg));
+                            }
+                        }
+                    }
+                }
+                _ => (),
             }
         }
 
         //2. Update gui boxes with specific keys marked by update
-        for (state, parent_index, child_index) in update_gui.iter() {
+        for (state, key, _child_index) in update_gui.iter() {
             match state {
                 StateChange::NoChange => (),
                 StateChange::UpdateGui => {
-                    if let Some(parent) = self.containers.get_mut(parent_index) {
-                        if let Some(child) = parent.elements.get(*child_index) {
-                            to_update.insert(parent_index.clone());
-                        }
-                    }
-                }
-                StateChange::DeleteParent => {
-                    if self.containers.contains_key(parent_index) {
-                        self.containers.remove(parent_index);
-                        to_update.insert(parent_index.clone());
+                    if self.containers.contains_key(key) {
+                        to_update.insert(*key);
                     }
                 }
+                StateChange::DeleteParent => self.remove_container(*key),
             }
         }
-        //3. send on click
-        for (index, object) in objects.iter() {
-            for triangle in object.get_screen_collider(camera) {
-                if triangle.intersects(&events.mouse_position) {
-                    messages.push((index, Message::ClickedOn));
+        //3. send messages to owning objects, then bubble up to global handlers
+        for (key, parent, message) in messages.iter() {
+            let propagation = match parent.and_then(|idx| objects.get_mut(idx)) {
+                Some(object) => object.process_message(message),
+                None => Propagation::Continue,
+            };
+            if propagation == Propagation::Continue {
+                if let Some(key) = key {
+                    self.send_global(*key, message);
                 }
             }
         }
-        //4. send messages to owning objects
-        for (index, message) in messages.iter() {
-            objects[*index].process_message(message);
-        }
-        //5. Get gui from all items. update gui as nessecary
-        for (index, object) in objects.iter() {
+        //4. Get gui from all items. update gui as nessecary
+        for (index, object) in objects.iter_mut() {
+            let key = ContainerKey::Object(index);
             match object.get_gui() {
                 GetGuiOutput::Contianer(c) => {
-                    self.containers.insert(index, c);
-                    to_update.insert(index.clone());
+                    self.insert_container(key, c);
+                    to_update.insert(key);
                 }
                 GetGuiOutput::NoChange => (),
-                GetGuiOutput::None => {
-                    self.containers.remove(&index);
-                }
+                GetGuiOutput::None => self.remove_container(key),
+            }
+        }
+        //dropping focus if the focused element went away
+        if let Some((key, child_index)) = self.focused {
+            if !self
+                .containers
+                .get(&key)
+                .map(|c| c.elements.contains(child_index))
+                .unwrap_or(false)
+            {
+                self.focused = None;
             }
         }
-        let mut output = HashMap::<(ArenaIndex, ArenaIndex), Model>::new();
-        //6. get deltas
-        for index in to_update.iter() {
-            if let Some(container) = self.containers.get_mut(index) {
-                let models = container.get_model();
-                for (child_idx, m) in models.iter() {
-                    output.insert(((index.clone()).clone(), child_idx.clone()), m.clone());
+        if self.theme_changed {
+            to_update.extend(self.containers.keys());
+            self.theme_changed = false;
+        }
+        //5. get deltas
+        let mut output = HashMap::<(ContainerKey, ArenaIndex), Vec<Model>>::new();
+        for key in to_update.iter() {
+            if let Some(container) = self.containers.get_mut(key) {
+                for (child_idx, m) in container.get_model(&self.theme).drain() {
+                    output.insert((*key, child_idx), m);
                 }
             }
         }
-        return output;
+        output
     }
-    pub fn submit_model(&mut self, map: HashMap<(ArenaIndex, ArenaIndex), RenderModel>) {
-        let mut model_map: HashMap<ArenaIndex, HashMap<ArenaIndex, RenderModel>> = HashMap::new();
-        for ((container_idx, child_idx), runtime_model) in map.iter() {
-            if let Some(container) = model_map.get_mut(&container_idx.clone()) {
-                container.insert(child_idx.clone(), runtime_model.clone());
-            } else {
-                model_map.insert(
-                    container_idx.clone(),
-                    HashMap::<ArenaIndex, RenderModel>::new(),
-                );
-                model_map
-                    .get_mut(container_idx)
-                    .unwrap()
-                    .insert(child_idx.clone(), runtime_model.clone());
-            }
+    pub fn submit_model(&mut self, map: HashMap<(ContainerKey, ArenaIndex), Vec<RenderModel>>) {
+        #[allow(clippy::type_complexity)]
+        let mut model_map: HashMap<ContainerKey, HashMap<ArenaIndex, Vec<RenderModel>>> =
+            HashMap::new();
+        for ((key, child_idx), runtime_model) in map.into_iter() {
+            model_map
+                .entry(key)
+                .or_default()
+                .insert(child_idx, runtime_model);
         }
-        for (container_idx, map) in model_map.iter() {
-            self.containers
-                .get_mut(container_idx)
-                .unwrap()
-                .submit_model(map);
+        for (key, map) in model_map.iter() {
+            if let Some(container) = self.containers.get_mut(key) {
+                container.submit_model(map);
+            }
         }
     }
-    pub fn get_runtime_model(&self) -> Vec<RenderModel> {
+    /// Gets the models to draw from back to front along with the translation to draw each model at
+    pub fn get_runtime_model(&self) -> Vec<GuiRenderModel<RenderModel>> {
         let mut out_vec = vec![];
-        for (_idx, container) in self.containers.iter() {
-            out_vec.append(&mut container.get_render_model());
+        for key in self.z_order.iter() {
+            if let Some(container) = self.containers.get(key) {
+                out_vec.append(&mut container.get_render_model());
+            }
+        }
+        out_vec
+    }
+    fn front_to_back(&self) -> Vec<ContainerKey> {
+        self.z_order
+            .iter()
+            .rev()
+            .filter(|key| Some(**key) != self.tooltip && self.containers.contains_key(key))
+            .copied()
+            .collect()
+    }
+    /// Moves windows being dragged by their title bar. Dragging starts when the left button goes down over a
+    /// title bar and ends when it is released
+    fn process_drag(&mut self, events: &EventPacket) {
+        let was_down = self.left_button_down;
+        for event in events.events.iter() {
+            if let Event::MouseMove {
+                buttons_pressed, ..
+            } = event
+            {
+                self.left_button_down = buttons_pressed.contains(&MouseButton::LeftClick);
+            }
+        }
+        let position = events.mouse_position;
+        if self.left_button_down && !was_down {
+            let top = self
+                .front_to_back()
+                .into_iter()
+                .find(|key| self.containers[key].top_element(&position).is_some());
+            if let Some(key) = top {
+                self.bring_to_front(key);
+                if self.containers[&key].in_title_bar(&position) {
+                    self.drag = Some((key, position));
+                }
+            }
+        }
+        if let Some((key, last_position)) = self.drag {
+            match self.containers.get_mut(&key) {
+                Some(container) if self.left_button_down => {
+                    container.offset += position - last_position;
+                    self.drag = Some((key, position));
+                }
+                Some(container) => {
+                    if let Some(window) = container.window.as_ref() {
+                        self.window_positions
+                            .insert(window.name.clone(), container.offset);
+                    }
+                    self.drag = None;
+                }
+                None => self.drag = None,
+            }
         }
-        return out_vec;
+    }
+    /// Moves keyboard focus, notifying the elements that lost and gained focus
+    fn set_focus(
+        &mut self,
+        focus: Option<(ContainerKey, ArenaIndex)>,
+        update_gui: &mut Vec<(StateChange, ContainerKey, ArenaIndex)>,
+        messages: &mut Vec<PendingMessage>,
+    ) {
+        if focus == self.focused {
+            return;
+        }
+        for (target, is_focused) in [(self.focused, false), (focus, true)].iter() {
+            if let Some((key, child_index)) = target {
+                if let Some(element) = self
+                    .containers
+                    .get_mut(key)
+                    .and_then(|c| c.elements.get_mut(*child_index))
+                {
+                    let (state_change, element_messages) = element.process_focus(*is_focused);
+                    update_gui.push((state_change, *key, *child_index));
+                    for msg in element_messages.into_iter() {
+                        messages.push((Some(*key), key.parent_index(), msg));
+                    }
+                }
+            }
+        }
+        self.focused = focus;
+    }
+    fn send_global(&mut self, key: ContainerKey, message: &Message) {
+        for handler in self.global_handlers.iter_mut() {
+            if handler.process_message(key, message) == Propagation::Stop {
+                return;
+            }
+        }
+    }
+    /// Sends `Message::ClickedOn` to every world object under the mouse
+    fn process_mouse_world<Parent: GuiParent<RenderModel>>(
+        mouse: &MouseClick,
+        camera: &Camera,
+        objects: &Arena<Parent>,
+    ) -> Vec<PendingMessage> {
+        objects
+            .iter()
+            .filter(|(_index, object)| {
+                mouse.button_pressed == MouseButton::LeftClick
+                    && object
+                        .get_screen_collider(camera)
+                        .iter()
+                        .any(|t| t.intersects(&mouse.position))
+            })
+            .map(|(index, _object)| (None, Some(index), Message::ClickedOn))
+            .collect()
     }
     ///
     /// checks if mouse interesected with one part of the gui. First return argument is the list of guis elements
-    /// that need updating. Second is a vector of (Index of Gameobjects to Send message to, Message to send)
+    /// that need updating. Second is a vector of (Container the message came from, Index of Gameobjects to Send message to, Message to send)
+    #[allow(clippy::type_complexity)]
     fn process_mouse_gui(
         &mut self,
         mouse: &MouseClick,
     ) -> (
-        Vec<(StateChange, ArenaIndex, ArenaIndex)>,
-        Vec<(ArenaIndex, Message)>,
+        Vec<(StateChange, ContainerKey, ArenaIndex)>,
+        Vec<PendingMessage>,
     ) {
         let mut update_events = vec![];
         let mut update_mesages = vec![];
-        for (parent_index, container) in self.containers.iter_mut() {
-            if container
-                .get_screen_collider()
-                .iter()
-                .map(|t| t.intersects(&mouse.position))
-                .fold(false, |acc, x| acc | x)
-            {
-                for (child_index, element) in container.elements.iter() {
-                    if element
-                        .get_screen_collider()
-                        .iter()
-                        .map(|t| t.intersects(&mouse.position))
-                        .fold(false, |acc, x| acc | x)
-                    {
-                        let (state_change, messages) =
-                            element.process_event(Event::MouseClick(mouse.clone()));
-                        update_events.push((state_change, parent_index.clone(), child_index));
-                        for msg in messages.iter() {
-                            update_mesages.push((container.parent_index, msg.clone()));
+        //only the top element under the mouse recieves the click
+        let target = self.front_to_back().into_iter().find_map(|key| {
+            self.containers[&key]
+                .top_element(&mouse.position)
+                .map(|child_index| (key, child_index))
+        });
+        if let Some((key, child_index)) = target {
+            let container = self.containers.get_mut(&key).unwrap();
+            let local_click = MouseClick {
+                position: mouse.position - container.element_offset(child_index),
+                button_pressed: mouse.button_pressed.clone(),
+            };
+            let (state_change, messages) =
+                container.elements[child_index].process_event(&Event::MouseClick(local_click));
+            update_events.push((state_change, key, child_index));
+            for msg in messages.into_iter() {
+                update_mesages.push((Some(key), key.parent_index(), msg));
+            }
+            self.bring_to_front(key);
+        }
+        (update_events, update_mesages)
+    }
+    /// Finds what the mouse is over. Gui elements are in front of the world.
+    fn find_hover<Parent: GuiParent<RenderModel>>(
+        &self,
+        position: &Vector2<f32>,
+        camera: &Camera,
+        objects: &Arena<Parent>,
+    ) -> Option<HoverTarget> {
+        for key in self.front_to_back() {
+            if let Some(child_index) = self.containers[&key].top_element(position) {
+                return Some(HoverTarget::Element(key, child_index));
+            }
+        }
+        objects
+            .iter()
+            .find(|(_index, object)| {
+                object
+                    .get_screen_collider(camera)
+                    .iter()
+                    .any(|t| t.intersects(position))
+            })
+            .map(|(index, _object)| HoverTarget::Object(index))
+    }
+    /// Sends hover enter and leave notifications, shows tooltips and picks the cursor
+    fn process_hover<Parent: GuiParent<RenderModel>>(
+        &mut self,
+        events: &EventPacket,
+        camera: &Camera,
+        objects: &Arena<Parent>,
+        update_gui: &mut Vec<(StateChange, ContainerKey, ArenaIndex)>,
+        messages: &mut Vec<PendingMessage>,
+        to_update: &mut HashSet<ContainerKey>,
+    ) {
+        let hovered = self.find_hover(&events.mouse_position, camera, objects);
+        if hovered != self.hovered {
+            for (target, is_hovered) in [(self.hovered, false), (hovered, true)].iter() {
+                match target {
+                    Some(HoverTarget::Element(key, child_index)) => {
+                        if let Some(element) = self
+                            .containers
+                            .get_mut(key)
+                            .and_then(|c| c.elements.get_mut(*child_index))
+                        {
+                            let (state_change, element_messages) =
+                                element.process_hover(*is_hovered);
+                            update_gui.push((state_change, *key, *child_index));
+                            for msg in element_messages.into_iter() {
+                                messages.push((Some(*key), key.parent_index(), msg));
+                            }
                         }
                     }
+                    Some(HoverTarget::Object(index)) => {
+                        let msg = if *is_hovered {
+                            Message::HoverEnter
+                        } else {
+                            Message::HoverLeave
+                        };
+                        messages.push((None, Some(*index), msg));
+                    }
+                    None => (),
+                }
+            }
+            self.hovered = hovered;
+            self.hover_time_ms = 0.0;
+            if let Some(tooltip) = self.tooltip.take() {
+                self.remove_global_container(tooltip);
+            }
+        } else {
+            self.hover_time_ms += events.delta_time_ms;
+        }
+        if self.tooltip.is_none() && self.hover_time_ms >= TOOLTIP_DELAY_MS {
+            let text = match self.hovered {
+                Some(HoverTarget::Element(key, child_index)) => self
+                    .containers
+                    .get(&key)
+                    .and_then(|c| c.elements.get(child_index))
+                    .and_then(|element| element.get_tooltip()),
+                Some(HoverTarget::Object(index)) => {
+                    objects.get(index).and_then(|object| object.get_tooltip())
                 }
+                None => None,
+            };
+            if let Some(text) = text {
+                let mut container = GuiContainer::new();
+                let padding = Vector2::new(self.theme.padding, self.theme.padding);
+                //keeping the tooltip on screen
+                let size = self.theme.font.text_size(&text, TOOLTIP_HEIGHT) + 2.0 * padding;
+                let position = Vector2::new(
+                    events.mouse_position.x.min(1.0 - size.x),
+                    (events.mouse_position.y - size.y).max(-1.0),
+                );
+                container.add_element(Box::new(Panel::new(
+                    BoundingBox::new(position, position + size),
+                    PanelKind::Tooltip,
+                )));
+                container.add_element(Box::new(
+                    Label::new(text, position + padding, TOOLTIP_HEIGHT).on(PanelKind::Tooltip),
+                ));
+                let key = self.add_global_container(container);
+                self.tooltip = Some(key);
+                to_update.insert(key);
             }
         }
-        return (update_events, update_mesages);
+        self.cursor = if let Some(cursor) = self.cursor_override {
+            cursor
+        } else if self.drag.is_some() {
+            CursorStyle::Grabbing
+        } else {
+            match self.hovered {
+                Some(HoverTarget::Element(key, child_index)) => self
+                    .containers
+                    .get(&key)
+                    .and_then(|c| c.elements.get(child_index))
+                    .map(|element| element.get_cursor())
+                    .unwrap_or(CursorStyle::Default),
+                Some(HoverTarget::Object(_)) => CursorStyle::Pointer,
+                None => CursorStyle::Default,
+            }
+        };
     }
 }
@@ -0,0 +1,76 @@
+use super::{
+    BoundingBox, CursorStyle, GuiElement, Label, Message, PanelKind, StateChange, Theme, WidgetId,
+};
+use crate::prelude::{Event, Model};
+use nalgebra::Vector2;
+/// Clickable box that sends `Message::ButtonPressed` when clicked
+pub struct Button {
+    id: WidgetId,
+    bounding_box: BoundingBox,
+    text: Option<String>,
+    tooltip: Option<String>,
+    hovered: bool,
+}
+impl Button {
+    pub fn new(id: WidgetId, bounding_box: BoundingBox) -> Self {
+        Self {
+            id,
+            bounding_box,
+            text: None,
+            tooltip: None,
+            hovered: false,
+        }
+    }
+    /// Text drawn centered on the button
+    pub fn with_text(mut self, text: String) -> Self {
+        self.text = Some(text);
+        self
+    }
+    pub fn with_tooltip(mut self, tooltip: String) -> Self {
+        self.tooltip = Some(tooltip);
+        self
+    }
+}
+impl GuiElement for Button {
+    fn get_box(&self) -> BoundingBox {
+        self.bounding_box.clone()
+    }
+    fn process_event(&mut self, event: &Event) -> (StateChange, Vec<Message>) {
+        match event {
+            Event::MouseClick(_) => (
+                StateChange::NoChange,
+                vec![Message::ButtonPressed { id: self.id }],
+            ),
+            _ => (StateChange::NoChange, vec![]),
+        }
+    }
+    fn get_model(&self, theme: &Theme) -> Vec<Model> {
+        let kind = if self.hovered {
+            PanelKind::ButtonHover
+        } else {
+            PanelKind::Button
+        };
+        let mut models = vec![theme.panel_model(kind, &self.bounding_box)];
+        if let Some(text) = self.text.as_ref() {
+            let height = self.bounding_box.max.y - self.bounding_box.min.y - 2.0 * theme.padding;
+            let center = (self.bounding_box.min + self.bounding_box.max) / 2.0;
+            let position = center - theme.font.text_size(text, height) / 2.0;
+            models.append(
+                &mut Label::new(text.clone(), Vector2::new(position.x, position.y), height)
+                    .on(kind)
+                    .get_model(theme),
+            );
+        }
+        models
+    }
+    fn process_hover(&mut self, hovered: bool) -> (StateChange, Vec<Message>) {
+        self.hovered = hovered;
+        (StateChange::UpdateGui, vec![])
+    }
+    fn get_tooltip(&self) -> Option<String> {
+        self.tooltip.clone()
+    }
+    fn get_cursor(&self) -> CursorStyle {
+        CursorStyle::Pointer
+    }
+}
@@ -0,0 +1,464 @@
+use crate::RGBATexture;
+use nalgebra::{Vector2, Vector4};
+use std::collections::HashMap;
+/// Width of a glyph in pixels
+pub const GLYPH_WIDTH: u32 = 5;
+/// Height of a glyph in pixels
+pub const GLYPH_HEIGHT: u32 = 7;
+/// Pixels between glyphs and around the edge of rendered text
+pub const GLYPH_PADDING: u32 = 1;
+/// Gets the rows of a glyph from top to bottom. The leftmost pixel of a row is the highest bit. Characters
+/// outside of printable ascii are drawn as a box.
+pub fn glyph(c: char) -> [u8; 7] {
+    match c {
+        ' ' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '!' => [
+            0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100,
+        ],
+        '"' => [
+            0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '#' => [
+            0b01010, 0b11111, 0b01010, 0b01010, 0b01010, 0b11111, 0b01010,
+        ],
+        '$' => [
+            0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100,
+        ],
+        '%' => [
+            0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011,
+        ],
+        '&' => [
+            0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101,
+        ],
+        '\'' => [
+            0b00100, 0b00100, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '(' => [
+            0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+        ],
+        ')' => [
+            0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+        ],
+        '*' => [
+            0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000,
+        ],
+        '+' => [
+            0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000,
+        ],
+        ',' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00110, 0b00100, 0b01000,
+        ],
+        '-' => [
+            0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+        ],
+        '.' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+        ],
+        '/' => [
+            0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000,
+        ],
+        '0' => [
+            0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+        ],
+        '1' => [
+            0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        '2' => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+        '3' => [
+            0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+        ],
+        '4' => [
+            0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+        ],
+        '5' => [
+            0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+        ],
+        '6' => [
+            0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+        ],
+        '7' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+        ],
+        '8' => [
+            0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+        ],
+        '9' => [
+            0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+        ],
+        ':' => [
+            0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+        ],
+        ';' => [
+            0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000,
+        ],
+        '<' => [
+            0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010,
+        ],
+        '=' => [
+            0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000,
+        ],
+        '>' => [
+            0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000,
+        ],
+        '?' => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+        ],
+        '@' => [
+            0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110,
+        ],
+        'A' => [
+            0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'B' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+        ],
+        'C' => [
+            0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+        'D' => [
+            0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100,
+        ],
+        'E' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+        ],
+        'F' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'G' => [
+            0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+        ],
+        'H' => [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'I' => [
+            0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'J' => [
+            0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+        'K' => [
+            0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+        ],
+        'L' => [
+            0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+        ],
+        'M' => [
+            0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+        ],
+        'N' => [
+            0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+        ],
+        'O' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'P' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'Q' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+        ],
+        'R' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+        ],
+        'S' => [
+            0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+        ],
+        'T' => [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        'U' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'V' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+        'W' => [
+            0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+        ],
+        'X' => [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+        ],
+        'Y' => [
+            0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+        ],
+        'Z' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+        ],
+        '[' => [
+            0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110,
+        ],
+        '\\' => [
+            0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000,
+        ],
+        ']' => [
+            0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110,
+        ],
+        '^' => [
+            0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '_' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+        ],
+        '`' => [
+            0b01000, 0b00100, 0b00010, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        'a' => [
+            0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111,
+        ],
+        'b' => [
+            0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110,
+        ],
+        'c' => [
+            0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+        'd' => [
+            0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111,
+        ],
+        'e' => [
+            0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110,
+        ],
+        'f' => [
+            0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000,
+        ],
+        'g' => [
+            0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+        ],
+        'h' => [
+            0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+        ],
+        'i' => [
+            0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'j' => [
+            0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+        'k' => [
+            0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010,
+        ],
+        'l' => [
+            0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'm' => [
+            0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001,
+        ],
+        'n' => [
+            0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+        ],
+        'o' => [
+            0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'p' => [
+            0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000,
+        ],
+        'q' => [
+            0b00000, 0b00000, 0b01101, 0b10011, 0b01111, 0b00001, 0b00001,
+        ],
+        'r' => [
+            0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000,
+        ],
+        's' => [
+            0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110,
+        ],
+        't' => [
+            0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110,
+        ],
+        'u' => [
+            0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101,
+        ],
+        'v' => [
+            0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+        'w' => [
+            0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010,
+        ],
+        'x' => [
+            0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001,
+        ],
+        'y' => [
+            0b00000, 0b00000, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+        ],
+        'z' => [
+            0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+        '{' => [
+            0b00010, 0b00100, 0b00100, 0b01000, 0b00100, 0b00100, 0b00010,
+        ],
+        '|' => [
+            0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        '}' => [
+            0b01000, 0b00100, 0b00100, 0b00010, 0b00100, 0b00100, 0b01000,
+        ],
+        '~' => [
+            0b00000, 0b00000, 0b01000, 0b10101, 0b00010, 0b00000, 0b00000,
+        ],
+        _ => [
+            0b11111, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11111,
+        ],
+    }
+}
+/// Bitmap font the gui draws text with. Each glyph is a grid of lit pixels, one bit per pixel.
+#[derive(Clone, Debug)]
+pub struct Font {
+    /// Size of a glyph in pixels
+    pub glyph_size: Vector2<u32>,
+    /// Pixels between glyphs and around the edge of rendered text
+    pub padding: u32,
+    /// Rows of each glyph from top to bottom. The leftmost pixel of a row is bit `glyph_size.x - 1`
+    glyphs: HashMap<char, Vec<u32>>,
+    /// Drawn for characters without a glyph
+    fallback: Vec<u32>,
+}
+impl Font {
+    /// Rows missing from a glyph are left blank and extra rows are ignored
+    pub fn new(
+        glyph_size: Vector2<u32>,
+        padding: u32,
+        glyphs: HashMap<char, Vec<u32>>,
+        fallback: Vec<u32>,
+    ) -> Self {
+        Self {
+            glyph_size,
+            padding,
+            glyphs,
+            fallback,
+        }
+    }
+    /// The built in 5 by 7 pixel font covering printable ascii
+    pub fn pixel() -> Self {
+        let rows = |c: char| glyph(c).iter().map(|row| *row as u32).collect();
+        Self::new(
+            Vector2::new(GLYPH_WIDTH, GLYPH_HEIGHT),
+            GLYPH_PADDING,
+            (' '..='~').map(|c| (c, rows(c))).collect(),
+            rows('\u{0}'),
+        )
+    }
+    /// The pixel font with every stroke doubled to the right, one pixel wider
+    pub fn bold() -> Self {
+        let pixel = Self::pixel();
+        let bold = |rows: &Vec<u32>| rows.iter().map(|row| row << 1 | row).collect();
+        Self::new(
+            pixel.glyph_size + Vector2::new(1, 0),
+            pixel.padding,
+            pixel
+                .glyphs
+                .iter()
+                .map(|(c, rows)| (*c, bold(rows)))
+                .collect(),
+            bold(&pixel.fallback),
+        )
+    }
+    fn glyph(&self, c: char) -> &[u32] {
+        self.glyphs.get(&c).unwrap_or(&self.fallback)
+    }
+    /// Height of a line of text in pixels
+    pub fn line_height(&self) -> u32 {
+        self.glyph_size.y + 2 * self.padding
+    }
+    /// Distance from the start of one glyph to the next in pixels
+    fn advance(&self) -> u32 {
+        self.glyph_size.x + self.padding
+    }
+    /// Size in pixels of the texture `render_text` produces for `text`
+    pub fn text_dimensions(&self, text: &str) -> Vector2<u32> {
+        let len = text.chars().count() as u32;
+        Vector2::new(self.padding + len * self.advance(), self.line_height())
+    }
+    /// Size of `text` in screen coordinates when drawn with a line height of `height`
+    pub fn text_size(&self, text: &str, height: f32) -> Vector2<f32> {
+        let pixel_size = height / self.line_height() as f32;
+        self.text_dimensions(text).map(|c| c as f32 * pixel_size)
+    }
+    /// Renders a single line of text into a texture. The first row of the texture is the bottom of
+    /// the text.
+    pub fn render_text(
+        &self,
+        text: &str,
+        foreground: Vector4<u8>,
+        background: Vector4<u8>,
+    ) -> RGBATexture {
+        let width = self.text_dimensions(text).x;
+        self.render_text_field(
+            text,
+            width,
+            None,
+            None,
+            &TextStyle {
+                foreground,
+                background,
+                selection: background,
+            },
+        )
+    }
+    /// Renders editable text into a texture `width` pixels wide. `caret` and `selection` are in
+    /// characters. If the caret would be off the right side of the texture the text is scrolled
+    /// so the caret stays visible.
+    pub fn render_text_field(
+        &self,
+        text: &str,
+        width: u32,
+        caret: Option<usize>,
+        selection: Option<(usize, usize)>,
+        style: &TextStyle,
+    ) -> RGBATexture {
+        let height = self.line_height();
+        let advance = self.advance();
+        let caret_x = caret.map(|c| c as u32 * advance);
+        let scroll = match caret_x {
+            Some(x) if x + 1 > width => x + 1 - width,
+            _ => 0,
+        };
+        let mut pixels = vec![style.background; (width * height) as usize];
+        let mut set_pixel = |x: u32, y: u32, color: Vector4<u8>| {
+            if x >= scroll && x - scroll < width && y < height {
+                pixels[(y * width + x - scroll) as usize] = color;
+            }
+        };
+        if let Some((start, end)) = selection {
+            for x in (start as u32 * advance)..(end as u32 * advance + self.padding) {
+                for y in 0..height {
+                    set_pixel(x, y, style.selection);
+                }
+            }
+        }
+        for (i, c) in text.chars().enumerate() {
+            let x_start = self.padding + i as u32 * advance;
+            for (row_index, row) in self
+                .glyph(c)
+                .iter()
+                .take(self.glyph_size.y as usize)
+                .enumerate()
+            {
+                let y = height - 1 - self.padding - row_index as u32;
+                for column in 0..self.glyph_size.x {
+                    if row & (1 << (self.glyph_size.x - 1 - column)) != 0 {
+                        set_pixel(x_start + column, y, style.foreground);
+                    }
+                }
+            }
+        }
+        if let Some(x) = caret_x {
+            for y in 0..height {
+                set_pixel(x, y, style.foreground);
+            }
+        }
+        RGBATexture::new(Vector2::new(width, height), pixels)
+    }
+}
+impl Default for Font {
+    fn default() -> Self {
+        Self::pixel()
+    }
+}
+/// Colors used to draw editable text
+pub struct TextStyle {
+    pub foreground: Vector4<u8>,
+    pub background: Vector4<u8>,
+    /// Background color of selected characters
+    pub selection: Vector4<u8>,
+}
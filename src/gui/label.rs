@@ -0,0 +1,54 @@
+use super::{BoundingBox, Font, GuiElement, Message, PanelKind, StateChange, Theme, Triangle};
+use crate::prelude::{Event, Model};
+use crate::TextureOptions;
+use nalgebra::Vector2;
+use std::cell::Cell;
+/// Single line of text. Labels do not take part in hit testing so they never block clicks.
+pub struct Label {
+    text: String,
+    /// Bottom left corner in screen coordinates
+    position: Vector2<f32>,
+    /// Height of a line of text in screen coordinates
+    height: f32,
+    /// Panel the label is drawn on top of, the background of the text matches it
+    background: PanelKind,
+    /// Size of the text with the font it was last drawn with. Starts out measured with the
+    /// default font
+    size: Cell<Vector2<f32>>,
+}
+impl Label {
+    pub fn new(text: String, position: Vector2<f32>, height: f32) -> Self {
+        let size = Cell::new(Font::default().text_size(&text, height));
+        Self {
+            text,
+            position,
+            height,
+            background: PanelKind::Window,
+            size,
+        }
+    }
+    /// Sets the panel the label is drawn on
+    pub fn on(mut self, background: PanelKind) -> Self {
+        self.background = background;
+        self
+    }
+}
+impl GuiElement for Label {
+    fn get_box(&self) -> BoundingBox {
+        BoundingBox::new(self.position, self.position + self.size.get())
+    }
+    fn process_event(&mut self, _event: &Event) -> (StateChange, Vec<Message>) {
+        (StateChange::NoChange, vec![])
+    }
+    fn get_model(&self, theme: &Theme) -> Vec<Model> {
+        self.size.set(theme.font.text_size(&self.text, self.height));
+        let background = theme.fill(self.background);
+        let mut model = self.get_box().to_model(background);
+        model.texture = theme.font.render_text(&self.text, theme.text, background);
+        model.texture_options = TextureOptions::pixelated();
+        vec![model]
+    }
+    fn get_screen_collider(&self) -> Vec<Triangle> {
+        vec![]
+    }
+}
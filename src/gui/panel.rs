@@ -0,0 +1,35 @@
+use super::{BoundingBox, CursorStyle, GuiElement, Message, PanelKind, StateChange, Theme};
+use crate::prelude::{Event, Model};
+/// Plain background box. Panels catch clicks so that clicks on a window do not reach the world behind it.
+pub struct Panel {
+    bounding_box: BoundingBox,
+    kind: PanelKind,
+    cursor: CursorStyle,
+}
+impl Panel {
+    pub fn new(bounding_box: BoundingBox, kind: PanelKind) -> Self {
+        Self {
+            bounding_box,
+            kind,
+            cursor: CursorStyle::Default,
+        }
+    }
+    pub fn with_cursor(mut self, cursor: CursorStyle) -> Self {
+        self.cursor = cursor;
+        self
+    }
+}
+impl GuiElement for Panel {
+    fn get_box(&self) -> BoundingBox {
+        self.bounding_box.clone()
+    }
+    fn process_event(&mut self, _event: &Event) -> (StateChange, Vec<Message>) {
+        (StateChange::NoChange, vec![])
+    }
+    fn get_model(&self, theme: &Theme) -> Vec<Model> {
+        vec![theme.panel_model(self.kind, &self.bounding_box)]
+    }
+    fn get_cursor(&self) -> CursorStyle {
+        self.cursor
+    }
+}
@@ -0,0 +1,81 @@
+use super::{
+    BoundingBox, CursorStyle, GuiElement, Message, PanelKind, StateChange, Theme, WidgetId,
+};
+use crate::prelude::{Event, Model};
+use nalgebra::Vector2;
+/// Horizontal slider. Clicking on the slider moves the handle to the mouse and sends
+/// `Message::SliderChanged` with the new value.
+pub struct Slider {
+    id: WidgetId,
+    bounding_box: BoundingBox,
+    /// Smallest and largest value the slider can hold
+    range: Vector2<f32>,
+    value: f32,
+}
+impl Slider {
+    pub fn new(id: WidgetId, bounding_box: BoundingBox, range: Vector2<f32>, value: f32) -> Self {
+        Self {
+            id,
+            bounding_box,
+            range,
+            //the range may run from a larger to a smaller value
+            value: value.max(range.x.min(range.y)).min(range.x.max(range.y)),
+        }
+    }
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+    /// Fraction of the way along the slider the current value is
+    fn fraction(&self) -> f32 {
+        if self.range.y != self.range.x {
+            (self.value - self.range.x) / (self.range.y - self.range.x)
+        } else {
+            0.0
+        }
+    }
+}
+impl GuiElement for Slider {
+    fn get_box(&self) -> BoundingBox {
+        self.bounding_box.clone()
+    }
+    fn process_event(&mut self, event: &Event) -> (StateChange, Vec<Message>) {
+        match event {
+            Event::MouseClick(click) => {
+                let width = self.bounding_box.max.x - self.bounding_box.min.x;
+                let fraction = if width > 0.0 {
+                    ((click.position.x - self.bounding_box.min.x) / width).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                self.value = self.range.x + fraction * (self.range.y - self.range.x);
+                (
+                    StateChange::UpdateGui,
+                    vec![Message::SliderChanged {
+                        id: self.id,
+                        value: self.value,
+                    }],
+                )
+            }
+            _ => (StateChange::NoChange, vec![]),
+        }
+    }
+    fn get_model(&self, theme: &Theme) -> Vec<Model> {
+        let mut models = vec![theme.panel_model(PanelKind::SliderTrack, &self.bounding_box)];
+        let fraction = self.fraction();
+        if fraction > 0.0 {
+            let fill = BoundingBox::new(
+                self.bounding_box.min,
+                Vector2::new(
+                    self.bounding_box.min.x
+                        + fraction * (self.bounding_box.max.x - self.bounding_box.min.x),
+                    self.bounding_box.max.y,
+                ),
+            );
+            models.push(theme.panel_model(PanelKind::SliderFill, &fill));
+        }
+        models
+    }
+    fn get_cursor(&self) -> CursorStyle {
+        CursorStyle::Pointer
+    }
+}
@@ -0,0 +1,185 @@
+use super::font::TextStyle;
+use super::{
+    BoundingBox, CursorStyle, GuiElement, Message, PanelKind, StateChange, Theme, WidgetId,
+};
+use crate::prelude::{Event, Model};
+use crate::TextureOptions;
+use nalgebra::Vector2;
+/// Single line text field. Needs keyboard focus to be edited, focus is given by clicking on it.
+pub struct TextInput {
+    id: WidgetId,
+    bounding_box: BoundingBox,
+    text: Vec<char>,
+    /// Position of the caret in characters
+    caret: usize,
+    /// Other end of the selection. The selection spans from the anchor to the caret
+    selection_anchor: Option<usize>,
+    focused: bool,
+}
+impl TextInput {
+    pub fn new(id: WidgetId, bounding_box: BoundingBox, text: String) -> Self {
+        let text: Vec<char> = text.chars().collect();
+        Self {
+            id,
+            bounding_box,
+            caret: text.len(),
+            text,
+            selection_anchor: None,
+            focused: false,
+        }
+    }
+    pub fn get_text(&self) -> String {
+        self.text.iter().collect()
+    }
+    /// Selected range in characters, start is always before end
+    fn selection(&self) -> Option<(usize, usize)> {
+        self.selection_anchor
+            .filter(|anchor| *anchor != self.caret)
+            .map(|anchor| (anchor.min(self.caret), anchor.max(self.caret)))
+    }
+    /// Removes the selected text. Returns true if there was a selection
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.selection_anchor = None;
+        if let Some((start, end)) = selection {
+            self.text.drain(start..end);
+            self.caret = start;
+            true
+        } else {
+            false
+        }
+    }
+    fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        for c in text.chars().filter(|c| !c.is_control()) {
+            self.text.insert(self.caret, c);
+            self.caret += 1;
+        }
+    }
+    /// Moves the caret. If `extend` is set the selection is grown instead of cleared
+    fn move_caret(&mut self, position: usize, extend: bool) {
+        if extend {
+            if self.selection_anchor.is_none() {
+                self.selection_anchor = Some(self.caret);
+            }
+        } else {
+            self.selection_anchor = None;
+        }
+        self.caret = position.min(self.text.len());
+    }
+    fn changed(&self) -> (StateChange, Vec<Message>) {
+        (
+            StateChange::UpdateGui,
+            vec![Message::TextChanged {
+                id: self.id,
+                text: self.get_text(),
+            }],
+        )
+    }
+}
+impl GuiElement for TextInput {
+    fn get_box(&self) -> BoundingBox {
+        self.bounding_box.clone()
+    }
+    fn process_event(&mut self, event: &Event) -> (StateChange, Vec<Message>) {
+        match event {
+            Event::KeyDown(key) => match key.key.as_str() {
+                "Backspace" => {
+                    if !self.delete_selection() && self.caret > 0 {
+                        self.caret -= 1;
+                        self.text.remove(self.caret);
+                    }
+                    self.changed()
+                }
+                "Delete" => {
+                    if !self.delete_selection() && self.caret < self.text.len() {
+                        self.text.remove(self.caret);
+                    }
+                    self.changed()
+                }
+                "ArrowLeft" => {
+                    self.move_caret(self.caret.saturating_sub(1), key.shift);
+                    (StateChange::UpdateGui, vec![])
+                }
+                "ArrowRight" => {
+                    self.move_caret(self.caret + 1, key.shift);
+                    (StateChange::UpdateGui, vec![])
+                }
+                "Home" => {
+                    self.move_caret(0, key.shift);
+                    (StateChange::UpdateGui, vec![])
+                }
+                "End" => {
+                    self.move_caret(self.text.len(), key.shift);
+                    (StateChange::UpdateGui, vec![])
+                }
+                "Enter" => (
+                    StateChange::NoChange,
+                    vec![Message::TextSubmitted {
+                        id: self.id,
+                        text: self.get_text(),
+                    }],
+                ),
+                "a" | "A" if key.ctrl => {
+                    self.selection_anchor = Some(0);
+                    self.caret = self.text.len();
+                    (StateChange::UpdateGui, vec![])
+                }
+                //single characters are printable, named keys such as "Shift" are not
+                k if !key.ctrl && k.chars().count() == 1 => {
+                    self.insert(k);
+                    self.changed()
+                }
+                _ => (StateChange::NoChange, vec![]),
+            },
+            Event::Paste(text) => {
+                self.insert(text);
+                self.changed()
+            }
+            _ => (StateChange::NoChange, vec![]),
+        }
+    }
+    fn get_model(&self, theme: &Theme) -> Vec<Model> {
+        let kind = if self.focused {
+            PanelKind::InputFocused
+        } else {
+            PanelKind::Input
+        };
+        let text_box = BoundingBox::new(
+            self.bounding_box.min + Vector2::new(theme.padding, theme.padding),
+            self.bounding_box.max - Vector2::new(theme.padding, theme.padding),
+        );
+        let background = theme.fill(kind);
+        let mut text_model = text_box.to_model(background);
+        let height = text_box.max.y - text_box.min.y;
+        let width = text_box.max.x - text_box.min.x;
+        let pixel_size = height / theme.font.line_height() as f32;
+        let text: String = self.text.iter().collect();
+        text_model.texture = theme.font.render_text_field(
+            &text,
+            ((width / pixel_size) as u32).max(1),
+            if self.focused { Some(self.caret) } else { None },
+            self.selection(),
+            &TextStyle {
+                foreground: theme.text,
+                background,
+                selection: theme.selection,
+            },
+        );
+        text_model.texture_options = TextureOptions::pixelated();
+        vec![theme.panel_model(kind, &self.bounding_box), text_model]
+    }
+    fn get_cursor(&self) -> CursorStyle {
+        CursorStyle::Text
+    }
+    fn is_focusable(&self) -> bool {
+        true
+    }
+    fn process_focus(&mut self, focused: bool) -> (StateChange, Vec<Message>) {
+        self.focused = focused;
+        if !focused {
+            self.selection_anchor = None;
+        }
+        (StateChange::UpdateGui, vec![])
+    }
+}
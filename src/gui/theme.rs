@@ -0,0 +1,316 @@
+use super::{BoundingBox, Font};
+use crate::atlas::AtlasBuilder;
+use crate::prelude::Model;
+use crate::{Mesh, RGBATexture, TextureOptions};
+use nalgebra::{Vector2, Vector3, Vector4};
+use std::collections::HashMap;
+/// Size of each generated nine slice region in the atlas
+const SLICE_SIZE: u32 = 8;
+/// Width of the border of generated nine slice regions in pixels
+const SLICE_BORDER: u32 = 2;
+/// The different kinds of panels widgets are drawn with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PanelKind {
+    Window,
+    TitleBar,
+    Button,
+    ButtonHover,
+    Close,
+    Input,
+    InputFocused,
+    SliderTrack,
+    SliderFill,
+    Tooltip,
+}
+impl PanelKind {
+    const ALL: [PanelKind; 10] = [
+        Self::Window,
+        Self::TitleBar,
+        Self::Button,
+        Self::ButtonHover,
+        Self::Close,
+        Self::Input,
+        Self::InputFocused,
+        Self::SliderTrack,
+        Self::SliderFill,
+        Self::Tooltip,
+    ];
+}
+/// Region of the atlas drawn as a nine slice panel. The corners are drawn unscaled, the edges are
+/// stretched along one axis and the center is stretched along both.
+#[derive(Clone, Debug)]
+pub struct NineSlice {
+    /// Bottom left pixel of the region
+    pub min: Vector2<u32>,
+    /// One past the top right pixel of the region
+    pub max: Vector2<u32>,
+    /// Width of the border in pixels
+    pub border: u32,
+    /// Color of the center of the region. Text drawn on the panel uses it as its background
+    pub fill: Vector4<u8>,
+}
+/// Fill and border color of a panel
+#[derive(Clone, Copy, Debug)]
+pub struct PanelColors {
+    pub fill: Vector4<u8>,
+    pub border: Vector4<u8>,
+}
+/// Colors used to generate a theme
+pub struct Palette {
+    pub panels: Vec<(PanelKind, PanelColors)>,
+    pub text: Vector4<u8>,
+    /// Background of selected text
+    pub selection: Vector4<u8>,
+}
+/// How the gui looks. All widgets get their colors and panels from the theme so switching the theme
+/// restyles the whole gui.
+#[derive(Clone)]
+pub struct Theme {
+    pub name: String,
+    /// Texture containing all of the panels
+    pub atlas: RGBATexture,
+    pub slices: HashMap<PanelKind, NineSlice>,
+    pub text: Vector4<u8>,
+    pub selection: Vector4<u8>,
+    /// Font labels, buttons and text inputs are drawn with
+    pub font: Font,
+    /// Space between the edge of a panel and its contents in screen coordinates
+    pub padding: f32,
+    /// Size nine slice borders are drawn at in screen coordinates
+    pub border_size: f32,
+}
+impl Theme {
+    /// Builds a theme with an atlas generated from flat colors
+    pub fn from_palette(name: &str, palette: Palette) -> Self {
+        let mut builder = AtlasBuilder::new();
+        let ids: Vec<_> = palette
+            .panels
+            .iter()
+            .map(|(_, colors)| {
+                let pixels = (0..SLICE_SIZE * SLICE_SIZE)
+                    .map(|i| {
+                        let (x, y) = (i % SLICE_SIZE, i / SLICE_SIZE);
+                        let is_border = x < SLICE_BORDER
+                            || y < SLICE_BORDER
+                            || x >= SLICE_SIZE - SLICE_BORDER
+                            || y >= SLICE_SIZE - SLICE_BORDER;
+                        if is_border {
+                            colors.border
+                        } else {
+                            colors.fill
+                        }
+                    })
+                    .collect();
+                builder.add(RGBATexture::new(
+                    Vector2::new(SLICE_SIZE, SLICE_SIZE),
+                    pixels,
+                ))
+            })
+            .collect();
+        let atlas = builder
+            .build()
+            .expect("generated slices are smaller than the max atlas size");
+        let slices = palette
+            .panels
+            .iter()
+            .zip(ids)
+            .map(|((kind, colors), id)| {
+                let region = atlas.region(id);
+                (
+                    *kind,
+                    NineSlice {
+                        min: region.min,
+                        max: region.max,
+                        border: SLICE_BORDER,
+                        fill: colors.fill,
+                    },
+                )
+            })
+            .collect();
+        Self {
+            name: name.to_string(),
+            atlas: atlas.texture,
+            slices,
+            text: palette.text,
+            selection: palette.selection,
+            font: Font::pixel(),
+            padding: 0.01,
+            border_size: 0.008,
+        }
+    }
+    pub fn dark() -> Self {
+        let panel = |fill: [u8; 3], border: [u8; 3]| PanelColors {
+            fill: Vector4::new(fill[0], fill[1], fill[2], 255),
+            border: Vector4::new(border[0], border[1], border[2], 255),
+        };
+        Self::from_palette(
+            "dark",
+            Palette {
+                panels: vec![
+                    (PanelKind::Window, panel([50, 50, 55], [25, 25, 30])),
+                    (PanelKind::TitleBar, panel([30, 30, 90], [15, 15, 50])),
+                    (PanelKind::Button, panel([70, 70, 80], [35, 35, 40])),
+                    (PanelKind::ButtonHover, panel([100, 100, 115], [35, 35, 40])),
+                    (PanelKind::Close, panel([170, 30, 30], [90, 15, 15])),
+                    (PanelKind::Input, panel([35, 35, 40], [20, 20, 25])),
+                    (PanelKind::InputFocused, panel([20, 20, 25], [90, 130, 200])),
+                    (PanelKind::SliderTrack, panel([35, 35, 40], [20, 20, 25])),
+                    (PanelKind::SliderFill, panel([90, 130, 200], [60, 90, 150])),
+                    (PanelKind::Tooltip, panel([20, 20, 20], [200, 200, 200])),
+                ],
+                text: Vector4::new(230, 230, 230, 255),
+                selection: Vector4::new(60, 90, 150, 255),
+            },
+        )
+    }
+    pub fn light() -> Self {
+        let panel = |fill: [u8; 3], border: [u8; 3]| PanelColors {
+            fill: Vector4::new(fill[0], fill[1], fill[2], 255),
+            border: Vector4::new(border[0], border[1], border[2], 255),
+        };
+        Self::from_palette(
+            "light",
+            Palette {
+                panels: vec![
+                    (PanelKind::Window, panel([235, 235, 235], [170, 170, 170])),
+                    (PanelKind::TitleBar, panel([120, 160, 220], [80, 110, 170])),
+                    (PanelKind::Button, panel([215, 215, 220], [150, 150, 160])),
+                    (
+                        PanelKind::ButtonHover,
+                        panel([240, 240, 250], [150, 150, 160]),
+                    ),
+                    (PanelKind::Close, panel([230, 90, 90], [170, 50, 50])),
+                    (PanelKind::Input, panel([250, 250, 250], [170, 170, 170])),
+                    (
+                        PanelKind::InputFocused,
+                        panel([255, 255, 255], [80, 130, 220]),
+                    ),
+                    (
+                        PanelKind::SliderTrack,
+                        panel([210, 210, 210], [160, 160, 160]),
+                    ),
+                    (
+                        PanelKind::SliderFill,
+                        panel([120, 160, 220], [80, 110, 170]),
+                    ),
+                    (PanelKind::Tooltip, panel([255, 255, 220], [120, 120, 100])),
+                ],
+                text: Vector4::new(20, 20, 20, 255),
+                selection: Vector4::new(150, 190, 255, 255),
+            },
+        )
+    }
+    /// Black and white with thick borders
+    pub fn high_contrast() -> Self {
+        let black = PanelColors {
+            fill: Vector4::new(0, 0, 0, 255),
+            border: Vector4::new(255, 255, 255, 255),
+        };
+        let yellow = PanelColors {
+            fill: Vector4::new(0, 0, 0, 255),
+            border: Vector4::new(255, 255, 0, 255),
+        };
+        let mut theme = Self::from_palette(
+            "high_contrast",
+            Palette {
+                panels: PanelKind::ALL
+                    .iter()
+                    .map(|kind| match kind {
+                        PanelKind::ButtonHover
+                        | PanelKind::InputFocused
+                        | PanelKind::SliderFill => (*kind, yellow),
+                        _ => (*kind, black),
+                    })
+                    .collect(),
+                text: Vector4::new(255, 255, 255, 255),
+                selection: Vector4::new(0, 0, 255, 255),
+            },
+        );
+        theme.border_size = 0.015;
+        theme.font = Font::bold();
+        theme
+    }
+    /// Gets a built in theme by name
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high_contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+    fn slice(&self, kind: PanelKind) -> NineSlice {
+        self.slices.get(&kind).cloned().unwrap_or(NineSlice {
+            min: Vector2::new(0, 0),
+            max: Vector2::new(1, 1),
+            border: 0,
+            fill: Vector4::new(255, 0, 255, 255),
+        })
+    }
+    /// Color of the center of a panel, used as the background of text drawn on the panel
+    pub fn fill(&self, kind: PanelKind) -> Vector4<u8> {
+        self.slice(kind).fill
+    }
+    /// Name the atlas is shared under, so every panel is drawn with one gpu texture
+    pub fn atlas_name(&self) -> String {
+        format!("theme_atlas_{}", self.name)
+    }
+    /// Builds a nine slice panel covering `bounding_box`
+    pub fn panel_model(&self, kind: PanelKind, bounding_box: &BoundingBox) -> Model {
+        let slice = self.slice(kind);
+        let size = bounding_box.max - bounding_box.min;
+        let border = self.border_size.min(size.x / 2.0).min(size.y / 2.0);
+        let xs = [
+            bounding_box.min.x,
+            bounding_box.min.x + border,
+            bounding_box.max.x - border,
+            bounding_box.max.x,
+        ];
+        let ys = [
+            bounding_box.min.y,
+            bounding_box.min.y + border,
+            bounding_box.max.y - border,
+            bounding_box.max.y,
+        ];
+        let atlas_dimensions = self.atlas.dimensions();
+        //sampling at pixel centers keeps filtering from reading neighboring regions
+        let to_uv = |pixel: f32, dimension: u32| pixel / dimension as f32;
+        let us = [
+            to_uv(slice.min.x as f32 + 0.5, atlas_dimensions.x),
+            to_uv((slice.min.x + slice.border) as f32, atlas_dimensions.x),
+            to_uv((slice.max.x - slice.border) as f32, atlas_dimensions.x),
+            to_uv(slice.max.x as f32 - 0.5, atlas_dimensions.x),
+        ];
+        let vs = [
+            to_uv(slice.min.y as f32 + 0.5, atlas_dimensions.y),
+            to_uv((slice.min.y + slice.border) as f32, atlas_dimensions.y),
+            to_uv((slice.max.y - slice.border) as f32, atlas_dimensions.y),
+            to_uv(slice.max.y as f32 - 0.5, atlas_dimensions.y),
+        ];
+        let mut vertices = vec![];
+        for row in 0..3 {
+            for column in 0..3 {
+                let vertex = |c: usize, r: usize| {
+                    (Vector3::new(xs[c], ys[r], 0.0), Vector2::new(us[c], vs[r]))
+                };
+                vertices.push(vertex(column, row));
+                vertices.push(vertex(column + 1, row));
+                vertices.push(vertex(column + 1, row + 1));
+                vertices.push(vertex(column, row));
+                vertices.push(vertex(column + 1, row + 1));
+                vertices.push(vertex(column, row + 1));
+            }
+        }
+        Model {
+            mesh: Mesh { vertices },
+            texture: self.atlas.clone(),
+            texture_options: TextureOptions::default(),
+            shared_texture: Some(self.atlas_name()),
+        }
+    }
+}
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
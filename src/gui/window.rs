@@ -0,0 +1,134 @@
+use super::{
+    BoundingBox, CursorStyle, GuiContainer, GuiElement, Label, Message, Panel, PanelKind,
+    StateChange, Theme,
+};
+use crate::prelude::{Event, Model};
+use generational_arena::Index as ArenaIndex;
+use nalgebra::Vector2;
+use std::collections::HashSet;
+/// Height of the title bar in screen coordinates
+const TITLE_BAR_HEIGHT: f32 = 0.08;
+/// How far one pixel of mouse wheel movement scrolls the content
+const SCROLL_SPEED: f32 = 0.001;
+/// Window specific state of a `GuiContainer`
+pub(super) struct WindowFrame {
+    /// Used to remember where the window was placed between sessions
+    pub(super) name: String,
+    pub(super) title_bar: BoundingBox,
+    pub(super) content_area: BoundingBox,
+    /// Elements that scroll with the content area
+    pub(super) content: HashSet<ArenaIndex>,
+    /// How far the content has been scrolled down
+    pub(super) scroll: f32,
+}
+impl WindowFrame {
+    /// Scrolls the content by `delta_y` pixels of wheel movement, keeping the content within the window
+    pub(super) fn scroll(&mut self, delta_y: f32, content_bottom: f32) {
+        let max_scroll = (self.content_area.min.y - content_bottom).max(0.0);
+        self.scroll = (self.scroll + delta_y * SCROLL_SPEED).clamp(0.0, max_scroll);
+    }
+}
+/// Builds a `GuiContainer` with a draggable title bar, a close button and a scrollable content area.
+/// The window is identified by `name` so its position can be restored the next time it is opened.
+pub struct Window {
+    name: String,
+    title: String,
+    bounding_box: BoundingBox,
+    content: Vec<Box<dyn GuiElement>>,
+}
+impl Window {
+    pub fn new(name: &str, title: &str, bounding_box: BoundingBox) -> Self {
+        Self {
+            name: name.to_string(),
+            title: title.to_string(),
+            bounding_box,
+            content: vec![],
+        }
+    }
+    /// Area content should be placed in, in screen coordinates before the window is moved or scrolled
+    pub fn content_area(&self) -> BoundingBox {
+        BoundingBox::new(
+            self.bounding_box.min,
+            Vector2::new(
+                self.bounding_box.max.x,
+                self.bounding_box.max.y - TITLE_BAR_HEIGHT,
+            ),
+        )
+    }
+    pub fn add_element(&mut self, element: Box<dyn GuiElement>) {
+        self.content.push(element);
+    }
+    pub fn build<RenderModel>(self) -> GuiContainer<RenderModel> {
+        let content_area = self.content_area();
+        let title_bar = BoundingBox::new(
+            Vector2::new(self.bounding_box.min.x, content_area.max.y),
+            self.bounding_box.max,
+        );
+        let mut container = GuiContainer::new();
+        container.add_element(Box::new(Panel::new(
+            content_area.clone(),
+            PanelKind::Window,
+        )));
+        container.add_element(Box::new(
+            Panel::new(title_bar.clone(), PanelKind::TitleBar).with_cursor(CursorStyle::Grab),
+        ));
+        let padding = TITLE_BAR_HEIGHT * 0.2;
+        container.add_element(Box::new(
+            Label::new(
+                self.title,
+                title_bar.min + Vector2::new(padding, padding),
+                TITLE_BAR_HEIGHT - 2.0 * padding,
+            )
+            .on(PanelKind::TitleBar),
+        ));
+        container.add_element(Box::new(CloseButton {
+            bounding_box: BoundingBox::new(
+                Vector2::new(title_bar.max.x - TITLE_BAR_HEIGHT, title_bar.min.y),
+                title_bar.max,
+            ),
+        }));
+        let content = self
+            .content
+            .into_iter()
+            .map(|element| container.add_element(element))
+            .collect();
+        container.window = Some(Box::new(WindowFrame {
+            name: self.name,
+            title_bar,
+            content_area,
+            content,
+            scroll: 0.0,
+        }));
+        container
+    }
+}
+/// Closes the window it is in
+struct CloseButton {
+    bounding_box: BoundingBox,
+}
+impl GuiElement for CloseButton {
+    fn get_box(&self) -> BoundingBox {
+        self.bounding_box.clone()
+    }
+    fn process_event(&mut self, event: &Event) -> (StateChange, Vec<Message>) {
+        match event {
+            Event::MouseClick(_) => (StateChange::DeleteParent, vec![Message::WindowClosed]),
+            _ => (StateChange::NoChange, vec![]),
+        }
+    }
+    fn get_model(&self, theme: &Theme) -> Vec<Model> {
+        let height = self.bounding_box.max.y - self.bounding_box.min.y - 2.0 * theme.padding;
+        let center = (self.bounding_box.min + self.bounding_box.max) / 2.0;
+        let position = center - theme.font.text_size("x", height) / 2.0;
+        let mut models = vec![theme.panel_model(PanelKind::Close, &self.bounding_box)];
+        models.append(
+            &mut Label::new("x".to_string(), position, height)
+                .on(PanelKind::Close)
+                .get_model(theme),
+        );
+        models
+    }
+    fn get_cursor(&self) -> CursorStyle {
+        CursorStyle::Pointer
+    }
+}
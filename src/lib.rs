@@ -1,26 +1,59 @@
+pub mod animation;
+pub mod assets;
+pub mod atlas;
 mod camera;
-mod game;
+pub mod cpu_engine;
+pub mod debug_draw;
+pub mod game;
 mod graphics_engine;
-mod gui;
+pub mod gui;
+pub mod model_loader;
+pub mod particles;
+pub mod post_process;
+pub mod render_queue;
+mod shaders;
+pub mod shadow;
+pub mod sky;
+pub mod terrain;
 mod utils;
+use animation::CharacterRenderer;
+use assets::{AssetData, AssetId, AssetManager, EmbeddedSource, Manifest};
 pub use camera::Camera;
-use generational_arena::Arena;
-use graphics_engine::GraphicsEngine;
-pub use graphics_engine::{Mesh, RGBATexture};
-use gui::{EventPacket as GuiEventPacket, GuiParent, GuiState};
+use debug_draw::DebugDraw;
+use generational_arena::{Arena, Index};
+pub use graphics_engine::{
+    annotate_shader_log, GraphicsEngine, ShaderDescription, SkinnedMesh, SkinnedVertex,
+    UniformValue,
+};
+pub use graphics_engine::{
+    BlendMode, GpuResource, Mesh, Mipmaps, RGBATexture, TextureFilter, TextureOptions, TextureWrap,
+};
+use graphics_engine::{RenderStats, ResourceReport};
+use gui::{CursorStyle, EventPacket as GuiEventPacket, GuiState, Theme};
 use js_sys::{Array as JsArray, Map as JsMap};
 use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+use particles::{Emitter, ParticleRenderer, ParticleSystem};
+use post_process::{PingPongBuffers, PostEffect, PostProcessor};
+use render_queue::{BoundingSphere, DrawItem, RenderQueue};
+use shadow::ShadowMap;
+pub use shadow::ShadowQuality;
+use sky::Sky;
+pub use sky::Weather;
 use std::collections::HashMap;
+use terrain::{OverlayLayer, Terrain, TerrainOverlay, TerrainRenderer};
 use wasm_bindgen::prelude::*;
 mod prelude {
     pub use super::{Camera, Event, Model, MouseClick};
-    pub use crate::graphics_engine::GraphicsEngine;
-    pub use crate::gui::{GetGuiOutput, GuiParent, Message as GuiMessage, Triangle};
+    pub use crate::gui::{GetGuiOutput, GuiParent, Message as GuiMessage, Propagation, Triangle};
 }
 #[derive(Clone)]
 pub struct Model {
     pub mesh: Mesh,
     pub texture: RGBATexture,
+    pub texture_options: TextureOptions,
+    /// Models with the same shared texture name use one gpu texture, uploaded for the first of
+    /// them. Used for textures like atlases that many models are drawn with
+    pub shared_texture: Option<String>,
 }
 pub fn log(s: &str) {
     web_sys::console::log(&JsArray::from(&JsValue::from(s)));
@@ -44,12 +77,34 @@ impl RenderTransform {
             matrix: Matrix4::new_nonuniform_scaling(scale),
         }
     }
+    /// Scales then moves to `translation`
+    pub fn new_translation_scale(translation: &Vector3<f32>, scale: &Vector3<f32>) -> Self {
+        Self {
+            matrix: Matrix4::new_translation(translation) * Matrix4::new_nonuniform_scaling(scale),
+        }
+    }
 }
 #[derive(Clone)]
 pub struct MouseClick {
+    /// position in screen coordinates
     position: Vector2<f32>,
     button_pressed: MouseButton,
 }
+impl MouseClick {
+    pub fn new(position: Vector2<f32>, button_pressed: MouseButton) -> Self {
+        Self {
+            position,
+            button_pressed,
+        }
+    }
+}
+#[derive(Clone)]
+pub struct KeyPress {
+    /// Value of the key as given by the browser, for example "a", "A" or "Backspace"
+    pub key: String,
+    pub ctrl: bool,
+    pub shift: bool,
+}
 #[derive(Clone)]
 pub enum Event {
     MouseMove {
@@ -63,6 +118,9 @@ pub enum Event {
         delta_time_ms: f32,
     },
     MouseClick(MouseClick),
+    KeyDown(KeyPress),
+    /// Text pasted from the clipboard
+    Paste(String),
 }
 impl Event {
     pub fn from_map(map: JsMap) -> Self {
@@ -70,6 +128,9 @@ impl Event {
         match name.as_str() {
             "mouse_move" => Self::from_mouse_move_map(map),
             "wheel" => Self::from_wheel_map(map),
+            "click" => Self::from_click_map(map),
+            "key_down" => Self::from_key_map(map),
+            "paste" => Event::Paste(map.get(&JsValue::from_str("text")).as_string().unwrap()),
             _ => panic!("invalid name"),
         }
     }
@@ -84,6 +145,32 @@ impl Event {
             delta_time_ms,
         }
     }
+    pub fn from_key_map(map: JsMap) -> Self {
+        let key = map.get(&JsValue::from_str("key")).as_string().unwrap();
+        let ctrl = map
+            .get(&JsValue::from_str("ctrl"))
+            .as_bool()
+            .unwrap_or(false);
+        let shift = map
+            .get(&JsValue::from_str("shift"))
+            .as_bool()
+            .unwrap_or(false);
+        Event::KeyDown(KeyPress { key, ctrl, shift })
+    }
+    pub fn from_click_map(map: JsMap) -> Self {
+        let x = map.get(&JsValue::from_str("position_x")).as_f64().unwrap() as f32;
+        let y = map.get(&JsValue::from_str("position_y")).as_f64().unwrap() as f32;
+        let button_pressed = match map.get(&JsValue::from_str("button")).as_f64().unwrap() as i32 {
+            0 => MouseButton::LeftClick,
+            1 => MouseButton::MiddleClick,
+            2 => MouseButton::RightClick,
+            _ => panic!("invalid button number"),
+        };
+        Event::MouseClick(MouseClick {
+            position: Vector2::new(x, y),
+            button_pressed,
+        })
+    }
     pub fn from_mouse_move_map(map: JsMap) -> Self {
         let buttons_pressed_number: i32 =
             map.get(&JsValue::from_str("buttons")).as_f64().unwrap() as i32;
@@ -117,6 +204,7 @@ impl Event {
     }
 }
 struct FramebufferSurface<E: GraphicsEngine> {
+    dimensions: Vector2<u32>,
     framebuffer: E::Framebuffer,
     texture: E::RuntimeTexture,
     mesh: E::RuntimeMesh,
@@ -135,25 +223,91 @@ impl<E: GraphicsEngine> FramebufferSurface<E> {
         }
     }
 }
-fn to_gui_event(event_state: &EventState, events: &Vec<Event>) -> GuiEventPacket {
+fn to_gui_event(event_state: &EventState, events: &[Event]) -> GuiEventPacket {
     GuiEventPacket {
-        events: events.clone(),
+        events: events.to_vec(),
         mouse_position: event_state.position,
+        delta_time_ms: event_state.delta_time_ms,
     }
 }
-type RuntimeModel<E: GraphicsEngine> = (E::RuntimeMesh, E::RuntimeTexture);
+type RuntimeModel<E> = (
+    <E as GraphicsEngine>::RuntimeMesh,
+    <E as GraphicsEngine>::RuntimeTexture,
+);
+/// Largest multiple of the canvas size screenshots can be taken at
+const MAX_SCREENSHOT_SCALE: u32 = 4;
+/// How much light shadows block, 1 is fully black
+const SHADOW_STRENGTH: f32 = 0.5;
+/// Texels of the terrain overlay per world unit
+const OVERLAY_RESOLUTION: u32 = 4;
+/// Snowflakes spawned per second while it is snowing hardest
+const SNOWFALL_RATE: f32 = 400.0;
+/// Puffs of powder spawned per second behind each skiing skiier
+const POWDER_RATE: f32 = 60.0;
 pub struct GraphicsContext<E: GraphicsEngine> {
     game_objects: Arena<Box<dyn game::GameObject<RuntimeModel<E>>>>,
     game_world_framebuffer: FramebufferSurface<E>,
     camera: Camera,
     engine: E,
+    /// Used for objects in the game world
+    world_material: E::Material,
+    render_queue: RenderQueue<E::RuntimeMesh, E::RuntimeTexture, E::Material>,
+    /// Models of objects that share their model, by model name
+    shared_models: HashMap<String, RuntimeModel<E>>,
+    /// Textures of models that share their texture, by shared texture name
+    shared_textures: HashMap<String, E::RuntimeTexture>,
+    /// Number of atlases built by `init_models`, used to name the next one
+    atlas_count: u32,
+    /// Effects applied while drawing the world framebuffer to the screen
+    post_processor: PostProcessor<E>,
+    /// Used for the gui and for drawing the world framebuffer
+    screen_material: E::Material,
+    /// Draws the depth of the world as seen from the sun
+    shadow_material: E::Material,
+    /// Used for animated characters close to the camera
+    skinned_material: E::Material,
+    skinned_shadow_material: E::Material,
+    /// Draws objects that are animated characters, by the asset they are loaded from
+    characters: HashMap<AssetId, CharacterRenderer<E>>,
+    assets: AssetManager,
+    /// Gpu models of each loaded model asset with bounds around each part
+    asset_models: HashMap<AssetId, Vec<(RuntimeModel<E>, BoundingSphere)>>,
+    /// None when shadows are turned off
+    shadow_map: Option<ShadowMap<E>>,
+    /// Draws the sky behind the world
+    sky_material: E::Material,
+    /// Shapes drawn over the world to help debugging, toggled with the backquote key
+    debug: DebugDraw,
+    debug_material: E::Material,
+    /// Ground the game is played on
+    terrain: Terrain,
+    /// Markings painted over the terrain such as ski tracks and groomed runs
+    terrain_overlay: TerrainOverlay,
+    terrain_renderer: TerrainRenderer<E>,
+    /// Texture repeated over the terrain, given to the terrain renderer once it loads
+    terrain_texture: AssetId,
+    /// Used for the terrain, blends the overlay over it
+    terrain_material: E::Material,
+    particles: ParticleSystem,
+    particle_renderer: ParticleRenderer<E>,
+    /// Emitter that follows the camera while the weather is snowing
+    snowfall: Index,
+    /// Powder emitter of each game object that has kicked up powder, keyed by the object
+    powder: HashMap<Index, Index>,
+    sky: Sky,
     gui: GuiState<RuntimeModel<E>>,
+    render_stats: RenderStats,
 }
 pub struct EventState {
+    /// Mouse position in screen coordinates. Ranges from -1 to 1 on both axis with the origin in the center
     pub position: Vector2<f32>,
+    /// Time since the last frame
+    pub delta_time_ms: f32,
 }
 impl<E: GraphicsEngine> GraphicsContext<E> {
-    pub fn process_events(&mut self, events: &Vec<Event>) {
+    /// Moves the camera. Keys are taken from those the gui did not use in `GuiState::game_loop`,
+    /// which should run first.
+    pub fn process_events(&mut self, event_state: &EventState, events: &[Event]) {
         for event in events {
             match event {
                 Event::MouseMove {
@@ -170,8 +324,24 @@ impl<E: GraphicsEngine> GraphicsContext<E> {
                 Event::Scroll {
                     delta_y,
                     delta_time_ms,
-                } => self.camera.update_radius(delta_y * delta_time_ms * 0.0001),
-                Event::MouseClick(_) => {}
+                } => {
+                    //scrolling over a window scrolls the window instead
+                    if !self.gui.captures_scroll(&event_state.position) {
+                        self.camera.update_radius(delta_y * delta_time_ms * 0.0001)
+                    }
+                }
+                Event::MouseClick(_) | Event::KeyDown(_) | Event::Paste(_) => {}
+            }
+        }
+        //keyboard input belongs to the focused gui element, so only keys the gui left over are used
+        for key in self.gui.take_unhandled_keys().iter() {
+            match key.key.as_str() {
+                "ArrowLeft" => self.camera.rotate_phi(-0.05),
+                "ArrowRight" => self.camera.rotate_phi(0.05),
+                "ArrowUp" => self.camera.rotate_theta(0.05),
+                "ArrowDown" => self.camera.rotate_theta(-0.05),
+                "`" => self.debug.toggle(),
+                _ => (),
             }
         }
     }
@@ -180,114 +350,779 @@ impl<E: GraphicsEngine> GraphicsContext<E> {
         event_state: EventState,
         events: Vec<Event>,
     ) -> Result<(), JsValue> {
-        self.process_events(&events);
-        self.engine
-            .bind_framebuffer(&self.game_world_framebuffer.framebuffer);
-        self.engine.clear_screen(Vector4::new(0.2, 0.2, 0.2, 1.0));
-        //binding game world framebuffer
-        self.engine
-            .bind_framebuffer(&self.game_world_framebuffer.framebuffer);
-        self.engine.send_view_matrix(self.camera.get_mat());
-        for (_k, object) in self.game_objects.iter() {
-            let render_model = object.get_render_model();
-            if let Some((model, texture)) = render_model.model {
-                self.engine.send_model_matrix(render_model.transform.matrix);
-                self.engine.bind_texture(texture);
-                self.engine.draw_mesh(model);
-            }
+        //the gui goes first so that keys reach whichever element a click in this frame focused
+        let models = self.gui.game_loop(
+            to_gui_event(&event_state, &events),
+            &self.camera,
+            &mut self.game_objects,
+        );
+        self.process_events(&event_state, &events);
+        self.sky.update(event_state.delta_time_ms);
+        if let Some(snowfall) = self.particles.emitter_mut(self.snowfall) {
+            snowfall.position = self.camera.position() + Vector3::new(0.0, 10.0, 0.0);
+            snowfall.rate = SNOWFALL_RATE * self.sky.snowfall();
+        }
+        for (_k, object) in self.game_objects.iter_mut() {
+            object.update(event_state.delta_time_ms);
         }
+        self.update_powder();
+        self.particles.update(event_state.delta_time_ms);
+        self.update_assets()
+            .map_err(|_| JsValue::from_str("failed to upload assets"))?;
+        self.draw_world(None)
+            .map_err(|_| JsValue::from_str("failed to draw world"))?;
+        self.draw_debug();
         //Drawing in gui world
 
         self.engine.bind_default_framebuffer();
         self.engine.clear_screen(Vector4::new(0.2, 0.2, 0.2, 1.0));
-        //settig coordinates to standard glm box
-        self.engine.send_model_matrix(Matrix4::identity());
-        self.engine.send_view_matrix(Matrix4::identity());
-
-        let models = self.gui.game_loop(
-            to_gui_event(&event_state, &events),
-            &self.camera,
-            &mut self.game_objects,
+        self.engine.set_blend_mode(BlendMode::Opaque);
+        self.post_processor.draw(
+            &mut self.engine,
+            &self.game_world_framebuffer.texture,
+            &self.game_world_framebuffer.mesh,
+            &self.screen_material,
         );
+        self.engine.bind_material(&self.screen_material);
+
         let mut gui_hashmap = HashMap::new();
-        for (key, model) in models.iter() {
-            gui_hashmap.insert(key.clone(), self.init_model(model).ok().unwrap());
+        for (key, models) in models.iter() {
+            let runtime_models = models
+                .iter()
+                .map(|model| self.init_model(model).ok().unwrap())
+                .collect();
+            gui_hashmap.insert(*key, runtime_models);
         }
         self.gui.submit_model(gui_hashmap);
-        for model in self.gui.get_runtime_model().iter() {
+        self.engine.set_blend_mode(BlendMode::Alpha);
+        for (model, offset, clip) in self.gui.get_runtime_model().iter() {
+            self.engine
+                .set_clip(clip.as_ref().map(|clip| (clip.min, clip.max)));
+            self.engine
+                .send_model_matrix(Matrix4::new_translation(&Vector3::new(
+                    offset.x, offset.y, 0.0,
+                )));
             self.draw_model(model)
         }
+        self.engine.set_clip(None);
+        self.render_stats = self.engine.take_stats();
+        Ok(())
+    }
+    /// Draws the game world at `scale` times the size of the canvas, with post processing but
+    /// without the gui. Used for screenshots larger than the screen. The scale is lowered to what
+    /// the gpu can draw into.
+    pub fn screenshot(&mut self, scale: u32) -> Result<RGBATexture, E::ErrorType> {
+        let world_dimensions = self.game_world_framebuffer.dimensions;
+        let largest_scale = self.engine.max_framebuffer_size() / world_dimensions.max();
+        let scale = scale.min(largest_scale).clamp(1, MAX_SCREENSHOT_SCALE);
+        let dimensions = world_dimensions * scale;
+        let mut output = self.engine.build_texture(
+            RGBATexture::constant_color(Vector4::new(0, 0, 0, 0), dimensions),
+            TextureOptions::default(),
+        )?;
+        let output_framebuffer = self.engine.build_framebuffer(&mut output);
+        //at the size of the canvas the world framebuffer and post processing buffers are reused
+        let scaled = if scale > 1 {
+            let mut scene = self.engine.build_float_texture(dimensions)?;
+            let scene_framebuffer = self.engine.build_framebuffer(&mut scene);
+            let buffers =
+                PingPongBuffers::new(&mut self.engine, dimensions)?.with_texel_scale(scale as f32);
+            Some((scene, scene_framebuffer, buffers))
+        } else {
+            None
+        };
+        self.draw_world(scaled.as_ref().map(|(_, framebuffer, _)| framebuffer))?;
+        self.engine.set_blend_mode(BlendMode::Opaque);
+        let (scene, buffers) = match scaled.as_ref() {
+            Some((scene, _, buffers)) => (scene, buffers),
+            None => (
+                &self.game_world_framebuffer.texture,
+                self.post_processor.buffers(),
+            ),
+        };
+        self.post_processor.draw_to(
+            &mut self.engine,
+            scene,
+            &self.game_world_framebuffer.mesh,
+            &self.screen_material,
+            buffers,
+            Some(&output_framebuffer),
+        );
+        let pixels = self.engine.read_pixels(&output_framebuffer)?;
+        self.engine.bind_default_framebuffer();
+        Ok(pixels)
+    }
+    /// Draws the game objects into `target`, or into the game world framebuffer if there is none
+    fn draw_world(&mut self, target: Option<&E::Framebuffer>) -> Result<(), E::ErrorType> {
+        let view_projection = self.camera.get_mat();
+        self.terrain_renderer.update(
+            &mut self.engine,
+            &mut self.terrain,
+            &mut self.terrain_overlay,
+        )?;
+        self.terrain_renderer.queue(
+            &mut self.engine,
+            &self.terrain,
+            &view_projection,
+            self.camera.position(),
+            &self.terrain_material,
+            &mut self.render_queue,
+        )?;
+        self.particle_renderer.queue(
+            &self.particles,
+            self.camera.screen_axes(),
+            &self.world_material,
+            &mut self.render_queue,
+        );
+        for (_k, object) in self.game_objects.iter() {
+            let render_model = object.get_render_model();
+            let asset_models = &self.asset_models;
+            let asset_models = render_model
+                .asset
+                .and_then(|asset| asset_models.get(&asset));
+            if let Some(asset_models) = asset_models {
+                for ((mesh, texture), bounds) in asset_models.iter() {
+                    self.render_queue.push(DrawItem {
+                        mesh: mesh.clone(),
+                        texture: texture.clone(),
+                        material: self.world_material.clone(),
+                        transform: render_model.transform.matrix,
+                        tint: render_model.tint,
+                        layer: render_model.layer,
+                        blend: render_model.blend,
+                        bounds: Some(bounds.clone()),
+                    });
+                }
+                continue;
+            }
+            let characters = &mut self.characters;
+            let character = render_model
+                .asset
+                .and_then(|asset| characters.get_mut(&asset));
+            if let (Some(character), Some(animator)) = (character, render_model.animator) {
+                character.queue(
+                    animator,
+                    render_model.transform.matrix,
+                    render_model.tint,
+                    self.camera.position(),
+                    &self.world_material,
+                    &mut self.render_queue,
+                );
+            } else if let Some((mesh, texture)) = render_model.model {
+                self.render_queue.push(DrawItem {
+                    mesh: mesh.clone(),
+                    texture: texture.clone(),
+                    material: self.world_material.clone(),
+                    transform: render_model.transform.matrix,
+                    tint: render_model.tint,
+                    layer: render_model.layer,
+                    blend: render_model.blend,
+                    bounds: render_model.bounds,
+                });
+            }
+        }
+        let colors = self.sky.colors();
+        let shadow_strength = SHADOW_STRENGTH * self.sky.sun_visibility();
+        self.engine.set_blend_mode(BlendMode::Opaque);
+        self.engine.set_depth_test(true);
+        let light_view_projection = match &self.shadow_map {
+            //shadows fade out as the sun sets so the pass is skipped at night
+            Some(shadow_map) if shadow_strength > 0.0 => {
+                let light_view_projection = shadow::light_view_projection(
+                    &view_projection,
+                    &self.sky.sun_direction(),
+                    shadow_map.size,
+                );
+                self.engine.bind_framebuffer(&shadow_map.framebuffer);
+                self.engine.clear_screen(Vector4::new(0.0, 0.0, 0.0, 0.0));
+                let casters = self.render_queue.build(&light_view_projection);
+                render_queue::draw_shadow_casters(
+                    &mut self.engine,
+                    &casters,
+                    light_view_projection,
+                    &self.shadow_material,
+                );
+                for character in self.characters.values() {
+                    character.draw(
+                        &mut self.engine,
+                        light_view_projection,
+                        &self.skinned_shadow_material,
+                    );
+                }
+                Some(light_view_projection)
+            }
+            _ => None,
+        };
+        match target {
+            Some(framebuffer) => self.engine.bind_framebuffer(framebuffer),
+            None => self
+                .engine
+                .bind_framebuffer(&self.game_world_framebuffer.framebuffer),
+        }
+        self.engine.clear_screen(colors.horizon.push(1.0));
+        self.engine.set_depth_test(false);
+        self.engine.bind_material(&self.sky_material);
+        self.engine.send_uniform(
+            "inverse_view_projection",
+            UniformValue::Mat4(
+                view_projection
+                    .try_inverse()
+                    .unwrap_or_else(Matrix4::identity),
+            ),
+        );
         self.engine
-            .bind_texture(&self.game_world_framebuffer.texture);
+            .send_uniform("zenith_color", UniformValue::Vec3(colors.zenith));
+        self.engine
+            .send_uniform("horizon_color", UniformValue::Vec3(colors.horizon));
+        self.engine
+            .send_uniform("sun_position", UniformValue::Vec3(self.sky.sun_position()));
+        self.engine.send_uniform(
+            "sun_color",
+            UniformValue::Vec3(Vector3::new(1.0, 0.95, 0.8) * self.sky.sun_visibility()),
+        );
         self.engine.draw_mesh(&self.game_world_framebuffer.mesh);
-
+        self.engine.set_depth_test(true);
+        //the terrain is lit the same way as everything else in the world
+        for material in [
+            &self.world_material,
+            &self.terrain_material,
+            &self.skinned_material,
+        ]
+        .iter()
+        {
+            self.engine.bind_material(material);
+            self.engine
+                .send_uniform("light_color", UniformValue::Vec3(colors.light));
+            self.engine.send_uniform(
+                "camera_position",
+                UniformValue::Vec3(self.camera.position()),
+            );
+            self.engine
+                .send_uniform("fog_color", UniformValue::Vec3(colors.horizon));
+            self.engine
+                .send_uniform("fog_density", UniformValue::Float(self.sky.fog_density()));
+            self.engine.send_uniform(
+                "fog_height_falloff",
+                UniformValue::Float(self.sky.fog_height_falloff),
+            );
+            match (&self.shadow_map, light_view_projection) {
+                (Some(shadow_map), Some(light_view_projection)) => {
+                    self.engine.bind_texture_unit(&shadow_map.texture, 1);
+                    self.engine
+                        .send_uniform("shadow_map", UniformValue::Texture(1));
+                    self.engine.send_uniform(
+                        "light_view_projection",
+                        UniformValue::Mat4(light_view_projection),
+                    );
+                    self.engine
+                        .send_uniform("shadow_strength", UniformValue::Float(shadow_strength));
+                }
+                _ => self
+                    .engine
+                    .send_uniform("shadow_strength", UniformValue::Float(0.0)),
+            }
+        }
+        self.engine
+            .bind_texture_unit(self.terrain_renderer.overlay(), 2);
+        self.engine
+            .send_uniform("overlay_map", UniformValue::Texture(2));
+        self.engine.send_uniform(
+            "overlay_bounds",
+            UniformValue::Vec4(self.terrain_overlay.bounds()),
+        );
+        let batches = self.render_queue.build(&view_projection);
+        self.render_queue.clear();
+        //skinned characters are opaque so they go before any transparent batches
+        for character in self.characters.values_mut() {
+            character.draw(&mut self.engine, view_projection, &self.skinned_material);
+            character.clear();
+        }
+        render_queue::draw_batches(&mut self.engine, &batches, view_projection);
+        self.engine.set_depth_test(false);
+        Ok(())
+    }
+    /// Used by systems outside of the game objects to draw debug shapes this frame
+    pub fn debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug
+    }
+    /// Moves the powder emitter of each object to where it is kicking up powder. Objects that
+    /// stop get no new puffs but the ones in the air are left to settle.
+    fn update_powder(&mut self) {
+        let particles = &mut self.particles;
+        let game_objects = &self.game_objects;
+        self.powder.retain(|object, emitter| {
+            let alive = game_objects.contains(*object);
+            if !alive {
+                particles.remove_emitter(*emitter);
+            }
+            alive
+        });
+        for (index, object) in self.game_objects.iter() {
+            let position = object.powder_position();
+            if let Some(position) = position {
+                self.powder.entry(index).or_insert_with(|| {
+                    //each object gets its own seed so skiiers side by side do not puff in step
+                    let seed = index.into_raw_parts().0 as u32 + 1;
+                    particles.add_emitter(Emitter::powder(position).with_seed(seed))
+                });
+            }
+            let emitter = self
+                .powder
+                .get(&index)
+                .and_then(|emitter| particles.emitter_mut(*emitter));
+            if let Some(emitter) = emitter {
+                match position {
+                    Some(position) => {
+                        emitter.position = position;
+                        emitter.rate = POWDER_RATE;
+                    }
+                    None => emitter.rate = 0.0,
+                }
+            }
+        }
+    }
+    /// Draws the debug shapes added this frame over the game world
+    fn draw_debug(&mut self) {
+        if !self.debug.is_enabled() {
+            return;
+        }
+        for (_k, object) in self.game_objects.iter() {
+            object.debug_draw(&mut self.debug);
+        }
+        let (right, up) = self.camera.screen_axes();
+        let lines = self.debug.take_lines(right, up);
+        self.engine
+            .bind_framebuffer(&self.game_world_framebuffer.framebuffer);
+        self.engine.set_depth_test(true);
+        self.engine.bind_material(&self.debug_material);
+        self.engine.send_view_matrix(self.camera.get_mat());
+        self.engine.draw_lines(&lines);
+        self.engine.set_depth_test(false);
+    }
+    /// Draw calls and state changes made while drawing the last frame
+    pub fn render_stats(&self) -> RenderStats {
+        self.render_stats
+    }
+    /// Gpu objects that are currently allocated
+    pub fn live_resources(&self) -> ResourceReport {
+        self.engine.live_resources()
+    }
+    /// Changes the shadow map resolution or turns shadows off
+    pub fn set_shadow_quality(&mut self, quality: ShadowQuality) -> Result<(), E::ErrorType> {
+        if self.shadow_map.as_ref().map(|map| map.size) == quality.map_size() {
+            return Ok(());
+        }
+        self.shadow_map = match quality.map_size() {
+            Some(size) => Some(ShadowMap::new(&mut self.engine, size)?),
+            None => None,
+        };
+        Ok(())
+    }
+    /// Sets the hour of the day from 0 to 24, which moves the sun and changes the sky
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        self.sky.set_time_of_day(hours)
+    }
+    /// Sets how many game hours pass per real second, 0 stops time
+    pub fn set_time_speed(&mut self, hours_per_second: f32) {
+        self.sky.set_time_speed(hours_per_second)
+    }
+    pub fn set_weather(&mut self, weather: Weather) {
+        self.sky.weather = weather;
+    }
+    /// Sets the wind blowing particles in units per second
+    pub fn set_wind(&mut self, wind: Vector3<f32>) {
+        self.particles.wind = wind;
+    }
+    /// Paints `layer` of the terrain overlay towards `strength` within `radius` of `center` on the
+    /// xz plane, a strength of 0 erases
+    pub fn paint_terrain(
+        &mut self,
+        layer: OverlayLayer,
+        center: Vector2<f32>,
+        radius: f32,
+        strength: f32,
+    ) {
+        self.terrain_overlay.paint(layer, center, radius, strength)
+    }
+    /// Paints `layer` of the terrain overlay along a line, used for ski tracks and run boundaries
+    pub fn paint_terrain_line(
+        &mut self,
+        layer: OverlayLayer,
+        from: Vector2<f32>,
+        to: Vector2<f32>,
+        width: f32,
+        strength: f32,
+    ) {
+        self.terrain_overlay
+            .paint_line(layer, from, to, width, strength)
+    }
+    /// Raises the terrain within `radius` of `center` on the xz plane, negative amounts dig. Only the
+    /// chunks touched are rebuilt.
+    pub fn raise_terrain(&mut self, center: Vector2<f32>, radius: f32, amount: f32) {
+        self.terrain.raise(center, radius, amount)
+    }
+    /// Turns a post processing effect on or off
+    pub fn set_post_effect(&mut self, effect: PostEffect, enabled: bool) {
+        self.post_processor.chain.set_enabled(effect, enabled)
+    }
+    /// Whether keyboard input is going to the gui
+    pub fn wants_keyboard(&self) -> bool {
+        self.gui.has_focus()
+    }
+    /// Saves where windows were moved to so they can be restored with `load_gui_layout`
+    pub fn save_gui_layout(&self) -> String {
+        self.gui.save_layout()
+    }
+    pub fn load_gui_layout(&mut self, layout: &str) {
+        self.gui.load_layout(layout)
+    }
+    /// Switches to a built in gui theme. Returns false if there is no theme called `name`
+    pub fn set_theme(&mut self, name: &str) -> bool {
+        match Theme::by_name(name) {
+            Some(theme) => {
+                //the old atlas is freed once the panels using it are rebuilt
+                self.shared_textures
+                    .remove(&self.gui.get_theme().atlas_name());
+                self.gui.set_theme(theme);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Css cursor the game would like shown
+    pub fn get_cursor(&self) -> CursorStyle {
+        self.gui.get_cursor()
+    }
+    /// Places a model asset on the terrain at `position` on the xz plane. A placeholder is shown
+    /// until the asset has loaded. The prop holds the reference to `asset` until it is removed.
+    pub fn add_prop(
+        &mut self,
+        name: &str,
+        asset: AssetId,
+        position: Vector2<f32>,
+    ) -> Result<Index, E::ErrorType>
+    where
+        RuntimeModel<E>: 'static,
+    {
+        let position = Vector3::new(position.x, self.terrain.height_at(position), position.y);
+        let index = self.insert_object(game::Prop::new(name, asset, position));
+        self.init_models()?;
+        Ok(index)
+    }
+    /// Adds an object to the world. Objects using an asset that has already loaded are told about
+    /// it straight away, the rest once it loads.
+    fn insert_object(&mut self, mut object: Box<dyn game::GameObject<RuntimeModel<E>>>) -> Index {
+        let asset = object.get_render_model().asset;
+        if let Some(data) = asset.and_then(|asset| self.assets.data(asset)) {
+            object.asset_loaded(data);
+        }
+        self.game_objects.insert(object)
+    }
+    /// Removes an object from the world and releases the asset it was drawn with. Returns false if
+    /// there is no such object.
+    pub fn remove_object(&mut self, index: Index) -> bool {
+        match self.game_objects.remove(index) {
+            Some(object) => {
+                if let Some(asset) = object.get_render_model().asset {
+                    self.assets.release(asset);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn assets(&mut self) -> &mut AssetManager {
+        &mut self.assets
+    }
+    /// Uploads assets that finished loading, hands them to the objects using them and frees the
+    /// gpu resources of unloaded ones
+    fn update_assets(&mut self) -> Result<(), E::ErrorType> {
+        self.assets.update();
+        for id in self.assets.take_loaded().into_iter() {
+            let data = match self.assets.data(id) {
+                Some(data) => data.clone(),
+                None => continue,
+            };
+            match &data {
+                AssetData::Models(models) => {
+                    let mut runtime_models = vec![];
+                    for model in models.iter() {
+                        let points: Vec<Vector3<f32>> = model
+                            .mesh
+                            .vertices
+                            .iter()
+                            .map(|(position, _)| *position)
+                            .collect();
+                        runtime_models.push((
+                            self.init_model(model)?,
+                            BoundingSphere::from_points(&points),
+                        ));
+                    }
+                    self.asset_models.insert(id, runtime_models);
+                }
+                AssetData::Character(character) => {
+                    let renderer = CharacterRenderer::new(&mut self.engine, character.clone())?;
+                    self.characters.insert(id, renderer);
+                }
+                AssetData::Texture(texture) => {
+                    if id == self.terrain_texture {
+                        self.terrain_renderer
+                            .set_texture(&mut self.engine, texture.clone())?;
+                    }
+                }
+            }
+            for (_k, object) in self.game_objects.iter_mut() {
+                if object.get_render_model().asset == Some(id) {
+                    object.asset_loaded(&data);
+                }
+            }
+        }
+        for (id, _key) in self.assets.take_unloaded().into_iter() {
+            //dropping the runtime models and renderers frees their gpu resources
+            self.asset_models.remove(&id);
+            self.characters.remove(&id);
+        }
         Ok(())
     }
     pub fn init_model(&mut self, model: &Model) -> Result<RuntimeModel<E>, E::ErrorType> {
         let mesh = self.engine.build_mesh(model.mesh.clone())?;
-        let texture = self.engine.build_texture(model.texture.clone())?;
+        let texture = match &model.shared_texture {
+            Some(name) => match self.shared_textures.get(name) {
+                Some(texture) => texture.clone(),
+                None => {
+                    let texture = self
+                        .engine
+                        .build_texture(model.texture.clone(), model.texture_options)?;
+                    self.shared_textures.insert(name.clone(), texture.clone());
+                    texture
+                }
+            },
+            None => self
+                .engine
+                .build_texture(model.texture.clone(), model.texture_options)?,
+        };
         Ok((mesh, texture))
     }
     pub fn draw_model(&mut self, model: &RuntimeModel<E>) {
-        todo!("draw model")
+        let (mesh, texture) = model;
+        self.engine.bind_texture(texture);
+        self.engine.draw_mesh(mesh);
     }
+    /// Builds the models of objects that do not have one yet. The textures of the new models are
+    /// packed into an atlas so that they are drawn without switching textures.
     pub fn init_models(&mut self) -> Result<(), E::ErrorType> {
-        for (_key, object) in self.game_objects.iter_mut() {
-            let model = object.get_model();
-            let mesh = self.engine.build_mesh(model.mesh.clone())?;
-            let texture = self.engine.build_texture(model.texture.clone())?;
-            object.submit_render_model((mesh, texture));
+        let mut names = vec![];
+        let mut models = vec![];
+        //objects waiting for a model along with the index of the model
+        let mut waiting = vec![];
+        for (key, object) in self.game_objects.iter_mut() {
+            if object.is_initilized() {
+                continue;
+            }
+            let name = object.get_model_name();
+            let shared_models = &self.shared_models;
+            if let Some(shared) = name.as_ref().and_then(|name| shared_models.get(name)) {
+                object.submit_render_model(shared.clone());
+                continue;
+            }
+            //objects sharing a model that is not built yet wait for the first of them
+            let index = match names.iter().position(|n| name.is_some() && *n == name) {
+                Some(index) => index,
+                None => {
+                    names.push(name);
+                    models.push(object.get_model());
+                    models.len() - 1
+                }
+            };
+            waiting.push((key, index));
+        }
+        if models.is_empty() {
+            return Ok(());
+        }
+        let models = atlas::pack_models(models, &format!("world_atlas_{}", self.atlas_count));
+        self.atlas_count += 1;
+        let mut runtime_models = vec![];
+        for (name, model) in names.into_iter().zip(models.iter()) {
+            let runtime_model = self.init_model(model)?;
+            if let Some(name) = name {
+                self.shared_models.insert(name, runtime_model.clone());
+            }
+            runtime_models.push(runtime_model);
+        }
+        for (key, index) in waiting.into_iter() {
+            if let Some(object) = self.game_objects.get_mut(key) {
+                object.submit_render_model(runtime_models[index].clone());
+            }
         }
         Ok(())
     }
 }
+/// Assets are fetched from the server in the browser and read from disk natively
+#[cfg(target_arch = "wasm32")]
+fn asset_source() -> Box<dyn assets::AssetSource> {
+    Box::new(assets::FetchSource::new("assets/"))
+}
+#[cfg(not(target_arch = "wasm32"))]
+fn asset_source() -> Box<dyn assets::AssetSource> {
+    Box::new(assets::FileSource::new("assets"))
+}
 pub fn start() -> Result<GraphicsContext<graphics_engine::WebGl>, JsValue> {
     let mut graphics = graphics_engine::WebGl::init()?;
-    let mut texture = graphics.build_texture(RGBATexture::constant_color(
-        Vector4::new(0, 0, 0, 0),
-        Vector2::new(800, 800),
-    ))?;
+    let world_material = graphics.build_material(&shaders::world())?;
+    let screen_material = graphics.build_material(&shaders::screen())?;
+    let shadow_material = graphics.build_material(&shaders::shadow_depth())?;
+    let sky_material = graphics.build_material(&shaders::sky())?;
+    let debug_material = graphics.build_material(&shaders::debug_lines())?;
+    let terrain_material = graphics.build_material(&shaders::terrain())?;
+    let skinned_material = graphics.build_material(&shaders::skinned())?;
+    let skinned_shadow_material = graphics.build_material(&shaders::skinned_shadow_depth())?;
+    let shadow_map = Some(ShadowMap::new(
+        &mut graphics,
+        ShadowQuality::High.map_size().unwrap(),
+    )?);
+    let dimensions = Vector2::new(800, 800);
+    //the world is drawn into floats so tone mapping can bring bright snow back into range
+    let mut texture = graphics.build_float_texture(dimensions)?;
     let framebuffer = graphics.build_framebuffer(&mut texture);
+    let post_processor = PostProcessor::new(&mut graphics, dimensions)?;
     let mesh = graphics
         .build_mesh(FramebufferSurface::<graphics_engine::WebGl>::get_model())
         .ok()
         .unwrap();
 
     let game_world_framebuffer = FramebufferSurface {
+        dimensions,
         texture,
         framebuffer,
         mesh,
     };
 
+    //flat bowl for the skiiers surrounded by hills that rise into mountains
+    let terrain = Terrain::from_fn(Vector2::new(4, 4), Vector2::new(-64.0, -64.0), |x, z| {
+        let rise = ((Vector2::new(x, z) - Vector2::new(5.0, 5.0)).norm() - 12.0).max(0.0);
+        rise * rise * 0.01 + (x * 0.15).sin() * (z * 0.11).cos() * rise * 0.08
+    })?;
+    //built in assets are decoded on the first frame along with anything else requested
+    let mut assets = AssetManager::new(Box::new(EmbeddedSource::new(asset_source())));
+    assets.add_manifest(Manifest::parse(assets::BUILT_IN_MANIFEST)?);
+    let terrain_texture = assets.request("snow");
     let mut game_objects = Arena::new();
-    game_objects.insert(game::Skiier::new());
-    game_objects.insert(Box::new(game::WorldGrid::new(Vector2::new(10, 10))));
+    for i in 0..20 {
+        //spread the skiiers over the bowl in a few jacket colors
+        let (x, z) = ((i % 5) as f32 * 2.0 + 0.5, (i / 5) as f32 * 2.0 + 0.5);
+        let position = Vector3::new(x, terrain.height_at(Vector2::new(x, z)), z);
+        let tint = match i % 3 {
+            0 => Vector4::new(1.0, 0.2, 0.2, 1.0),
+            1 => Vector4::new(0.2, 0.4, 1.0, 1.0),
+            _ => Vector4::new(1.0, 0.8, 0.1, 1.0),
+        };
+        game_objects.insert(game::Skiier::new(
+            position,
+            tint,
+            assets.request("skier"),
+            i as f32 * 1.7,
+        ));
+    }
+    let mut terrain_overlay = TerrainOverlay::new(&terrain, OVERLAY_RESOLUTION)?;
+    //a groomed run down to the bowl, marked on both sides, with moguls near the top
+    terrain_overlay.paint_line(
+        OverlayLayer::Groomed,
+        Vector2::new(5.0, -50.0),
+        Vector2::new(5.0, 12.0),
+        14.0,
+        1.0,
+    );
+    for x in [-2.5, 12.5].iter() {
+        terrain_overlay.paint_line(
+            OverlayLayer::Boundary,
+            Vector2::new(*x, -50.0),
+            Vector2::new(*x, 12.0),
+            0.3,
+            1.0,
+        );
+    }
+    terrain_overlay.paint(OverlayLayer::Moguls, Vector2::new(5.0, -35.0), 6.0, 1.0);
+    for i in 0..12 {
+        let z = -50.0 + i as f32 * 5.0;
+        let swing = |z: f32| 5.0 + (z * 0.3).sin() * 3.0;
+        terrain_overlay.paint_line(
+            OverlayLayer::Tracks,
+            Vector2::new(swing(z), z),
+            Vector2::new(swing(z + 5.0), z + 5.0),
+            0.4,
+            0.8,
+        );
+    }
+    let terrain_renderer = TerrainRenderer::new(&mut graphics, &terrain_overlay)?;
+    let mut particles = ParticleSystem::new();
+    let snowfall =
+        particles.add_emitter(Emitter::snowfall(Vector3::new(0.0, 0.0, 0.0), 30.0).with_rate(0.0));
+    let cannon_position = Vector3::new(-8.0, 0.0, 16.0);
+    particles.add_emitter(Emitter::snow_cannon(
+        cannon_position + Vector3::new(0.0, terrain.height_at(cannon_position.xz()) + 1.5, 0.0),
+        Vector3::new(0.6, 0.7, -0.4),
+    ));
+    let particle_renderer = ParticleRenderer::new(&mut graphics)?;
 
     let mut g = GraphicsContext {
         engine: graphics,
+        world_material,
+        render_queue: RenderQueue::new(),
+        shared_models: HashMap::new(),
+        shared_textures: HashMap::new(),
+        atlas_count: 0,
+        post_processor,
+        screen_material,
+        shadow_material,
+        skinned_material,
+        skinned_shadow_material,
+        characters: HashMap::new(),
+        assets,
+        asset_models: HashMap::new(),
+        shadow_map,
+        sky_material,
+        debug: DebugDraw::new(),
+        debug_material,
+        terrain,
+        terrain_overlay,
+        terrain_renderer,
+        terrain_texture,
+        terrain_material,
+        particles,
+        particle_renderer,
+        snowfall,
+        powder: HashMap::new(),
+        sky: Sky::new(10.0, Weather::Clear),
         camera: Camera::new(Vector3::new(0.0, 0.0, 0.0), 40.0, 0.0, 0.0),
         game_objects,
         game_world_framebuffer,
         gui: GuiState::new(),
+        render_stats: RenderStats::default(),
     };
     g.init_models()?;
     Ok(g)
 }
 fn to_event_state(map: &JsMap) -> EventState {
-    let x = if let Some(x) = map.get(&JsValue::from("position_x")).as_f64() {
-        x
-    } else {
-        0.0
-    } as f32;
-    let y = if let Some(y) = map.get(&JsValue::from("position_y")).as_f64() {
-        y
-    } else {
-        0.0
-    } as f32;
+    let x = map
+        .get(&JsValue::from("position_x"))
+        .as_f64()
+        .unwrap_or(0.0) as f32;
+    let y = map
+        .get(&JsValue::from("position_y"))
+        .as_f64()
+        .unwrap_or(0.0) as f32;
+    let delta_time_ms = map
+        .get(&JsValue::from("delta_time_ms"))
+        .as_f64()
+        .unwrap_or(0.0) as f32;
     EventState {
         position: Vector2::new(x, y),
+        delta_time_ms,
     }
 }
+/// Prop placed in the world by `WebGame`, used to remove it again
+#[wasm_bindgen]
+pub struct PropId(Index);
 #[wasm_bindgen]
 pub struct WebGame {
     engine: GraphicsContext<graphics_engine::WebGl>,
@@ -303,6 +1138,207 @@ impl WebGame {
             .ok()
             .unwrap();
     }
+    /// Number of draw calls made in the last frame
+    #[wasm_bindgen]
+    pub fn draw_calls(&self) -> u32 {
+        self.engine.render_stats().draw_calls
+    }
+    /// Number of program, texture, vertex array and framebuffer binds made in the last frame
+    #[wasm_bindgen]
+    pub fn state_changes(&self) -> u32 {
+        self.engine.render_stats().state_changes
+    }
+    /// Describes the gpu objects that are currently allocated, used to find leaks
+    #[wasm_bindgen]
+    pub fn gpu_resources(&self) -> String {
+        let live = self.engine.live_resources();
+        format!(
+            "meshes: {} textures: {} framebuffers: {} materials: {}",
+            live.meshes, live.textures, live.framebuffers, live.materials
+        )
+    }
+    /// Sets the shadow quality to "off", "low" or "high". Returns false if there is no such setting
+    #[wasm_bindgen]
+    pub fn set_shadow_quality(&mut self, name: &str) -> Result<bool, JsValue> {
+        match ShadowQuality::by_name(name) {
+            Some(quality) => {
+                self.engine.set_shadow_quality(quality)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    /// Sets the hour of the day from 0 to 24
+    #[wasm_bindgen]
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        self.engine.set_time_of_day(hours)
+    }
+    /// Sets how many game hours pass per real second, 0 stops time
+    #[wasm_bindgen]
+    pub fn set_time_speed(&mut self, hours_per_second: f32) {
+        self.engine.set_time_speed(hours_per_second)
+    }
+    /// Sets the weather to "clear", "overcast" or "snowing". Returns false if there is no such weather
+    #[wasm_bindgen]
+    pub fn set_weather(&mut self, name: &str) -> bool {
+        match Weather::by_name(name) {
+            Some(weather) => {
+                self.engine.set_weather(weather);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Sets the wind blowing snow along the ground in units per second
+    #[wasm_bindgen]
+    pub fn set_wind(&mut self, x: f32, z: f32) {
+        self.engine.set_wind(Vector3::new(x, 0.0, z))
+    }
+    /// Paints a layer of the terrain overlay around `x`, `z`. Layers are "groomed", "tracks",
+    /// "moguls" and "boundary". Returns false if there is no such layer
+    #[wasm_bindgen]
+    pub fn paint_terrain(
+        &mut self,
+        layer: &str,
+        x: f32,
+        z: f32,
+        radius: f32,
+        strength: f32,
+    ) -> bool {
+        match OverlayLayer::by_name(layer) {
+            Some(layer) => {
+                self.engine
+                    .paint_terrain(layer, Vector2::new(x, z), radius, strength);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Raises the terrain around `x`, `z` by up to `amount` within `radius`, negative amounts dig
+    #[wasm_bindgen]
+    pub fn raise_terrain(&mut self, x: f32, z: f32, radius: f32, amount: f32) {
+        self.engine
+            .raise_terrain(Vector2::new(x, z), radius, amount)
+    }
+    /// Turns a post processing effect on or off. Effects are "bloom", "tone_mapping",
+    /// "color_grading", "fxaa" and "vignette". Returns false if there is no such effect
+    #[wasm_bindgen]
+    pub fn set_post_effect(&mut self, name: &str, enabled: bool) -> bool {
+        match PostEffect::by_name(name) {
+            Some(effect) => {
+                self.engine.set_post_effect(effect, enabled);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Renders the game world at `scale` times the canvas size, up to 4 or less if the gpu can not
+    /// draw that large, and returns it as a png
+    #[wasm_bindgen]
+    pub fn screenshot(&mut self, scale: u32) -> Result<Vec<u8>, JsValue> {
+        Ok(self.engine.screenshot(scale)?.to_png()?)
+    }
+    /// If true the page should not handle key presses itself
+    #[wasm_bindgen]
+    pub fn wants_keyboard(&self) -> bool {
+        self.engine.wants_keyboard()
+    }
+    /// Gets window positions to store between sessions
+    #[wasm_bindgen]
+    pub fn save_gui_layout(&self) -> String {
+        self.engine.save_gui_layout()
+    }
+    /// Restores window positions from a string returned by `save_gui_layout`
+    #[wasm_bindgen]
+    pub fn load_gui_layout(&mut self, layout: &str) {
+        self.engine.load_gui_layout(layout)
+    }
+    /// Switches the gui theme. Built in themes are "dark", "light" and "high_contrast"
+    #[wasm_bindgen]
+    pub fn set_theme(&mut self, name: &str) -> bool {
+        self.engine.set_theme(name)
+    }
+    /// Gets the css cursor that should be shown over the canvas
+    #[wasm_bindgen]
+    pub fn get_cursor(&self) -> String {
+        self.engine.get_cursor().to_css().to_string()
+    }
+    /// Places the model asset with manifest id or path `asset` on the terrain at `x`, `z`. The
+    /// asset is fetched in the background if it is not loaded yet and a placeholder is shown
+    /// meanwhile.
+    #[wasm_bindgen]
+    pub fn add_prop(&mut self, asset: &str, x: f32, z: f32) -> Result<PropId, JsValue> {
+        let id = self.engine.assets().request(asset);
+        self.engine
+            .add_prop(asset, id, Vector2::new(x, z))
+            .map(PropId)
+    }
+    /// Removes a prop, unloading its asset if nothing else uses it
+    #[wasm_bindgen]
+    pub fn remove_prop(&mut self, prop: &PropId) -> bool {
+        self.engine.remove_object(prop.0)
+    }
+    /// Places a glTF 2.0 model, binary or with embedded buffers, on the terrain at `x`, `z`
+    #[wasm_bindgen]
+    pub fn add_gltf_prop(
+        &mut self,
+        name: &str,
+        bytes: &[u8],
+        x: f32,
+        z: f32,
+    ) -> Result<PropId, JsValue> {
+        let id = self
+            .engine
+            .assets()
+            .insert(vec![(format!("{}.glb", name), bytes.to_vec())])?;
+        self.engine
+            .add_prop(name, id, Vector2::new(x, z))
+            .map(PropId)
+    }
+    /// Places a Wavefront OBJ model on the terrain at `x`, `z`. `files` maps the file names of its
+    /// material libraries and textures to their bytes
+    #[wasm_bindgen]
+    pub fn add_obj_prop(
+        &mut self,
+        name: &str,
+        obj: &[u8],
+        files: JsMap,
+        x: f32,
+        z: f32,
+    ) -> Result<PropId, JsValue> {
+        let mut asset_files = vec![(format!("{}.obj", name), obj.to_vec())];
+        files.for_each(&mut |bytes, name| {
+            if let Some(name) = name.as_string() {
+                asset_files.push((name, js_sys::Uint8Array::new(&bytes).to_vec()));
+            }
+        });
+        let id = self.engine.assets().insert(asset_files)?;
+        self.engine
+            .add_prop(name, id, Vector2::new(x, z))
+            .map(PropId)
+    }
+    /// Adds the bundles listed in an asset manifest
+    #[wasm_bindgen]
+    pub fn load_manifest(&mut self, text: &str) -> Result<(), JsValue> {
+        let manifest = Manifest::parse(text)?;
+        self.engine.assets().add_manifest(manifest);
+        Ok(())
+    }
+    /// Starts loading every asset of a bundle from the manifest
+    #[wasm_bindgen]
+    pub fn load_bundle(&mut self, name: &str) -> Result<(), JsValue> {
+        Ok(self.engine.assets().load_bundle(name)?)
+    }
+    /// Releases the assets of a bundle, unloading those not used by anything else
+    #[wasm_bindgen]
+    pub fn unload_bundle(&mut self, name: &str) -> bool {
+        self.engine.assets().unload_bundle(name)
+    }
+    /// Number of assets still loading, used to show a loading indicator
+    #[wasm_bindgen]
+    pub fn loading_assets(&mut self) -> usize {
+        self.engine.assets().loading_count()
+    }
 }
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -0,0 +1,540 @@
+use super::animation::{
+    AnimationClip, Channel, Character, Interpolation as ChannelInterpolation, Joint,
+    JointTransform, Keyframes, Skeleton,
+};
+use super::graphics_engine::{SkinnedMesh, SkinnedVertex};
+use super::{Mesh, Model, RGBATexture, TextureOptions, TextureWrap};
+use gltf::animation::util::ReadOutputs;
+use gltf::animation::Interpolation;
+use gltf::mesh::Mode;
+use gltf::Gltf;
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector2, Vector3, Vector4};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::path::Path;
+/// Loads the default scene of a binary or embedded glTF 2.0 file as static models, one per
+/// material. Node transforms are baked into the vertices so the parts of a model stay where they
+/// were placed. Primitives made of points or lines are skipped.
+pub fn load_gltf(bytes: &[u8]) -> Result<Vec<Model>, String> {
+    let gltf = Gltf::from_slice(bytes).map_err(|e| format!("invalid gltf: {}", e))?;
+    let buffers = buffer_data(&gltf)?;
+    let get_buffer = |buffer: gltf::Buffer| buffers.get(buffer.index()).map(|data| data.as_slice());
+    let scene = gltf
+        .default_scene()
+        .or_else(|| gltf.scenes().next())
+        .ok_or_else(|| "gltf has no scene".to_string())?;
+    //vertices of each material by material index, None is the default material
+    let mut materials: BTreeMap<Option<usize>, (gltf::Material, Mesh)> = BTreeMap::new();
+    let mut stack: Vec<(gltf::Node, Matrix4<f32>)> = scene
+        .nodes()
+        .map(|node| (node, Matrix4::identity()))
+        .collect();
+    while let Some((node, parent)) = stack.pop() {
+        let transform = parent * Matrix4::from(node.transform().matrix());
+        stack.extend(node.children().map(|child| (child, transform)));
+        let mesh = match node.mesh() {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(get_buffer);
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or_else(|| "primitive has no positions".to_string())?
+                .collect();
+            let uvs: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+                Some(uvs) => uvs.into_f32().collect(),
+                None => vec![],
+            };
+            let indices = triangle_indices(
+                primitive.mode(),
+                reader.read_indices().map(|i| i.into_u32().collect()),
+                positions.len(),
+            );
+            let material = primitive.material();
+            let (_, mesh) = materials
+                .entry(material.index())
+                .or_insert_with(|| (material, Mesh { vertices: vec![] }));
+            for index in indices.iter().map(|i| *i as usize) {
+                let position = positions
+                    .get(index)
+                    .ok_or_else(|| format!("vertex index {} out of range", index))?;
+                let uv = uvs.get(index).copied().unwrap_or([0.0, 0.0]);
+                mesh.vertices.push((
+                    transform
+                        .transform_point(&Vector3::from(*position).into())
+                        .coords,
+                    //glTF textures start at the top row and ours at the bottom
+                    Vector2::new(uv[0], 1.0 - uv[1]),
+                ));
+            }
+        }
+    }
+    materials
+        .into_values()
+        .filter(|(_, mesh)| !mesh.vertices.is_empty())
+        .map(|(material, mesh)| {
+            Ok(Model {
+                mesh,
+                texture: material_texture(&material, &buffers)?,
+                texture_options: material_texture_options(&material),
+                shared_texture: None,
+            })
+        })
+        .collect()
+}
+/// Loads a Wavefront OBJ file as static models, one per material. Faces with more than three sides
+/// are triangulated. Material libraries and the textures they use are looked up by file name in
+/// `files`, objects without a material are white.
+pub fn load_obj(obj: &[u8], files: &HashMap<String, Vec<u8>>) -> Result<Vec<Model>, String> {
+    let file = |path: &Path| {
+        path.file_name()
+            .and_then(|name| files.get(name.to_string_lossy().as_ref()))
+    };
+    let options = tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ignore_points: true,
+        ignore_lines: true,
+    };
+    let (objects, materials) =
+        tobj::load_obj_buf(&mut Cursor::new(obj), &options, |path| match file(path) {
+            Some(bytes) => tobj::load_mtl_buf(&mut Cursor::new(bytes)),
+            None => Err(tobj::LoadError::OpenFileFailed),
+        })
+        .map_err(|e| format!("invalid obj: {}", e))?;
+    let materials = materials.map_err(|e| format!("could not load the material library: {}", e))?;
+    let mut meshes: BTreeMap<Option<usize>, Mesh> = BTreeMap::new();
+    for object in objects.iter() {
+        let mesh = &object.mesh;
+        let material_mesh = meshes
+            .entry(mesh.material_id)
+            .or_insert_with(|| Mesh { vertices: vec![] });
+        for index in mesh.indices.iter().map(|i| *i as usize) {
+            let position = mesh
+                .positions
+                .get(index * 3..index * 3 + 3)
+                .ok_or_else(|| format!("vertex index {} out of range", index))?;
+            //obj texture coordinates start at the bottom like ours
+            let uv = mesh
+                .texcoords
+                .get(index * 2..index * 2 + 2)
+                .unwrap_or(&[0.0, 0.0]);
+            material_mesh.vertices.push((
+                Vector3::new(position[0], position[1], position[2]),
+                Vector2::new(uv[0], uv[1]),
+            ));
+        }
+    }
+    meshes
+        .into_iter()
+        .filter(|(_, mesh)| !mesh.vertices.is_empty())
+        .map(|(material, mesh)| {
+            let texture = match material.and_then(|index| materials.get(index)) {
+                Some(material) if !material.diffuse_texture.is_empty() => {
+                    let name = texture_file_name(&material.diffuse_texture);
+                    let bytes = file(Path::new(name))
+                        .ok_or_else(|| format!("texture \"{}\" not found", name))?;
+                    let extension = Path::new(name)
+                        .extension()
+                        .map(|extension| extension.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    RGBATexture::from_extension(bytes, &extension)?
+                }
+                Some(material) => {
+                    let color = Vector4::new(
+                        material.diffuse[0],
+                        material.diffuse[1],
+                        material.diffuse[2],
+                        material.dissolve,
+                    );
+                    RGBATexture::constant_color(
+                        color.map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8),
+                        Vector2::new(1, 1),
+                    )
+                }
+                None => RGBATexture::constant_color(
+                    Vector4::new(255, 255, 255, 255),
+                    Vector2::new(1, 1),
+                ),
+            };
+            Ok(Model {
+                mesh,
+                texture,
+                texture_options: TextureOptions::tiled(),
+                shared_texture: None,
+            })
+        })
+        .collect()
+}
+/// File name of a material texture such as "-s 2 2 old wood.png". Options come before the name,
+/// which is the rest of the line so it may contain spaces.
+fn texture_file_name(map: &str) -> &str {
+    //splits off the first word and the text after it
+    let word = |text: &str| -> (usize, usize) {
+        let end = text.find(char::is_whitespace).unwrap_or(text.len());
+        let next = text.len() - text[end..].trim_start().len();
+        (end, next)
+    };
+    let mut rest = map.trim();
+    while rest.starts_with('-') {
+        let (end, next) = word(rest);
+        //-o, -s and -t take one to three numbers, the others a fixed number of arguments
+        let (required, optional) = match &rest[..end] {
+            "-o" | "-s" | "-t" => (1, 2),
+            "-mm" => (2, 0),
+            "-blendu" | "-blendv" | "-bm" | "-boost" | "-cc" | "-clamp" | "-imfchan"
+            | "-texres" | "-type" => (1, 0),
+            _ => break,
+        };
+        rest = &rest[next..];
+        for i in 0..required + optional {
+            let (end, next) = word(rest);
+            if i >= required && rest[..end].parse::<f32>().is_err() {
+                break;
+            }
+            rest = &rest[next..];
+        }
+    }
+    rest
+}
+/// Indices of the triangles of a primitive, made from strips and fans if needed. Points and lines
+/// have no triangles.
+pub fn triangle_indices(mode: Mode, indices: Option<Vec<u32>>, vertex_count: usize) -> Vec<u32> {
+    let indices = indices.unwrap_or_else(|| (0..vertex_count as u32).collect());
+    match mode {
+        Mode::Triangles => indices[..indices.len() - indices.len() % 3].to_vec(),
+        Mode::TriangleStrip => (0..indices.len().saturating_sub(2))
+            .flat_map(|i| {
+                //every other triangle of a strip is flipped to keep the winding the same
+                if i % 2 == 0 {
+                    [indices[i], indices[i + 1], indices[i + 2]]
+                } else {
+                    [indices[i + 1], indices[i], indices[i + 2]]
+                }
+            })
+            .collect(),
+        Mode::TriangleFan => (1..indices.len().saturating_sub(1))
+            .flat_map(|i| [indices[0], indices[i], indices[i + 1]])
+            .collect(),
+        _ => vec![],
+    }
+}
+/// Whether a glTF file has a skin, in which case it is loaded as a character
+pub fn is_character(bytes: &[u8]) -> bool {
+    Gltf::from_slice(bytes)
+        .map(|gltf| gltf.skins().next().is_some())
+        .unwrap_or(false)
+}
+/// Loads a rigged character from a binary or embedded glTF 2.0 file. The first skin in the file
+/// is the skeleton, every primitive of the mesh it is attached to becomes one skinned mesh and
+/// every animation becomes a looping clip.
+pub fn load_character(bytes: &[u8]) -> Result<Character, String> {
+    let gltf = Gltf::from_slice(bytes).map_err(|e| format!("invalid gltf: {}", e))?;
+    let buffers = buffer_data(&gltf)?;
+    let get_buffer = |buffer: gltf::Buffer| buffers.get(buffer.index()).map(|data| data.as_slice());
+    let skin = gltf
+        .skins()
+        .next()
+        .ok_or_else(|| "gltf has no skin".to_string())?;
+    let skin_nodes: Vec<usize> = skin.joints().map(|node| node.index()).collect();
+    //parent node of every node that has one
+    let mut node_parents = HashMap::new();
+    for node in gltf.nodes() {
+        for child in node.children() {
+            node_parents.insert(child.index(), node.index());
+        }
+    }
+    //a joint's parent is its closest ancestor that is also a joint, other ancestors only move the
+    //whole skeleton
+    let joint_parent = |node: usize| {
+        let mut ancestor = node_parents.get(&node);
+        while let Some(a) = ancestor {
+            if let Some(joint) = skin_nodes.iter().position(|n| n == a) {
+                return Some(joint);
+            }
+            ancestor = node_parents.get(a);
+        }
+        None
+    };
+    let parents: Vec<Option<usize>> = skin_nodes.iter().map(|n| joint_parent(*n)).collect();
+    //skeletons need parents before children, which glTF does not promise
+    let mut order: Vec<usize> = vec![];
+    while order.len() < skin_nodes.len() {
+        let before = order.len();
+        for (joint, parent) in parents.iter().enumerate() {
+            let ready = match parent {
+                Some(parent) => order.contains(parent),
+                None => true,
+            };
+            if ready && !order.contains(&joint) {
+                order.push(joint);
+            }
+        }
+        if order.len() == before {
+            return Err("skin joints form a cycle".to_string());
+        }
+    }
+    let mut new_index = vec![0; skin_nodes.len()];
+    for (index, joint) in order.iter().enumerate() {
+        new_index[*joint] = index;
+    }
+    let inverse_binds: Vec<Matrix4<f32>> =
+        match skin.reader(get_buffer).read_inverse_bind_matrices() {
+            Some(matrices) => matrices.map(Matrix4::from).collect(),
+            None => vec![Matrix4::identity(); skin_nodes.len()],
+        };
+    let nodes: Vec<gltf::Node> = gltf.nodes().collect();
+    let joints = order
+        .iter()
+        .map(|joint| {
+            let node = &nodes[skin_nodes[*joint]];
+            Joint {
+                name: node
+                    .name()
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("joint_{}", joint)),
+                parent: parents[*joint].map(|parent| new_index[parent]),
+                rest: joint_transform(node),
+                inverse_bind: inverse_binds
+                    .get(*joint)
+                    .copied()
+                    .unwrap_or_else(Matrix4::identity),
+            }
+        })
+        .collect();
+    //transform of the nodes above the root joint
+    let mut root_transform = Matrix4::identity();
+    if let Some(root) = order.first() {
+        let mut ancestor = node_parents.get(&skin_nodes[*root]);
+        while let Some(a) = ancestor {
+            root_transform = joint_transform(&nodes[*a]).matrix() * root_transform;
+            ancestor = node_parents.get(a);
+        }
+    }
+    let skeleton = Skeleton::new(joints, root_transform)?;
+    let mesh = gltf
+        .nodes()
+        .find(|node| node.skin().map(|s| s.index()) == Some(skin.index()))
+        .and_then(|node| node.mesh())
+        .ok_or_else(|| "no mesh uses the skin".to_string())?;
+    let mut vertices = vec![];
+    let mut texture = None;
+    for primitive in mesh.primitives() {
+        let reader = primitive.reader(get_buffer);
+        let positions: Vec<[f32; 3]> = reader
+            .read_positions()
+            .ok_or_else(|| "primitive has no positions".to_string())?
+            .collect();
+        let uvs: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+            Some(uvs) => uvs.into_f32().collect(),
+            None => vec![[0.0, 0.0]; positions.len()],
+        };
+        let vertex_joints: Vec<[u16; 4]> = reader
+            .read_joints(0)
+            .ok_or_else(|| "primitive has no joints".to_string())?
+            .into_u16()
+            .collect();
+        let weights: Vec<[f32; 4]> = reader
+            .read_weights(0)
+            .ok_or_else(|| "primitive has no weights".to_string())?
+            .into_f32()
+            .collect();
+        let indices = triangle_indices(
+            primitive.mode(),
+            reader.read_indices().map(|i| i.into_u32().collect()),
+            positions.len(),
+        );
+        for index in indices.iter().map(|i| *i as usize) {
+            if index >= positions.len() || index >= vertex_joints.len() || index >= weights.len() {
+                return Err(format!("vertex index {} out of range", index));
+            }
+            let mut joints = [0; 4];
+            for (joint, skin_joint) in joints.iter_mut().zip(vertex_joints[index].iter()) {
+                *joint = *new_index
+                    .get(*skin_joint as usize)
+                    .ok_or_else(|| format!("vertex uses missing joint {}", skin_joint))?
+                    as u16;
+            }
+            let total: f32 = weights[index].iter().sum();
+            let uv = uvs.get(index).copied().unwrap_or([0.0, 0.0]);
+            vertices.push(SkinnedVertex {
+                position: Vector3::from(positions[index]),
+                //glTF textures start at the top row and ours at the bottom
+                uv: Vector2::new(uv[0], 1.0 - uv[1]),
+                joints,
+                weights: if total > 0.0 {
+                    weights[index].map(|w| w / total)
+                } else {
+                    [1.0, 0.0, 0.0, 0.0]
+                },
+            });
+        }
+        if texture.is_none() {
+            texture = Some(material_texture(&primitive.material(), &buffers)?);
+        }
+    }
+    let mut clips = vec![];
+    for animation in gltf.animations() {
+        let mut channels = vec![];
+        for channel in animation.channels() {
+            let joint = match skin_nodes
+                .iter()
+                .position(|n| *n == channel.target().node().index())
+            {
+                Some(joint) => new_index[joint],
+                None => continue,
+            };
+            let reader = channel.reader(get_buffer);
+            let times: Vec<f32> = match reader.read_inputs() {
+                Some(inputs) => inputs.collect(),
+                None => continue,
+            };
+            //cubic spline outputs hold an in tangent, the value and an out tangent per keyframe.
+            //only the values are kept and played linearly, the tangents are discarded.
+            let cubic = channel.sampler().interpolation() == Interpolation::CubicSpline;
+            let interpolation = match channel.sampler().interpolation() {
+                Interpolation::Step => ChannelInterpolation::Step,
+                Interpolation::Linear | Interpolation::CubicSpline => ChannelInterpolation::Linear,
+            };
+            let values = |count: usize| {
+                (0..times.len())
+                    .map(move |i| if cubic { i * 3 + 1 } else { i })
+                    .filter(move |i| *i < count)
+            };
+            let keyframes = match reader.read_outputs() {
+                Some(ReadOutputs::Translations(outputs)) => {
+                    let outputs: Vec<[f32; 3]> = outputs.collect();
+                    Keyframes::Translation(
+                        values(outputs.len())
+                            .map(|i| Vector3::from(outputs[i]))
+                            .collect(),
+                    )
+                }
+                Some(ReadOutputs::Rotations(outputs)) => {
+                    let outputs: Vec<[f32; 4]> = outputs.into_f32().collect();
+                    Keyframes::Rotation(
+                        values(outputs.len())
+                            .map(|i| rotation(outputs[i]))
+                            .collect(),
+                    )
+                }
+                Some(ReadOutputs::Scales(outputs)) => {
+                    let outputs: Vec<[f32; 3]> = outputs.collect();
+                    Keyframes::Scale(
+                        values(outputs.len())
+                            .map(|i| Vector3::from(outputs[i]))
+                            .collect(),
+                    )
+                }
+                _ => continue,
+            };
+            let keyframe_count = match &keyframes {
+                Keyframes::Translation(values) | Keyframes::Scale(values) => values.len(),
+                Keyframes::Rotation(values) => values.len(),
+            };
+            if keyframe_count != times.len() {
+                return Err(format!(
+                    "animation \"{}\" has {} times but {} values",
+                    animation.name().unwrap_or(""),
+                    times.len(),
+                    keyframe_count
+                ));
+            }
+            channels.push(Channel {
+                joint,
+                times,
+                keyframes,
+                interpolation,
+            });
+        }
+        let name = animation
+            .name()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("animation_{}", animation.index()));
+        clips.push(AnimationClip::new(&name, true, channels));
+    }
+    Ok(Character {
+        mesh: SkinnedMesh { vertices },
+        texture: texture.unwrap_or_else(|| {
+            RGBATexture::constant_color(Vector4::new(255, 255, 255, 255), Vector2::new(1, 1))
+        }),
+        skeleton,
+        clips,
+    })
+}
+/// Contents of every buffer, by buffer index. Buffers must be in the binary chunk of a .glb file or
+/// embedded as base64 data uris.
+fn buffer_data(gltf: &Gltf) -> Result<Vec<Vec<u8>>, String> {
+    gltf.buffers()
+        .map(|buffer| match buffer.source() {
+            gltf::buffer::Source::Bin => gltf
+                .blob
+                .clone()
+                .ok_or_else(|| "gltf has no binary chunk".to_string()),
+            gltf::buffer::Source::Uri(uri) => data_uri(uri),
+        })
+        .collect()
+}
+/// Decodes a base64 `data:` uri. Files outside of the gltf can not be read.
+fn data_uri(uri: &str) -> Result<Vec<u8>, String> {
+    match uri.find(";base64,") {
+        Some(start) if uri.starts_with("data:") => {
+            base64::decode(&uri[start + 8..]).map_err(|e| format!("invalid base64 data: {}", e))
+        }
+        _ => Err(format!("external file \"{}\" is not supported", uri)),
+    }
+}
+/// Base color texture of a material, or a single pixel of its base color if it has none
+fn material_texture(material: &gltf::Material, buffers: &[Vec<u8>]) -> Result<RGBATexture, String> {
+    let pbr = material.pbr_metallic_roughness();
+    let info = match pbr.base_color_texture() {
+        Some(info) => info,
+        None => {
+            let color =
+                Vector4::from(pbr.base_color_factor()).map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+            return Ok(RGBATexture::constant_color(color, Vector2::new(1, 1)));
+        }
+    };
+    let bytes = match info.texture().source().source() {
+        gltf::image::Source::View { view, .. } => {
+            let buffer = buffers
+                .get(view.buffer().index())
+                .ok_or_else(|| "image is in a missing buffer".to_string())?;
+            buffer
+                .get(view.offset()..view.offset() + view.length())
+                .ok_or_else(|| "image is outside of its buffer".to_string())?
+                .to_vec()
+        }
+        gltf::image::Source::Uri { uri, .. } => data_uri(uri)?,
+    };
+    RGBATexture::from_bytes(&bytes)
+}
+/// Sampling of a material's base color texture. glTF textures repeat unless their sampler says
+/// otherwise.
+fn material_texture_options(material: &gltf::Material) -> TextureOptions {
+    let wrap = |mode| match mode {
+        gltf::texture::WrappingMode::ClampToEdge => TextureWrap::ClampToEdge,
+        gltf::texture::WrappingMode::MirroredRepeat => TextureWrap::MirroredRepeat,
+        gltf::texture::WrappingMode::Repeat => TextureWrap::Repeat,
+    };
+    match material.pbr_metallic_roughness().base_color_texture() {
+        Some(info) => {
+            let sampler = info.texture().sampler();
+            TextureOptions::tiled().with_wrap(wrap(sampler.wrap_s()), wrap(sampler.wrap_t()))
+        }
+        None => TextureOptions::default(),
+    }
+}
+fn joint_transform(node: &gltf::Node) -> JointTransform {
+    let (translation, rotation_xyzw, scale) = node.transform().decomposed();
+    JointTransform {
+        translation: Vector3::from(translation),
+        rotation: rotation(rotation_xyzw),
+        scale: Vector3::from(scale),
+    }
+}
+/// glTF stores quaternions as x, y, z, w
+fn rotation(xyzw: [f32; 4]) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_quaternion(Quaternion::new(xyzw[3], xyzw[0], xyzw[1], xyzw[2]))
+}
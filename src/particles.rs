@@ -0,0 +1,382 @@
+use super::graphics_engine::{GraphicsEngine, Mesh, TextureOptions};
+use super::render_queue::{BoundingSphere, DrawItem, RenderQueue};
+use super::{BlendMode, RGBATexture};
+use generational_arena::{Arena, Index};
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+/// Length of one simulation step in seconds. Particles always advance in whole steps so the
+/// result does not depend on the frame rate.
+pub const STEP: f32 = 1.0 / 60.0;
+/// Steps simulated at most per update, so a long pause such as a hidden tab does not stall the game
+const MAX_STEPS_PER_UPDATE: u32 = 30;
+/// Small random number generator so an emitter with the same settings and seed always makes the
+/// same particles
+#[derive(Clone, Debug)]
+struct Random {
+    state: u32,
+}
+impl Random {
+    fn new(seed: u32) -> Self {
+        //xorshift never leaves a state of zero
+        Self { state: seed.max(1) }
+    }
+    fn next(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+    /// Uniform number from -1 to 1
+    fn signed(&mut self) -> f32 {
+        self.next() as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+    /// Uniform point within `extent` of the origin along each axis
+    fn vector(&mut self, extent: Vector3<f32>) -> Vector3<f32> {
+        Vector3::new(
+            self.signed() * extent.x,
+            self.signed() * extent.y,
+            self.signed() * extent.z,
+        )
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Particle {
+    pub position: Vector3<f32>,
+    /// Units per second
+    pub velocity: Vector3<f32>,
+    /// Seconds since the particle was spawned
+    pub age: f32,
+    pub lifetime: f32,
+}
+impl Particle {
+    /// Fraction of its life that has passed from 0 to 1
+    pub fn life(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+/// Spawns particles at a steady rate and moves them under gravity and wind
+#[derive(Clone, Debug)]
+pub struct Emitter {
+    pub position: Vector3<f32>,
+    /// Particles spawn up to this far from `position` along each axis
+    pub spawn_extent: Vector3<f32>,
+    /// Particles spawned per second
+    pub rate: f32,
+    /// Seconds each particle lives
+    pub lifetime: f32,
+    pub velocity: Vector3<f32>,
+    /// Most the starting velocity is randomly changed by along each axis
+    pub velocity_spread: Vector3<f32>,
+    /// Downward acceleration in units per second squared
+    pub gravity: f32,
+    /// How quickly particles are carried along by the wind, 0 ignores it. Also acts as drag.
+    pub wind_response: f32,
+    /// Color when spawned, faded to `end_color` over the particle's life
+    pub start_color: Vector4<f32>,
+    pub end_color: Vector4<f32>,
+    /// Width of the billboard when spawned, grown or shrunk to `end_size` over the particle's life
+    pub start_size: f32,
+    pub end_size: f32,
+    /// No more particles are spawned while this many are alive
+    pub max_particles: usize,
+    particles: Vec<Particle>,
+    /// Part of a particle waiting to be spawned, carried over between steps
+    spawn_debt: f32,
+    random: Random,
+}
+impl Emitter {
+    /// White particles that float in place for a second
+    pub fn new(position: Vector3<f32>) -> Self {
+        Self {
+            position,
+            spawn_extent: Vector3::new(0.0, 0.0, 0.0),
+            rate: 10.0,
+            lifetime: 1.0,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            velocity_spread: Vector3::new(0.0, 0.0, 0.0),
+            gravity: 0.0,
+            wind_response: 0.0,
+            start_color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            end_color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            start_size: 0.1,
+            end_size: 0.1,
+            max_particles: 2000,
+            particles: vec![],
+            spawn_debt: 0.0,
+            random: Random::new(1),
+        }
+    }
+    /// Flakes drifting down over an area `extent` wide in x and z around `position`
+    pub fn snowfall(position: Vector3<f32>, extent: f32) -> Self {
+        Self::new(position)
+            .with_spawn_extent(Vector3::new(extent, 0.5, extent))
+            .with_rate(400.0)
+            .with_lifetime(12.0)
+            .with_velocity(Vector3::new(0.0, -1.5, 0.0), Vector3::new(0.3, 0.3, 0.3))
+            .with_gravity(0.75)
+            .with_wind_response(0.5)
+            .with_color(
+                Vector4::new(1.0, 1.0, 1.0, 0.9),
+                Vector4::new(1.0, 1.0, 1.0, 0.6),
+            )
+            .with_size(0.08, 0.08)
+            .with_max_particles(5000)
+    }
+    /// Puffs of powder kicked up behind a skier
+    pub fn powder(position: Vector3<f32>) -> Self {
+        Self::new(position)
+            .with_spawn_extent(Vector3::new(0.2, 0.05, 0.2))
+            .with_rate(60.0)
+            .with_lifetime(1.2)
+            .with_velocity(Vector3::new(0.0, 1.2, 0.0), Vector3::new(0.8, 0.4, 0.8))
+            .with_gravity(2.0)
+            .with_wind_response(1.5)
+            .with_color(
+                Vector4::new(1.0, 1.0, 1.0, 0.7),
+                Vector4::new(1.0, 1.0, 1.0, 0.0),
+            )
+            .with_size(0.1, 0.5)
+    }
+    /// Plume of snow blown out of a snow cannon along `direction`
+    pub fn snow_cannon(position: Vector3<f32>, direction: Vector3<f32>) -> Self {
+        Self::new(position)
+            .with_rate(300.0)
+            .with_lifetime(3.0)
+            .with_velocity(direction.normalize() * 12.0, Vector3::new(1.2, 1.2, 1.2))
+            .with_gravity(4.0)
+            .with_wind_response(0.3)
+            .with_color(
+                Vector4::new(0.95, 0.97, 1.0, 0.8),
+                Vector4::new(1.0, 1.0, 1.0, 0.0),
+            )
+            .with_size(0.15, 0.8)
+    }
+    pub fn with_spawn_extent(mut self, extent: Vector3<f32>) -> Self {
+        self.spawn_extent = extent;
+        self
+    }
+    pub fn with_rate(mut self, rate: f32) -> Self {
+        self.rate = rate;
+        self
+    }
+    pub fn with_lifetime(mut self, lifetime: f32) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+    pub fn with_velocity(mut self, velocity: Vector3<f32>, spread: Vector3<f32>) -> Self {
+        self.velocity = velocity;
+        self.velocity_spread = spread;
+        self
+    }
+    pub fn with_gravity(mut self, gravity: f32) -> Self {
+        self.gravity = gravity;
+        self
+    }
+    pub fn with_wind_response(mut self, wind_response: f32) -> Self {
+        self.wind_response = wind_response;
+        self
+    }
+    pub fn with_color(mut self, start: Vector4<f32>, end: Vector4<f32>) -> Self {
+        self.start_color = start;
+        self.end_color = end;
+        self
+    }
+    pub fn with_size(mut self, start: f32, end: f32) -> Self {
+        self.start_size = start;
+        self.end_size = end;
+        self
+    }
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+    /// Emitters with the same settings and seed make the same particles
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.random = Random::new(seed);
+        self
+    }
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+    pub fn color_at(&self, life: f32) -> Vector4<f32> {
+        self.start_color.lerp(&self.end_color, life)
+    }
+    pub fn size_at(&self, life: f32) -> f32 {
+        self.start_size + (self.end_size - self.start_size) * life
+    }
+    /// Advances the emitter by one `STEP`
+    fn step(&mut self, wind: Vector3<f32>) {
+        let gravity = Vector3::new(0.0, -self.gravity, 0.0);
+        for particle in self.particles.iter_mut() {
+            particle.age += STEP;
+            let acceleration = gravity + (wind - particle.velocity) * self.wind_response;
+            particle.velocity += acceleration * STEP;
+            particle.position += particle.velocity * STEP;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+        self.spawn_debt += self.rate * STEP;
+        while self.spawn_debt >= 1.0 {
+            self.spawn_debt -= 1.0;
+            if self.particles.len() >= self.max_particles {
+                continue;
+            }
+            let particle = Particle {
+                position: self.position + self.random.vector(self.spawn_extent),
+                velocity: self.velocity + self.random.vector(self.velocity_spread),
+                age: 0.0,
+                lifetime: self.lifetime,
+            };
+            self.particles.push(particle);
+        }
+    }
+}
+/// Every emitter in the world and the wind blowing their particles
+pub struct ParticleSystem {
+    emitters: Arena<Emitter>,
+    /// Units per second
+    pub wind: Vector3<f32>,
+    /// Seconds not yet simulated, always less than one step
+    pending: f32,
+}
+impl ParticleSystem {
+    pub fn new() -> Self {
+        Self {
+            emitters: Arena::new(),
+            wind: Vector3::new(0.0, 0.0, 0.0),
+            pending: 0.0,
+        }
+    }
+    pub fn add_emitter(&mut self, emitter: Emitter) -> Index {
+        self.emitters.insert(emitter)
+    }
+    /// Removes an emitter along with its particles
+    pub fn remove_emitter(&mut self, index: Index) -> Option<Emitter> {
+        self.emitters.remove(index)
+    }
+    pub fn emitter(&self, index: Index) -> Option<&Emitter> {
+        self.emitters.get(index)
+    }
+    pub fn emitter_mut(&mut self, index: Index) -> Option<&mut Emitter> {
+        self.emitters.get_mut(index)
+    }
+    pub fn emitters(&self) -> impl Iterator<Item = &Emitter> {
+        self.emitters.iter().map(|(_index, emitter)| emitter)
+    }
+    pub fn particle_count(&self) -> usize {
+        self.emitters().map(|emitter| emitter.particles.len()).sum()
+    }
+    /// Simulates every whole step that fits in the time passed, the rest is kept for the next update
+    pub fn update(&mut self, delta_time_ms: f32) {
+        self.pending += delta_time_ms / 1000.0;
+        let steps = (self.pending / STEP) as u32;
+        //time past the most steps allowed is dropped rather than counted off step by step
+        self.pending = if steps > MAX_STEPS_PER_UPDATE {
+            0.0
+        } else {
+            (self.pending - steps as f32 * STEP).max(0.0)
+        };
+        for _ in 0..steps.min(MAX_STEPS_PER_UPDATE) {
+            for (_index, emitter) in self.emitters.iter_mut() {
+                emitter.step(self.wind);
+            }
+        }
+    }
+}
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+/// Model matrix turning the unit quad into a billboard of width `size` at `position` facing the
+/// camera, whose screen axes are `right` and `up` in world space
+pub fn billboard_transform(
+    position: Vector3<f32>,
+    size: f32,
+    right: Vector3<f32>,
+    up: Vector3<f32>,
+) -> Matrix4<f32> {
+    let forward = right.cross(&up);
+    Matrix4::from_columns(&[
+        (right * size).push(0.0),
+        (up * size).push(0.0),
+        forward.push(0.0),
+        position.push(1.0),
+    ])
+}
+/// Quad and soft round texture every particle is drawn with
+pub struct ParticleRenderer<E: GraphicsEngine> {
+    mesh: E::RuntimeMesh,
+    texture: E::RuntimeTexture,
+}
+impl<E: GraphicsEngine> ParticleRenderer<E> {
+    pub fn new(engine: &mut E) -> Result<Self, E::ErrorType> {
+        Ok(Self {
+            mesh: engine.build_mesh(Self::quad())?,
+            texture: engine.build_texture(Self::flake(), TextureOptions::default())?,
+        })
+    }
+    /// Square one unit wide centered on the origin in the xy plane
+    fn quad() -> Mesh {
+        let corner = |x: f32, y: f32| (Vector3::new(x, y, 0.0), Vector2::new(x + 0.5, y + 0.5));
+        Mesh {
+            vertices: vec![
+                corner(-0.5, -0.5),
+                corner(0.5, -0.5),
+                corner(0.5, 0.5),
+                corner(-0.5, -0.5),
+                corner(0.5, 0.5),
+                corner(-0.5, 0.5),
+            ],
+        }
+    }
+    /// White dot fading out towards its edge
+    fn flake() -> RGBATexture {
+        let size = 16;
+        let mut texture =
+            RGBATexture::constant_color(Vector4::new(255, 255, 255, 0), Vector2::new(size, size));
+        let center = (size as f32 - 1.0) / 2.0;
+        for y in 0..size {
+            for x in 0..size {
+                let distance =
+                    (Vector2::new(x as f32, y as f32) - Vector2::repeat(center)).norm() / center;
+                let alpha = (1.0 - distance).clamp(0.0, 1.0).sqrt();
+                texture.set_pixel(x, y, Vector4::new(255, 255, 255, (alpha * 255.0) as u8));
+            }
+        }
+        texture
+    }
+    /// Adds every particle to `queue` as a billboard facing the camera. Particles are drawn after
+    /// the rest of the world so they batch into as few draws as possible.
+    pub fn queue(
+        &self,
+        system: &ParticleSystem,
+        screen_axes: (Vector3<f32>, Vector3<f32>),
+        material: &E::Material,
+        queue: &mut RenderQueue<E::RuntimeMesh, E::RuntimeTexture, E::Material>,
+    ) {
+        let (right, up) = screen_axes;
+        for emitter in system.emitters() {
+            for particle in emitter.particles() {
+                let life = particle.life();
+                queue.push(DrawItem {
+                    mesh: self.mesh.clone(),
+                    texture: self.texture.clone(),
+                    material: material.clone(),
+                    transform: billboard_transform(
+                        particle.position,
+                        emitter.size_at(life),
+                        right,
+                        up,
+                    ),
+                    tint: emitter.color_at(life),
+                    layer: 1,
+                    blend: BlendMode::Alpha,
+                    bounds: Some(BoundingSphere {
+                        center: Vector3::new(0.0, 0.0, 0.0),
+                        radius: 0.71,
+                    }),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,561 @@
+use super::cpu_engine::{Fragment, FragmentProgram};
+use super::graphics_engine::{GraphicsEngine, ShaderDescription, TextureOptions, UniformValue};
+use super::{shaders, RGBATexture};
+use nalgebra::{Vector2, Vector3, Vector4};
+use std::rc::Rc;
+/// Number of steps along each color axis of a color grading lut
+pub const LUT_SIZE: u32 = 16;
+/// An effect applied to the whole game world after it is drawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PostEffect {
+    /// Adds a glow around bright areas such as sunlit snow
+    Bloom,
+    /// Compresses bright colors with a filmic curve after applying exposure
+    ToneMapping,
+    /// Remaps colors through `PostSettings::lut`
+    ColorGrading,
+    /// Smooths jagged edges
+    Fxaa,
+    /// Darkens the corners of the screen
+    Vignette,
+}
+impl PostEffect {
+    /// Effects in the order they are applied
+    pub const ALL: [PostEffect; 5] = [
+        PostEffect::Bloom,
+        PostEffect::ToneMapping,
+        PostEffect::ColorGrading,
+        PostEffect::Fxaa,
+        PostEffect::Vignette,
+    ];
+    /// Gets the effect called "bloom", "tone_mapping", "color_grading", "fxaa" or "vignette"
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "bloom" => Some(Self::Bloom),
+            "tone_mapping" => Some(Self::ToneMapping),
+            "color_grading" => Some(Self::ColorGrading),
+            "fxaa" => Some(Self::Fxaa),
+            "vignette" => Some(Self::Vignette),
+            _ => None,
+        }
+    }
+}
+/// Values used by the effects
+#[derive(Clone)]
+pub struct PostSettings {
+    pub exposure: f32,
+    /// Brightness above which pixels glow
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    /// How dark the corners get, 1 is black
+    pub vignette_strength: f32,
+    /// `LUT_SIZE` slices of `LUT_SIZE` by `LUT_SIZE` pixels placed side by side. Red increases to the
+    /// right within a slice, green increases upwards and blue selects the slice.
+    pub lut: RGBATexture,
+}
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            bloom_threshold: 0.8,
+            bloom_intensity: 0.6,
+            vignette_strength: 0.3,
+            lut: identity_lut(),
+        }
+    }
+}
+/// A lut that leaves colors unchanged
+pub fn identity_lut() -> RGBATexture {
+    let mut lut = RGBATexture::constant_color(
+        Vector4::new(0, 0, 0, 255),
+        Vector2::new(LUT_SIZE * LUT_SIZE, LUT_SIZE),
+    );
+    let step = |i: u32| (i * 255 / (LUT_SIZE - 1)) as u8;
+    for blue in 0..LUT_SIZE {
+        for green in 0..LUT_SIZE {
+            for red in 0..LUT_SIZE {
+                lut.set_pixel(
+                    blue * LUT_SIZE + red,
+                    green,
+                    Vector4::new(step(red), step(green), step(blue), 255),
+                );
+            }
+        }
+    }
+    lut
+}
+/// Where a pass reads from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassInput {
+    /// The drawn game world
+    Scene,
+    /// One of the two ping pong buffers
+    Buffer(usize),
+}
+/// Where a pass draws to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassOutput {
+    Buffer(usize),
+    Screen,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pass {
+    pub effect: PostEffect,
+    pub input: PassInput,
+    pub output: PassOutput,
+}
+/// Ordered effects that can each be turned on and off
+#[derive(Clone)]
+pub struct PostProcessChain {
+    enabled: Vec<(PostEffect, bool)>,
+    pub settings: PostSettings,
+}
+impl PostProcessChain {
+    /// Chain with bloom, fxaa and the vignette enabled
+    pub fn new() -> Self {
+        Self {
+            enabled: PostEffect::ALL
+                .iter()
+                .map(|effect| {
+                    let on = matches!(
+                        effect,
+                        PostEffect::Bloom | PostEffect::Fxaa | PostEffect::Vignette
+                    );
+                    (*effect, on)
+                })
+                .collect(),
+            settings: PostSettings::default(),
+        }
+    }
+    pub fn set_enabled(&mut self, effect: PostEffect, enabled: bool) {
+        for (e, on) in self.enabled.iter_mut() {
+            if *e == effect {
+                *on = enabled;
+            }
+        }
+    }
+    pub fn is_enabled(&self, effect: PostEffect) -> bool {
+        self.enabled.iter().any(|(e, on)| *e == effect && *on)
+    }
+    /// Enabled effects in order
+    pub fn effects(&self) -> Vec<PostEffect> {
+        self.enabled
+            .iter()
+            .filter(|(_, on)| *on)
+            .map(|(effect, _)| *effect)
+            .collect()
+    }
+    /// Plans the passes needed to draw the scene to the screen. Passes alternate between the two
+    /// buffers and the last one draws to the screen. Empty when no effect is enabled, in which case
+    /// the scene is copied to the screen as is.
+    pub fn passes(&self) -> Vec<Pass> {
+        let effects = self.effects();
+        let last = effects.len().saturating_sub(1);
+        effects
+            .iter()
+            .enumerate()
+            .map(|(i, effect)| Pass {
+                effect: *effect,
+                input: if i == 0 {
+                    PassInput::Scene
+                } else {
+                    PassInput::Buffer((i - 1) % 2)
+                },
+                output: if i == last {
+                    PassOutput::Screen
+                } else {
+                    PassOutput::Buffer(i % 2)
+                },
+            })
+            .collect()
+    }
+}
+impl Default for PostProcessChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+fn luma(color: Vector3<f32>) -> f32 {
+    color.dot(&Vector3::new(0.299, 0.587, 0.114))
+}
+/// Fragment programs doing the same math as the effect shaders, so a `PostProcessor` can draw with
+/// a `CpuEngine` when testing
+pub fn cpu_programs() -> Vec<(String, FragmentProgram)> {
+    PostEffect::ALL
+        .iter()
+        .map(|effect| {
+            let program: FragmentProgram = match effect {
+                PostEffect::Bloom => Rc::new(bloom),
+                PostEffect::ToneMapping => Rc::new(tone_map),
+                PostEffect::ColorGrading => Rc::new(grade),
+                PostEffect::Fxaa => Rc::new(fxaa),
+                PostEffect::Vignette => Rc::new(vignette),
+            };
+            (shader(*effect).name, program)
+        })
+        .collect()
+}
+fn bloom(fragment: &Fragment) -> Vector4<f32> {
+    let texel = fragment.texel("u_texture") * fragment.float("texel_scale");
+    let threshold = fragment.float("bloom_threshold");
+    let mut glow = Vector3::new(0.0, 0.0, 0.0);
+    for i in -2..=2 {
+        for j in -2..=2 {
+            let offset = Vector2::new(i as f32, j as f32) * 2.0;
+            let sample = fragment
+                .sample("u_texture", fragment.uv + offset.component_mul(&texel))
+                .xyz();
+            glow += sample.map(|c| (c - threshold).max(0.0));
+        }
+    }
+    let scene = fragment.sample("u_texture", fragment.uv);
+    (scene.xyz() + glow / 25.0 * fragment.float("bloom_intensity")).push(scene.w)
+}
+/// Filmic curve fitted to ACES
+fn tone_map(fragment: &Fragment) -> Vector4<f32> {
+    let scene = fragment.sample("u_texture", fragment.uv);
+    let exposure = fragment.float("exposure");
+    let curve = |c: f32| {
+        let c = c * exposure;
+        (c * (2.51 * c + 0.03) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0)
+    };
+    scene.xyz().map(curve).push(scene.w)
+}
+fn grade(fragment: &Fragment) -> Vector4<f32> {
+    let scene = fragment.sample("u_texture", fragment.uv);
+    let max = (LUT_SIZE - 1) as f32;
+    let scaled = scene.xyz().map(|c| c.clamp(0.0, 1.0) * max);
+    let blue = scaled.z.floor();
+    let next_blue = (blue + 1.0).min(max);
+    let slice = |blue: f32| {
+        fragment.sample(
+            "lut",
+            Vector2::new(
+                (blue * LUT_SIZE as f32 + scaled.x + 0.5) / (LUT_SIZE * LUT_SIZE) as f32,
+                (scaled.y + 0.5) / LUT_SIZE as f32,
+            ),
+        )
+    };
+    let graded = slice(blue).lerp(&slice(next_blue), scaled.z - blue);
+    graded.xyz().push(scene.w)
+}
+const FXAA_REDUCE_MUL: f32 = 1.0 / 8.0;
+const FXAA_REDUCE_MIN: f32 = 1.0 / 128.0;
+const FXAA_SPAN_MAX: f32 = 8.0;
+fn fxaa(fragment: &Fragment) -> Vector4<f32> {
+    let texel = fragment.texel("u_texture") * fragment.float("texel_scale");
+    let uv = fragment.uv;
+    let at = |dx: f32, dy: f32| {
+        fragment
+            .sample("u_texture", uv + Vector2::new(dx, dy).component_mul(&texel))
+            .xyz()
+    };
+    let middle = fragment.sample("u_texture", uv);
+    let luma_nw = luma(at(-1.0, 1.0));
+    let luma_ne = luma(at(1.0, 1.0));
+    let luma_sw = luma(at(-1.0, -1.0));
+    let luma_se = luma(at(1.0, -1.0));
+    let luma_m = luma(middle.xyz());
+    let luma_min = luma_m.min(luma_nw.min(luma_ne).min(luma_sw.min(luma_se)));
+    let luma_max = luma_m.max(luma_nw.max(luma_ne).max(luma_sw.max(luma_se)));
+    let direction = Vector2::new(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let reduce =
+        ((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL).max(FXAA_REDUCE_MIN);
+    let scale = 1.0 / (direction.x.abs().min(direction.y.abs()) + reduce);
+    let direction = (direction * scale)
+        .map(|d| d.clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX))
+        .component_mul(&texel);
+    let along = |t: f32| fragment.sample("u_texture", uv + direction * t).xyz();
+    let a = (along(1.0 / 3.0 - 0.5) + along(2.0 / 3.0 - 0.5)) * 0.5;
+    let b = a * 0.5 + (along(-0.5) + along(0.5)) * 0.25;
+    let luma_b = luma(b);
+    if luma_b < luma_min || luma_b > luma_max {
+        a.push(middle.w)
+    } else {
+        b.push(middle.w)
+    }
+}
+fn vignette(fragment: &Fragment) -> Vector4<f32> {
+    let scene = fragment.sample("u_texture", fragment.uv);
+    let distance = (fragment.uv - Vector2::new(0.5, 0.5)).norm();
+    let t = ((distance - 0.3) / (0.75 - 0.3)).clamp(0.0, 1.0);
+    let smooth = t * t * (3.0 - 2.0 * t);
+    let darken = fragment.float("vignette_strength") * smooth;
+    (scene.xyz() * (1.0 - darken)).push(scene.w)
+}
+/// Draws the game world to the screen through a `PostProcessChain` using two ping pong framebuffers
+pub struct PostProcessor<E: GraphicsEngine> {
+    pub chain: PostProcessChain,
+    materials: Vec<(PostEffect, E::Material)>,
+    buffers: PingPongBuffers<E>,
+    lut: E::RuntimeTexture,
+}
+/// The two framebuffers passes take turns drawing into, matching `PassInput::Buffer` and
+/// `PassOutput::Buffer`
+pub struct PingPongBuffers<E: GraphicsEngine> {
+    buffers: Vec<(E::Framebuffer, E::RuntimeTexture)>,
+    texel_scale: f32,
+}
+impl<E: GraphicsEngine> PingPongBuffers<E> {
+    /// Builds buffers that should be the size of the scene texture. They hold floats so colors
+    /// brightened by bloom are only clamped by tone mapping.
+    pub fn new(engine: &mut E, dimensions: Vector2<u32>) -> Result<Self, E::ErrorType> {
+        let mut buffers = vec![];
+        for _ in 0..2 {
+            let mut texture = engine.build_float_texture(dimensions)?;
+            let framebuffer = engine.build_framebuffer(&mut texture);
+            buffers.push((framebuffer, texture));
+        }
+        Ok(Self {
+            buffers,
+            texel_scale: 1.0,
+        })
+    }
+    /// Size of the buffers relative to the screen. Bloom and fxaa reach this many texels per
+    /// screen pixel so drawing at a higher resolution looks the same.
+    pub fn with_texel_scale(mut self, texel_scale: f32) -> Self {
+        self.texel_scale = texel_scale;
+        self
+    }
+}
+impl<E: GraphicsEngine> PostProcessor<E> {
+    /// Builds buffers the size of the scene texture
+    pub fn new(engine: &mut E, dimensions: Vector2<u32>) -> Result<Self, E::ErrorType> {
+        let chain = PostProcessChain::new();
+        let mut materials = vec![];
+        for effect in PostEffect::ALL.iter() {
+            materials.push((*effect, engine.build_material(&shader(*effect))?));
+        }
+        let buffers = PingPongBuffers::new(engine, dimensions)?;
+        let lut = engine.build_texture(chain.settings.lut.clone(), TextureOptions::default())?;
+        Ok(Self {
+            chain,
+            materials,
+            buffers,
+            lut,
+        })
+    }
+    /// Buffers the size of the scene texture `draw` uses
+    pub fn buffers(&self) -> &PingPongBuffers<E> {
+        &self.buffers
+    }
+    /// Replaces the color grading lut, see `PostSettings::lut`
+    pub fn set_lut(&mut self, engine: &mut E, lut: RGBATexture) -> Result<(), E::ErrorType> {
+        self.lut = engine.build_texture(lut.clone(), TextureOptions::default())?;
+        self.chain.settings.lut = lut;
+        Ok(())
+    }
+    /// Draws `scene` to the default framebuffer with `quad` covering the screen. `copy_material`
+    /// draws the scene unchanged when no effect is enabled.
+    pub fn draw(
+        &self,
+        engine: &mut E,
+        scene: &E::RuntimeTexture,
+        quad: &E::RuntimeMesh,
+        copy_material: &E::Material,
+    ) {
+        self.draw_to(engine, scene, quad, copy_material, &self.buffers, None)
+    }
+    /// Draws `scene` into `target` using `buffers` of the same size as the scene, or to the
+    /// default framebuffer if there is no target
+    pub fn draw_to(
+        &self,
+        engine: &mut E,
+        scene: &E::RuntimeTexture,
+        quad: &E::RuntimeMesh,
+        copy_material: &E::Material,
+        buffers: &PingPongBuffers<E>,
+        target: Option<&E::Framebuffer>,
+    ) {
+        let bind_target = |engine: &mut E| match target {
+            Some(framebuffer) => engine.bind_framebuffer(framebuffer),
+            None => engine.bind_default_framebuffer(),
+        };
+        let passes = self.chain.passes();
+        if passes.is_empty() {
+            bind_target(engine);
+            engine.bind_material(copy_material);
+            engine.send_model_matrix(nalgebra::Matrix4::identity());
+            engine.bind_texture(scene);
+            engine.draw_mesh(quad);
+            return;
+        }
+        let settings = &self.chain.settings;
+        for pass in passes {
+            match pass.output {
+                PassOutput::Buffer(i) => engine.bind_framebuffer(&buffers.buffers[i].0),
+                PassOutput::Screen => bind_target(engine),
+            }
+            let material = &self
+                .materials
+                .iter()
+                .find(|(effect, _)| *effect == pass.effect)
+                .unwrap()
+                .1;
+            engine.bind_material(material);
+            match pass.effect {
+                PostEffect::Bloom => {
+                    engine.send_uniform("texel_scale", UniformValue::Float(buffers.texel_scale));
+                    engine.send_uniform(
+                        "bloom_threshold",
+                        UniformValue::Float(settings.bloom_threshold),
+                    );
+                    engine.send_uniform(
+                        "bloom_intensity",
+                        UniformValue::Float(settings.bloom_intensity),
+                    );
+                }
+                PostEffect::ToneMapping => {
+                    engine.send_uniform("exposure", UniformValue::Float(settings.exposure))
+                }
+                PostEffect::ColorGrading => {
+                    engine.bind_texture_unit(&self.lut, 1);
+                    engine.send_uniform("lut", UniformValue::Texture(1));
+                }
+                PostEffect::Fxaa => {
+                    engine.send_uniform("texel_scale", UniformValue::Float(buffers.texel_scale))
+                }
+                PostEffect::Vignette => engine.send_uniform(
+                    "vignette_strength",
+                    UniformValue::Float(settings.vignette_strength),
+                ),
+            }
+            match pass.input {
+                PassInput::Scene => engine.bind_texture(scene),
+                PassInput::Buffer(i) => engine.bind_texture(&buffers.buffers[i].1),
+            }
+            engine.draw_mesh(quad);
+        }
+    }
+}
+/// Shader running `effect` over a fullscreen quad
+fn shader(effect: PostEffect) -> ShaderDescription {
+    let (name, uniforms, body) = match effect {
+        PostEffect::Bloom => (
+            "bloom",
+            vec!["bloom_threshold", "bloom_intensity", "texel_scale"],
+            r#"
+        uniform float bloom_threshold;
+        uniform float bloom_intensity;
+        uniform float texel_scale;
+        void main() {
+            vec3 glow = vec3(0.0);
+            for (int i = -2; i <= 2; i++) {
+                for (int j = -2; j <= 2; j++) {
+                    vec3 sample_color = texture(u_texture,o_uv+vec2(i,j)*2.0*texel_scale*texel()).rgb;
+                    glow += max(sample_color-bloom_threshold,vec3(0.0));
+                }
+            }
+            vec4 scene = texture(u_texture,o_uv);
+            color = vec4(scene.rgb+glow/25.0*bloom_intensity,scene.a);
+        }"#,
+        ),
+        PostEffect::ToneMapping => (
+            "tone_mapping",
+            vec!["exposure"],
+            r#"
+        uniform float exposure;
+        void main() {
+            vec4 scene = texture(u_texture,o_uv);
+            vec3 c = scene.rgb*exposure;
+            c = clamp(c*(2.51*c+0.03)/(c*(2.43*c+0.59)+0.14),0.0,1.0);
+            color = vec4(c,scene.a);
+        }"#,
+        ),
+        PostEffect::ColorGrading => (
+            "color_grading",
+            vec!["lut"],
+            r#"
+        uniform sampler2D lut;
+        const float LUT_SIZE = 16.0;
+        vec3 lut_slice(float blue, vec3 scaled) {
+            vec2 uv = vec2((blue*LUT_SIZE+scaled.r+0.5)/(LUT_SIZE*LUT_SIZE),(scaled.g+0.5)/LUT_SIZE);
+            return texture(lut,uv).rgb;
+        }
+        void main() {
+            vec4 scene = texture(u_texture,o_uv);
+            vec3 scaled = clamp(scene.rgb,0.0,1.0)*(LUT_SIZE-1.0);
+            float blue = floor(scaled.b);
+            float next_blue = min(blue+1.0,LUT_SIZE-1.0);
+            vec3 graded = mix(lut_slice(blue,scaled),lut_slice(next_blue,scaled),scaled.b-blue);
+            color = vec4(graded,scene.a);
+        }"#,
+        ),
+        PostEffect::Fxaa => (
+            "fxaa",
+            vec!["texel_scale"],
+            r#"
+        uniform float texel_scale;
+        const float FXAA_REDUCE_MUL = 1.0/8.0;
+        const float FXAA_REDUCE_MIN = 1.0/128.0;
+        const float FXAA_SPAN_MAX = 8.0;
+        float luma(vec3 c) {
+            return dot(c,vec3(0.299,0.587,0.114));
+        }
+        vec3 at(vec2 offset) {
+            return texture(u_texture,o_uv+offset*texel_scale*texel()).rgb;
+        }
+        void main() {
+            vec4 middle = texture(u_texture,o_uv);
+            float luma_nw = luma(at(vec2(-1.0,1.0)));
+            float luma_ne = luma(at(vec2(1.0,1.0)));
+            float luma_sw = luma(at(vec2(-1.0,-1.0)));
+            float luma_se = luma(at(vec2(1.0,-1.0)));
+            float luma_m = luma(middle.rgb);
+            float luma_min = min(luma_m,min(min(luma_nw,luma_ne),min(luma_sw,luma_se)));
+            float luma_max = max(luma_m,max(max(luma_nw,luma_ne),max(luma_sw,luma_se)));
+            vec2 direction = vec2(-((luma_nw+luma_ne)-(luma_sw+luma_se)),(luma_nw+luma_sw)-(luma_ne+luma_se));
+            float reduce = max((luma_nw+luma_ne+luma_sw+luma_se)*0.25*FXAA_REDUCE_MUL,FXAA_REDUCE_MIN);
+            float scale = 1.0/(min(abs(direction.x),abs(direction.y))+reduce);
+            direction = clamp(direction*scale,-FXAA_SPAN_MAX,FXAA_SPAN_MAX)*texel_scale*texel();
+            vec3 a = 0.5*(texture(u_texture,o_uv+direction*(1.0/3.0-0.5)).rgb+texture(u_texture,o_uv+direction*(2.0/3.0-0.5)).rgb);
+            vec3 b = a*0.5+0.25*(texture(u_texture,o_uv-direction*0.5).rgb+texture(u_texture,o_uv+direction*0.5).rgb);
+            float luma_b = luma(b);
+            color = vec4((luma_b < luma_min || luma_b > luma_max) ? a : b,middle.a);
+        }"#,
+        ),
+        PostEffect::Vignette => (
+            "vignette",
+            vec!["vignette_strength"],
+            r#"
+        uniform float vignette_strength;
+        void main() {
+            vec4 scene = texture(u_texture,o_uv);
+            float darken = vignette_strength*smoothstep(0.3,0.75,distance(o_uv,vec2(0.5)));
+            color = vec4(scene.rgb*(1.0-darken),scene.a);
+        }"#,
+        ),
+    };
+    let mut uniform_names = vec!["u_texture".to_string()];
+    uniform_names.extend(uniforms.iter().map(|u| u.to_string()));
+    ShaderDescription {
+        name: name.to_string(),
+        vertex_source: r#"#version 300 es
+        in vec3 position;
+        in vec2 uv;
+        out vec2 o_uv;
+        void main() {
+            gl_Position = vec4(position,1.0);
+            o_uv = uv;
+        }
+    "#
+        .to_string(),
+        fragment_source: format!(
+            r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        in vec2 o_uv;
+        uniform sampler2D u_texture;
+        vec2 texel() {{
+            return 1.0/vec2(textureSize(u_texture,0));
+        }}{}
+    "#,
+            body
+        ),
+        attributes: shaders::mesh_attributes(),
+        uniforms: uniform_names,
+    }
+}
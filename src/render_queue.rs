@@ -0,0 +1,230 @@
+use super::graphics_engine::{BlendMode, GpuResource, GraphicsEngine, Instance, UniformValue};
+use nalgebra::{Matrix4, Vector3, Vector4};
+/// Sphere containing a mesh, in the mesh's model space
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+impl BoundingSphere {
+    /// Smallest sphere around the origin containing all points
+    pub fn from_points(points: &[Vector3<f32>]) -> Self {
+        let radius = points.iter().map(|p| p.norm()).fold(0.0, f32::max);
+        Self {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            radius,
+        }
+    }
+    /// Moves the sphere into world space. The radius grows by the largest scale of the transform
+    pub fn transform(&self, transform: &Matrix4<f32>) -> Self {
+        let center = transform.transform_point(&self.center.into()).coords;
+        let scale = (0..3)
+            .map(|i| transform.column(i).xyz().norm())
+            .fold(0.0, f32::max);
+        Self {
+            center,
+            radius: self.radius * scale,
+        }
+    }
+}
+/// One mesh to draw in the world
+#[derive(Clone)]
+pub struct DrawItem<Mesh, Texture, Material> {
+    pub mesh: Mesh,
+    pub texture: Texture,
+    pub material: Material,
+    pub transform: Matrix4<f32>,
+    pub tint: Vector4<f32>,
+    /// Lower layers are drawn first
+    pub layer: i32,
+    /// Transparent items are drawn after opaque ones in their layer, from back to front
+    pub blend: BlendMode,
+    /// Used to skip items outside of the view. Items without bounds are always drawn.
+    pub bounds: Option<BoundingSphere>,
+}
+/// Items sharing a mesh, texture and material drawn with one instanced draw call
+pub struct DrawBatch<Mesh, Texture, Material> {
+    pub mesh: Mesh,
+    pub texture: Texture,
+    pub material: Material,
+    pub blend: BlendMode,
+    pub instances: Vec<Instance>,
+}
+/// Collects the items drawn in a frame and orders them for drawing. Opaque items are grouped by
+/// material, texture and mesh and drawn front to back within each group. Transparent items are
+/// drawn back to front and only grouped when neighbors share the same state.
+pub struct RenderQueue<Mesh, Texture, Material> {
+    items: Vec<DrawItem<Mesh, Texture, Material>>,
+}
+impl<Mesh: GpuResource + Clone, Texture: GpuResource + Clone, Material: GpuResource + Clone>
+    RenderQueue<Mesh, Texture, Material>
+{
+    pub fn new() -> Self {
+        Self { items: vec![] }
+    }
+    pub fn push(&mut self, item: DrawItem<Mesh, Texture, Material>) {
+        self.items.push(item);
+    }
+    /// Removes all items, called once the frame has been drawn
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+    /// Culls items outside of the view and sorts the rest into batches. The queue is kept so it can
+    /// be built again from another view, such as the sun's for shadows.
+    pub fn build(&self, view_projection: &Matrix4<f32>) -> Vec<DrawBatch<Mesh, Texture, Material>> {
+        let frustum = Frustum::new(view_projection);
+        let mut visible: Vec<(f32, DrawItem<Mesh, Texture, Material>)> = self
+            .items
+            .iter()
+            .filter(|item| match &item.bounds {
+                Some(bounds) => frustum.intersects(&bounds.transform(&item.transform)),
+                None => true,
+            })
+            .cloned()
+            .map(|item| {
+                //clip space w is the distance along the view direction
+                let center = match &item.bounds {
+                    Some(bounds) => bounds.center,
+                    None => Vector3::new(0.0, 0.0, 0.0),
+                };
+                let depth = (view_projection * item.transform * center.push(1.0)).w;
+                (depth, item)
+            })
+            .collect();
+        visible.sort_by(|(depth_a, a), (depth_b, b)| {
+            let state = |item: &DrawItem<Mesh, Texture, Material>| {
+                (
+                    item.blend,
+                    item.material.id(),
+                    item.texture.id(),
+                    item.mesh.id(),
+                )
+            };
+            let (a_transparent, b_transparent) =
+                (a.blend.is_transparent(), b.blend.is_transparent());
+            a.layer
+                .cmp(&b.layer)
+                .then(a_transparent.cmp(&b_transparent))
+                .then_with(|| {
+                    if a_transparent {
+                        depth_b
+                            .partial_cmp(depth_a)
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    } else {
+                        state(a).cmp(&state(b)).then(
+                            depth_a
+                                .partial_cmp(depth_b)
+                                .unwrap_or(std::cmp::Ordering::Equal),
+                        )
+                    }
+                })
+        });
+        let mut batches: Vec<DrawBatch<Mesh, Texture, Material>> = vec![];
+        let mut last_layer = None;
+        for (_depth, item) in visible.into_iter() {
+            let instance = Instance {
+                transform: item.transform,
+                tint: item.tint,
+            };
+            let same_layer = last_layer.replace(item.layer) == Some(item.layer);
+            if let Some(batch) = batches.last_mut() {
+                if same_layer
+                    && batch.blend == item.blend
+                    && batch.material.id() == item.material.id()
+                    && batch.texture.id() == item.texture.id()
+                    && batch.mesh.id() == item.mesh.id()
+                {
+                    batch.instances.push(instance);
+                    continue;
+                }
+            }
+            batches.push(DrawBatch {
+                mesh: item.mesh,
+                texture: item.texture,
+                material: item.material,
+                blend: item.blend,
+                instances: vec![instance],
+            });
+        }
+        batches
+    }
+}
+impl<Mesh: GpuResource + Clone, Texture: GpuResource + Clone, Material: GpuResource + Clone> Default
+    for RenderQueue<Mesh, Texture, Material>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+/// Draws batches built by `RenderQueue::build`
+pub fn draw_batches<E: GraphicsEngine>(
+    engine: &mut E,
+    batches: &[DrawBatch<E::RuntimeMesh, E::RuntimeTexture, E::Material>],
+    view_projection: Matrix4<f32>,
+) {
+    for batch in batches.iter() {
+        engine.set_blend_mode(batch.blend);
+        engine.bind_material(&batch.material);
+        engine.send_view_matrix(view_projection);
+        engine.send_uniform(
+            "alpha_cutoff",
+            UniformValue::Float(batch.blend.alpha_cutoff()),
+        );
+        engine.bind_texture(&batch.texture);
+        engine.draw_instanced(&batch.mesh, &batch.instances);
+    }
+}
+/// Draws the opaque and cutout batches into a shadow map with `material`. Transparent batches do not
+/// cast shadows.
+pub fn draw_shadow_casters<E: GraphicsEngine>(
+    engine: &mut E,
+    batches: &[DrawBatch<E::RuntimeMesh, E::RuntimeTexture, E::Material>],
+    light_view_projection: Matrix4<f32>,
+    material: &E::Material,
+) {
+    engine.set_blend_mode(BlendMode::Opaque);
+    engine.bind_material(material);
+    engine.send_view_matrix(light_view_projection);
+    for batch in batches.iter().filter(|batch| !batch.blend.is_transparent()) {
+        engine.send_uniform(
+            "alpha_cutoff",
+            UniformValue::Float(batch.blend.alpha_cutoff()),
+        );
+        engine.bind_texture(&batch.texture);
+        engine.draw_instanced(&batch.mesh, &batch.instances);
+    }
+}
+/// Planes of the view volume stored as (normal, distance) with normals pointing inwards
+pub struct Frustum {
+    planes: [Vector4<f32>; 6],
+}
+impl Frustum {
+    /// Extracts the planes from a view projection matrix
+    pub fn new(matrix: &Matrix4<f32>) -> Self {
+        let row = |i: usize| matrix.row(i).transpose();
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(3) + row(2),
+            row(3) - row(2),
+        ];
+        Self {
+            planes: planes.map(Self::normalize),
+        }
+    }
+    fn normalize(plane: Vector4<f32>) -> Vector4<f32> {
+        let length = plane.xyz().norm();
+        if length > 0.0 {
+            plane / length
+        } else {
+            plane
+        }
+    }
+    pub fn intersects(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(&sphere.center) + plane.w >= -sphere.radius)
+    }
+}
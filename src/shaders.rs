@@ -0,0 +1,376 @@
+use super::animation::MAX_JOINTS;
+use super::graphics_engine::{ShaderDescription, VertexAttribute};
+/// Position and texture coordinate of each vertex
+pub fn mesh_attributes() -> Vec<(String, VertexAttribute)> {
+    vec![
+        ("position".to_string(), VertexAttribute::Position),
+        ("uv".to_string(), VertexAttribute::Uv),
+    ]
+}
+fn instanced_attributes() -> Vec<(String, VertexAttribute)> {
+    let mut attributes = mesh_attributes();
+    attributes.push((
+        "instance_transform".to_string(),
+        VertexAttribute::InstanceTransform,
+    ));
+    attributes.push(("instance_tint".to_string(), VertexAttribute::InstanceTint));
+    attributes
+}
+/// Instanced attributes with the joints and weights of a `SkinnedVertex`
+fn skinned_attributes() -> Vec<(String, VertexAttribute)> {
+    let mut attributes = instanced_attributes();
+    attributes.push(("joints".to_string(), VertexAttribute::Joints));
+    attributes.push(("weights".to_string(), VertexAttribute::Weights));
+    attributes
+}
+/// Defines `skin`, which moves a model space position by the bones of the vertex. Unskinned meshes
+/// get a `skin` that does nothing.
+fn skinning(skinned: bool) -> String {
+    if skinned {
+        format!(
+            r#"
+        in vec4 joints;
+        in vec4 weights;
+        uniform mat4 bones[{}];
+        vec4 skin(vec3 position) {{
+            mat4 bone = bones[int(joints.x)]*weights.x+bones[int(joints.y)]*weights.y
+                +bones[int(joints.z)]*weights.z+bones[int(joints.w)]*weights.w;
+            return bone*vec4(position,1.0);
+        }}"#,
+            MAX_JOINTS
+        )
+    } else {
+        r#"
+        vec4 skin(vec3 position) {
+            return vec4(position,1.0);
+        }"#
+        .to_string()
+    }
+}
+/// Textured meshes viewed through the camera, drawn with `GraphicsEngine::draw_instanced`. Shadows
+/// are read from the depth texture on unit 1 with 3x3 percentage closer filtering and are skipped
+/// when `shadow_strength` is 0. Distant and low lying fragments fade into `fog_color`.
+pub fn world() -> ShaderDescription {
+    lit(
+        "world",
+        false,
+        vec![],
+        r#"
+        vec4 surface() {
+            return texture(u_texture,o_uv)*o_tint;
+        }"#,
+    )
+}
+/// Lit like `world` but with each vertex moved by the `bones` of a skeleton, used for characters
+/// drawn with `GraphicsEngine::build_skinned_mesh` meshes
+pub fn skinned() -> ShaderDescription {
+    lit(
+        "skinned",
+        true,
+        vec![],
+        r#"
+        vec4 surface() {
+            return texture(u_texture,o_uv)*o_tint;
+        }"#,
+    )
+}
+/// Terrain lit like `world` with the overlay texture on unit 2 painted over it. `overlay_bounds`
+/// holds the xz corner and size of the area the overlay covers. Its red channel is groomed
+/// corduroy, green ski tracks, blue moguls and alpha run boundaries.
+pub fn terrain() -> ShaderDescription {
+    lit(
+        "terrain",
+        false,
+        vec!["overlay_map", "overlay_bounds"],
+        r#"
+        uniform sampler2D overlay_map;
+        uniform vec4 overlay_bounds;
+        vec4 surface() {
+            vec4 color = texture(u_texture,o_uv)*o_tint;
+            vec4 overlay = texture(overlay_map,(o_world_position.xz-overlay_bounds.xy)/overlay_bounds.zw);
+            //grooming leaves fine ridges running across the slope
+            float corduroy = 0.5+0.5*sin(o_world_position.z*25.0);
+            color.rgb *= 1.0-overlay.r*0.15*corduroy;
+            float bumps = sin(o_world_position.x*1.3)*sin(o_world_position.z*1.3);
+            color.rgb *= 1.0+overlay.b*0.25*bumps;
+            //packed snow in tracks looks darker and bluer
+            color.rgb = mix(color.rgb,color.rgb*vec3(0.72,0.78,0.9),overlay.g);
+            color.rgb = mix(color.rgb,vec3(1.0,0.45,0.1),overlay.a*0.8);
+            return color;
+        }"#,
+    )
+}
+/// Lights, shadows and fogs the color returned by the `surface` function defined in `surface`.
+/// Skinned shaders take the `bones` uniform and the skinned vertex attributes.
+fn lit(name: &str, skinned: bool, uniforms: Vec<&str>, surface: &str) -> ShaderDescription {
+    let mut uniform_names: Vec<String> = [
+        "camera",
+        "u_texture",
+        "alpha_cutoff",
+        "light_view_projection",
+        "shadow_map",
+        "shadow_strength",
+        "light_color",
+        "camera_position",
+        "fog_color",
+        "fog_density",
+        "fog_height_falloff",
+    ]
+    .iter()
+    .map(|u| u.to_string())
+    .collect();
+    uniform_names.extend(uniforms.iter().map(|u| u.to_string()));
+    if skinned {
+        uniform_names.push("bones".to_string());
+    }
+    ShaderDescription {
+        name: name.to_string(),
+        vertex_source: format!(
+            r#"#version 300 es
+        in vec3 position;
+        in vec2 uv;
+        in mat4 instance_transform;
+        in vec4 instance_tint;
+        out vec2 o_uv;
+        out vec4 o_tint;
+        out vec4 o_light_position;
+        out vec3 o_world_position;
+        uniform mat4 camera;
+        uniform mat4 light_view_projection;{}
+        void main() {{
+            vec4 world_position = instance_transform*skin(position);
+            gl_Position = camera*world_position;
+            o_uv = uv;
+            o_tint = instance_tint;
+            o_light_position = light_view_projection*world_position;
+            o_world_position = world_position.xyz;
+        }}
+    "#,
+            skinning(skinned)
+        ),
+        fragment_source: format!(
+            r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        in vec2 o_uv;
+        in vec4 o_tint;
+        in vec4 o_light_position;
+        in vec3 o_world_position;
+        uniform sampler2D u_texture;
+        uniform float alpha_cutoff;
+        uniform sampler2D shadow_map;
+        uniform float shadow_strength;
+        uniform vec3 light_color;
+        uniform vec3 camera_position;
+        uniform vec3 fog_color;
+        uniform float fog_density;
+        uniform float fog_height_falloff;
+        float fog() {{
+            float distance = length(o_world_position-camera_position);
+            float height = exp(-fog_height_falloff*max(o_world_position.y,0.0));
+            return 1.0-exp(-fog_density*distance*height);
+        }}
+        float shadow() {{
+            vec3 position = o_light_position.xyz/o_light_position.w*0.5+0.5;
+            //outside of the shadow map is always lit
+            if (position.z > 1.0 || any(lessThan(position.xy,vec2(0.0))) || any(greaterThan(position.xy,vec2(1.0)))) {{
+                return 0.0;
+            }}
+            vec2 texel = 1.0/vec2(textureSize(shadow_map,0));
+            float bias = 0.002;
+            float shadowed = 0.0;
+            for (int x = -1; x <= 1; x++) {{
+                for (int y = -1; y <= 1; y++) {{
+                    float depth = texture(shadow_map,position.xy+vec2(x,y)*texel).r;
+                    shadowed += position.z-bias > depth ? 1.0 : 0.0;
+                }}
+            }}
+            return shadowed/9.0;
+        }}{}
+        void main() {{
+            color = surface();
+            if (color.a < alpha_cutoff) {{
+                discard;
+            }}
+            color.rgb *= light_color;
+            if (shadow_strength > 0.0) {{
+                color.rgb *= 1.0-shadow_strength*shadow();
+            }}
+            color.rgb = mix(color.rgb,fog_color,fog());
+        }}
+    "#,
+            surface
+        ),
+        attributes: if skinned {
+            skinned_attributes()
+        } else {
+            instanced_attributes()
+        },
+        uniforms: uniform_names,
+    }
+}
+/// Gradient from the horizon to the zenith with the sun, drawn behind the world over a fullscreen
+/// quad. The view ray of each pixel is found from `inverse_view_projection`.
+pub fn sky() -> ShaderDescription {
+    ShaderDescription {
+        name: "sky".to_string(),
+        vertex_source: r#"#version 300 es
+        in vec3 position;
+        in vec2 uv;
+        out vec2 o_screen;
+        void main() {
+            gl_Position = vec4(position.xy,1.0,1.0);
+            o_screen = position.xy;
+        }
+    "#
+        .to_string(),
+        fragment_source: r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        in vec2 o_screen;
+        uniform mat4 inverse_view_projection;
+        uniform vec3 zenith_color;
+        uniform vec3 horizon_color;
+        uniform vec3 sun_position;
+        uniform vec3 sun_color;
+        void main() {
+            vec4 near = inverse_view_projection*vec4(o_screen,-1.0,1.0);
+            vec4 far = inverse_view_projection*vec4(o_screen,1.0,1.0);
+            vec3 ray = normalize(far.xyz/far.w-near.xyz/near.w);
+            vec3 sky = mix(horizon_color,zenith_color,sqrt(clamp(ray.y,0.0,1.0)));
+            float sun_angle = max(dot(ray,sun_position),0.0);
+            float disc = smoothstep(0.9990,0.9995,sun_angle);
+            float glow = pow(sun_angle,64.0)*0.3;
+            color = vec4(sky+sun_color*(disc+glow),1.0);
+        }
+    "#
+        .to_string(),
+        attributes: mesh_attributes(),
+        uniforms: vec![
+            "inverse_view_projection".to_string(),
+            "zenith_color".to_string(),
+            "horizon_color".to_string(),
+            "sun_position".to_string(),
+            "sun_color".to_string(),
+        ],
+    }
+}
+/// Colored lines viewed through the camera, drawn with `GraphicsEngine::draw_lines`
+pub fn debug_lines() -> ShaderDescription {
+    ShaderDescription {
+        name: "debug_lines".to_string(),
+        vertex_source: r#"#version 300 es
+        in vec3 position;
+        in vec4 color;
+        out vec4 o_color;
+        uniform mat4 camera;
+        void main() {
+            gl_Position = camera*vec4(position,1.0);
+            o_color = color;
+        }
+    "#
+        .to_string(),
+        fragment_source: r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        in vec4 o_color;
+        void main() {
+            color = o_color;
+        }
+    "#
+        .to_string(),
+        attributes: vec![
+            ("position".to_string(), VertexAttribute::Position),
+            ("color".to_string(), VertexAttribute::Color),
+        ],
+        uniforms: vec!["camera".to_string()],
+    }
+}
+/// Writes only depth, used to draw the world from the sun into a shadow map. Cutout textures are
+/// still tested so their holes let light through.
+pub fn shadow_depth() -> ShaderDescription {
+    depth("shadow_depth", false)
+}
+/// Shadow depth of skinned meshes moved by `bones`
+pub fn skinned_shadow_depth() -> ShaderDescription {
+    depth("skinned_shadow_depth", true)
+}
+fn depth(name: &str, skinned: bool) -> ShaderDescription {
+    let mut uniforms = vec![
+        "camera".to_string(),
+        "u_texture".to_string(),
+        "alpha_cutoff".to_string(),
+    ];
+    if skinned {
+        uniforms.push("bones".to_string());
+    }
+    ShaderDescription {
+        name: name.to_string(),
+        vertex_source: format!(
+            r#"#version 300 es
+        in vec3 position;
+        in vec2 uv;
+        in mat4 instance_transform;
+        in vec4 instance_tint;
+        out vec2 o_uv;
+        out float o_alpha;
+        uniform mat4 camera;{}
+        void main() {{
+            gl_Position = camera*instance_transform*skin(position);
+            o_uv = uv;
+            o_alpha = instance_tint.a;
+        }}
+    "#,
+            skinning(skinned)
+        ),
+        fragment_source: r#"#version 300 es
+        precision highp float;
+        in vec2 o_uv;
+        in float o_alpha;
+        uniform sampler2D u_texture;
+        uniform float alpha_cutoff;
+        void main() {
+            if (texture(u_texture,o_uv).a*o_alpha < alpha_cutoff) {
+                discard;
+            }
+        }
+    "#
+        .to_string(),
+        attributes: if skinned {
+            skinned_attributes()
+        } else {
+            instanced_attributes()
+        },
+        uniforms,
+    }
+}
+/// Textured meshes already in screen coordinates, used for the gui and for drawing framebuffers to
+/// the screen
+pub fn screen() -> ShaderDescription {
+    ShaderDescription {
+        name: "screen".to_string(),
+        vertex_source: r#"#version 300 es
+        in vec3 position;
+        in vec2 uv;
+        out vec2 o_uv;
+        uniform mat4 model;
+        void main() {
+            gl_Position = model*vec4(position,1.0);
+            o_uv = uv;
+        }
+    "#
+        .to_string(),
+        fragment_source: r#"#version 300 es
+        precision highp float;
+        out vec4 color;
+        in vec2 o_uv;
+        uniform sampler2D u_texture;
+        void main() {
+            color = texture(u_texture,o_uv);
+        }
+    "#
+        .to_string(),
+        attributes: mesh_attributes(),
+        uniforms: vec!["model".to_string(), "u_texture".to_string()],
+    }
+}
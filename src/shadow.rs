@@ -0,0 +1,108 @@
+use super::graphics_engine::GraphicsEngine;
+use nalgebra::{Matrix4, Point3, Vector2, Vector3, Vector4};
+/// How far from the camera shadows are drawn. Past this distance objects are unshadowed.
+pub const MAX_SHADOW_DISTANCE: f32 = 60.0;
+/// Resolution of the shadow map. Lower settings are faster on weak hardware.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShadowQuality {
+    Off,
+    Low,
+    High,
+}
+impl ShadowQuality {
+    /// Gets the setting called "off", "low" or "high"
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "low" => Some(Self::Low),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+    /// Width and height of the shadow map, None if shadows are not drawn
+    pub fn map_size(&self) -> Option<u32> {
+        match self {
+            Self::Off => None,
+            Self::Low => Some(1024),
+            Self::High => Some(2048),
+        }
+    }
+}
+/// Depth texture the sun's view of the world is drawn into
+pub struct ShadowMap<E: GraphicsEngine> {
+    pub texture: E::RuntimeTexture,
+    pub framebuffer: E::Framebuffer,
+    pub size: u32,
+}
+impl<E: GraphicsEngine> ShadowMap<E> {
+    pub fn new(engine: &mut E, size: u32) -> Result<Self, E::ErrorType> {
+        let mut texture = engine.build_depth_texture(Vector2::new(size, size))?;
+        let framebuffer = engine.build_framebuffer(&mut texture);
+        Ok(Self {
+            texture,
+            framebuffer,
+            size,
+        })
+    }
+}
+/// Builds an orthographic projection looking along `sun_direction` that covers the part of the
+/// camera's view within `MAX_SHADOW_DISTANCE`. Objects between the sun and the view are included so
+/// they still cast shadows into it.
+pub fn light_view_projection(
+    camera_view_projection: &Matrix4<f32>,
+    sun_direction: &Vector3<f32>,
+    map_size: u32,
+) -> Matrix4<f32> {
+    let inverse = camera_view_projection
+        .try_inverse()
+        .unwrap_or_else(Matrix4::identity);
+    let unproject = |x: f32, y: f32, z: f32| {
+        let point = inverse * Vector4::new(x, y, z, 1.0);
+        point.xyz() / point.w
+    };
+    let mut corners = vec![];
+    for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].iter() {
+        let near = unproject(*x, *y, -1.0);
+        let far = unproject(*x, *y, 1.0);
+        let ray = far - near;
+        let length = ray.norm().min(MAX_SHADOW_DISTANCE);
+        corners.push(near);
+        corners.push(near + ray.normalize() * length);
+    }
+    let direction = sun_direction.normalize();
+    //look_at needs an up direction that is not parallel to the view
+    let up = if direction.y.abs() > 0.99 {
+        Vector3::new(0.0, 0.0, 1.0)
+    } else {
+        Vector3::new(0.0, 1.0, 0.0)
+    };
+    //the light view stays fixed in the world so the texel snapping below lines up between frames
+    let view = Matrix4::look_at_rh(&Point3::from(-direction), &Point3::origin(), &up);
+    let mut min = Vector3::repeat(f32::MAX);
+    let mut max = Vector3::repeat(f32::MIN);
+    for corner in corners.iter() {
+        let light_space = view.transform_point(&Point3::from(*corner)).coords;
+        min = min.inf(&light_space);
+        max = max.sup(&light_space);
+    }
+    //snapping the bounds to whole texels keeps shadow edges from shimmering as the camera moves.
+    //the size is kept at exactly `map_size` texels, with one texel of room for the snapping, so
+    //the texels of the map stay on the grid
+    let texel = (max.x - min.x).max(max.y - min.y) / map_size.saturating_sub(1).max(1) as f32;
+    if texel > 0.0 {
+        min.x = (min.x / texel).floor() * texel;
+        min.y = (min.y / texel).floor() * texel;
+        max.x = min.x + texel * map_size as f32;
+        max.y = min.y + texel * map_size as f32;
+    }
+    //the view looks down negative z so the nearest point has the largest z
+    let projection = Matrix4::new_orthographic(
+        min.x,
+        max.x,
+        min.y,
+        max.y,
+        -max.z - MAX_SHADOW_DISTANCE,
+        -min.z,
+    );
+    projection * view
+}
@@ -0,0 +1,152 @@
+use nalgebra::Vector3;
+/// Game hours that pass per real second
+const DEFAULT_TIME_SPEED: f32 = 0.01;
+/// Hour the sun rises, it sets the same number of hours before midnight
+const SUNRISE: f32 = 6.0;
+/// Sky colors through the day as (hour, zenith, horizon). The last entry wraps around to the first.
+/// Sunrise and sunset colors peak when the sun is on the horizon and mornings mirror evenings.
+const KEYFRAMES: [(f32, [f32; 3], [f32; 3]); 8] = [
+    (0.0, [0.01, 0.02, 0.06], [0.05, 0.07, 0.12]),
+    (4.0, [0.01, 0.02, 0.06], [0.05, 0.07, 0.12]),
+    (SUNRISE, [0.25, 0.35, 0.6], [0.95, 0.6, 0.4]),
+    (9.0, [0.2, 0.45, 0.85], [0.7, 0.82, 0.95]),
+    (16.0, [0.2, 0.45, 0.85], [0.7, 0.82, 0.95]),
+    (24.0 - SUNRISE, [0.2, 0.25, 0.5], [0.95, 0.5, 0.3]),
+    (20.0, [0.01, 0.02, 0.06], [0.05, 0.07, 0.12]),
+    (24.0, [0.01, 0.02, 0.06], [0.05, 0.07, 0.12]),
+];
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weather {
+    Clear,
+    Overcast,
+    Snowing,
+}
+impl Weather {
+    /// Gets the weather called "clear", "overcast" or "snowing"
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "clear" => Some(Self::Clear),
+            "overcast" => Some(Self::Overcast),
+            "snowing" => Some(Self::Snowing),
+            _ => None,
+        }
+    }
+    /// How far colors are pulled towards grey by clouds
+    fn cloudiness(&self) -> f32 {
+        match self {
+            Self::Clear => 0.0,
+            Self::Overcast => 0.7,
+            Self::Snowing => 0.85,
+        }
+    }
+    /// How hard it is snowing from 0 to 1
+    fn snowfall(&self) -> f32 {
+        match self {
+            Self::Clear | Self::Overcast => 0.0,
+            Self::Snowing => 1.0,
+        }
+    }
+    /// Fog thickness per unit of distance at ground level
+    fn fog_density(&self) -> f32 {
+        match self {
+            Self::Clear => 0.004,
+            Self::Overcast => 0.012,
+            Self::Snowing => 0.03,
+        }
+    }
+}
+/// Colors of the sky used to draw it and to fog the world
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkyColors {
+    /// Color straight up
+    pub zenith: Vector3<f32>,
+    /// Color at the horizon, distant objects fade into this
+    pub horizon: Vector3<f32>,
+    /// Light falling on the world
+    pub light: Vector3<f32>,
+}
+/// Time of day and weather, which decide the color of the sky, the fog and where the sun is
+pub struct Sky {
+    /// Hour of the day from 0 to 24
+    time_of_day: f32,
+    /// Game hours that pass per real second
+    time_speed: f32,
+    pub weather: Weather,
+    /// How much thinner fog gets per unit of height, so that peaks poke out of valley fog
+    pub fog_height_falloff: f32,
+}
+impl Sky {
+    pub fn new(time_of_day: f32, weather: Weather) -> Self {
+        Self {
+            time_of_day: time_of_day.rem_euclid(24.0),
+            time_speed: DEFAULT_TIME_SPEED,
+            weather,
+            fog_height_falloff: 0.1,
+        }
+    }
+    /// Hour of the day from 0 to 24
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        self.time_of_day = hours.rem_euclid(24.0);
+    }
+    /// Sets how many game hours pass per real second, 0 stops time
+    pub fn set_time_speed(&mut self, hours_per_second: f32) {
+        self.time_speed = hours_per_second;
+    }
+    /// Advances the time of day
+    pub fn update(&mut self, delta_time_ms: f32) {
+        self.set_time_of_day(self.time_of_day + self.time_speed * delta_time_ms / 1000.0);
+    }
+    /// Direction from the world to the sun. The sun rises in the east (positive x) and passes south
+    /// of the zenith.
+    pub fn sun_position(&self) -> Vector3<f32> {
+        let angle = (self.time_of_day - SUNRISE) / (24.0 - 2.0 * SUNRISE) * std::f32::consts::PI;
+        Vector3::new(angle.cos(), angle.sin(), 0.4).normalize()
+    }
+    /// Direction the sunlight travels in
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        -self.sun_position()
+    }
+    /// How strongly the sun shines from 0 at night or under thick clouds to 1 in clear daylight.
+    /// Used to fade out shadows.
+    pub fn sun_visibility(&self) -> f32 {
+        let height = (self.sun_position().y * 4.0).clamp(0.0, 1.0);
+        height * (1.0 - self.weather.cloudiness())
+    }
+    /// Colors at the time of day, blended between keyframes and greyed by clouds
+    pub fn colors(&self) -> SkyColors {
+        let next = KEYFRAMES
+            .iter()
+            .position(|(hour, _, _)| *hour > self.time_of_day)
+            .unwrap_or(KEYFRAMES.len() - 1)
+            .max(1);
+        let (start_hour, start_zenith, start_horizon) = KEYFRAMES[next - 1];
+        let (end_hour, end_zenith, end_horizon) = KEYFRAMES[next];
+        let t = ((self.time_of_day - start_hour) / (end_hour - start_hour)).clamp(0.0, 1.0);
+        let blend =
+            |start: [f32; 3], end: [f32; 3]| Vector3::from(start).lerp(&Vector3::from(end), t);
+        let overcast = |color: Vector3<f32>| {
+            let grey = color.dot(&Vector3::new(0.299, 0.587, 0.114));
+            color.lerp(&Vector3::repeat(grey), self.weather.cloudiness())
+        };
+        let horizon = overcast(blend(start_horizon, end_horizon));
+        let daylight = self.sun_position().y.clamp(0.0, 1.0).sqrt();
+        //moonlight keeps the world visible at night
+        let light = Vector3::new(0.2, 0.22, 0.35).lerp(&Vector3::repeat(1.0), daylight);
+        SkyColors {
+            zenith: overcast(blend(start_zenith, end_zenith)),
+            horizon,
+            light: overcast(light) * (1.0 - 0.3 * self.weather.cloudiness()),
+        }
+    }
+    /// Fog thickness per unit of distance at height 0
+    pub fn fog_density(&self) -> f32 {
+        self.weather.fog_density()
+    }
+    /// How hard it is snowing from 0 to 1
+    pub fn snowfall(&self) -> f32 {
+        self.weather.snowfall()
+    }
+}
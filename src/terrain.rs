@@ -0,0 +1,453 @@
+use super::graphics_engine::{GraphicsEngine, Mesh, TextureOptions};
+use super::render_queue::{BoundingSphere, DrawItem, Frustum, RenderQueue};
+use super::{BlendMode, RGBATexture};
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+use std::collections::{HashMap, HashSet};
+/// Cells along each side of a chunk. Must be divisible by the step of the coarsest level of detail.
+pub const CHUNK_CELLS: u32 = 32;
+/// Coarsest level of detail, which skips `1 << MAX_LOD` cells per triangle
+pub const MAX_LOD: u32 = 3;
+/// Chunks closer than this are drawn at full detail. Detail halves every time the distance doubles.
+const LOD_DISTANCE: f32 = 40.0;
+/// How far skirts hang below the edge of a chunk per cell skipped. Skirts hide the cracks between
+/// chunks drawn at different levels of detail.
+const SKIRT_DEPTH: f32 = 1.0;
+/// Identifies a chunk by its position in chunks from the corner of the terrain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkId {
+    pub x: u32,
+    pub z: u32,
+}
+/// Level of detail for a chunk `distance` away from the camera
+pub fn lod_for_distance(distance: f32) -> u32 {
+    if distance < LOD_DISTANCE {
+        0
+    } else {
+        ((distance / LOD_DISTANCE).log2().floor() as u32 + 1).min(MAX_LOD)
+    }
+}
+/// Heightmap split into square chunks that are meshed separately. Vertices are one unit apart,
+/// starting at `origin` on the xz plane.
+pub struct Terrain {
+    /// Number of cells along x and z, a multiple of `CHUNK_CELLS`
+    cells: Vector2<u32>,
+    origin: Vector2<f32>,
+    /// Height of each vertex in rows along x
+    heights: Vec<f32>,
+    /// Chunks edited since `take_dirty` was last called
+    dirty: HashSet<ChunkId>,
+}
+impl Terrain {
+    /// Builds terrain with `chunks` chunks along each axis, taking heights from `height` at the
+    /// world position of each vertex. There has to be at least one chunk along each axis.
+    pub fn from_fn(
+        chunks: Vector2<u32>,
+        origin: Vector2<f32>,
+        height: impl Fn(f32, f32) -> f32,
+    ) -> Result<Self, String> {
+        if chunks.x == 0 || chunks.y == 0 {
+            return Err(format!("{}x{} chunk terrain is empty", chunks.x, chunks.y));
+        }
+        let cells = chunks * CHUNK_CELLS;
+        let mut heights = Vec::with_capacity(((cells.x + 1) * (cells.y + 1)) as usize);
+        for z in 0..=cells.y {
+            for x in 0..=cells.x {
+                heights.push(height(origin.x + x as f32, origin.y + z as f32));
+            }
+        }
+        Ok(Self {
+            cells,
+            origin,
+            heights,
+            dirty: HashSet::new(),
+        })
+    }
+    /// World position of the corner with the lowest x and z
+    pub fn origin(&self) -> Vector2<f32> {
+        self.origin
+    }
+    pub fn chunk_count(&self) -> Vector2<u32> {
+        self.cells / CHUNK_CELLS
+    }
+    /// Height of the vertex at `x`, `z` counted from the origin
+    pub fn height(&self, x: u32, z: u32) -> f32 {
+        self.heights[(z * (self.cells.x + 1) + x) as usize]
+    }
+    /// Height at a world position, interpolated between vertices. Positions off the terrain use the
+    /// nearest edge.
+    pub fn height_at(&self, position: Vector2<f32>) -> f32 {
+        let local = position - self.origin;
+        let x = local.x.clamp(0.0, self.cells.x as f32);
+        let z = local.y.clamp(0.0, self.cells.y as f32);
+        let (x0, z0) = (
+            (x.floor() as u32).min(self.cells.x - 1),
+            (z.floor() as u32).min(self.cells.y - 1),
+        );
+        let (fx, fz) = (x - x0 as f32, z - z0 as f32);
+        let bottom = self.height(x0, z0) * (1.0 - fx) + self.height(x0 + 1, z0) * fx;
+        let top = self.height(x0, z0 + 1) * (1.0 - fx) + self.height(x0 + 1, z0 + 1) * fx;
+        bottom * (1.0 - fz) + top * fz
+    }
+    /// Sets the height of a vertex and marks every chunk sharing it for rebuilding
+    pub fn set_height(&mut self, x: u32, z: u32, height: f32) {
+        let index = (z * (self.cells.x + 1) + x) as usize;
+        self.heights[index] = height;
+        let chunks = self.chunk_count();
+        //vertices on a chunk edge belong to the chunks on both sides
+        let owners = |v: u32, count: u32| {
+            let chunk = v / CHUNK_CELLS;
+            let mut owners = vec![];
+            if chunk < count {
+                owners.push(chunk);
+            }
+            if v.is_multiple_of(CHUNK_CELLS) && chunk > 0 {
+                owners.push(chunk - 1);
+            }
+            owners
+        };
+        for chunk_z in owners(z, chunks.y) {
+            for chunk_x in owners(x, chunks.x) {
+                self.dirty.insert(ChunkId {
+                    x: chunk_x,
+                    z: chunk_z,
+                });
+            }
+        }
+    }
+    /// Raises the terrain within `radius` of `center` by up to `amount`, falling off smoothly to the
+    /// edge. Negative amounts dig.
+    pub fn raise(&mut self, center: Vector2<f32>, radius: f32, amount: f32) {
+        let local = center - self.origin;
+        let min_x = (local.x - radius).floor().max(0.0) as u32;
+        let min_z = (local.y - radius).floor().max(0.0) as u32;
+        let max_x = ((local.x + radius).ceil().max(0.0) as u32).min(self.cells.x);
+        let max_z = ((local.y + radius).ceil().max(0.0) as u32).min(self.cells.y);
+        for z in min_z..=max_z {
+            for x in min_x..=max_x {
+                let distance = (Vector2::new(x as f32, z as f32) - local).norm();
+                if distance >= radius {
+                    continue;
+                }
+                let falloff = 0.5 + 0.5 * (distance / radius * std::f32::consts::PI).cos();
+                self.set_height(x, z, self.height(x, z) + amount * falloff);
+            }
+        }
+    }
+    /// Takes the chunks edited since the last call
+    pub fn take_dirty(&mut self) -> Vec<ChunkId> {
+        self.dirty.drain().collect()
+    }
+    /// Sphere around a chunk in world space
+    pub fn chunk_bounds(&self, chunk: ChunkId) -> BoundingSphere {
+        let (mut min_height, mut max_height) = (f32::MAX, f32::MIN);
+        for z in chunk.z * CHUNK_CELLS..=(chunk.z + 1) * CHUNK_CELLS {
+            for x in chunk.x * CHUNK_CELLS..=(chunk.x + 1) * CHUNK_CELLS {
+                min_height = min_height.min(self.height(x, z));
+                max_height = max_height.max(self.height(x, z));
+            }
+        }
+        let half = CHUNK_CELLS as f32 / 2.0;
+        let center = Vector3::new(
+            self.origin.x + chunk.x as f32 * CHUNK_CELLS as f32 + half,
+            (min_height + max_height) / 2.0,
+            self.origin.y + chunk.z as f32 * CHUNK_CELLS as f32 + half,
+        );
+        BoundingSphere {
+            center,
+            radius: Vector3::new(half, (max_height - min_height) / 2.0, half).norm(),
+        }
+    }
+    /// Builds the mesh of a chunk in world space with one triangle pair per `1 << lod` cells and a
+    /// skirt around the edge. Texture coordinates repeat once per cell.
+    pub fn chunk_mesh(&self, chunk: ChunkId, lod: u32) -> Mesh {
+        let step = 1 << lod.min(MAX_LOD);
+        let start = Vector2::new(chunk.x * CHUNK_CELLS, chunk.z * CHUNK_CELLS);
+        let vertex = |x: u32, z: u32| {
+            (
+                Vector3::new(
+                    self.origin.x + x as f32,
+                    self.height(x, z),
+                    self.origin.y + z as f32,
+                ),
+                Vector2::new(x as f32, z as f32),
+            )
+        };
+        let mut vertices = vec![];
+        for z in (start.y..start.y + CHUNK_CELLS).step_by(step as usize) {
+            for x in (start.x..start.x + CHUNK_CELLS).step_by(step as usize) {
+                let corners = [
+                    vertex(x, z),
+                    vertex(x + step, z + step),
+                    vertex(x + step, z),
+                    vertex(x, z),
+                    vertex(x, z + step),
+                    vertex(x + step, z + step),
+                ];
+                vertices.extend_from_slice(&corners);
+            }
+        }
+        //walks each edge of the chunk, hanging a quad below every segment
+        let depth = SKIRT_DEPTH * step as f32;
+        let end = start + Vector2::repeat(CHUNK_CELLS);
+        let edges = [
+            (start, Vector2::new(step, 0)),
+            (Vector2::new(start.x, end.y), Vector2::new(step, 0)),
+            (start, Vector2::new(0, step)),
+            (Vector2::new(end.x, start.y), Vector2::new(0, step)),
+        ];
+        for (edge_start, direction) in edges.iter() {
+            for i in 0..CHUNK_CELLS / step {
+                let a = edge_start + direction * i;
+                let b = a + direction;
+                let top_a = vertex(a.x, a.y);
+                let top_b = vertex(b.x, b.y);
+                let bottom_a = (top_a.0 - Vector3::new(0.0, depth, 0.0), top_a.1);
+                let bottom_b = (top_b.0 - Vector3::new(0.0, depth, 0.0), top_b.1);
+                vertices.extend_from_slice(&[top_a, top_b, bottom_a, top_b, bottom_b, bottom_a]);
+            }
+        }
+        Mesh { vertices }
+    }
+}
+/// Kinds of marking painted on the terrain overlay, each kept in one channel of its texture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverlayLayer {
+    /// Corduroy left by grooming machines
+    Groomed,
+    /// Packed snow skiers have passed over
+    Tracks,
+    Moguls,
+    /// Edges of marked runs
+    Boundary,
+}
+impl OverlayLayer {
+    /// Gets the layer called "groomed", "tracks", "moguls" or "boundary"
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "groomed" => Some(Self::Groomed),
+            "tracks" => Some(Self::Tracks),
+            "moguls" => Some(Self::Moguls),
+            "boundary" => Some(Self::Boundary),
+            _ => None,
+        }
+    }
+    fn channel(&self) -> usize {
+        match self {
+            Self::Groomed => 0,
+            Self::Tracks => 1,
+            Self::Moguls => 2,
+            Self::Boundary => 3,
+        }
+    }
+}
+/// Texture stretched over the terrain that gameplay paints markings into. Only the texels painted
+/// since the last upload are sent to the gpu.
+pub struct TerrainOverlay {
+    texture: RGBATexture,
+    origin: Vector2<f32>,
+    /// Texels per world unit
+    resolution: u32,
+    /// Smallest and largest texel painted since `take_dirty_region` was last called
+    dirty: Option<(Vector2<u32>, Vector2<u32>)>,
+}
+impl TerrainOverlay {
+    /// Blank overlay covering `terrain` with `resolution` texels per world unit. The resolution
+    /// can not be 0.
+    pub fn new(terrain: &Terrain, resolution: u32) -> Result<Self, String> {
+        if resolution == 0 {
+            return Err("terrain overlay needs at least one texel per unit".to_string());
+        }
+        Ok(Self {
+            texture: RGBATexture::constant_color(
+                Vector4::new(0, 0, 0, 0),
+                terrain.cells * resolution,
+            ),
+            origin: terrain.origin(),
+            resolution,
+            dirty: None,
+        })
+    }
+    pub fn texture(&self) -> &RGBATexture {
+        &self.texture
+    }
+    /// Corner and size of the covered area on the xz plane, as read by the terrain shader
+    pub fn bounds(&self) -> Vector4<f32> {
+        let size = self.texture.dimensions().map(|d| d as f32) / self.resolution as f32;
+        Vector4::new(self.origin.x, self.origin.y, size.x, size.y)
+    }
+    /// Paints `layer` towards `strength` within `radius` of `center`, softening towards the edge.
+    /// A strength of 0 erases.
+    pub fn paint(&mut self, layer: OverlayLayer, center: Vector2<f32>, radius: f32, strength: f32) {
+        self.paint_line(layer, center, center, radius * 2.0, strength)
+    }
+    /// Paints `layer` towards `strength` along the line from `from` to `to`, used for tracks and
+    /// run boundaries
+    pub fn paint_line(
+        &mut self,
+        layer: OverlayLayer,
+        from: Vector2<f32>,
+        to: Vector2<f32>,
+        width: f32,
+        strength: f32,
+    ) {
+        let radius = width / 2.0;
+        if radius <= 0.0 {
+            return;
+        }
+        let resolution = self.resolution as f32;
+        let dimensions = self.texture.dimensions();
+        let to_texel = |world: f32, origin: f32, max: u32| {
+            (((world - origin) * resolution).max(0.0) as u32).min(max - 1)
+        };
+        let min = Vector2::new(
+            to_texel(from.x.min(to.x) - radius, self.origin.x, dimensions.x),
+            to_texel(from.y.min(to.y) - radius, self.origin.y, dimensions.y),
+        );
+        let max = Vector2::new(
+            to_texel(from.x.max(to.x) + radius, self.origin.x, dimensions.x),
+            to_texel(from.y.max(to.y) + radius, self.origin.y, dimensions.y),
+        );
+        let segment = to - from;
+        let target = strength.clamp(0.0, 1.0) * 255.0;
+        let mut painted = false;
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let position = self.origin
+                    + (Vector2::new(x as f32, y as f32) + Vector2::repeat(0.5)) / resolution;
+                let along = if segment.norm_squared() > 0.0 {
+                    ((position - from).dot(&segment) / segment.norm_squared()).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let distance = (position - (from + segment * along)).norm();
+                //full strength in the middle half, fading out to the edge
+                let coverage = ((1.0 - distance / radius) * 2.0).clamp(0.0, 1.0);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let mut pixel = self.texture.get_pixel(x, y);
+                let old = pixel[layer.channel()] as f32;
+                pixel[layer.channel()] = (old + (target - old) * coverage).round() as u8;
+                self.texture.set_pixel(x, y, pixel);
+                painted = true;
+            }
+        }
+        if painted {
+            self.dirty = Some(match self.dirty {
+                Some((dirty_min, dirty_max)) => (dirty_min.inf(&min), dirty_max.sup(&max)),
+                None => (min, max),
+            });
+        }
+    }
+    /// Takes the texels painted since the last call along with where they start in the texture
+    pub fn take_dirty_region(&mut self) -> Option<(Vector2<u32>, RGBATexture)> {
+        let (min, max) = self.dirty.take()?;
+        let region = self
+            .texture
+            .sub_image(min, max - min + Vector2::new(1, 1))
+            .ok()?;
+        Some((min, region))
+    }
+}
+/// Gpu meshes of the terrain, built when a chunk is first drawn at a level of detail
+pub struct TerrainRenderer<E: GraphicsEngine> {
+    texture: E::RuntimeTexture,
+    /// Copy of the overlay on the gpu
+    overlay: E::RuntimeTexture,
+    meshes: HashMap<(ChunkId, u32), E::RuntimeMesh>,
+    /// Bounds of each chunk, only recomputed once the chunk is edited
+    bounds: HashMap<ChunkId, BoundingSphere>,
+}
+impl<E: GraphicsEngine> TerrainRenderer<E> {
+    /// The terrain is white until `set_texture` is called
+    pub fn new(engine: &mut E, overlay: &TerrainOverlay) -> Result<Self, E::ErrorType> {
+        let white =
+            RGBATexture::constant_color(Vector4::new(255, 255, 255, 255), Vector2::new(1, 1));
+        Ok(Self {
+            texture: engine.build_texture(white, TextureOptions::tiled())?,
+            overlay: engine.build_texture(overlay.texture().clone(), TextureOptions::default())?,
+            meshes: HashMap::new(),
+            bounds: HashMap::new(),
+        })
+    }
+    pub fn overlay(&self) -> &E::RuntimeTexture {
+        &self.overlay
+    }
+    /// Replaces the texture repeated over the terrain, such as once it has loaded
+    pub fn set_texture(
+        &mut self,
+        engine: &mut E,
+        texture: RGBATexture,
+    ) -> Result<(), E::ErrorType> {
+        self.texture = engine.build_texture(texture, TextureOptions::tiled())?;
+        Ok(())
+    }
+    /// Frees the meshes and bounds of edited chunks so only they are rebuilt and uploads newly
+    /// painted parts of the overlay
+    pub fn update(
+        &mut self,
+        engine: &mut E,
+        terrain: &mut Terrain,
+        overlay: &mut TerrainOverlay,
+    ) -> Result<(), E::ErrorType> {
+        let dirty = terrain.take_dirty();
+        if !dirty.is_empty() {
+            self.meshes.retain(|(chunk, _), _| !dirty.contains(chunk));
+            for chunk in dirty.iter() {
+                self.bounds.remove(chunk);
+            }
+        }
+        if let Some((offset, region)) = overlay.take_dirty_region() {
+            engine.update_texture_region(&self.overlay, offset, &region)?;
+        }
+        Ok(())
+    }
+    /// Adds the chunks in view to `queue` at a level of detail picked by their distance to the
+    /// camera. Chunks outside the view are skipped before their meshes are built.
+    pub fn queue(
+        &mut self,
+        engine: &mut E,
+        terrain: &Terrain,
+        view_projection: &Matrix4<f32>,
+        camera_position: Vector3<f32>,
+        material: &E::Material,
+        queue: &mut RenderQueue<E::RuntimeMesh, E::RuntimeTexture, E::Material>,
+    ) -> Result<(), E::ErrorType> {
+        let frustum = Frustum::new(view_projection);
+        let chunks = terrain.chunk_count();
+        for z in 0..chunks.y {
+            for x in 0..chunks.x {
+                let chunk = ChunkId { x, z };
+                let bounds = self
+                    .bounds
+                    .entry(chunk)
+                    .or_insert_with(|| terrain.chunk_bounds(chunk))
+                    .clone();
+                if !frustum.intersects(&bounds) {
+                    continue;
+                }
+                let distance = ((bounds.center - camera_position).norm() - bounds.radius).max(0.0);
+                let lod = lod_for_distance(distance);
+                let mesh = match self.meshes.get(&(chunk, lod)) {
+                    Some(mesh) => mesh.clone(),
+                    None => {
+                        let mesh = engine.build_mesh(terrain.chunk_mesh(chunk, lod))?;
+                        self.meshes.insert((chunk, lod), mesh.clone());
+                        mesh
+                    }
+                };
+                queue.push(DrawItem {
+                    mesh,
+                    texture: self.texture.clone(),
+                    material: material.clone(),
+                    transform: Matrix4::identity(),
+                    tint: Vector4::new(1.0, 1.0, 1.0, 1.0),
+                    layer: 0,
+                    blend: BlendMode::Opaque,
+                    bounds: Some(bounds),
+                });
+            }
+        }
+        Ok(())
+    }
+}
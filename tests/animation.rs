@@ -0,0 +1,266 @@
+use nalgebra::{Matrix4, UnitQuaternion, Vector2, Vector3, Vector4};
+use ski_tycoon::animation::{
+    slerp, AnimationClip, Animator, Channel, Character, CharacterRenderer, Interpolation, Joint,
+    JointTransform, Keyframes, Skeleton,
+};
+use ski_tycoon::cpu_engine::{CpuEngine, Fragment};
+use ski_tycoon::render_queue::RenderQueue;
+use ski_tycoon::{
+    GpuResource, GraphicsEngine, RGBATexture, ShaderDescription, SkinnedMesh, SkinnedVertex,
+};
+use std::rc::Rc;
+
+fn translation_channel(interpolation: Interpolation) -> Channel {
+    Channel {
+        joint: 1,
+        times: vec![1.0, 2.0, 4.0],
+        keyframes: Keyframes::Translation(vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(2.0, 4.0, 0.0),
+        ]),
+        interpolation,
+    }
+}
+
+fn translation_at(channel: &Channel, time: f32) -> Vector3<f32> {
+    let mut transform = JointTransform::identity();
+    channel.apply(time, &mut transform);
+    transform.translation
+}
+
+/// Root joint with a child one unit up, each with a rest pose matching its bind pose
+fn skeleton() -> Skeleton {
+    let joint = |name: &str, parent: Option<usize>, height: f32| Joint {
+        name: name.to_string(),
+        parent,
+        rest: JointTransform {
+            translation: Vector3::new(0.0, if parent.is_some() { 1.0 } else { 0.0 }, 0.0),
+            ..JointTransform::identity()
+        },
+        inverse_bind: Matrix4::new_translation(&Vector3::new(0.0, -height, 0.0)),
+    };
+    Skeleton::new(
+        vec![joint("root", None, 0.0), joint("arm", Some(0), 1.0)],
+        Matrix4::identity(),
+    )
+    .unwrap()
+}
+
+/// Clip that slides the arm along x to `x` and stays there
+fn slide(name: &str, x: f32) -> AnimationClip {
+    AnimationClip::new(
+        name,
+        true,
+        vec![Channel {
+            joint: 1,
+            times: vec![0.0, 1.0],
+            keyframes: Keyframes::Translation(vec![
+                Vector3::new(x, 1.0, 0.0),
+                Vector3::new(x, 1.0, 0.0),
+            ]),
+            interpolation: Interpolation::Linear,
+        }],
+    )
+}
+
+/// Looping clip moving the arm from 0 to 2 along x over two seconds
+fn swing() -> AnimationClip {
+    AnimationClip::new(
+        "swing",
+        true,
+        vec![Channel {
+            joint: 1,
+            times: vec![0.0, 2.0],
+            keyframes: Keyframes::Translation(vec![
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(2.0, 1.0, 0.0),
+            ]),
+            interpolation: Interpolation::Linear,
+        }],
+    )
+}
+
+/// Two vertices, one on the root and one on the arm
+fn character(clips: Vec<AnimationClip>) -> Character {
+    let vertex = |y: f32, joint: u16| SkinnedVertex {
+        position: Vector3::new(0.0, y, 0.0),
+        uv: Vector2::new(0.0, y),
+        joints: [joint, 0, 0, 0],
+        weights: [1.0, 0.0, 0.0, 0.0],
+    };
+    Character {
+        mesh: SkinnedMesh {
+            vertices: vec![vertex(0.0, 0), vertex(2.0, 1)],
+        },
+        texture: RGBATexture::constant_color(Vector4::new(255, 255, 255, 255), Vector2::new(1, 1)),
+        skeleton: skeleton(),
+        clips,
+    }
+}
+
+fn arm_x(animator: &Animator, clips: &[AnimationClip]) -> f32 {
+    animator.pose(&skeleton(), clips).joints[1].translation.x
+}
+
+#[test]
+fn linear_channels_interpolate_and_clamp() {
+    let channel = translation_channel(Interpolation::Linear);
+    assert_eq!(translation_at(&channel, 0.0), Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(translation_at(&channel, 1.5), Vector3::new(1.0, 0.0, 0.0));
+    assert_eq!(translation_at(&channel, 3.0), Vector3::new(2.0, 2.0, 0.0));
+    assert_eq!(translation_at(&channel, 10.0), Vector3::new(2.0, 4.0, 0.0));
+}
+
+#[test]
+fn step_channels_hold_each_keyframe() {
+    let channel = translation_channel(Interpolation::Step);
+    assert_eq!(translation_at(&channel, 0.5), Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(translation_at(&channel, 1.9), Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(translation_at(&channel, 2.0), Vector3::new(2.0, 0.0, 0.0));
+    assert_eq!(translation_at(&channel, 3.9), Vector3::new(2.0, 0.0, 0.0));
+    assert_eq!(translation_at(&channel, 4.0), Vector3::new(2.0, 4.0, 0.0));
+    assert_eq!(translation_at(&channel, 10.0), Vector3::new(2.0, 4.0, 0.0));
+}
+
+#[test]
+fn rotation_channels_only_change_rotation() {
+    let quarter = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+    let channel = Channel {
+        joint: 0,
+        times: vec![0.0, 1.0],
+        keyframes: Keyframes::Rotation(vec![UnitQuaternion::identity(), quarter]),
+        interpolation: Interpolation::Linear,
+    };
+    let mut transform = JointTransform::identity();
+    transform.translation = Vector3::new(1.0, 2.0, 3.0);
+    channel.apply(0.5, &mut transform);
+    assert!((transform.rotation.angle() - std::f32::consts::FRAC_PI_4).abs() < 1e-5);
+    assert_eq!(transform.translation, Vector3::new(1.0, 2.0, 3.0));
+}
+
+#[test]
+fn slerp_takes_the_shorter_way() {
+    let a = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.2);
+    let b = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.6);
+    assert!(slerp(&a, &b, 0.0).angle_to(&a) < 1e-5);
+    assert!(slerp(&a, &b, 1.0).angle_to(&b) < 1e-5);
+    assert!(
+        slerp(&a, &b, 0.5).angle_to(&UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.4))
+            < 1e-5
+    );
+    //the same rotation as b stored with the opposite sign still blends through 0.4
+    let flipped = UnitQuaternion::new_unchecked(-b.into_inner());
+    let halfway = slerp(&a, &flipped, 0.5);
+    assert!(halfway.angle_to(&UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.4)) < 1e-5);
+    //equal rotations do not divide by zero
+    assert!(slerp(&a, &a, 0.5).angle_to(&a) < 1e-5);
+}
+
+#[test]
+fn animator_cross_fades_between_clips() {
+    let clips = vec![slide("left", -1.0), slide("right", 1.0)];
+    let mut animator = Animator::new(0);
+    assert_eq!(arm_x(&animator, &clips), -1.0);
+    animator.play(1, 1.0);
+    assert_eq!(animator.clip(), 1);
+    assert_eq!(arm_x(&animator, &clips), -1.0);
+    animator.update(0.25);
+    assert!((arm_x(&animator, &clips) + 0.5).abs() < 1e-5);
+    animator.update(0.5);
+    assert!((arm_x(&animator, &clips) - 0.5).abs() < 1e-5);
+    animator.update(0.25);
+    assert_eq!(arm_x(&animator, &clips), 1.0);
+    //playing the current clip again does not restart it
+    animator.play(1, 1.0);
+    assert_eq!(animator.time(), 1.0);
+    //without a fade the new clip shows at once
+    animator.play(0, 0.0);
+    assert_eq!(arm_x(&animator, &clips), -1.0);
+}
+
+#[test]
+fn clips_played_once_hold_their_last_pose() {
+    let clips = vec![slide("still", 0.0), swing()];
+    let mut looping = Animator::new(0);
+    looping.play(1, 0.0);
+    let mut once = Animator::new(0);
+    once.play_once(1, 0.0);
+    for animator in [&mut looping, &mut once].iter_mut() {
+        animator.update(1.0);
+    }
+    assert_eq!(arm_x(&looping, &clips), 1.0);
+    assert_eq!(arm_x(&once, &clips), 1.0);
+    for animator in [&mut looping, &mut once].iter_mut() {
+        animator.update(2.5);
+    }
+    assert!((arm_x(&looping, &clips) - 1.5).abs() < 1e-5);
+    assert_eq!(arm_x(&once, &clips), 2.0);
+}
+
+#[test]
+fn missing_clips_show_the_rest_pose() {
+    let animator = Animator::new(3);
+    assert_eq!(animator.pose(&skeleton(), &[]), skeleton().rest_pose());
+}
+
+#[test]
+fn posed_mesh_moves_vertices_with_their_joints() {
+    let character = character(vec![slide("right", 1.0)]);
+    //the rest pose is the bind pose, so nothing moves
+    let rest = character.posed_mesh(&character.skeleton.rest_pose());
+    assert_eq!(rest.vertices[1].0, Vector3::new(0.0, 2.0, 0.0));
+    assert_eq!(rest.vertices[1].1, Vector2::new(0.0, 2.0));
+    //only the vertex on the arm follows it
+    let pose = character.clips[0].sample(&character.skeleton, 0.0);
+    let posed = character.posed_mesh(&pose);
+    assert_eq!(posed.vertices[0].0, Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(posed.vertices[1].0, Vector3::new(1.0, 2.0, 0.0));
+}
+
+#[test]
+fn distant_clips_played_once_hold_their_last_baked_frame() {
+    let mut engine = CpuEngine::new(Vector2::new(4, 4)).with_program(
+        "character",
+        Rc::new(|_: &Fragment| Vector4::new(1.0, 1.0, 1.0, 1.0)),
+    );
+    let material = engine
+        .build_material(&ShaderDescription {
+            name: "character".to_string(),
+            vertex_source: String::new(),
+            fragment_source: String::new(),
+            attributes: vec![],
+            uniforms: vec![],
+        })
+        .unwrap();
+    let mut renderer = CharacterRenderer::new(
+        &mut engine,
+        Rc::new(character(vec![slide("still", 0.0), swing()])),
+    )
+    .unwrap();
+    //the camera is far enough away for the baked frames to be used
+    let mut baked_mesh = |animator: &Animator| {
+        let mut queue = RenderQueue::new();
+        renderer.queue(
+            animator,
+            Matrix4::identity(),
+            Vector4::new(1.0, 1.0, 1.0, 1.0),
+            Vector3::new(0.0, 0.0, 100.0),
+            &material,
+            &mut queue,
+        );
+        let batches = queue.build(&Matrix4::identity());
+        assert_eq!(batches.len(), 1);
+        batches[0].mesh.id()
+    };
+    let mut once = Animator::new(0);
+    once.play_once(1, 0.0);
+    once.update(2.0);
+    let last = baked_mesh(&once);
+    once.update(7.3);
+    assert_eq!(baked_mesh(&once), last);
+    let mut looping = Animator::new(0);
+    looping.play(1, 0.0);
+    looping.update(9.3);
+    assert_ne!(baked_mesh(&looping), last);
+}
@@ -0,0 +1,288 @@
+use ski_tycoon::assets::{
+    placeholder_model, AssetData, AssetManager, AssetSource, AssetState, EmbeddedSource,
+    FileSource, Manifest, BUILT_IN_MANIFEST,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const SNOW: &[u8] = include_bytes!("../snow.png");
+const SKIER: &[u8] = include_bytes!("../skier.glb");
+
+/// Files served by a `MemorySource`, and every path the manager asked for
+#[derive(Default)]
+struct Files {
+    files: HashMap<String, Vec<u8>>,
+    requested: Vec<String>,
+    pending: Vec<String>,
+}
+/// Serves files from memory on the next poll after they are requested
+#[derive(Clone, Default)]
+struct MemorySource(Rc<RefCell<Files>>);
+impl MemorySource {
+    fn with(files: &[(&str, &[u8])]) -> Self {
+        let source = Self::default();
+        for (path, bytes) in files.iter() {
+            source
+                .0
+                .borrow_mut()
+                .files
+                .insert(path.to_string(), bytes.to_vec());
+        }
+        source
+    }
+    fn requested(&self) -> Vec<String> {
+        self.0.borrow().requested.clone()
+    }
+}
+impl AssetSource for MemorySource {
+    fn request(&mut self, path: &str) {
+        let mut files = self.0.borrow_mut();
+        files.requested.push(path.to_string());
+        files.pending.push(path.to_string());
+    }
+    fn poll(&mut self) -> Vec<(String, Result<Vec<u8>, String>)> {
+        let mut files = self.0.borrow_mut();
+        let pending = std::mem::take(&mut files.pending);
+        pending
+            .into_iter()
+            .map(|path| {
+                let bytes = files
+                    .files
+                    .get(&path)
+                    .cloned()
+                    .ok_or_else(|| format!("{} not found", path));
+                (path, bytes)
+            })
+            .collect()
+    }
+}
+
+fn manager(source: &MemorySource) -> AssetManager {
+    AssetManager::new(Box::new(source.clone()))
+}
+
+const MANIFEST: &str = "
+# everything on the mountain
+bundle base
+skier models/skier.glb
+snow textures/snow.png
+
+bundle lodge
+snow textures/snow.png
+cabin models/cabin.obj models/cabin.mtl textures/wood.png
+";
+
+#[test]
+fn manifest_lists_bundles() {
+    let manifest = Manifest::parse(MANIFEST).unwrap();
+    assert_eq!(manifest.bundles.len(), 2);
+    assert_eq!(manifest.bundles[0].name, "base");
+    assert_eq!(manifest.bundles[0].assets[1].id, "snow");
+    assert_eq!(
+        manifest.bundles[1].assets[1].files,
+        vec!["models/cabin.obj", "models/cabin.mtl", "textures/wood.png"]
+    );
+}
+
+#[test]
+fn manifest_errors_name_the_line() {
+    let error = Manifest::parse("skier skier.glb").unwrap_err();
+    assert!(error.starts_with("line 1"), "{}", error);
+    let error = Manifest::parse("bundle base\n\nskier").unwrap_err();
+    assert!(error.starts_with("line 3"), "{}", error);
+}
+
+#[test]
+fn requests_share_one_asset() {
+    let source = MemorySource::with(&[("snow.png", SNOW)]);
+    let mut assets = manager(&source);
+    let ids: Vec<_> = (0..500).map(|_| assets.request("snow.png")).collect();
+    assert!(ids.iter().all(|id| *id == ids[0]));
+    assert_eq!(assets.references(ids[0]), 500);
+    assert_eq!(source.requested(), vec!["snow.png"]);
+    for id in ids[1..].iter() {
+        assert!(!assets.release(*id));
+    }
+    assert!(assets.release(ids[0]));
+    assert_eq!(assets.state(ids[0]), None);
+    assert_eq!(
+        assets.take_unloaded(),
+        vec![(ids[0], "snow.png".to_string())]
+    );
+}
+
+#[test]
+fn assets_load_after_their_files_arrive() {
+    let source = MemorySource::with(&[("snow.png", SNOW), ("skier.glb", SKIER)]);
+    let mut assets = manager(&source);
+    let snow = assets.request("snow.png");
+    let skier = assets.request("skier.glb");
+    assert_eq!(assets.state(snow), Some(AssetState::Loading));
+    assert!(assets.data(snow).is_none());
+    assert_eq!(assets.loading_count(), 2);
+    assets.update();
+    assert_eq!(assets.state(snow), Some(AssetState::Loaded));
+    assert_eq!(assets.loading_count(), 0);
+    assert_eq!(assets.take_loaded(), vec![snow, skier]);
+    assert!(assets.take_loaded().is_empty());
+    match assets.data(snow) {
+        Some(AssetData::Texture(texture)) => assert!(texture.dimensions().x > 0),
+        _ => panic!("snow should be a texture"),
+    }
+    match assets.data(skier) {
+        Some(AssetData::Character(character)) => assert!(!character.clips.is_empty()),
+        _ => panic!("skier should be a character"),
+    }
+}
+
+#[test]
+fn missing_and_unknown_files_fail() {
+    let source = MemorySource::with(&[("notes.txt", b"hello")]);
+    let mut assets = manager(&source);
+    let missing = assets.request("missing.png");
+    let unknown = assets.request("notes.txt");
+    assets.update();
+    assert!(matches!(assets.state(missing), Some(AssetState::Failed(_))));
+    assert!(matches!(assets.state(unknown), Some(AssetState::Failed(_))));
+    assert!(assets.take_loaded().is_empty());
+}
+
+#[test]
+fn bundles_load_and_unload_together() {
+    let source = MemorySource::with(&[("models/skier.glb", SKIER), ("textures/snow.png", SNOW)]);
+    let mut assets = manager(&source);
+    assets.add_manifest(Manifest::parse(MANIFEST).unwrap());
+    assert!(assets.load_bundle("missing").is_err());
+    assets.load_bundle("base").unwrap();
+    assets.load_bundle("base").unwrap();
+    let snow = assets.request("snow");
+    assert_eq!(assets.references(snow), 2);
+    assets.load_bundle("lodge").unwrap();
+    assets.update();
+    assert_eq!(assets.state(snow), Some(AssetState::Loaded));
+    //textures/snow.png is in both bundles but is only read once
+    assert_eq!(
+        source.requested(),
+        vec![
+            "models/skier.glb",
+            "textures/snow.png",
+            "models/cabin.obj",
+            "models/cabin.mtl",
+            "textures/wood.png"
+        ]
+    );
+    assert!(assets.unload_bundle("base"));
+    assert!(!assets.unload_bundle("base"));
+    let unloaded: Vec<_> = assets
+        .take_unloaded()
+        .into_iter()
+        .map(|(_, key)| key)
+        .collect();
+    assert_eq!(unloaded, vec!["models/skier.glb"]);
+    assert!(assets.unload_bundle("lodge"));
+    assert_eq!(assets.state(snow), Some(AssetState::Loaded));
+    assets.release(snow);
+    assert_eq!(assets.state(snow), None);
+}
+
+#[test]
+fn obj_assets_find_their_other_files() {
+    let obj = b"mtllib cabin.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nusemtl wood\nf 1/1 2/2 3/3\n";
+    let mtl = b"newmtl wood\nKd 0.5 0.3 0.1\n";
+    let source = MemorySource::with(&[("models/cabin.obj", obj), ("models/cabin.mtl", mtl)]);
+    let mut assets = manager(&source);
+    assets.add_manifest(
+        Manifest::parse("bundle lodge\ncabin models/cabin.obj models/cabin.mtl").unwrap(),
+    );
+    let cabin = assets.request("cabin");
+    assets.update();
+    match assets.data(cabin) {
+        Some(AssetData::Models(models)) => {
+            assert_eq!(models[0].mesh.vertices.len(), 3);
+            assert_eq!(models[0].texture.pixels()[0].x, 127);
+        }
+        _ => panic!("cabin should load: {:?}", assets.state(cabin)),
+    }
+}
+
+#[test]
+fn inserted_assets_load_immediately() {
+    let mut assets = manager(&MemorySource::default());
+    assert!(assets.insert(vec![]).is_err());
+    let skier = assets
+        .insert(vec![("skier.glb".to_string(), SKIER.to_vec())])
+        .unwrap();
+    assert_eq!(assets.state(skier), Some(AssetState::Loaded));
+    let again = assets
+        .insert(vec![("skier.glb".to_string(), SKIER.to_vec())])
+        .unwrap();
+    assert_eq!(skier, again);
+    assert_eq!(assets.references(skier), 2);
+    //a different upload under the same name is a different asset
+    let other = assets
+        .insert(vec![("skier.glb".to_string(), vec![])])
+        .unwrap();
+    assert_ne!(skier, other);
+    assert!(matches!(assets.state(other), Some(AssetState::Failed(_))));
+    assert!(matches!(assets.data(skier), Some(AssetData::Character(_))));
+}
+
+#[test]
+fn built_in_assets_are_served_without_reading_files() {
+    let source = MemorySource::with(&[("snow.png", SNOW)]);
+    let mut assets = AssetManager::new(Box::new(EmbeddedSource::new(Box::new(source.clone()))));
+    assets.add_manifest(Manifest::parse(BUILT_IN_MANIFEST).unwrap());
+    let skier = assets.request("skier");
+    let snow = assets.request("snow");
+    let other = assets.request("snow.png");
+    assets.update();
+    assert!(matches!(assets.data(skier), Some(AssetData::Character(_))));
+    assert!(matches!(assets.data(snow), Some(AssetData::Texture(_))));
+    assert_eq!(assets.state(other), Some(AssetState::Loaded));
+    assert_eq!(source.requested(), vec!["snow.png"]);
+}
+
+#[test]
+fn files_arriving_after_release_are_ignored() {
+    let source = MemorySource::with(&[("snow.png", SNOW)]);
+    let mut assets = manager(&source);
+    let snow = assets.request("snow.png");
+    assets.release(snow);
+    assets.update();
+    assert!(assets.take_loaded().is_empty());
+    //requesting it again reads the file again
+    let snow = assets.request("snow.png");
+    assets.update();
+    assert_eq!(assets.state(snow), Some(AssetState::Loaded));
+    assert_eq!(source.requested().len(), 2);
+}
+
+#[test]
+fn placeholder_is_a_unit_box() {
+    let model = placeholder_model();
+    assert_eq!(model.mesh.vertices.len(), 36);
+    let heights: Vec<f32> = model.mesh.vertices.iter().map(|(p, _)| p.y).collect();
+    assert_eq!(heights.iter().cloned().fold(f32::MAX, f32::min), 0.0);
+    assert_eq!(heights.iter().cloned().fold(f32::MIN, f32::max), 1.0);
+}
+
+#[test]
+fn file_source_reads_in_the_background() {
+    let directory = std::env::temp_dir().join(format!("ski_tycoon_assets_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("snow.png"), SNOW).unwrap();
+    let mut assets = AssetManager::new(Box::new(FileSource::new(&directory)));
+    let snow = assets.request("snow.png");
+    let missing = assets.request("missing.png");
+    for _ in 0..500 {
+        assets.update();
+        if assets.loading_count() == 0 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(assets.state(snow), Some(AssetState::Loaded));
+    assert!(matches!(assets.state(missing), Some(AssetState::Failed(_))));
+}
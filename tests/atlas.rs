@@ -0,0 +1,148 @@
+use nalgebra::{Vector2, Vector3, Vector4};
+use ski_tycoon::atlas::{pack_models, AtlasBuilder};
+use ski_tycoon::{Mesh, Model, RGBATexture, TextureOptions};
+
+fn solid(value: u8, dimensions: Vector2<u32>) -> RGBATexture {
+    RGBATexture::constant_color(Vector4::new(value, value, value, 255), dimensions)
+}
+
+fn model(texture: RGBATexture, options: TextureOptions, uv: Vector2<f32>) -> Model {
+    Model {
+        mesh: Mesh {
+            vertices: vec![(Vector3::new(0.0, 0.0, 0.0), uv)],
+        },
+        texture,
+        texture_options: options,
+        shared_texture: None,
+    }
+}
+
+#[test]
+fn regions_do_not_overlap_and_hold_their_texture() {
+    let mut builder = AtlasBuilder::new().with_padding(2);
+    let sizes = [(8, 8), (3, 12), (16, 4), (5, 5), (1, 1)];
+    let ids: Vec<_> = sizes
+        .iter()
+        .enumerate()
+        .map(|(i, (x, y))| builder.add(solid(i as u8 * 40 + 10, Vector2::new(*x, *y))))
+        .collect();
+    let atlas = builder.build().unwrap();
+    let dimensions = atlas.texture.dimensions();
+    assert!(dimensions.x.is_power_of_two() && dimensions.y.is_power_of_two());
+    for (i, id) in ids.iter().enumerate() {
+        let region = atlas.region(*id);
+        assert_eq!(
+            region.max - region.min,
+            Vector2::new(sizes[i].0, sizes[i].1)
+        );
+        for other in ids[i + 1..].iter() {
+            let other = atlas.region(*other);
+            //the padding around each region must not overlap either
+            let apart = region.max.x + 4 <= other.min.x
+                || other.max.x + 4 <= region.min.x
+                || region.max.y + 4 <= other.min.y
+                || other.max.y + 4 <= region.min.y;
+            assert!(apart, "{:?} overlaps {:?}", region, other);
+        }
+        let value = i as u8 * 40 + 10;
+        for y in region.min.y..region.max.y {
+            for x in region.min.x..region.max.x {
+                assert_eq!(atlas.texture.get_pixel(x, y).x, value);
+            }
+        }
+    }
+}
+
+#[test]
+fn edges_bleed_into_padding() {
+    let mut texture = solid(0, Vector2::new(2, 2));
+    texture.set_pixel(0, 0, Vector4::new(200, 0, 0, 255));
+    let mut builder = AtlasBuilder::new().with_padding(3);
+    let id = builder.add(texture);
+    let atlas = builder.build().unwrap();
+    let region = atlas.region(id);
+    assert_eq!(region.min, Vector2::new(3, 3));
+    //the corner pixel fills the whole corner of the padding
+    for y in 0..3 {
+        for x in 0..3 {
+            assert_eq!(atlas.texture.get_pixel(x, y).x, 200);
+        }
+    }
+}
+
+#[test]
+fn too_wide_textures_fail() {
+    let mut builder = AtlasBuilder::new().with_max_width(16);
+    builder.add(solid(0, Vector2::new(15, 1)));
+    assert!(builder.build().is_err());
+}
+
+#[test]
+fn uvs_map_into_the_region() {
+    let mut builder = AtlasBuilder::new().with_padding(0);
+    builder.add(solid(0, Vector2::new(4, 4)));
+    let id = builder.add(solid(0, Vector2::new(4, 4)));
+    let atlas = builder.build().unwrap();
+    let region = atlas.region(id);
+    let size = atlas.texture.dimensions();
+    let uv = region.to_atlas_uv(Vector2::new(0.5, 1.0));
+    assert_eq!(
+        uv,
+        Vector2::new(
+            (region.min.x as f32 + 2.0) / size.x as f32,
+            region.max.y as f32 / size.y as f32
+        )
+    );
+    let mesh = Mesh {
+        vertices: vec![(Vector3::new(1.0, 2.0, 3.0), Vector2::new(0.0, 0.0))],
+    };
+    let remapped = atlas.remap_mesh(id, &mesh);
+    assert_eq!(remapped.vertices[0].0, Vector3::new(1.0, 2.0, 3.0));
+    assert_eq!(remapped.vertices[0].1, region.uv_min);
+}
+
+#[test]
+fn models_share_an_atlas_per_texture_options() {
+    let inside = Vector2::new(0.5, 0.5);
+    let models = vec![
+        model(
+            solid(1, Vector2::new(2, 2)),
+            TextureOptions::default(),
+            inside,
+        ),
+        model(
+            solid(2, Vector2::new(4, 2)),
+            TextureOptions::default(),
+            inside,
+        ),
+        model(
+            solid(3, Vector2::new(2, 2)),
+            TextureOptions::pixelated(),
+            inside,
+        ),
+        model(
+            solid(4, Vector2::new(2, 2)),
+            TextureOptions::tiled(),
+            inside,
+        ),
+        model(
+            solid(5, Vector2::new(2, 2)),
+            TextureOptions::default(),
+            Vector2::new(2.0, 0.5),
+        ),
+    ];
+    let packed = pack_models(models, "atlas");
+    assert_eq!(packed.len(), 5);
+    assert_eq!(packed[0].shared_texture, Some("atlas_0".to_string()));
+    assert_eq!(packed[1].shared_texture, Some("atlas_0".to_string()));
+    assert_eq!(
+        packed[0].texture.dimensions(),
+        packed[1].texture.dimensions()
+    );
+    assert_ne!(packed[0].mesh.vertices[0].1, inside);
+    //alone in its group, repeating, and sampling outside of its texture
+    for model in packed[2..].iter() {
+        assert_eq!(model.shared_texture, None);
+        assert_eq!(model.texture.dimensions(), Vector2::new(2, 2));
+    }
+}
@@ -0,0 +1,40 @@
+use nalgebra::{Vector3, Vector4};
+use ski_tycoon::Camera;
+
+/// Screen position of a world point
+fn project(camera: &Camera, point: Vector3<f32>) -> Vector3<f32> {
+    let clip = camera.get_mat() * Vector4::new(point.x, point.y, point.z, 1.0);
+    clip.xyz() / clip.w
+}
+
+#[test]
+fn eye_orbits_the_origin() {
+    let origin = Vector3::new(10.0, 2.0, -5.0);
+    let camera = Camera::new(origin, 20.0, 0.5, 0.3);
+    assert!(((camera.position() - origin).norm() - 20.0).abs() < 1e-3);
+    //the origin is in the middle of the screen wherever it is
+    let center = project(&camera, origin);
+    assert!(center.x.abs() < 1e-4 && center.y.abs() < 1e-4);
+    let moved = Camera::new(Vector3::new(0.0, 0.0, 0.0), 20.0, 0.5, 0.3);
+    let offset = Vector3::new(1.0, 1.0, 0.0);
+    assert!((project(&camera, origin + offset) - project(&moved, offset)).norm() < 1e-4);
+}
+
+#[test]
+fn screen_axes_stay_finite_looking_straight_down() {
+    let mut camera = Camera::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        10.0,
+        0.5,
+        std::f32::consts::FRAC_PI_2,
+    );
+    camera.rotate_theta(1.0);
+    for _ in 0..2 {
+        let (right, up) = camera.screen_axes();
+        assert!((right.norm() - 1.0).abs() < 1e-3, "{}", right);
+        assert!((up.norm() - 1.0).abs() < 1e-3, "{}", up);
+        assert!(right.dot(&up).abs() < 1e-3);
+        assert!(camera.get_mat().iter().all(|v| v.is_finite()));
+        camera.rotate_theta(-10.0);
+    }
+}
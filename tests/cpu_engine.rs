@@ -0,0 +1,164 @@
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+use ski_tycoon::cpu_engine::{CpuEngine, Fragment};
+use ski_tycoon::{
+    BlendMode, GraphicsEngine, Mesh, RGBATexture, ShaderDescription, TextureOptions, UniformValue,
+};
+use std::rc::Rc;
+
+/// Shader whose program draws the "red" uniform with half transparent blue
+fn shader() -> ShaderDescription {
+    ShaderDescription {
+        name: "flat".to_string(),
+        vertex_source: String::new(),
+        fragment_source: String::new(),
+        attributes: vec![],
+        uniforms: vec!["model".to_string(), "red".to_string()],
+    }
+}
+
+fn engine() -> CpuEngine {
+    let flat = |fragment: &Fragment| Vector4::new(fragment.float("red"), 0.0, 1.0, 0.5);
+    CpuEngine::new(Vector2::new(4, 4)).with_program("flat", Rc::new(flat))
+}
+
+/// Square covering the screen at a depth from -1 to 1
+fn square(depth: f32) -> Mesh {
+    let corner = |x: f32, y: f32| (Vector3::new(x, y, depth), Vector2::new(0.0, 0.0));
+    Mesh {
+        vertices: vec![
+            corner(-1.0, -1.0),
+            corner(1.0, -1.0),
+            corner(1.0, 1.0),
+            corner(-1.0, -1.0),
+            corner(1.0, 1.0),
+            corner(-1.0, 1.0),
+        ],
+    }
+}
+
+fn red(screen: &RGBATexture) -> Vec<u8> {
+    screen.pixels().iter().map(|p| p.x).collect()
+}
+
+#[test]
+fn materials_need_a_program() {
+    let mut engine = engine();
+    let mut missing = shader();
+    missing.name = "missing".to_string();
+    assert!(engine.build_material(&missing).is_err());
+    assert!(engine.build_material(&shader()).is_ok());
+}
+
+#[test]
+fn depth_test_keeps_the_nearest_fragment() {
+    let mut engine = engine();
+    let material = engine.build_material(&shader()).unwrap();
+    let near = engine.build_mesh(square(-0.5)).unwrap();
+    let far = engine.build_mesh(square(0.5)).unwrap();
+    engine.bind_material(&material);
+    engine.set_depth_test(true);
+    engine.clear_screen(Vector4::new(0.0, 0.0, 0.0, 1.0));
+    engine.send_uniform("red", UniformValue::Float(1.0));
+    engine.draw_mesh(&near);
+    engine.send_uniform("red", UniformValue::Float(0.2));
+    engine.draw_mesh(&far);
+    assert!(red(&engine.screen()).iter().all(|r| *r == 255));
+    engine.set_depth_test(false);
+    engine.draw_mesh(&far);
+    assert!(red(&engine.screen()).iter().all(|r| *r == 51));
+}
+
+#[test]
+fn alpha_blends_once_per_pixel() {
+    let mut engine = engine();
+    let material = engine.build_material(&shader()).unwrap();
+    let mesh = engine.build_mesh(square(0.0)).unwrap();
+    engine.bind_material(&material);
+    engine.clear_screen(Vector4::new(0.0, 0.0, 0.0, 1.0));
+    engine.set_blend_mode(BlendMode::Alpha);
+    engine.draw_mesh(&mesh);
+    //pixels on the diagonal shared by both triangles are not blended twice
+    let screen = engine.screen();
+    assert!(
+        screen.pixels().iter().all(|p| p.z == 128),
+        "{:?}",
+        screen.pixels()
+    );
+}
+
+#[test]
+fn clip_and_model_limit_drawing() {
+    let mut engine = engine();
+    let material = engine.build_material(&shader()).unwrap();
+    let mesh = engine.build_mesh(square(0.0)).unwrap();
+    engine.bind_material(&material);
+    engine.send_uniform("red", UniformValue::Float(1.0));
+    engine.set_clip(Some((Vector2::new(-1.0, -1.0), Vector2::new(0.0, 1.0))));
+    engine.draw_mesh(&mesh);
+    assert_eq!(
+        red(&engine.screen()),
+        vec![255, 255, 0, 0, 255, 255, 0, 0, 255, 255, 0, 0, 255, 255, 0, 0]
+    );
+    engine.set_clip(None);
+    engine.clear_screen(Vector4::new(0.0, 0.0, 0.0, 1.0));
+    engine.send_model_matrix(Matrix4::new_nonuniform_scaling(&Vector3::new(
+        1.0, 0.5, 1.0,
+    )));
+    engine.draw_mesh(&mesh);
+    assert_eq!(
+        red(&engine.screen()),
+        vec![0, 0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn resources_are_counted_until_deleted_or_dropped() {
+    let mut engine = engine();
+    let mesh = engine.build_mesh(square(0.0)).unwrap();
+    let mut texture = engine.build_float_texture(Vector2::new(2, 2)).unwrap();
+    let framebuffer = engine.build_framebuffer(&mut texture);
+    let report = engine.live_resources();
+    assert_eq!(
+        (report.meshes, report.textures, report.framebuffers),
+        (1, 1, 1)
+    );
+    engine.delete_mesh(&mesh);
+    engine.delete_mesh(&mesh);
+    drop(framebuffer);
+    drop(texture);
+    let report = engine.live_resources();
+    assert_eq!(
+        (report.meshes, report.textures, report.framebuffers),
+        (0, 0, 0)
+    );
+}
+
+#[test]
+fn texture_regions_out_of_bounds_are_rejected() {
+    let mut engine = engine();
+    let texture = engine
+        .build_texture(
+            RGBATexture::constant_color(Vector4::new(0, 0, 0, 255), Vector2::new(4, 4)),
+            TextureOptions::default(),
+        )
+        .unwrap();
+    let region = RGBATexture::constant_color(Vector4::new(255, 0, 0, 255), Vector2::new(2, 2));
+    assert!(engine
+        .update_texture_region(&texture, Vector2::new(2, 2), &region)
+        .is_ok());
+    assert!(engine
+        .update_texture_region(&texture, Vector2::new(3, 0), &region)
+        .is_err());
+    //offsets that would overflow when added to the size are out of bounds too
+    for offset in [
+        Vector2::new(u32::MAX, 0),
+        Vector2::new(0, u32::MAX - 1),
+        Vector2::new(u32::MAX, u32::MAX),
+    ]
+    .iter()
+    {
+        assert!(engine
+            .update_texture_region(&texture, *offset, &region)
+            .is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use nalgebra::{Vector3, Vector4};
+use ski_tycoon::debug_draw::DebugDraw;
+
+fn white() -> Vector4<f32> {
+    Vector4::new(1.0, 1.0, 1.0, 1.0)
+}
+
+fn enabled() -> DebugDraw {
+    let mut debug = DebugDraw::new();
+    debug.toggle();
+    debug
+}
+
+/// Screen axes of a camera looking down -z
+fn take(debug: &mut DebugDraw) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+    let vertices = debug.take_lines(Vector3::x(), Vector3::y());
+    assert_eq!(vertices.len() % 2, 0);
+    vertices
+        .chunks(2)
+        .map(|line| (line[0].position, line[1].position))
+        .collect()
+}
+
+#[test]
+fn disabled_ignores_shapes() {
+    let mut debug = DebugDraw::new();
+    assert!(!debug.is_enabled());
+    debug.line(Vector3::zeros(), Vector3::x(), white());
+    debug.text(Vector3::zeros(), "a", 1.0, white());
+    assert!(take(&mut debug).is_empty());
+}
+
+#[test]
+fn toggling_clears_pending_shapes() {
+    let mut debug = enabled();
+    debug.line(Vector3::zeros(), Vector3::x(), white());
+    debug.toggle();
+    debug.toggle();
+    assert!(take(&mut debug).is_empty());
+}
+
+#[test]
+fn lines_are_taken_once() {
+    let mut debug = enabled();
+    let color = Vector4::new(1.0, 0.0, 0.0, 1.0);
+    debug.line(Vector3::zeros(), Vector3::new(1.0, 2.0, 3.0), color);
+    let vertices = debug.take_lines(Vector3::x(), Vector3::y());
+    assert_eq!(vertices.len(), 2);
+    assert_eq!(vertices[1].position, Vector3::new(1.0, 2.0, 3.0));
+    assert_eq!(vertices[0].color, color);
+    assert!(take(&mut debug).is_empty());
+}
+
+#[test]
+fn box_has_twelve_unit_edges() {
+    let mut debug = enabled();
+    debug.aabb(Vector3::zeros(), Vector3::new(1.0, 1.0, 1.0), white());
+    let lines = take(&mut debug);
+    assert_eq!(lines.len(), 12);
+    assert!(lines
+        .iter()
+        .all(|(from, to)| ((to - from).norm() - 1.0).abs() < 1e-6));
+}
+
+#[test]
+fn sphere_points_lie_on_the_radius() {
+    let mut debug = enabled();
+    let center = Vector3::new(1.0, 2.0, 3.0);
+    debug.sphere(center, 2.0, white());
+    let lines = take(&mut debug);
+    assert_eq!(lines.len(), 3 * 24);
+    assert!(lines
+        .iter()
+        .all(|(from, to)| ((from - center).norm() - 2.0).abs() < 1e-4
+            && ((to - center).norm() - 2.0).abs() < 1e-4));
+}
+
+#[test]
+fn arrow_head_points_back_from_the_tip() {
+    let mut debug = enabled();
+    let to = Vector3::new(0.0, 10.0, 0.0);
+    debug.arrow(Vector3::zeros(), to, white());
+    let lines = take(&mut debug);
+    assert_eq!(lines.len(), 5);
+    for (from, end) in lines[1..].iter() {
+        assert_eq!(*from, to);
+        assert!(end.y < to.y);
+    }
+    //a zero length arrow has no head
+    debug.arrow(to, to, white());
+    assert_eq!(take(&mut debug).len(), 1);
+}
+
+#[test]
+fn grid_spans_its_size_on_the_ground() {
+    let mut debug = enabled();
+    debug.grid(Vector3::new(0.0, 1.0, 0.0), 4.0, 2, white());
+    let lines = take(&mut debug);
+    assert_eq!(lines.len(), 6);
+    assert!(lines
+        .iter()
+        .all(|(from, to)| from.y == 1.0 && to.y == 1.0 && ((to - from).norm() - 4.0).abs() < 1e-6));
+}
+
+#[test]
+fn text_is_laid_out_along_the_screen_axes() {
+    let mut debug = enabled();
+    debug.text(Vector3::new(0.0, 0.0, 5.0), "11", 1.0, white());
+    let lines = take(&mut debug);
+    assert!(!lines.is_empty());
+    //every line stays in the plane facing the camera and the second glyph is right of the first
+    assert!(lines.iter().all(|(from, to)| from.z == 5.0 && to.z == 5.0));
+    assert!(lines.iter().all(|(from, _)| from.y >= 0.0 && from.y <= 1.0));
+    let max_x = lines.iter().map(|(_, to)| to.x).fold(0.0, f32::max);
+    let min_x = lines
+        .iter()
+        .map(|(from, _)| from.x)
+        .fold(f32::MAX, f32::min);
+    assert!(max_x - min_x > 1.0);
+}
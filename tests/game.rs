@@ -0,0 +1,50 @@
+use nalgebra::{Vector3, Vector4};
+use ski_tycoon::assets::{AssetManager, EmbeddedSource, FileSource, Manifest, BUILT_IN_MANIFEST};
+use ski_tycoon::game::{GameObject, Prop, Skiier};
+
+fn manager() -> AssetManager {
+    let mut assets = AssetManager::new(Box::new(EmbeddedSource::new(Box::new(FileSource::new(
+        "missing",
+    )))));
+    assets.add_manifest(Manifest::parse(BUILT_IN_MANIFEST).unwrap());
+    assets
+}
+
+fn radius(object: &dyn GameObject<()>) -> f32 {
+    object.get_render_model().bounds.unwrap().radius
+}
+
+#[test]
+fn props_fit_their_collider_to_the_loaded_asset() {
+    let mut assets = manager();
+    let obj = b"v -4 0 -3\nv 4 0 -3\nv 0 6 3\nf 1 2 3\n";
+    let tower = assets
+        .insert(vec![("tower.obj".to_string(), obj.to_vec())])
+        .unwrap();
+    let mut prop = Prop::new::<()>("Tower", tower, Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(prop.get_render_model().asset, Some(tower));
+    //the placeholder is a unit box
+    assert!(radius(prop.as_ref()) < 2.0);
+    prop.asset_loaded(assets.data(tower).unwrap());
+    let loaded = radius(prop.as_ref());
+    assert!(loaded > 6.0, "{}", loaded);
+}
+
+#[test]
+fn skiiers_are_drawn_with_the_built_in_character() {
+    let mut assets = manager();
+    let skier = assets.request("skier");
+    assets.update();
+    let mut skiier = Skiier::new::<()>(
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector4::new(1.0, 0.0, 0.0, 1.0),
+        skier,
+        0.0,
+    );
+    assert_eq!(skiier.get_render_model().asset, Some(skier));
+    assert_eq!(skiier.get_render_model().animator.unwrap().clip(), 0);
+    let placeholder = radius(skiier.as_ref());
+    skiier.asset_loaded(assets.data(skier).unwrap());
+    assert_ne!(radius(skiier.as_ref()), placeholder);
+    assert!(skiier.powder_position().is_some());
+}
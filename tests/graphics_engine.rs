@@ -0,0 +1,36 @@
+use ski_tycoon::annotate_shader_log;
+
+/// Shader with a typo on line 3
+fn source() -> String {
+    "#version 300 es\nprecision highp float;\nout vec4 colr;\nvoid main() {\n    color = vec4(1.0);\n}"
+        .to_string()
+}
+
+#[test]
+fn errors_show_their_source_line() {
+    let log = "ERROR: 0:5: 'color' : undeclared identifier\nERROR: 0:3: 'colr' : unused output";
+    assert_eq!(
+        annotate_shader_log(log, &source()),
+        "ERROR: 0:5: 'color' : undeclared identifier\n    color = vec4(1.0);\n\
+         ERROR: 0:3: 'colr' : unused output\n    out vec4 colr;"
+    );
+}
+
+#[test]
+fn lines_without_a_source_line_pass_through() {
+    let log = [
+        "ERROR: 0:12: line past the end",
+        "ERROR: 0:0: line zero",
+        "ERROR: 0:-1: negative line",
+        "ERROR: 0:99999999999999999999999: too large",
+        "ERROR: 0:five: not a number",
+        "ERROR: 0",
+        "1 compilation errors. No code generated.",
+    ]
+    .join("\n");
+    assert_eq!(annotate_shader_log(&log, &source()), log);
+    assert_eq!(
+        annotate_shader_log("ERROR: 0:1: empty", ""),
+        "ERROR: 0:1: empty"
+    );
+}
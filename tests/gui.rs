@@ -0,0 +1,482 @@
+use generational_arena::Arena;
+use nalgebra::{Vector2, Vector3};
+use ski_tycoon::gui::{
+    BoundingBox, Button, ContainerKey, EventPacket, GetGuiOutput, GlobalHandler, GuiContainer,
+    GuiElement, GuiParent, GuiState, Message, Propagation, TextInput, Theme, Triangle, WidgetId,
+    Window,
+};
+use ski_tycoon::{Camera, Event, KeyPress, MouseButton, MouseClick};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Received = Rc<RefCell<Vec<Message>>>;
+
+/// Global handler that records every message and answers with `propagation`
+struct Recorder {
+    received: Received,
+    propagation: Propagation,
+}
+impl GlobalHandler for Recorder {
+    fn process_message(&mut self, _key: ContainerKey, message: &Message) -> Propagation {
+        self.received.borrow_mut().push(message.clone());
+        self.propagation
+    }
+}
+fn recorder(gui: &mut GuiState<()>, propagation: Propagation) -> Received {
+    let received = Received::default();
+    gui.add_global_handler(Box::new(Recorder {
+        received: received.clone(),
+        propagation,
+    }));
+    received
+}
+
+/// Game object that opens `gui` once and stops the button presses in `stops`
+struct Owner {
+    gui: Option<GuiContainer<()>>,
+    stops: Vec<WidgetId>,
+    received: Received,
+}
+impl GuiParent<()> for Owner {
+    fn get_gui(&mut self) -> GetGuiOutput<()> {
+        match self.gui.take() {
+            Some(container) => GetGuiOutput::Contianer(container),
+            None => GetGuiOutput::NoChange,
+        }
+    }
+    fn process_message(&mut self, message: &Message) -> Propagation {
+        self.received.borrow_mut().push(message.clone());
+        match message {
+            Message::ButtonPressed { id } if self.stops.contains(id) => Propagation::Stop,
+            _ => Propagation::Continue,
+        }
+    }
+    fn get_screen_collider(&self, _camera: &Camera) -> Vec<Triangle> {
+        vec![]
+    }
+    fn get_tooltip(&self) -> Option<String> {
+        None
+    }
+}
+
+fn camera() -> Camera {
+    Camera::new(Vector3::new(0.0, 0.0, 0.0), 10.0, 0.0, 0.5)
+}
+
+fn bounding_box(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> BoundingBox {
+    BoundingBox::new(Vector2::new(min_x, min_y), Vector2::new(max_x, max_y))
+}
+
+fn click(x: f32, y: f32) -> Event {
+    Event::MouseClick(MouseClick::new(Vector2::new(x, y), MouseButton::LeftClick))
+}
+
+fn key(key: &str) -> Event {
+    Event::KeyDown(KeyPress {
+        key: key.to_string(),
+        ctrl: false,
+        shift: false,
+    })
+}
+
+fn shift(key: &str) -> Event {
+    Event::KeyDown(KeyPress {
+        key: key.to_string(),
+        ctrl: false,
+        shift: true,
+    })
+}
+
+fn mouse_move(left_down: bool) -> Event {
+    Event::MouseMove {
+        delta_x: 0.0,
+        delta_y: 0.0,
+        delta_time_ms: 16.0,
+        buttons_pressed: if left_down {
+            vec![MouseButton::LeftClick]
+        } else {
+            vec![]
+        },
+    }
+}
+
+/// Runs one frame of `gui` with the mouse at `position` and no game objects
+fn frame(gui: &mut GuiState<()>, position: Vector2<f32>, events: Vec<Event>) {
+    let mut objects: Arena<Owner> = Arena::new();
+    frame_with(gui, &mut objects, position, events);
+}
+
+fn frame_with(
+    gui: &mut GuiState<()>,
+    objects: &mut Arena<Owner>,
+    position: Vector2<f32>,
+    events: Vec<Event>,
+) {
+    gui.game_loop(
+        EventPacket {
+            mouse_position: position,
+            events,
+            delta_time_ms: 16.0,
+        },
+        &camera(),
+        objects,
+    );
+}
+
+fn click_at(gui: &mut GuiState<()>, x: f32, y: f32) {
+    frame(gui, Vector2::new(x, y), vec![click(x, y)]);
+}
+
+fn pressed(received: &Received) -> Vec<u32> {
+    received
+        .borrow()
+        .iter()
+        .filter_map(|message| match message {
+            Message::ButtonPressed { id } => Some(id.0),
+            _ => None,
+        })
+        .collect()
+}
+
+fn button(id: u32, area: BoundingBox) -> Box<dyn GuiElement> {
+    Box::new(Button::new(WidgetId(id), area))
+}
+
+#[test]
+fn messages_bubble_from_objects_to_global_handlers() {
+    let mut gui = GuiState::new();
+    let global = recorder(&mut gui, Propagation::Continue);
+    let mut container = GuiContainer::new();
+    container.add_element(button(1, bounding_box(-0.5, -0.1, -0.3, 0.1)));
+    container.add_element(button(2, bounding_box(0.3, -0.1, 0.5, 0.1)));
+    let owned = Received::default();
+    let mut objects = Arena::new();
+    objects.insert(Owner {
+        gui: Some(container),
+        stops: vec![WidgetId(1)],
+        received: owned.clone(),
+    });
+    //the first frame opens the object's container
+    frame_with(&mut gui, &mut objects, Vector2::new(0.0, 0.9), vec![]);
+    for x in [-0.4, 0.4].iter() {
+        frame_with(
+            &mut gui,
+            &mut objects,
+            Vector2::new(*x, 0.0),
+            vec![click(*x, 0.0)],
+        );
+    }
+    assert_eq!(pressed(&owned), vec![1, 2]);
+    //the object stopped the first button
+    assert_eq!(pressed(&global), vec![2]);
+}
+
+#[test]
+fn stopped_messages_skip_later_global_handlers() {
+    let mut gui = GuiState::new();
+    let first = recorder(&mut gui, Propagation::Stop);
+    let second = recorder(&mut gui, Propagation::Continue);
+    let mut container = GuiContainer::new();
+    container.add_element(button(7, bounding_box(-0.1, -0.1, 0.1, 0.1)));
+    gui.add_global_container(container);
+    click_at(&mut gui, 0.0, 0.0);
+    assert_eq!(pressed(&first), vec![7]);
+    assert!(second.borrow().is_empty());
+}
+
+/// Gui with a text input holding `text` on the right and a button on the left
+fn text_input_gui(text: &str) -> (GuiState<()>, Received) {
+    let mut gui = GuiState::new();
+    let received = recorder(&mut gui, Propagation::Continue);
+    let mut container = GuiContainer::new();
+    container.add_element(Box::new(TextInput::new(
+        WidgetId(3),
+        bounding_box(0.0, -0.1, 0.8, 0.1),
+        text.to_string(),
+    )));
+    container.add_element(button(4, bounding_box(-0.8, -0.1, -0.2, 0.1)));
+    gui.add_global_container(container);
+    (gui, received)
+}
+
+fn last_text(received: &Received) -> Option<String> {
+    received
+        .borrow()
+        .iter()
+        .rev()
+        .find_map(|message| match message {
+            Message::TextChanged { text, .. } => Some(text.clone()),
+            _ => None,
+        })
+}
+
+#[test]
+fn keys_go_to_the_focused_element_or_the_game() {
+    let (mut gui, received) = text_input_gui("");
+    let position = Vector2::new(0.4, 0.0);
+    frame(&mut gui, position, vec![key("w")]);
+    assert!(!gui.has_focus());
+    let keys: Vec<String> = gui
+        .take_unhandled_keys()
+        .into_iter()
+        .map(|k| k.key)
+        .collect();
+    assert_eq!(keys, vec!["w"]);
+    assert!(gui.take_unhandled_keys().is_empty());
+    //keys pressed in the frame of the click go to the newly focused input
+    frame(
+        &mut gui,
+        position,
+        vec![click(0.4, 0.0), key("h"), key("i")],
+    );
+    assert!(gui.has_focus());
+    assert!(gui.take_unhandled_keys().is_empty());
+    assert_eq!(last_text(&received), Some("hi".to_string()));
+    frame(&mut gui, position, vec![key("Escape"), key("w")]);
+    assert!(!gui.has_focus());
+    let keys: Vec<String> = gui
+        .take_unhandled_keys()
+        .into_iter()
+        .map(|k| k.key)
+        .collect();
+    assert_eq!(keys, vec!["w"]);
+    assert_eq!(last_text(&received), Some("hi".to_string()));
+    //clicking on something that can not be focused also drops focus
+    frame(&mut gui, position, vec![click(0.4, 0.0)]);
+    assert!(gui.has_focus());
+    click_at(&mut gui, -0.5, 0.0);
+    assert!(!gui.has_focus());
+}
+
+/// Sends `events` to a focused text input starting with `text` and returns its text afterwards
+fn edit(text: &str, events: Vec<Event>) -> String {
+    let mut input = TextInput::new(
+        WidgetId(0),
+        bounding_box(0.0, 0.0, 1.0, 0.1),
+        text.to_string(),
+    );
+    input.process_focus(true);
+    for event in events.iter() {
+        input.process_event(event);
+    }
+    input.get_text()
+}
+
+#[test]
+fn text_input_edits_at_the_caret() {
+    assert_eq!(edit("ski", vec![key("s")]), "skis");
+    assert_eq!(
+        edit("sk", vec![key("ArrowLeft"), key("a"), key("End"), key("i")]),
+        "saki"
+    );
+    assert_eq!(edit("lift", vec![key("Home"), key("Delete")]), "ift");
+    assert_eq!(
+        edit("lift", vec![key("ArrowLeft"), key("Backspace")]),
+        "lit"
+    );
+    //named keys that are not handled do not insert their name
+    assert_eq!(edit("run", vec![key("Shift"), key("F5")]), "run");
+}
+
+#[test]
+fn text_input_edits_at_the_edges_of_the_text() {
+    assert_eq!(edit("ab", vec![key("Home"), key("Backspace")]), "ab");
+    assert_eq!(edit("ab", vec![key("Delete")]), "ab");
+    assert_eq!(edit("", vec![key("Backspace"), key("Delete")]), "");
+    assert_eq!(
+        edit("ab", vec![key("ArrowRight"), key("ArrowRight"), key("c")]),
+        "abc"
+    );
+    assert_eq!(
+        edit("ab", vec![key("Home"), key("ArrowLeft"), key("c")]),
+        "cab"
+    );
+}
+
+#[test]
+fn text_input_replaces_the_selection() {
+    assert_eq!(edit("chairlift", vec![shift("Home"), key("t")]), "t");
+    assert_eq!(
+        edit(
+            "chairlift",
+            vec![
+                key("ArrowLeft"),
+                key("ArrowLeft"),
+                key("ArrowLeft"),
+                key("ArrowLeft"),
+                shift("Home"),
+                key("Backspace")
+            ]
+        ),
+        "lift"
+    );
+    assert_eq!(
+        edit(
+            "gondola",
+            vec![key("Home"), shift("ArrowRight"), key("Delete")]
+        ),
+        "ondola"
+    );
+    //moving without shift drops the selection
+    assert_eq!(
+        edit("tbar", vec![shift("Home"), key("End"), key("s")]),
+        "tbars"
+    );
+    let select_all = Event::KeyDown(KeyPress {
+        key: "a".to_string(),
+        ctrl: true,
+        shift: false,
+    });
+    assert_eq!(edit("old", vec![select_all, key("x")]), "x");
+}
+
+#[test]
+fn text_input_pastes_without_control_characters() {
+    assert_eq!(edit("a", vec![Event::Paste("b\nc\td".to_string())]), "abcd");
+    assert_eq!(
+        edit(
+            "ski run",
+            vec![
+                shift("ArrowLeft"),
+                shift("ArrowLeft"),
+                shift("ArrowLeft"),
+                Event::Paste("lift".to_string())
+            ]
+        ),
+        "ski lift"
+    );
+}
+
+/// Window of buttons 0.6 wide and tall with its bottom left corner at `x`, `y`
+fn window(name: &str, id: u32, x: f32, y: f32) -> GuiContainer<()> {
+    let mut window = Window::new(name, name, bounding_box(x, y, x + 0.6, y + 0.6));
+    window.add_element(button(id, bounding_box(x, y, x + 0.6, y + 0.3)));
+    window.build()
+}
+
+#[test]
+fn clicked_windows_come_to_the_front() {
+    let mut gui = GuiState::new();
+    let received = recorder(&mut gui, Propagation::Continue);
+    gui.add_global_container(window("a", 1, -0.5, -0.5));
+    //opened windows cascade, so the second one ends up at -0.25, -0.45
+    gui.add_global_container(window("b", 2, -0.2, -0.4));
+    //where the buttons overlap the window opened last is on top
+    click_at(&mut gui, -0.1, -0.3);
+    assert_eq!(pressed(&received), vec![2]);
+    //clicking the part of the first window that is not covered raises it
+    click_at(&mut gui, -0.45, -0.48);
+    click_at(&mut gui, -0.1, -0.3);
+    assert_eq!(pressed(&received), vec![2, 1, 1]);
+}
+
+#[test]
+fn close_button_closes_the_window() {
+    let mut gui = GuiState::new();
+    let received = recorder(&mut gui, Propagation::Continue);
+    gui.add_global_container(window("lodge", 1, -0.3, -0.3));
+    assert!(gui.captures_scroll(&Vector2::new(0.0, -0.2)));
+    //the close button is in the top right corner of the title bar
+    click_at(&mut gui, 0.28, 0.28);
+    assert_eq!(*received.borrow(), vec![Message::WindowClosed]);
+    assert!(!gui.captures_scroll(&Vector2::new(0.0, -0.2)));
+    click_at(&mut gui, 0.0, -0.2);
+    assert!(pressed(&received).is_empty());
+}
+
+#[test]
+fn window_scrolling_stops_at_the_content() {
+    let mut gui = GuiState::new();
+    let received = recorder(&mut gui, Propagation::Continue);
+    let mut window = Window::new("runs", "Runs", bounding_box(-0.5, -0.5, 0.5, 0.5));
+    //the content area ends at 0.42 below the title bar, the second button starts out hidden
+    window.add_element(button(1, bounding_box(-0.5, 0.3, 0.5, 0.4)));
+    window.add_element(button(2, bounding_box(-0.5, -0.7, 0.5, -0.6)));
+    gui.add_global_container(window.build());
+    let scroll = |delta_y: f32| Event::Scroll {
+        delta_y,
+        delta_time_ms: 16.0,
+    };
+    click_at(&mut gui, 0.0, -0.45);
+    assert!(pressed(&received).is_empty());
+    //scrolling far down only goes as far as the bottom of the content
+    frame(&mut gui, Vector2::new(0.0, 0.0), vec![scroll(10000.0)]);
+    click_at(&mut gui, 0.0, -0.45);
+    assert_eq!(pressed(&received), vec![2]);
+    //the first button scrolled out of the content area, under the title bar
+    click_at(&mut gui, 0.0, 0.35);
+    assert_eq!(pressed(&received), vec![2]);
+    frame(&mut gui, Vector2::new(0.0, 0.0), vec![scroll(-10000.0)]);
+    click_at(&mut gui, 0.0, 0.35);
+    assert_eq!(pressed(&received), vec![2, 1]);
+    //scrolling outside of the window does nothing
+    frame(&mut gui, Vector2::new(0.9, 0.9), vec![scroll(10000.0)]);
+    click_at(&mut gui, 0.0, 0.35);
+    assert_eq!(pressed(&received), vec![2, 1, 1]);
+}
+
+/// Drags the window with its title bar at `from` by `by`
+fn drag(gui: &mut GuiState<()>, from: Vector2<f32>, by: Vector2<f32>) {
+    frame(gui, from, vec![mouse_move(true)]);
+    frame(gui, from + by, vec![mouse_move(true)]);
+    frame(gui, from + by, vec![mouse_move(false)]);
+}
+
+#[test]
+fn window_positions_survive_a_layout_round_trip() {
+    let mut gui = GuiState::new();
+    gui.add_global_container(window("lift info 2", 1, -0.3, -0.3));
+    drag(&mut gui, Vector2::new(0.0, 0.25), Vector2::new(0.5, -0.25));
+    let layout = gui.save_layout();
+    assert_eq!(layout.lines().count(), 1);
+    assert!(layout.starts_with("lift info 2 "), "{}", layout);
+    let mut restored = GuiState::new();
+    let received = recorder(&mut restored, Propagation::Continue);
+    restored.load_layout(&format!("broken\nnot a position\n{}", layout));
+    assert_eq!(restored.save_layout(), layout);
+    restored.add_global_container(window("lift info 2", 1, -0.3, -0.3));
+    //the button at -0.3..0.3, -0.3..0 is drawn where the window was dragged to
+    click_at(&mut restored, -0.2, -0.1);
+    assert!(pressed(&received).is_empty());
+    click_at(&mut restored, 0.7, -0.4);
+    assert_eq!(pressed(&received), vec![1]);
+}
+
+#[test]
+fn theme_changes_rebuild_every_container() {
+    let mut gui = GuiState::new();
+    let mut toolbar = GuiContainer::new();
+    toolbar.add_element(button(1, bounding_box(-1.0, 0.9, -0.8, 1.0)));
+    toolbar.add_element(button(2, bounding_box(-0.8, 0.9, -0.6, 1.0)));
+    let toolbar = gui.add_global_container(toolbar);
+    let lodge = gui.add_global_container(window("lodge", 3, -0.3, -0.3));
+    let mut objects: Arena<Owner> = Arena::new();
+    let mut run = |gui: &mut GuiState<()>| {
+        gui.game_loop(
+            EventPacket {
+                mouse_position: Vector2::new(0.9, -0.9),
+                events: vec![],
+                delta_time_ms: 16.0,
+            },
+            &camera(),
+            &mut objects,
+        )
+    };
+    assert!(run(&mut gui).is_empty());
+    gui.set_theme(Theme::light());
+    let models = run(&mut gui);
+    assert_eq!(models.keys().filter(|(key, _)| *key == toolbar).count(), 2);
+    //background, title bar, title, close button and the button in the window
+    assert_eq!(models.keys().filter(|(key, _)| *key == lodge).count(), 5);
+    let atlases: Vec<&String> = models
+        .values()
+        .flatten()
+        .filter_map(|model| model.shared_texture.as_ref())
+        .collect();
+    assert!(!atlases.is_empty());
+    assert!(atlases
+        .iter()
+        .all(|atlas| **atlas == Theme::light().atlas_name()));
+    //the gui is only rebuilt once
+    assert!(run(&mut gui).is_empty());
+}
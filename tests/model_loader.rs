@@ -0,0 +1,279 @@
+use gltf::mesh::Mode;
+use nalgebra::{Vector2, Vector3, Vector4};
+use ski_tycoon::model_loader::{load_gltf, load_obj, triangle_indices};
+use std::collections::HashMap;
+
+const SNOW: &[u8] = include_bytes!("../snow.png");
+
+/// Primitive of the mesh in a test glTF
+struct Primitive {
+    /// glTF primitive mode, 4 for triangles and 5 for strips
+    mode: u32,
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Option<Vec<u32>>,
+    material: usize,
+}
+/// Builds the buffer, views and accessors of a glTF
+#[derive(Default)]
+struct Accessors {
+    buffer: Vec<u8>,
+    json: Vec<String>,
+}
+impl Accessors {
+    /// Adds an accessor reading `values`, `width` floats or integers at a time
+    fn add(&mut self, values: &[[f32; 3]], width: usize, component_type: u32) -> usize {
+        for value in values.iter() {
+            for v in value[..width].iter() {
+                if component_type == 5126 {
+                    self.buffer.extend_from_slice(&v.to_le_bytes());
+                } else {
+                    self.buffer.extend_from_slice(&(*v as u32).to_le_bytes());
+                }
+            }
+        }
+        let kind = ["SCALAR", "VEC2", "VEC3"][width - 1];
+        //positions need their bounds
+        let bounds = if width == 3 {
+            let bound = |pick: fn(f32, f32) -> f32| {
+                let bound = values.iter().fold(values[0], |a, b| {
+                    [pick(a[0], b[0]), pick(a[1], b[1]), pick(a[2], b[2])]
+                });
+                format!("[{},{},{}]", bound[0], bound[1], bound[2])
+            };
+            format!(r#","min":{},"max":{}"#, bound(f32::min), bound(f32::max))
+        } else {
+            String::new()
+        };
+        self.json.push(format!(
+            r#"{{"bufferView":{},"componentType":{},"count":{},"type":"{}"{}}}"#,
+            self.json.len(),
+            component_type,
+            values.len(),
+            kind,
+            bounds
+        ));
+        self.json.len() - 1
+    }
+}
+/// Embedded glTF with one node moved 10 along x holding a mesh of `primitives`. Material 0 is
+/// red and material 1 blue.
+fn gltf(primitives: &[Primitive]) -> Vec<u8> {
+    let mut accessors = Accessors::default();
+    let mut views = vec![];
+    let mut view = |accessors: &Accessors, start: usize| {
+        views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{}}}"#,
+            start,
+            accessors.buffer.len() - start
+        ));
+    };
+    let mut primitive_json = vec![];
+    for primitive in primitives.iter() {
+        let start = accessors.buffer.len();
+        let position = accessors.add(&primitive.positions, 3, 5126);
+        view(&accessors, start);
+        let mut attributes = format!(r#""POSITION":{}"#, position);
+        if !primitive.uvs.is_empty() {
+            let start = accessors.buffer.len();
+            let uvs: Vec<[f32; 3]> = primitive.uvs.iter().map(|uv| [uv[0], uv[1], 0.0]).collect();
+            let uv = accessors.add(&uvs, 2, 5126);
+            view(&accessors, start);
+            attributes += &format!(r#","TEXCOORD_0":{}"#, uv);
+        }
+        let indices = match &primitive.indices {
+            Some(indices) => {
+                let start = accessors.buffer.len();
+                let values: Vec<[f32; 3]> = indices.iter().map(|i| [*i as f32, 0.0, 0.0]).collect();
+                let index = accessors.add(&values, 1, 5125);
+                view(&accessors, start);
+                format!(r#","indices":{}"#, index)
+            }
+            None => String::new(),
+        };
+        primitive_json.push(format!(
+            r#"{{"attributes":{{{}}},"mode":{},"material":{}{}}}"#,
+            attributes, primitive.mode, primitive.material, indices
+        ));
+    }
+    format!(
+        r#"{{"asset":{{"version":"2.0"}},"scene":0,"scenes":[{{"nodes":[0]}}],
+        "nodes":[{{"mesh":0,"translation":[10,0,0]}}],
+        "meshes":[{{"primitives":[{}]}}],
+        "materials":[{{"pbrMetallicRoughness":{{"baseColorFactor":[1,0,0,1]}}}},
+            {{"pbrMetallicRoughness":{{"baseColorFactor":[0,0,1,1]}}}}],
+        "buffers":[{{"byteLength":{},"uri":"data:application/octet-stream;base64,{}"}}],
+        "bufferViews":[{}],"accessors":[{}]}}"#,
+        primitive_json.join(","),
+        accessors.buffer.len(),
+        base64::encode(&accessors.buffer),
+        views.join(","),
+        accessors.json.join(",")
+    )
+    .into_bytes()
+}
+
+fn triangle(material: usize) -> Primitive {
+    Primitive {
+        mode: 4,
+        positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        uvs: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 0.25]],
+        indices: None,
+        material,
+    }
+}
+
+/// Normal of each triangle in a list of vertices
+fn normals(positions: &[Vector3<f32>]) -> Vec<Vector3<f32>> {
+    positions
+        .chunks_exact(3)
+        .map(|t| (t[1] - t[0]).cross(&(t[2] - t[0])).normalize())
+        .collect()
+}
+
+#[test]
+fn triangle_lists_drop_unfinished_triangles() {
+    assert_eq!(
+        triangle_indices(Mode::Triangles, Some(vec![3, 2, 1, 0, 4]), 5),
+        vec![3, 2, 1]
+    );
+    assert_eq!(
+        triangle_indices(Mode::Triangles, None, 6),
+        vec![0, 1, 2, 3, 4, 5]
+    );
+}
+
+#[test]
+fn strips_and_fans_are_triangulated_with_one_winding() {
+    assert_eq!(
+        triangle_indices(Mode::TriangleStrip, None, 5),
+        vec![0, 1, 2, 2, 1, 3, 2, 3, 4]
+    );
+    assert_eq!(
+        triangle_indices(Mode::TriangleFan, Some(vec![7, 8, 9, 10]), 4),
+        vec![7, 8, 9, 7, 9, 10]
+    );
+    assert!(triangle_indices(Mode::TriangleStrip, None, 2).is_empty());
+    assert!(triangle_indices(Mode::Lines, None, 4).is_empty());
+    assert!(triangle_indices(Mode::Points, None, 4).is_empty());
+}
+
+#[test]
+fn gltf_vertices_are_placed_by_their_node_with_flipped_uvs() {
+    let models = load_gltf(&gltf(&[triangle(0)])).unwrap();
+    assert_eq!(models.len(), 1);
+    let vertices = &models[0].mesh.vertices;
+    assert_eq!(vertices.len(), 3);
+    assert_eq!(vertices[2].0, Vector3::new(10.0, 1.0, 0.0));
+    //the texture's top row is at v 0 in glTF
+    assert_eq!(vertices[0].1, Vector2::new(0.0, 1.0));
+    assert_eq!(vertices[2].1, Vector2::new(0.0, 0.75));
+    assert_eq!(
+        models[0].texture.get_pixel(0, 0),
+        Vector4::new(255, 0, 0, 255)
+    );
+}
+
+#[test]
+fn gltf_primitives_sharing_a_material_are_merged() {
+    let models = load_gltf(&gltf(&[triangle(1), triangle(0), triangle(1)])).unwrap();
+    assert_eq!(models.len(), 2);
+    let sizes: Vec<usize> = models.iter().map(|m| m.mesh.vertices.len()).collect();
+    assert_eq!(sizes, vec![3, 6]);
+    assert_eq!(
+        models[1].texture.get_pixel(0, 0),
+        Vector4::new(0, 0, 255, 255)
+    );
+}
+
+#[test]
+fn gltf_strips_keep_their_winding() {
+    let strip = Primitive {
+        mode: 5,
+        positions: vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 2.0, 0.0],
+        ],
+        uvs: vec![],
+        indices: Some(vec![0, 1, 2, 3, 4]),
+        material: 0,
+    };
+    let models = load_gltf(&gltf(&[strip])).unwrap();
+    let positions: Vec<Vector3<f32>> = models[0].mesh.vertices.iter().map(|v| v.0).collect();
+    assert_eq!(positions.len(), 9);
+    for normal in normals(&positions) {
+        assert_eq!(normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+}
+
+#[test]
+fn gltf_errors_on_indices_past_the_vertices() {
+    let mut broken = triangle(0);
+    broken.indices = Some(vec![0, 1, 5]);
+    assert!(load_gltf(&gltf(&[broken])).is_err());
+    assert!(load_gltf(b"not a gltf").is_err());
+}
+
+const QUADS: &[u8] = b"mtllib quads.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 0.25
+vt 0 1
+usemtl snow
+f 1/1 2/2 3/3 4/4
+usemtl plain
+f 1/1 2/2 3/3
+";
+
+fn obj_files(mtl: &str) -> HashMap<String, Vec<u8>> {
+    let mut files = HashMap::new();
+    files.insert("quads.mtl".to_string(), mtl.as_bytes().to_vec());
+    files.insert("old snow.png".to_string(), SNOW.to_vec());
+    files
+}
+
+#[test]
+fn obj_faces_are_triangulated_per_material() {
+    let mtl = "newmtl snow\nKd 1 1 1\nmap_Kd -s 2 2 old snow.png\nnewmtl plain\nKd 0 1 0\n";
+    let models = load_obj(QUADS, &obj_files(mtl)).unwrap();
+    assert_eq!(models.len(), 2);
+    let snow = &models[0].mesh.vertices;
+    assert_eq!(snow.len(), 6);
+    let positions: Vec<Vector3<f32>> = snow.iter().map(|v| v.0).collect();
+    for normal in normals(&positions) {
+        assert_eq!(normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+    //obj uvs already start at the bottom
+    let uv = snow
+        .iter()
+        .find(|v| v.0 == Vector3::new(1.0, 1.0, 0.0))
+        .unwrap()
+        .1;
+    assert_eq!(uv, Vector2::new(1.0, 0.25));
+    //the texture name has a space in it and comes after the options
+    assert!(models[0].texture.dimensions().x > 1);
+    assert_eq!(
+        models[1].texture.get_pixel(0, 0),
+        Vector4::new(0, 255, 0, 255)
+    );
+}
+
+#[test]
+fn obj_texture_names_skip_options() {
+    let load = |map: &str| {
+        let mtl = format!("newmtl snow\nmap_Kd {}\nnewmtl plain\n", map);
+        load_obj(QUADS, &obj_files(&mtl)).map(|models| models[0].texture.dimensions().x)
+    };
+    assert!(load("old snow.png").unwrap() > 1);
+    assert!(load("-clamp on -o 0.5 old snow.png").unwrap() > 1);
+    assert!(load("-mm 0 1 -t 1 1 1 old snow.png").unwrap() > 1);
+    let missing = load("-s 2 new snow.png").err().unwrap();
+    assert!(missing.contains("\"new snow.png\""), "{}", missing);
+}
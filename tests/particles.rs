@@ -0,0 +1,162 @@
+use nalgebra::{Point3, Vector3, Vector4};
+use ski_tycoon::particles::{billboard_transform, Emitter, Particle, ParticleSystem, STEP};
+
+/// Advances one step per update like a game running at the simulation rate. Each update is a
+/// little longer than a step so float error never drops one.
+fn run(system: &mut ParticleSystem, steps: u32) {
+    for _ in 0..steps {
+        system.update(STEP * 1000.0 + 0.001);
+    }
+}
+
+fn system_with(emitter: Emitter) -> ParticleSystem {
+    let mut system = ParticleSystem::new();
+    system.add_emitter(emitter);
+    system
+}
+
+fn particles(system: &ParticleSystem) -> Vec<Particle> {
+    system
+        .emitters()
+        .flat_map(|emitter| emitter.particles().iter().cloned())
+        .collect()
+}
+
+#[test]
+fn same_seed_gives_same_particles() {
+    let emitter = || Emitter::snow_cannon(Vector3::new(1.0, 2.0, 3.0), Vector3::new(1.0, 1.0, 0.0));
+    let mut a = system_with(emitter().with_seed(7));
+    let mut b = system_with(emitter().with_seed(7));
+    let mut c = system_with(emitter().with_seed(8));
+    for _ in 0..30 {
+        run(&mut a, 2);
+        run(&mut b, 2);
+        run(&mut c, 2);
+    }
+    assert!(a.particle_count() > 0);
+    assert_eq!(particles(&a), particles(&b));
+    assert_ne!(particles(&a), particles(&c));
+}
+
+#[test]
+fn result_does_not_depend_on_frame_length() {
+    let emitter = || Emitter::powder(Vector3::new(0.0, 0.0, 0.0)).with_seed(3);
+    let mut fast = system_with(emitter());
+    let mut slow = system_with(emitter());
+    run(&mut fast, 20);
+    for _ in 0..5 {
+        slow.update(STEP * 4000.0 + 0.001);
+    }
+    let (fast, slow) = (particles(&fast), particles(&slow));
+    assert_eq!(fast.len(), slow.len());
+    for (a, b) in fast.iter().zip(slow.iter()) {
+        assert!((a.position - b.position).norm() < 1e-4);
+    }
+}
+
+#[test]
+fn spawns_at_rate_and_removes_old_particles() {
+    let mut system = system_with(Emitter::new(Vector3::new(0.0, 0.0, 0.0)).with_rate(60.0));
+    run(&mut system, 30);
+    assert_eq!(system.particle_count(), 30);
+    //particles live one second so the count levels off at one second of spawning, give or take
+    //the step a particle dies in
+    run(&mut system, 300);
+    assert!((60..=61).contains(&system.particle_count()));
+}
+
+#[test]
+fn max_particles_caps_spawning() {
+    let mut system = system_with(
+        Emitter::new(Vector3::new(0.0, 0.0, 0.0))
+            .with_rate(600.0)
+            .with_max_particles(25),
+    );
+    run(&mut system, 20);
+    assert_eq!(system.particle_count(), 25);
+}
+
+#[test]
+fn long_pauses_are_not_caught_up() {
+    let mut system = system_with(Emitter::new(Vector3::new(0.0, 0.0, 0.0)).with_rate(60.0));
+    system.update(10_000.0);
+    assert_eq!(system.particle_count(), 30);
+}
+
+#[test]
+fn gravity_accelerates_particles_down() {
+    let mut system = system_with(
+        Emitter::new(Vector3::new(0.0, 10.0, 0.0))
+            .with_rate(60.0)
+            .with_lifetime(5.0)
+            .with_gravity(9.8),
+    );
+    run(&mut system, 61);
+    let oldest = &particles(&system)[0];
+    assert!((oldest.velocity.y + 9.8).abs() < 0.01);
+    //semi implicit euler is close to the exact drop of half g t squared
+    assert!((10.0 - oldest.position.y - 4.9).abs() < 0.2);
+    assert_eq!(oldest.position.x, 0.0);
+}
+
+#[test]
+fn wind_carries_particles() {
+    let mut system = system_with(
+        Emitter::new(Vector3::new(0.0, 0.0, 0.0))
+            .with_rate(60.0)
+            .with_lifetime(10.0)
+            .with_wind_response(2.0),
+    );
+    system.wind = Vector3::new(3.0, 0.0, 0.0);
+    run(&mut system, 301);
+    let oldest = &particles(&system)[0];
+    assert!((oldest.velocity - system.wind).norm() < 0.01);
+    assert!(oldest.position.x > 10.0);
+}
+
+#[test]
+fn color_and_size_change_over_life() {
+    let emitter = Emitter::new(Vector3::new(0.0, 0.0, 0.0))
+        .with_color(
+            Vector4::new(1.0, 1.0, 1.0, 1.0),
+            Vector4::new(0.0, 0.5, 1.0, 0.0),
+        )
+        .with_size(0.2, 1.0);
+    assert_eq!(emitter.color_at(0.0), Vector4::new(1.0, 1.0, 1.0, 1.0));
+    assert_eq!(emitter.color_at(0.5), Vector4::new(0.5, 0.75, 1.0, 0.5));
+    assert_eq!(emitter.color_at(1.0), Vector4::new(0.0, 0.5, 1.0, 0.0));
+    assert!((emitter.size_at(0.5) - 0.6).abs() < 1e-6);
+}
+
+#[test]
+fn removed_emitter_takes_its_particles() {
+    let mut system = ParticleSystem::new();
+    let index = system.add_emitter(Emitter::new(Vector3::new(0.0, 0.0, 0.0)).with_rate(60.0));
+    run(&mut system, 10);
+    assert_eq!(system.particle_count(), 10);
+    assert!(system.remove_emitter(index).is_some());
+    assert_eq!(system.particle_count(), 0);
+}
+
+#[test]
+fn billboards_face_the_camera() {
+    let right = Vector3::new(0.0, 0.0, -1.0);
+    let up = Vector3::new(0.0, 1.0, 0.0);
+    let position = Vector3::new(4.0, 5.0, 6.0);
+    let transform = billboard_transform(position, 2.0, right, up);
+    let corner = transform.transform_point(&Point3::new(0.5, 0.5, 0.0));
+    assert_eq!(corner.coords, position + right + up);
+    assert_eq!(transform.column(3).xyz(), position);
+}
+
+#[test]
+fn long_pauses_simulate_a_bounded_number_of_steps() {
+    let emitter = || Emitter::powder(Vector3::new(0.0, 0.0, 0.0)).with_seed(3);
+    let mut paused = system_with(emitter());
+    let mut capped = system_with(emitter());
+    //a tab hidden for days catches up no further than a short pause
+    paused.update(1e12);
+    capped.update(60_000.0);
+    assert!(paused.particle_count() > 0);
+    assert_eq!(particles(&paused), particles(&capped));
+}
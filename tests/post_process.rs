@@ -0,0 +1,277 @@
+use nalgebra::{Vector2, Vector3, Vector4};
+use ski_tycoon::cpu_engine::{CpuEngine, Fragment};
+use ski_tycoon::post_process::{
+    cpu_programs, identity_lut, PassInput, PassOutput, PingPongBuffers, PostEffect,
+    PostProcessChain, PostProcessor, LUT_SIZE,
+};
+use ski_tycoon::{GraphicsEngine, Mesh, RGBATexture, ShaderDescription, TextureOptions};
+use std::rc::Rc;
+
+fn chain_with(effects: &[PostEffect]) -> PostProcessChain {
+    let mut chain = PostProcessChain::new();
+    for effect in PostEffect::ALL.iter() {
+        chain.set_enabled(*effect, effects.contains(effect));
+    }
+    chain
+}
+
+/// Quad covering the screen, like the one the game world is drawn with
+fn quad() -> Mesh {
+    let corner = |x: f32, y: f32| {
+        (
+            Vector3::new(x, y, 0.0),
+            Vector2::new(x.max(0.0), y.max(0.0)),
+        )
+    };
+    Mesh {
+        vertices: vec![
+            corner(1.0, -1.0),
+            corner(-1.0, -1.0),
+            corner(1.0, 1.0),
+            corner(-1.0, -1.0),
+            corner(-1.0, 1.0),
+            corner(1.0, 1.0),
+        ],
+    }
+}
+
+fn copy_shader() -> ShaderDescription {
+    ShaderDescription {
+        name: "copy".to_string(),
+        vertex_source: String::new(),
+        fragment_source: String::new(),
+        attributes: vec![],
+        uniforms: vec!["u_texture".to_string()],
+    }
+}
+
+fn engine(dimensions: Vector2<u32>) -> CpuEngine {
+    let copy = |fragment: &Fragment| fragment.sample("u_texture", fragment.uv);
+    cpu_programs().into_iter().fold(
+        CpuEngine::new(dimensions).with_program("copy", Rc::new(copy)),
+        |engine, (name, program)| engine.with_program(&name, program),
+    )
+}
+
+/// Draws `scene` to the screen of a cpu engine through a post processor running `effects`, after
+/// `configure` has changed its settings
+fn process_with(
+    scene: &RGBATexture,
+    effects: &[PostEffect],
+    configure: impl FnOnce(&mut PostProcessor<CpuEngine>, &mut CpuEngine),
+) -> RGBATexture {
+    let dimensions = scene.dimensions();
+    let mut engine = engine(dimensions);
+    let mut processor = PostProcessor::new(&mut engine, dimensions).unwrap();
+    processor.chain = chain_with(effects);
+    configure(&mut processor, &mut engine);
+    let scene = engine
+        .build_texture(scene.clone(), TextureOptions::default())
+        .unwrap();
+    let quad = engine.build_mesh(quad()).unwrap();
+    let copy_material = engine.build_material(&copy_shader()).unwrap();
+    processor.draw(&mut engine, &scene, &quad, &copy_material);
+    engine.screen()
+}
+
+fn process(scene: &RGBATexture, effects: &[PostEffect]) -> RGBATexture {
+    process_with(scene, effects, |_, _| ())
+}
+
+/// The tone mapping curve at full brightness
+fn tone_mapped_white() -> f32 {
+    2.54 / 3.16
+}
+
+fn gradient(size: u32) -> RGBATexture {
+    let mut texture =
+        RGBATexture::constant_color(Vector4::new(0, 0, 0, 255), Vector2::new(size, size));
+    for y in 0..size {
+        for x in 0..size {
+            let value = ((x + y) * 255 / (2 * size - 2)) as u8;
+            texture.set_pixel(x, y, Vector4::new(value, 255 - value, value / 2, 255));
+        }
+    }
+    texture
+}
+
+#[test]
+fn passes_ping_pong_and_end_on_screen() {
+    let chain = chain_with(&[PostEffect::Bloom, PostEffect::Fxaa, PostEffect::Vignette]);
+    let passes = chain.passes();
+    let effects: Vec<PostEffect> = passes.iter().map(|pass| pass.effect).collect();
+    assert_eq!(
+        effects,
+        vec![PostEffect::Bloom, PostEffect::Fxaa, PostEffect::Vignette]
+    );
+    assert_eq!(passes[0].input, PassInput::Scene);
+    assert_eq!(passes[0].output, PassOutput::Buffer(0));
+    assert_eq!(passes[1].input, PassInput::Buffer(0));
+    assert_eq!(passes[1].output, PassOutput::Buffer(1));
+    assert_eq!(passes[2].input, PassInput::Buffer(1));
+    assert_eq!(passes[2].output, PassOutput::Screen);
+}
+
+#[test]
+fn passes_follow_chain_order_regardless_of_enable_order() {
+    let mut chain = chain_with(&[]);
+    chain.set_enabled(PostEffect::Vignette, true);
+    chain.set_enabled(PostEffect::ToneMapping, true);
+    let effects: Vec<PostEffect> = chain.passes().iter().map(|pass| pass.effect).collect();
+    assert_eq!(effects, vec![PostEffect::ToneMapping, PostEffect::Vignette]);
+}
+
+#[test]
+fn single_pass_draws_scene_to_screen() {
+    let passes = chain_with(&[PostEffect::Fxaa]).passes();
+    assert_eq!(passes.len(), 1);
+    assert_eq!(passes[0].input, PassInput::Scene);
+    assert_eq!(passes[0].output, PassOutput::Screen);
+}
+
+#[test]
+fn empty_chain_leaves_image_unchanged() {
+    let chain = chain_with(&[]);
+    assert!(chain.passes().is_empty());
+    let image = gradient(8);
+    assert_eq!(process(&image, &[]).pixels(), image.pixels());
+}
+
+#[test]
+fn identity_lut_leaves_colors_unchanged() {
+    let lut = identity_lut();
+    assert_eq!(
+        lut.dimensions(),
+        Vector2::new(LUT_SIZE * LUT_SIZE, LUT_SIZE)
+    );
+    let image = gradient(8);
+    let graded = process(&image, &[PostEffect::ColorGrading]);
+    for (a, b) in graded.pixels().iter().zip(image.pixels().iter()) {
+        for i in 0..4 {
+            assert!((a[i] as i32 - b[i] as i32).abs() <= 1, "{} != {}", a, b);
+        }
+    }
+}
+
+#[test]
+fn color_grading_uses_lut() {
+    let lut = RGBATexture::constant_color(
+        Vector4::new(255, 0, 0, 255),
+        Vector2::new(LUT_SIZE * LUT_SIZE, LUT_SIZE),
+    );
+    let graded = process_with(
+        &gradient(4),
+        &[PostEffect::ColorGrading],
+        |processor, engine| processor.set_lut(engine, lut).unwrap(),
+    );
+    assert!(graded
+        .pixels()
+        .iter()
+        .all(|p| *p == Vector4::new(255, 0, 0, 255)));
+}
+
+#[test]
+fn fxaa_leaves_flat_color_unchanged() {
+    let image = RGBATexture::constant_color(Vector4::new(40, 120, 200, 255), Vector2::new(8, 8));
+    let result = process(&image, &[PostEffect::Fxaa]);
+    assert_eq!(result.pixels(), image.pixels());
+}
+
+#[test]
+fn fxaa_softens_hard_edges() {
+    //diagonal black and white edge
+    let mut image = RGBATexture::constant_color(Vector4::new(0, 0, 0, 255), Vector2::new(8, 8));
+    for y in 0..8 {
+        for x in 0..8 {
+            if x > y {
+                image.set_pixel(x, y, Vector4::new(255, 255, 255, 255));
+            }
+        }
+    }
+    let result = process(&image, &[PostEffect::Fxaa]);
+    let softened = result
+        .pixels()
+        .iter()
+        .filter(|p| p.x > 0 && p.x < 255)
+        .count();
+    assert!(softened > 0);
+}
+
+#[test]
+fn vignette_darkens_corners_only() {
+    let image = RGBATexture::constant_color(Vector4::new(200, 200, 200, 255), Vector2::new(16, 16));
+    let result = process(&image, &[PostEffect::Vignette]);
+    assert_eq!(result.get_pixel(8, 8), image.get_pixel(8, 8));
+    assert!(result.get_pixel(0, 0).x < 200);
+    assert_eq!(result.get_pixel(0, 0).w, 255);
+}
+
+#[test]
+fn bloom_spreads_bright_pixels() {
+    let mut image =
+        RGBATexture::constant_color(Vector4::new(50, 50, 50, 255), Vector2::new(16, 16));
+    image.set_pixel(8, 8, Vector4::new(255, 255, 255, 255));
+    let result = process(&image, &[PostEffect::Bloom]);
+    assert!(result.get_pixel(10, 8).x > 50);
+    assert_eq!(result.get_pixel(0, 0), image.get_pixel(0, 0));
+}
+
+#[test]
+fn tone_mapping_is_monotonic_and_exposure_brightens() {
+    let image = gradient(8);
+    let dim = process(&image, &[PostEffect::ToneMapping]);
+    let bright = process_with(&image, &[PostEffect::ToneMapping], |processor, _| {
+        processor.chain.settings.exposure = 2.0
+    });
+    for (a, b) in dim.pixels().iter().zip(bright.pixels().iter()) {
+        assert!(b.x >= a.x && b.y >= a.y && b.z >= a.z);
+    }
+    for x in 1..8 {
+        assert!(dim.get_pixel(x, 0).x >= dim.get_pixel(x - 1, 0).x);
+    }
+    assert!(dim
+        .pixels()
+        .iter()
+        .all(|p| p.y as f32 <= tone_mapped_white() * 255.0 + 0.5));
+}
+
+#[test]
+fn bloom_above_one_reaches_tone_mapping() {
+    //the ping pong buffers hold floats, so white brightened by bloom is not clamped before tone
+    //mapping compresses it
+    let image = RGBATexture::constant_color(Vector4::new(255, 255, 255, 255), Vector2::new(8, 8));
+    let result = process(&image, &[PostEffect::Bloom, PostEffect::ToneMapping]);
+    let white = (tone_mapped_white() * 255.0).round() as u8;
+    assert!(
+        result.pixels().iter().all(|p| p.x > white + 2),
+        "{}",
+        result.get_pixel(0, 0)
+    );
+}
+
+#[test]
+fn bloom_reaches_as_far_on_screen_at_any_scale() {
+    //a bright pixel glows on pixels up to 4 texels away at the size of the screen
+    let glow_at = |scale: u32, distance: u32| {
+        let size = 16 * scale;
+        let mut image =
+            RGBATexture::constant_color(Vector4::new(0, 0, 0, 255), Vector2::new(size, size));
+        image.set_pixel(size / 2, size / 2, Vector4::new(255, 255, 255, 255));
+        let mut engine = engine(image.dimensions());
+        let mut processor = PostProcessor::new(&mut engine, image.dimensions()).unwrap();
+        processor.chain = chain_with(&[PostEffect::Bloom]);
+        let buffers = PingPongBuffers::new(&mut engine, image.dimensions())
+            .unwrap()
+            .with_texel_scale(scale as f32);
+        let scene = engine
+            .build_texture(image, TextureOptions::default())
+            .unwrap();
+        let quad = engine.build_mesh(quad()).unwrap();
+        let copy_material = engine.build_material(&copy_shader()).unwrap();
+        processor.draw_to(&mut engine, &scene, &quad, &copy_material, &buffers, None);
+        engine.screen().get_pixel(size / 2 + distance, size / 2).x
+    };
+    assert!(glow_at(1, 4) > 0);
+    assert_eq!(glow_at(1, 8), 0);
+    assert!(glow_at(2, 8) > 0);
+}
@@ -0,0 +1,161 @@
+use nalgebra::{Matrix4, Perspective3, Vector3, Vector4};
+use ski_tycoon::render_queue::{BoundingSphere, DrawBatch, DrawItem, Frustum, RenderQueue};
+use ski_tycoon::{BlendMode, GpuResource};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Resource(u32);
+impl GpuResource for Resource {
+    fn id(&self) -> u32 {
+        self.0
+    }
+}
+type Queue = RenderQueue<Resource, Resource, Resource>;
+type Batch = DrawBatch<Resource, Resource, Resource>;
+
+/// Camera at the origin looking down negative z
+fn view_projection() -> Matrix4<f32> {
+    Perspective3::new(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0).to_homogeneous()
+}
+
+fn sphere(radius: f32) -> BoundingSphere {
+    BoundingSphere {
+        center: Vector3::new(0.0, 0.0, 0.0),
+        radius,
+    }
+}
+
+fn item(
+    mesh: u32,
+    texture: u32,
+    blend: BlendMode,
+    position: Vector3<f32>,
+) -> DrawItem<Resource, Resource, Resource> {
+    DrawItem {
+        mesh: Resource(mesh),
+        texture: Resource(texture),
+        material: Resource(0),
+        transform: Matrix4::new_translation(&position),
+        tint: Vector4::new(1.0, 1.0, 1.0, 1.0),
+        layer: 0,
+        blend,
+        bounds: Some(sphere(1.0)),
+    }
+}
+
+/// Distance along negative z of every instance, batch by batch
+fn depths(batches: &[Batch]) -> Vec<Vec<f32>> {
+    batches
+        .iter()
+        .map(|batch| {
+            batch
+                .instances
+                .iter()
+                .map(|instance| -instance.transform[(2, 3)])
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn frustum_keeps_spheres_touching_the_view() {
+    let frustum = Frustum::new(&view_projection());
+    let at = |x: f32, z: f32, radius: f32| BoundingSphere {
+        center: Vector3::new(x, 0.0, z),
+        radius,
+    };
+    assert!(frustum.intersects(&at(0.0, -10.0, 1.0)));
+    //behind the camera and past the far plane
+    assert!(!frustum.intersects(&at(0.0, 10.0, 1.0)));
+    assert!(!frustum.intersects(&at(0.0, -102.0, 1.0)));
+    //the field of view is 90 degrees so the side planes are at |x| = -z
+    assert!(!frustum.intersects(&at(12.0, -10.0, 1.0)));
+    assert!(frustum.intersects(&at(12.0, -10.0, 2.0)));
+}
+
+#[test]
+fn bounds_move_with_the_transform() {
+    let transform =
+        Matrix4::new_translation(&Vector3::new(1.0, 2.0, 3.0)) * Matrix4::new_scaling(2.0);
+    let moved = BoundingSphere::from_points(&[Vector3::new(0.0, 3.0, 4.0)]).transform(&transform);
+    assert_eq!(moved.center, Vector3::new(1.0, 2.0, 3.0));
+    assert_eq!(moved.radius, 10.0);
+}
+
+#[test]
+fn items_outside_the_view_are_culled() {
+    let mut queue = Queue::new();
+    queue.push(item(0, 0, BlendMode::Opaque, Vector3::new(0.0, 0.0, -5.0)));
+    queue.push(item(0, 0, BlendMode::Opaque, Vector3::new(0.0, 0.0, 5.0)));
+    let mut unbounded = item(0, 0, BlendMode::Opaque, Vector3::new(0.0, 0.0, 5.0));
+    unbounded.bounds = None;
+    queue.push(unbounded);
+    let batches = queue.build(&view_projection());
+    assert_eq!(depths(&batches), vec![vec![-5.0, 5.0]]);
+    queue.clear();
+    assert!(queue.build(&view_projection()).is_empty());
+}
+
+#[test]
+fn opaque_items_draw_front_to_back_in_batches() {
+    let mut queue = Queue::new();
+    for (mesh, z) in [(1, -30.0), (2, -10.0), (1, -20.0), (2, -40.0), (1, -5.0)].iter() {
+        queue.push(item(
+            *mesh,
+            0,
+            BlendMode::Opaque,
+            Vector3::new(0.0, 0.0, *z),
+        ));
+    }
+    let batches = queue.build(&view_projection());
+    let meshes: Vec<u32> = batches.iter().map(|batch| batch.mesh.0).collect();
+    assert_eq!(meshes, vec![1, 2]);
+    assert_eq!(
+        depths(&batches),
+        vec![vec![5.0, 20.0, 30.0], vec![10.0, 40.0]]
+    );
+}
+
+#[test]
+fn transparent_items_draw_back_to_front_after_opaque() {
+    let mut queue = Queue::new();
+    queue.push(item(1, 0, BlendMode::Alpha, Vector3::new(0.0, 0.0, -10.0)));
+    queue.push(item(2, 0, BlendMode::Alpha, Vector3::new(0.0, 0.0, -20.0)));
+    queue.push(item(1, 0, BlendMode::Alpha, Vector3::new(0.0, 0.0, -30.0)));
+    queue.push(item(1, 0, BlendMode::Alpha, Vector3::new(0.0, 0.0, -40.0)));
+    queue.push(item(3, 0, BlendMode::Opaque, Vector3::new(0.0, 0.0, -50.0)));
+    let batches = queue.build(&view_projection());
+    let meshes: Vec<u32> = batches.iter().map(|batch| batch.mesh.0).collect();
+    //only neighbors with the same state share a batch so the order is kept
+    assert_eq!(meshes, vec![3, 1, 2, 1]);
+    assert_eq!(
+        depths(&batches),
+        vec![vec![50.0], vec![40.0, 30.0], vec![20.0], vec![10.0]]
+    );
+}
+
+#[test]
+fn batches_split_by_texture_blend_and_layer() {
+    let mut queue = Queue::new();
+    let position = Vector3::new(0.0, 0.0, -10.0);
+    queue.push(item(1, 1, BlendMode::Opaque, position));
+    queue.push(item(1, 2, BlendMode::Opaque, position));
+    queue.push(item(1, 1, BlendMode::Cutout, position));
+    queue.push(item(1, 1, BlendMode::Opaque, position));
+    let mut overlay = item(1, 1, BlendMode::Opaque, position);
+    overlay.layer = -1;
+    queue.push(overlay);
+    let batches = queue.build(&view_projection());
+    let states: Vec<(BlendMode, u32, usize)> = batches
+        .iter()
+        .map(|batch| (batch.blend, batch.texture.0, batch.instances.len()))
+        .collect();
+    assert_eq!(
+        states,
+        vec![
+            (BlendMode::Opaque, 1, 1),
+            (BlendMode::Opaque, 1, 2),
+            (BlendMode::Opaque, 2, 1),
+            (BlendMode::Cutout, 1, 1),
+        ]
+    );
+}
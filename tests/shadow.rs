@@ -0,0 +1,101 @@
+use nalgebra::{Matrix4, Vector3, Vector4};
+use ski_tycoon::shadow::{light_view_projection, MAX_SHADOW_DISTANCE};
+use ski_tycoon::Camera;
+
+const MAP_SIZE: u32 = 2048;
+
+fn camera(origin: Vector3<f32>) -> Camera {
+    Camera::new(origin, 20.0, 0.7, 0.5)
+}
+
+fn sun() -> Vector3<f32> {
+    Vector3::new(0.3, -1.0, 0.4)
+}
+
+/// Corners of the camera's view, cut off `MAX_SHADOW_DISTANCE` from the near plane
+fn frustum_corners(view_projection: &Matrix4<f32>) -> Vec<Vector3<f32>> {
+    let inverse = view_projection.try_inverse().unwrap();
+    let unproject = |x: f32, y: f32, z: f32| {
+        let point = inverse * Vector4::new(x, y, z, 1.0);
+        point.xyz() / point.w
+    };
+    let mut corners = vec![];
+    for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].iter() {
+        let near = unproject(*x, *y, -1.0);
+        let far = unproject(*x, *y, 1.0);
+        let ray = far - near;
+        corners.push(near);
+        corners.push(near + ray.normalize() * ray.norm().min(MAX_SHADOW_DISTANCE));
+    }
+    corners
+}
+
+fn assert_covers_view(view_projection: &Matrix4<f32>, sun: &Vector3<f32>) {
+    let light = light_view_projection(view_projection, sun, MAP_SIZE);
+    for corner in frustum_corners(view_projection).iter() {
+        let clip = light * corner.push(1.0);
+        let clip = clip.xyz() / clip.w;
+        assert!(
+            clip.iter().all(|c| c.abs() <= 1.0 + 1e-4),
+            "{} is outside of the shadow map at {}",
+            corner,
+            clip
+        );
+    }
+}
+
+#[test]
+fn shadow_map_covers_the_near_part_of_the_view() {
+    for origin in [Vector3::new(0.0, 0.0, 0.0), Vector3::new(-35.0, 12.0, 80.0)].iter() {
+        assert_covers_view(&camera(*origin).get_mat(), &sun());
+    }
+    //a low sun from the side
+    assert_covers_view(
+        &camera(Vector3::new(0.0, 0.0, 0.0)).get_mat(),
+        &Vector3::new(-1.0, -0.2, 0.0),
+    );
+}
+
+#[test]
+fn sun_straight_up_or_down_gives_a_finite_projection() {
+    let view_projection = camera(Vector3::new(0.0, 0.0, 0.0)).get_mat();
+    for sun in [Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0)].iter() {
+        let light = light_view_projection(&view_projection, sun, MAP_SIZE);
+        assert!(light.iter().all(|v| v.is_finite()), "{}", light);
+        assert_covers_view(&view_projection, sun);
+    }
+}
+
+/// Width of the area the shadow map covers, in world units
+fn map_width(light: &Matrix4<f32>) -> f32 {
+    2.0 / light.row(0).transpose().xyz().norm()
+}
+
+#[test]
+fn small_camera_moves_keep_the_bounds_on_whole_texels() {
+    let start = Vector3::new(3.0, 0.0, -2.0);
+    let light = light_view_projection(&camera(start).get_mat(), &sun(), MAP_SIZE);
+    let texel = map_width(&light) / MAP_SIZE as f32;
+    let point = Vector4::new(1.0, 2.0, 3.0, 1.0);
+    let mut unchanged = 0;
+    for step in 1..10 {
+        let moved = start + Vector3::new(1.0, 0.2, -0.5).normalize() * texel * step as f32 * 0.1;
+        let moved_light = light_view_projection(&camera(moved).get_mat(), &sun(), MAP_SIZE);
+        //the size of the bounds stays the same, up to rounding in unprojecting the view
+        assert!((map_width(&moved_light) - map_width(&light)).abs() < texel * 0.05);
+        //and they only ever move by whole texels, so a fixed point stays on the same texel or
+        //jumps to the next one
+        let shift = (moved_light * point - light * point).xy() * MAP_SIZE as f32 / 2.0;
+        for offset in shift.iter() {
+            assert!(
+                (offset - offset.round()).abs() < 0.05 && offset.abs() < 1.05,
+                "moved by {} texels",
+                shift
+            );
+        }
+        if shift.norm() < 0.05 {
+            unchanged += 1;
+        }
+    }
+    assert!(unchanged > 0);
+}
@@ -0,0 +1,77 @@
+use nalgebra::Vector3;
+use ski_tycoon::sky::{Sky, Weather};
+
+fn sky_at(hour: f32) -> Sky {
+    Sky::new(hour, Weather::Clear)
+}
+
+fn close(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+    (a - b).norm() < 1e-4
+}
+
+#[test]
+fn sun_is_on_the_horizon_at_sunrise_and_sunset() {
+    assert!(sky_at(6.0).sun_position().y.abs() < 1e-4);
+    assert!(sky_at(18.0).sun_position().y.abs() < 1e-4);
+    assert!(sky_at(6.0).sun_position().x > 0.0);
+    assert!(sky_at(18.0).sun_position().x < 0.0);
+    let noon = sky_at(12.0).sun_position();
+    assert!(noon.y > sky_at(9.0).sun_position().y);
+    assert!(sky_at(0.0).sun_position().y < 0.0);
+    assert!((noon.norm() - 1.0).abs() < 1e-4);
+    assert_eq!(sky_at(12.0).sun_direction(), -noon);
+}
+
+#[test]
+fn sun_is_hidden_at_night_and_by_clouds() {
+    assert_eq!(sky_at(0.0).sun_visibility(), 0.0);
+    assert_eq!(sky_at(12.0).sun_visibility(), 1.0);
+    assert!(Sky::new(12.0, Weather::Overcast).sun_visibility() < 0.5);
+}
+
+#[test]
+fn sunset_colors_peak_when_the_sun_sets() {
+    let sunset = sky_at(18.0).colors();
+    assert!(close(sunset.horizon, Vector3::new(0.95, 0.5, 0.3)));
+    for hour in [16.5, 17.5, 18.5, 19.5].iter() {
+        assert!(sky_at(*hour).colors().horizon.x < sunset.horizon.x);
+    }
+    let sunrise = sky_at(6.0).colors();
+    assert!(close(sunrise.horizon, Vector3::new(0.95, 0.6, 0.4)));
+}
+
+#[test]
+fn colors_blend_between_keyframes_and_wrap_at_midnight() {
+    let day = sky_at(12.0).colors();
+    assert!(close(day.zenith, Vector3::new(0.2, 0.45, 0.85)));
+    let night = sky_at(22.0).colors();
+    assert_eq!(night, sky_at(2.0).colors());
+    let dawn = sky_at(7.5).colors();
+    assert!(close(
+        dawn.zenith,
+        Vector3::new(0.25, 0.35, 0.6).lerp(&Vector3::new(0.2, 0.45, 0.85), 0.5)
+    ));
+    assert!(day.light.x > night.light.x);
+}
+
+#[test]
+fn clouds_grey_the_sky() {
+    let clear = sky_at(12.0).colors();
+    let snowing = Sky::new(12.0, Weather::Snowing).colors();
+    let spread = |c: Vector3<f32>| c.max() - c.min();
+    assert!(spread(snowing.zenith) < spread(clear.zenith));
+    assert!(snowing.light.x < clear.light.x);
+    assert!(Sky::new(12.0, Weather::Snowing).fog_density() > sky_at(12.0).fog_density());
+}
+
+#[test]
+fn time_wraps_around_the_day() {
+    let mut sky = sky_at(25.0);
+    assert_eq!(sky.time_of_day(), 1.0);
+    sky.set_time_speed(1.0);
+    sky.update(23_500.0);
+    assert!((sky.time_of_day() - 0.5).abs() < 1e-3);
+    sky.set_time_speed(0.0);
+    sky.update(1000.0);
+    assert!((sky.time_of_day() - 0.5).abs() < 1e-3);
+}
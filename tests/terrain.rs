@@ -0,0 +1,273 @@
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+use ski_tycoon::cpu_engine::{CpuEngine, Fragment};
+use ski_tycoon::render_queue::RenderQueue;
+use ski_tycoon::terrain::{
+    lod_for_distance, ChunkId, OverlayLayer, Terrain, TerrainOverlay, TerrainRenderer, CHUNK_CELLS,
+    MAX_LOD,
+};
+use ski_tycoon::{GraphicsEngine, RGBATexture, ShaderDescription};
+use std::rc::Rc;
+
+/// Two by two chunks of rolling hills starting at the origin
+fn hills() -> Terrain {
+    Terrain::from_fn(Vector2::new(2, 2), Vector2::new(0.0, 0.0), |x, z| {
+        (x * 0.3).sin() * 4.0 + (z * 0.2).cos() * 3.0
+    })
+    .unwrap()
+}
+
+fn sorted(mut chunks: Vec<ChunkId>) -> Vec<(u32, u32)> {
+    chunks.sort_by_key(|chunk| (chunk.x, chunk.z));
+    chunks.iter().map(|chunk| (chunk.x, chunk.z)).collect()
+}
+
+#[test]
+fn detail_halves_as_distance_doubles() {
+    assert_eq!(lod_for_distance(0.0), 0);
+    assert_eq!(lod_for_distance(39.0), 0);
+    assert_eq!(lod_for_distance(40.0), 1);
+    assert_eq!(lod_for_distance(79.0), 1);
+    assert_eq!(lod_for_distance(80.0), 2);
+    assert_eq!(lod_for_distance(160.0), 3);
+    assert_eq!(lod_for_distance(1e6), MAX_LOD);
+}
+
+#[test]
+fn empty_terrains_and_overlays_are_rejected() {
+    for chunks in [Vector2::new(0, 0), Vector2::new(0, 2), Vector2::new(3, 0)].iter() {
+        assert!(Terrain::from_fn(*chunks, Vector2::new(0.0, 0.0), |_, _| 0.0).is_err());
+    }
+    assert!(TerrainOverlay::new(&hills(), 0).is_err());
+    //a single chunk still answers heights past its edges
+    let flat = Terrain::from_fn(Vector2::new(1, 1), Vector2::new(-4.0, 2.0), |_, _| 3.0).unwrap();
+    assert_eq!(flat.height_at(Vector2::new(100.0, -100.0)), 3.0);
+}
+
+#[test]
+fn edge_vertices_dirty_every_owner() {
+    let mut terrain = hills();
+    terrain.set_height(5, 5, 1.0);
+    assert_eq!(sorted(terrain.take_dirty()), vec![(0, 0)]);
+    assert!(terrain.take_dirty().is_empty());
+    //on the edge between two chunks along x
+    terrain.set_height(CHUNK_CELLS, 5, 1.0);
+    assert_eq!(sorted(terrain.take_dirty()), vec![(0, 0), (1, 0)]);
+    //corner shared by all four chunks
+    terrain.set_height(CHUNK_CELLS, CHUNK_CELLS, 1.0);
+    assert_eq!(
+        sorted(terrain.take_dirty()),
+        vec![(0, 0), (0, 1), (1, 0), (1, 1)]
+    );
+    //the far edge of the terrain only has one chunk
+    terrain.set_height(2 * CHUNK_CELLS, 0, 1.0);
+    assert_eq!(sorted(terrain.take_dirty()), vec![(1, 0)]);
+}
+
+#[test]
+fn neighbors_at_different_detail_meet_on_their_edge() {
+    let terrain = hills();
+    let edge_x = CHUNK_CELLS as f32;
+    let on_edge = |vertices: &[(Vector3<f32>, Vector2<f32>)]| -> Vec<Vector3<f32>> {
+        vertices
+            .iter()
+            .map(|(position, _)| *position)
+            .filter(|position| position.x == edge_x)
+            .collect()
+    };
+    let fine = terrain.chunk_mesh(ChunkId { x: 0, z: 0 }, 0);
+    let coarse = terrain.chunk_mesh(ChunkId { x: 1, z: 0 }, MAX_LOD);
+    let fine_edge = on_edge(&fine.vertices);
+    let coarse_edge = on_edge(&coarse.vertices);
+    //vertices on top of the surface lie on the terrain and the coarse ones are shared by the fine
+    //chunk, so only the skirts have to cover the gaps between them
+    for position in coarse_edge.iter() {
+        let height = terrain.height_at(Vector2::new(position.x, position.z));
+        if (position.y - height).abs() < 1e-4 {
+            assert!(fine_edge.contains(position), "{}", position);
+        } else {
+            assert!(position.y < height);
+        }
+    }
+    let skirt_depth = |edge: &[Vector3<f32>]| {
+        edge.iter()
+            .map(|p| terrain.height_at(Vector2::new(p.x, p.z)) - p.y)
+            .fold(0.0, f32::max)
+    };
+    assert!(skirt_depth(&coarse_edge) > skirt_depth(&fine_edge));
+}
+
+#[test]
+fn chunk_meshes_shrink_with_detail() {
+    let terrain = hills();
+    let chunk = ChunkId { x: 1, z: 1 };
+    let counts: Vec<usize> = (0..=MAX_LOD)
+        .map(|lod| terrain.chunk_mesh(chunk, lod).vertices.len())
+        .collect();
+    for lod in 1..counts.len() {
+        assert!(counts[lod] < counts[lod - 1]);
+    }
+    let cells = (CHUNK_CELLS * CHUNK_CELLS) as usize;
+    let skirts = 4 * CHUNK_CELLS as usize;
+    assert_eq!(counts[0], (cells + skirts) * 6);
+}
+
+#[test]
+fn chunk_bounds_contain_the_surface() {
+    let terrain = hills();
+    let chunk = ChunkId { x: 1, z: 0 };
+    let bounds = terrain.chunk_bounds(chunk);
+    for z in 0..=CHUNK_CELLS {
+        for x in CHUNK_CELLS..=2 * CHUNK_CELLS {
+            let point = Vector3::new(x as f32, terrain.height(x, z), z as f32);
+            assert!((point - bounds.center).norm() <= bounds.radius + 1e-4);
+        }
+    }
+}
+
+#[test]
+fn renderer_culls_chunks_with_their_edited_bounds() {
+    let flat = |_: &Fragment| Vector4::new(1.0, 1.0, 1.0, 1.0);
+    let mut engine = CpuEngine::new(Vector2::new(4, 4)).with_program("terrain", Rc::new(flat));
+    let material = engine
+        .build_material(&ShaderDescription {
+            name: "terrain".to_string(),
+            vertex_source: String::new(),
+            fragment_source: String::new(),
+            attributes: vec![],
+            uniforms: vec![],
+        })
+        .unwrap();
+    let mut terrain = hills();
+    let mut overlay = TerrainOverlay::new(&terrain, 1).unwrap();
+    let mut renderer = TerrainRenderer::new(&mut engine, &overlay).unwrap();
+    let texture = RGBATexture::constant_color(Vector4::new(200, 200, 255, 255), Vector2::new(2, 2));
+    renderer.set_texture(&mut engine, texture).unwrap();
+    //looking down on the whole terrain from 100 units above, seeing 200 units down
+    let eye = Vector3::new(32.0, 100.0, 32.0);
+    let view_projection = Matrix4::new_orthographic(-40.0, 40.0, -40.0, 40.0, 0.1, 200.0)
+        * Matrix4::look_at_rh(
+            &eye.into(),
+            &Vector3::new(32.0, 0.0, 32.0).into(),
+            &Vector3::new(0.0, 0.0, -1.0),
+        );
+    let drawn_chunks =
+        |engine: &mut CpuEngine, renderer: &mut TerrainRenderer<CpuEngine>, terrain: &Terrain| {
+            let mut queue = RenderQueue::new();
+            renderer
+                .queue(
+                    engine,
+                    terrain,
+                    &view_projection,
+                    eye,
+                    &material,
+                    &mut queue,
+                )
+                .unwrap();
+            queue
+                .build(&view_projection)
+                .iter()
+                .map(|batch| batch.instances.len())
+                .sum::<usize>()
+        };
+    assert_eq!(drawn_chunks(&mut engine, &mut renderer, &terrain), 4);
+    //lifting a whole chunk above the camera takes it out of view once the renderer sees the edit
+    for z in 0..=CHUNK_CELLS {
+        for x in 0..=CHUNK_CELLS {
+            terrain.set_height(x, z, 1000.0);
+        }
+    }
+    renderer
+        .update(&mut engine, &mut terrain, &mut overlay)
+        .unwrap();
+    assert_eq!(drawn_chunks(&mut engine, &mut renderer, &terrain), 3);
+}
+
+/// Overlay with two texels per unit over the hills
+fn overlay() -> TerrainOverlay {
+    TerrainOverlay::new(&hills(), 2).unwrap()
+}
+
+#[test]
+fn overlay_covers_the_terrain() {
+    let overlay = overlay();
+    assert_eq!(
+        overlay.texture().dimensions(),
+        Vector2::new(4 * CHUNK_CELLS, 4 * CHUNK_CELLS)
+    );
+    let size = 2.0 * CHUNK_CELLS as f32;
+    assert_eq!(overlay.bounds(), Vector4::new(0.0, 0.0, size, size));
+}
+
+#[test]
+fn painting_fills_its_layer_and_fades_to_the_edge() {
+    let mut overlay = overlay();
+    overlay.paint(OverlayLayer::Tracks, Vector2::new(10.0, 10.0), 2.0, 1.0);
+    let texture = overlay.texture();
+    let center = texture.get_pixel(20, 20);
+    //only the tracks channel is painted
+    assert_eq!(center, Vector4::new(0, 255, 0, 0));
+    //softer towards the edge and untouched past the radius
+    assert!(texture.get_pixel(23, 20).y < 255 && texture.get_pixel(23, 20).y > 0);
+    assert_eq!(texture.get_pixel(25, 20).y, 0);
+    //strength 0 erases
+    overlay.paint(OverlayLayer::Tracks, Vector2::new(10.0, 10.0), 2.0, 0.0);
+    assert_eq!(overlay.texture().get_pixel(20, 20).y, 0);
+}
+
+#[test]
+fn lines_paint_along_their_length() {
+    let mut overlay = overlay();
+    overlay.paint_line(
+        OverlayLayer::Boundary,
+        Vector2::new(5.0, 5.0),
+        Vector2::new(25.0, 5.0),
+        1.0,
+        1.0,
+    );
+    let texture = overlay.texture();
+    for x in (10..50).step_by(5) {
+        assert_eq!(texture.get_pixel(x, 10).w, 255, "{}", x);
+    }
+    assert_eq!(texture.get_pixel(30, 14).w, 0);
+}
+
+#[test]
+fn zero_width_paints_nothing() {
+    let mut overlay = overlay();
+    let before = overlay.texture().clone();
+    overlay.paint_line(
+        OverlayLayer::Groomed,
+        Vector2::new(5.0, 5.0),
+        Vector2::new(6.0, 5.0),
+        0.0,
+        1.0,
+    );
+    //right on a texel center, where the distance to the line is 0
+    overlay.paint(OverlayLayer::Groomed, Vector2::new(5.25, 5.25), 0.0, 1.0);
+    overlay.paint(OverlayLayer::Groomed, Vector2::new(5.0, 5.0), -1.0, 1.0);
+    assert_eq!(overlay.texture().pixels(), before.pixels());
+    assert!(overlay.take_dirty_region().is_none());
+}
+
+#[test]
+fn dirty_region_grows_around_paint_until_taken() {
+    let mut overlay = overlay();
+    assert!(overlay.take_dirty_region().is_none());
+    overlay.paint(OverlayLayer::Moguls, Vector2::new(5.0, 5.0), 1.0, 1.0);
+    overlay.paint(OverlayLayer::Moguls, Vector2::new(20.0, 10.0), 1.0, 1.0);
+    let (offset, region) = overlay.take_dirty_region().unwrap();
+    //both spots are inside the region, which matches the overlay where it was taken from
+    for spot in [Vector2::new(10, 10), Vector2::new(40, 20)].iter() {
+        let local = spot - offset;
+        assert!(local.x < region.dimensions().x && local.y < region.dimensions().y);
+        assert_eq!(
+            region.get_pixel(local.x, local.y),
+            overlay.texture().get_pixel(spot.x, spot.y)
+        );
+    }
+    assert!(overlay.take_dirty_region().is_none());
+    //painting off the terrain is clamped to its edge
+    overlay.paint(OverlayLayer::Moguls, Vector2::new(-1.0, -1.0), 2.0, 1.0);
+    let (offset, _) = overlay.take_dirty_region().unwrap();
+    assert_eq!(offset, Vector2::new(0, 0));
+}
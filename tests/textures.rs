@@ -0,0 +1,159 @@
+use nalgebra::{Vector2, Vector4};
+use ski_tycoon::RGBATexture;
+
+const SNOW: &[u8] = include_bytes!("../snow.png");
+
+/// Red, green, blue and a half transparent white
+fn colors() -> [Vector4<u8>; 4] {
+    [
+        Vector4::new(255, 0, 0, 255),
+        Vector4::new(0, 255, 0, 255),
+        Vector4::new(0, 0, 255, 255),
+        Vector4::new(255, 255, 255, 128),
+    ]
+}
+
+/// Uncompressed 2x2 TGA whose first row in the file is red and green and whose second is blue and
+/// white. `top_first` sets whether the file stores the top row first.
+fn tga(top_first: bool) -> Vec<u8> {
+    let descriptor = if top_first { 0x28 } else { 0x08 };
+    let mut bytes = vec![
+        0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 32, descriptor,
+    ];
+    for color in colors().iter() {
+        bytes.extend_from_slice(&[color.z, color.y, color.x, color.w]);
+    }
+    bytes
+}
+
+fn texture(dimensions: Vector2<u32>) -> RGBATexture {
+    let pixels = (0..dimensions.x * dimensions.y)
+        .map(|i| Vector4::new(i as u8, 0, 0, 255))
+        .collect();
+    RGBATexture::new(dimensions, pixels)
+}
+
+#[test]
+fn tga_is_decoded_with_row_zero_at_the_bottom() {
+    let [red, green, blue, white] = colors();
+    let bottom_first = RGBATexture::from_tga(&tga(false)).unwrap();
+    assert_eq!(bottom_first.dimensions(), Vector2::new(2, 2));
+    assert_eq!(bottom_first.pixels(), &[red, green, blue, white]);
+    let top_first = RGBATexture::from_extension(&tga(true), "TGA").unwrap();
+    assert_eq!(top_first.pixels(), &[blue, white, red, green]);
+}
+
+#[test]
+fn tga_needs_its_extension() {
+    assert!(RGBATexture::from_bytes(&tga(false)).is_err());
+    assert!(RGBATexture::from_extension(&tga(false), "tga").is_ok());
+}
+
+#[test]
+fn png_is_detected_from_its_header() {
+    let guessed = RGBATexture::from_bytes(SNOW).unwrap();
+    let named = RGBATexture::from_extension(SNOW, "png").unwrap();
+    assert_eq!(guessed.pixels(), named.pixels());
+    assert!(RGBATexture::from_jpeg(SNOW).is_err());
+}
+
+#[test]
+fn flip_reverses_rows() {
+    let mut flipped = texture(Vector2::new(2, 3));
+    flipped.flip_vertical();
+    let values: Vec<u8> = flipped.pixels().iter().map(|p| p.x).collect();
+    assert_eq!(values, vec![4, 5, 2, 3, 0, 1]);
+}
+
+#[test]
+fn sub_image_copies_a_region() {
+    let source = texture(Vector2::new(4, 4));
+    let region = source
+        .sub_image(Vector2::new(1, 2), Vector2::new(2, 2))
+        .unwrap();
+    let values: Vec<u8> = region.pixels().iter().map(|p| p.x).collect();
+    assert_eq!(values, vec![9, 10, 13, 14]);
+    assert!(source
+        .sub_image(Vector2::new(3, 0), Vector2::new(2, 1))
+        .is_err());
+    assert!(source
+        .sub_image(Vector2::new(1, 0), Vector2::new(u32::MAX, 1))
+        .is_err());
+}
+
+#[test]
+fn copy_from_writes_a_region() {
+    let red = colors()[0];
+    let mut target = texture(Vector2::new(3, 3));
+    let source = RGBATexture::constant_color(red, Vector2::new(2, 1));
+    target.copy_from(&source, Vector2::new(1, 2)).unwrap();
+    assert_eq!(target.get_pixel(1, 2), red);
+    assert_eq!(target.get_pixel(2, 2), red);
+    assert_eq!(target.get_pixel(0, 2).x, 6);
+    assert!(target.copy_from(&source, Vector2::new(2, 0)).is_err());
+    assert!(target
+        .copy_from(&source, Vector2::new(u32::MAX, 0))
+        .is_err());
+}
+
+#[test]
+fn png_round_trips_with_the_top_row_first() {
+    let [red, green, blue, white] = colors();
+    let original = RGBATexture::new(Vector2::new(2, 2), vec![red, green, blue, white]);
+    let png = original.to_png().unwrap();
+    let decoded = RGBATexture::from_png(&png).unwrap();
+    assert_eq!(decoded.dimensions(), original.dimensions());
+    assert_eq!(decoded.pixels(), original.pixels());
+    //image viewers show the first row of the file at the top
+    let file = image::load_from_memory(&png).unwrap().to_rgba8();
+    let top_left = file.get_pixel(0, 0);
+    assert_eq!(
+        Vector4::new(top_left[0], top_left[1], top_left[2], top_left[3]),
+        blue
+    );
+}
+
+#[test]
+fn mipmaps_halve_down_to_one_pixel() {
+    let sizes = |dimensions: Vector2<u32>| -> Vec<Vector2<u32>> {
+        texture(dimensions)
+            .mipmap_chain()
+            .iter()
+            .map(|level| level.dimensions())
+            .collect()
+    };
+    assert_eq!(
+        sizes(Vector2::new(16, 4)),
+        vec![
+            Vector2::new(8, 2),
+            Vector2::new(4, 1),
+            Vector2::new(2, 1),
+            Vector2::new(1, 1)
+        ]
+    );
+    assert_eq!(
+        sizes(Vector2::new(5, 3)),
+        vec![Vector2::new(2, 1), Vector2::new(1, 1)]
+    );
+    assert!(sizes(Vector2::new(1, 1)).is_empty());
+}
+
+#[test]
+fn odd_mipmaps_drop_the_last_row_and_column() {
+    //the red channel of each pixel is its index, row by row
+    let levels = texture(Vector2::new(5, 3)).mipmap_chain();
+    assert_eq!(levels[0].get_pixel(0, 0), Vector4::new(3, 0, 0, 255));
+    assert_eq!(levels[0].get_pixel(1, 0), Vector4::new(5, 0, 0, 255));
+    assert_eq!(levels[1].get_pixel(0, 0), Vector4::new(4, 0, 0, 255));
+}
+
+#[test]
+fn mipmaps_average_every_channel() {
+    let mut square = RGBATexture::constant_color(Vector4::new(0, 0, 0, 0), Vector2::new(2, 2));
+    for (i, color) in colors().iter().enumerate() {
+        square.set_pixel(i as u32 % 2, i as u32 / 2, *color);
+    }
+    let levels = square.mipmap_chain();
+    assert_eq!(levels.len(), 1);
+    assert_eq!(levels[0].pixels(), &[Vector4::new(127, 127, 127, 223)]);
+}
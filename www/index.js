@@ -4,9 +4,34 @@ let last_y = null;
 let last_time = new Date();
 let wheel_last_time = new Date();
 let events = []
-function canvas_click() {
-    console.log("clicked??")
-    document.getElementById("canvas").requestPointerLock();
+// converts a position in client pixels to screen coordinates ranging from -1 to 1
+function to_screen_coordinates(client_x, client_y) {
+    let rect = document.getElementById("canvas").getBoundingClientRect();
+    return [
+        2.0 * (client_x - rect.left) / rect.width - 1.0,
+        1.0 - 2.0 * (client_y - rect.top) / rect.height
+    ];
+}
+function canvas_click(event) {
+    let [x, y] = to_screen_coordinates(event.clientX, event.clientY);
+    let click_event = new Map();
+    click_event.set("name", "click");
+    click_event.set("position_x", x);
+    click_event.set("position_y", y);
+    click_event.set("button", event.button);
+    events.push(click_event);
+}
+// the pointer is only locked while the right button rotates the camera, so the cursor stays
+// visible and keeps moving over the gui the rest of the time
+function mouse_down(event) {
+    if (event.button === 2) {
+        document.getElementById("canvas").requestPointerLock();
+    }
+}
+function mouse_up(event) {
+    if (event.button === 2 && document.pointerLockElement === document.getElementById("canvas")) {
+        document.exitPointerLock();
+    }
 }
 function mouse_move(event) {
 
@@ -20,15 +45,19 @@ function mouse_move(event) {
     if (last_y === null) {
         last_y = event.clientY;
     }
-    let delta_x = Number(event.clientX - last_x);
+    // while locked the client position stops changing, so the deltas come from the movement
+    // and the cursor stays where the lock started
+    let locked = document.pointerLockElement === document.getElementById("canvas");
     mouse_event.set("name", "mouse_move");
-    mouse_event.set("delta_x", event.clientX - last_x);
-    mouse_event.set("delta_y", event.clientY - last_y);
+    mouse_event.set("delta_x", locked ? event.movementX : event.clientX - last_x);
+    mouse_event.set("delta_y", locked ? event.movementY : event.clientY - last_y);
     mouse_event.set("delta_time_ms", Number(now - last_time))
     mouse_event.set("buttons", event.buttons);
     events.push(mouse_event)
-    last_x = event.clientX;
-    last_y = event.clientY;
+    if (!locked) {
+        last_x = event.clientX;
+        last_y = event.clientY;
+    }
     last_time = now;
 }
 function onwheel(event) {
@@ -41,24 +70,82 @@ function onwheel(event) {
     events.push(wheel_event);
     last_time = now;
 }
+function key_down(event) {
+    let key_event = new Map();
+    key_event.set("name", "key_down");
+    key_event.set("key", event.key);
+    key_event.set("ctrl", event.ctrlKey || event.metaKey);
+    key_event.set("shift", event.shiftKey);
+    events.push(key_event);
+    // keeping keys like backspace and space from navigating or scrolling the page while typing.
+    // pasting is let through so that the paste event fires
+    let is_paste = (event.ctrlKey || event.metaKey) && event.key === "v";
+    if (game.wants_keyboard() && !is_paste) {
+        event.preventDefault();
+    }
+}
+function on_paste(event) {
+    let paste_event = new Map();
+    paste_event.set("name", "paste");
+    paste_event.set("text", event.clipboardData.getData("text"));
+    events.push(paste_event);
+    event.preventDefault();
+}
 document.getElementById("canvas").onclick = canvas_click;
+document.getElementById("canvas").onmousedown = mouse_down;
+document.addEventListener("mouseup", mouse_up);
+document.getElementById("canvas").oncontextmenu = (event) => event.preventDefault();
+document.addEventListener("keydown", key_down);
+document.addEventListener("paste", on_paste);
 document.getElementById("canvas").onmousemove = mouse_move
 document.getElementById("canvas").onwheel = onwheel;
+let canvas = document.getElementById("canvas");
+let stats = document.getElementById("stats");
 let game = rust.init_game();
+game.load_gui_layout(localStorage.getItem("gui_layout") || "");
+function save_gui_layout() {
+    localStorage.setItem("gui_layout", game.save_gui_layout());
+}
+window.addEventListener("beforeunload", save_gui_layout);
+document.addEventListener("visibilitychange", () => {
+    if (document.visibilityState === "hidden") {
+        save_gui_layout();
+    }
+});
+// downloads the game world rendered at `scale` times the canvas size as a png
+function take_screenshot(scale) {
+    let png = game.screenshot(scale);
+    let url = URL.createObjectURL(new Blob([png], { type: "image/png" }));
+    let link = document.createElement("a");
+    link.href = url;
+    link.download = "ski_tycoon.png";
+    link.click();
+    // the download starts after click returns, so the url has to stay valid a little longer
+    setTimeout(() => URL.revokeObjectURL(url), 1000);
+}
+document.getElementById("screenshot").onclick = () => take_screenshot(1);
+document.getElementById("screenshot_large").onclick = () => take_screenshot(4);
+let last_frame_time = performance.now();
 function render() {
+    let now = performance.now();
     let event_state = new Map();
-    if (last_x === null) {
-        event_state.set("position_x", 0.0);
+    event_state.set("delta_time_ms", now - last_frame_time);
+    last_frame_time = now;
+    if (last_x !== null && last_y !== null) {
+        let [x, y] = to_screen_coordinates(last_x, last_y);
+        event_state.set("position_x", x);
+        event_state.set("position_y", y);
     } else {
-        event_state.set("position_x", last_x);
-    }
-    if (last_y === null) {
+        event_state.set("position_x", 0.0);
         event_state.set("position_y", 0.0);
-    } else {
-        event_state.set("position_y", last_y);
     }
     game.render_frame(event_state, events);
     events = []
+    stats.textContent = `draw calls: ${game.draw_calls()} state changes: ${game.state_changes()} ${game.gpu_resources()}`;
+    let cursor = game.get_cursor();
+    if (canvas.style.cursor !== cursor) {
+        canvas.style.cursor = cursor;
+    }
     requestAnimationFrame(render)
 }
 requestAnimationFrame(render)
\ No newline at end of file